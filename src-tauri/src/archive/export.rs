@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::manifest::{
+    file_entry_name, ArchiveManifest, ManifestCard, ManifestFile, ARCHIVE_APP,
+    ARCHIVE_SCHEMA_VERSION, MANIFEST_ENTRY,
+};
+use super::Result;
+use crate::jobs::Job;
+use crate::library::{now_millis, Library, LibraryError};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: PathBuf,
+    pub cards: usize,
+    /// Distinct files written; twins share one entry.
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Writes the whole library to `dest` as a version-3 archive (see
+/// [`manifest`](super::manifest)), streaming each file straight from the blob store.
+/// The zip is built next to `dest` and renamed into place at the end, so an existing
+/// archive is never left half-overwritten, and a failed or cancelled export leaves
+/// nothing behind.
+pub fn export_archive(library: &Library, dest: &Path, job: &mut Job<'_>) -> Result<ExportSummary> {
+    let manifest = build_manifest(library)?;
+    let partial = partial_path(dest);
+    let result = write_archive(library, &manifest, &partial, job).and_then(|counts| {
+        fs::rename(&partial, dest)
+            .map(|_| counts)
+            .map_err(Into::into)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    let (files, bytes) = result?;
+    Ok(ExportSummary {
+        path: dest.to_path_buf(),
+        cards: manifest.cards.len(),
+        files,
+        bytes,
+    })
+}
+
+fn build_manifest(library: &Library) -> Result<ArchiveManifest> {
+    let mut cards = Vec::new();
+    for meta in library.list_cards()? {
+        let hash = library
+            .file_hash(&meta.id)?
+            .ok_or_else(|| LibraryError::NotFound(meta.id.clone()))?;
+        let size = fs::metadata(library.card_file_path(&meta.id)?)?.len();
+        let mut face_files = Vec::with_capacity(meta.faces.len());
+        for face in &meta.faces {
+            let hash = library.face_hash(&face.id)?;
+            let size = fs::metadata(library.face_file_path(&face.id)?)?.len();
+            face_files.push(manifest_file(hash, size, &face.mime, &face.orig_ext));
+        }
+        cards.push(ManifestCard {
+            file: manifest_file(hash, size, &meta.mime, &meta.orig_ext),
+            face_files,
+            meta,
+        });
+    }
+    Ok(ArchiveManifest {
+        app: ARCHIVE_APP.to_string(),
+        schema_version: ARCHIVE_SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: now_millis(),
+        cards,
+        order_map: library.order_map()?,
+        custom_collections: library.custom_collections()?,
+        saved_queries: library.saved_queries()?,
+        smart_collections: library.smart_collections()?,
+        checklist_matches: library.checklist_matches()?.into_iter().collect(),
+    })
+}
+
+fn manifest_file(hash: String, size: u64, mime: &str, orig_ext: &str) -> ManifestFile {
+    ManifestFile {
+        path: file_entry_name(&hash),
+        sha256: Some(hash),
+        size,
+        mime: mime.to_string(),
+        orig_ext: orig_ext.to_string(),
+    }
+}
+
+/// Returns the number of files written and their total size.
+fn write_archive(
+    library: &Library,
+    manifest: &ArchiveManifest,
+    path: &Path,
+    job: &mut Job<'_>,
+) -> Result<(usize, u64)> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    // PDFs and images are already compressed; deflating them again only costs time.
+    let stored = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    zip.start_file(MANIFEST_ENTRY, deflated)?;
+    serde_json::to_writer_pretty(&mut zip, manifest).map_err(io::Error::from)?;
+
+    // Each distinct file once, with the name of the first card it belongs to
+    let mut seen = HashSet::new();
+    let mut files: Vec<(&str, &ManifestFile, PathBuf)> = Vec::new();
+    for card in &manifest.cards {
+        if seen.insert(card.file.path.as_str()) {
+            let source = library.card_file_path(&card.meta.id)?;
+            files.push((&card.meta.name, &card.file, source));
+        }
+        for (face, file) in card.meta.faces.iter().zip(&card.face_files) {
+            if seen.insert(file.path.as_str()) {
+                files.push((&card.meta.name, file, library.face_file_path(&face.id)?));
+            }
+        }
+    }
+    job.set_totals(
+        files.len(),
+        files.iter().map(|(_, file, _)| file.size).sum(),
+    );
+
+    for (name, file, source) in &files {
+        job.start_entry(name)?;
+        zip.start_file(file.path.as_str(), stored)?;
+        let mut blob = File::open(source)?;
+        let copied = io::copy(&mut blob, &mut zip)?;
+        job.finish_entry(copied);
+    }
+
+    zip.finish()?.flush()?;
+    let progress = job.progress();
+    Ok((progress.files_done, progress.bytes_done))
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
@@ -0,0 +1,379 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use zip::read::ZipFile;
+use zip::ZipArchive;
+
+use super::{ArchiveError, Result};
+use crate::jobs::Job;
+
+/// Entries smaller than this are exempt from the compression-ratio check; tiny text
+/// files routinely compress far better than any real card does.
+const RATIO_CHECK_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Most [`Budget::read`] reserves up front. The header's size is only a claim, so a
+/// larger entry grows the buffer as its bytes actually arrive.
+const READ_PREALLOC_MAX: u64 = 4 * 1024 * 1024;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Caps applied while reading untrusted archives, so a crafted zip can't fill the disk
+/// or memory. The defaults leave plenty of headroom for a multi-thousand-card binder.
+#[derive(Debug, Clone)]
+pub struct ExtractLimits {
+    pub max_entries: usize,
+    pub max_total_size: u64,
+    /// Largest allowed uncompressed:compressed ratio for a single entry.
+    pub max_ratio: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 50_000,
+            max_total_size: 16 * 1024 * 1024 * 1024,
+            max_ratio: 200,
+        }
+    }
+}
+
+/// Tracks how much has been decompressed so far against the limits.
+pub(super) struct Budget<'a> {
+    limits: &'a ExtractLimits,
+    used: u64,
+}
+
+impl<'a> Budget<'a> {
+    pub(super) fn new<R: Read + Seek>(
+        zip: &ZipArchive<R>,
+        limits: &'a ExtractLimits,
+    ) -> Result<Self> {
+        if zip.len() > limits.max_entries {
+            return Err(ArchiveError::TooManyEntries {
+                limit: limits.max_entries,
+            });
+        }
+        Ok(Self { limits, used: 0 })
+    }
+
+    /// Copies an entry into `out`, enforcing the size and ratio limits on the bytes that
+    /// actually come out of the decompressor — the sizes in the header can lie. At most
+    /// one byte past either limit is read before the entry is rejected.
+    pub(super) fn copy(
+        &mut self,
+        entry: &mut ZipFile<'_>,
+        out: &mut impl io::Write,
+    ) -> Result<u64> {
+        let name = entry.name().to_string();
+        let compressed = entry.compressed_size().max(1);
+        self.check_ratio(&name, entry.size(), compressed)?;
+        if entry.size() > self.remaining() {
+            return Err(ArchiveError::TooLarge {
+                limit: self.limits.max_total_size,
+            });
+        }
+
+        let remaining = self.remaining();
+        let cap = remaining.min(self.max_size(compressed));
+        let written = io::copy(&mut (&mut *entry).take(cap + 1), out)?;
+        if written > remaining {
+            return Err(ArchiveError::TooLarge {
+                limit: self.limits.max_total_size,
+            });
+        }
+        self.check_ratio(&name, written, compressed)?;
+        self.used += written;
+        Ok(written)
+    }
+
+    pub(super) fn read(&mut self, entry: &mut ZipFile<'_>) -> Result<Vec<u8>> {
+        let claimed = entry.size().min(self.remaining()).min(READ_PREALLOC_MAX);
+        let mut bytes = Vec::with_capacity(claimed as usize);
+        self.copy(entry, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> u64 {
+        self.limits.max_total_size.saturating_sub(self.used)
+    }
+
+    /// The largest entry [`Budget::check_ratio`] lets through for `compressed` bytes.
+    fn max_size(&self, compressed: u64) -> u64 {
+        compressed
+            .saturating_mul(self.limits.max_ratio.saturating_add(1))
+            .saturating_sub(1)
+            .max(RATIO_CHECK_MIN_SIZE - 1)
+    }
+
+    fn check_ratio(&self, name: &str, size: u64, compressed: u64) -> Result<()> {
+        if size >= RATIO_CHECK_MIN_SIZE && size / compressed > self.limits.max_ratio {
+            return Err(ArchiveError::CompressionRatio {
+                name: name.to_string(),
+                limit: self.limits.max_ratio,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Extracts `zip_path` into `dest`. Entries that would land outside `dest` (`../x`,
+/// absolute paths, drive prefixes) and symlinks are rejected. If `dest` didn't exist
+/// beforehand it is removed again when extraction fails or is cancelled, so no partial
+/// output is left.
+pub fn extract_to_dir(
+    zip_path: &Path,
+    dest: &Path,
+    limits: &ExtractLimits,
+    job: &mut Job<'_>,
+) -> Result<()> {
+    let created = !dest.exists();
+    let result = extract_inner(zip_path, dest, limits, job);
+    if result.is_err() && created {
+        let _ = fs::remove_dir_all(dest);
+    }
+    result
+}
+
+fn extract_inner(
+    zip_path: &Path,
+    dest: &Path,
+    limits: &ExtractLimits,
+    job: &mut Job<'_>,
+) -> Result<()> {
+    let mut zip = ZipArchive::new(File::open(zip_path)?)?;
+    let mut budget = Budget::new(&zip, limits)?;
+    fs::create_dir_all(dest)?;
+
+    let (mut files, mut bytes) = (0, 0);
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i)?;
+        if !entry.is_dir() {
+            files += 1;
+            bytes += entry.size();
+        }
+    }
+    job.set_totals(files, bytes);
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        job.start_entry(entry.name())?;
+        let outpath = dest.join(safe_relative_path(&entry)?);
+        if entry.is_dir() {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut outfile = File::create(&outpath)?;
+        let written = budget.copy(&mut entry, &mut outfile)?;
+        job.finish_entry(written);
+    }
+    Ok(())
+}
+
+fn safe_relative_path(entry: &ZipFile<'_>) -> Result<PathBuf> {
+    if entry
+        .unix_mode()
+        .is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+    {
+        return Err(ArchiveError::Symlink(entry.name().to_string()));
+    }
+    entry
+        .enclosed_name()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| ArchiveError::UnsafePath(entry.name().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::CancelToken;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn write_zip(path: &Path, build: impl FnOnce(&mut ZipWriter<File>)) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        build(&mut zip);
+        zip.finish().unwrap();
+    }
+
+    fn add_file(zip: &mut ZipWriter<File>, name: &str, bytes: &[u8]) {
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(name, options).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+
+    fn extract_crafted(
+        build: impl FnOnce(&mut ZipWriter<File>),
+        limits: &ExtractLimits,
+    ) -> (tempfile::TempDir, Result<()>) {
+        let tmp = tempfile::tempdir().unwrap();
+        let zip_path = tmp.path().join("crafted.zip");
+        write_zip(&zip_path, build);
+        let result = extract_to_dir(
+            &zip_path,
+            &tmp.path().join("out"),
+            limits,
+            &mut Job::detached(),
+        );
+        (tmp, result)
+    }
+
+    #[test]
+    fn extracts_well_formed_archive() {
+        let (tmp, result) = extract_crafted(
+            |zip| {
+                add_file(zip, "metadata.json", b"[]");
+                add_file(zip, "files/a.bin", b"card");
+            },
+            &ExtractLimits::default(),
+        );
+        result.unwrap();
+        assert_eq!(
+            fs::read(tmp.path().join("out/files/a.bin")).unwrap(),
+            b"card"
+        );
+    }
+
+    #[test]
+    fn rejects_parent_dir_traversal() {
+        let (tmp, result) = extract_crafted(
+            |zip| add_file(zip, "files/../../escaped.txt", b"pwned"),
+            &ExtractLimits::default(),
+        );
+        assert!(
+            matches!(result, Err(ArchiveError::UnsafePath(name)) if name == "files/../../escaped.txt")
+        );
+        assert!(!tmp.path().join("escaped.txt").exists());
+        assert!(!tmp.path().join("out").exists());
+    }
+
+    #[test]
+    fn rejects_absolute_path() {
+        let (_tmp, result) = extract_crafted(
+            |zip| add_file(zip, "/tmp/escaped.txt", b"pwned"),
+            &ExtractLimits::default(),
+        );
+        assert!(matches!(result, Err(ArchiveError::UnsafePath(_))));
+    }
+
+    #[test]
+    fn rejects_symlink() {
+        let (_tmp, result) = extract_crafted(
+            |zip| {
+                zip.add_symlink("files/link", "/etc/passwd", FileOptions::default())
+                    .unwrap()
+            },
+            &ExtractLimits::default(),
+        );
+        assert!(matches!(result, Err(ArchiveError::Symlink(name)) if name == "files/link"));
+    }
+
+    #[test]
+    fn rejects_too_many_entries() {
+        let limits = ExtractLimits {
+            max_entries: 2,
+            ..Default::default()
+        };
+        let (_tmp, result) = extract_crafted(
+            |zip| {
+                for i in 0..3 {
+                    add_file(zip, &format!("files/{i}.bin"), b"x");
+                }
+            },
+            &limits,
+        );
+        assert!(matches!(
+            result,
+            Err(ArchiveError::TooManyEntries { limit: 2 })
+        ));
+    }
+
+    #[test]
+    fn rejects_total_size_over_limit() {
+        let limits = ExtractLimits {
+            max_total_size: 1000,
+            ..Default::default()
+        };
+        let (tmp, result) = extract_crafted(
+            |zip| {
+                add_file(zip, "files/a.bin", &[1; 600]);
+                add_file(zip, "files/b.bin", &[2; 600]);
+            },
+            &limits,
+        );
+        assert!(matches!(
+            result,
+            Err(ArchiveError::TooLarge { limit: 1000 })
+        ));
+        assert!(!tmp.path().join("out").exists());
+    }
+
+    #[test]
+    fn cancelled_extraction_leaves_no_output() {
+        let tmp = tempfile::tempdir().unwrap();
+        let zip_path = tmp.path().join("archive.zip");
+        write_zip(&zip_path, |zip| {
+            add_file(zip, "files/a.bin", b"first");
+            add_file(zip, "files/b.bin", b"second");
+        });
+
+        let token = CancelToken::default();
+        let mut job = Job::new(token.clone(), |progress| {
+            if progress.files_done == 1 {
+                token.cancel();
+            }
+        });
+        let out = tmp.path().join("out");
+        let result = extract_to_dir(&zip_path, &out, &ExtractLimits::default(), &mut job);
+        assert!(matches!(result, Err(ArchiveError::Cancelled)));
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn stops_reading_an_entry_whose_header_lies_about_its_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        let zip_path = tmp.path().join("bomb.zip");
+        write_zip(&zip_path, |zip| {
+            add_file(zip, "files/bomb.bin", &vec![0; 32 * 1024 * 1024])
+        });
+        // Claim 1000 bytes in both the local header and the central directory
+        let mut bytes = fs::read(&zip_path).unwrap();
+        for (signature, offset) in [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)] {
+            let at = bytes.windows(4).position(|w| w == signature).unwrap() + offset;
+            bytes[at..at + 4].copy_from_slice(&1000u32.to_le_bytes());
+        }
+        fs::write(&zip_path, bytes).unwrap();
+
+        let mut zip = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let limits = ExtractLimits::default();
+        let mut budget = Budget::new(&zip, &limits).unwrap();
+        let mut entry = zip.by_index(0).unwrap();
+        assert_eq!(entry.size(), 1000);
+        let compressed = entry.compressed_size();
+        let mut out = Vec::new();
+        let result = budget.copy(&mut entry, &mut out);
+        assert!(matches!(
+            result,
+            Err(ArchiveError::CompressionRatio { limit: 200, .. })
+        ));
+        assert_eq!(out.len() as u64, budget.max_size(compressed) + 1);
+        assert!((out.len() as u64) < 32 * 1024 * 1024);
+    }
+
+    #[test]
+    fn rejects_zip_bomb_ratio() {
+        let (_tmp, result) = extract_crafted(
+            |zip| add_file(zip, "files/bomb.bin", &vec![0; 8 * 1024 * 1024]),
+            &ExtractLimits::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(ArchiveError::CompressionRatio { limit: 200, .. })
+        ));
+    }
+}
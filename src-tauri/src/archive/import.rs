@@ -0,0 +1,715 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use sha2::{Digest, Sha256};
+
+use super::extract::Budget;
+use super::manifest::{read_manifest, ManifestCard};
+use super::{ArchiveError, ExtractLimits, Result};
+use crate::jobs::Job;
+use crate::library::{hash_bytes, AddOutcome, CardFace, CardMeta, Library, LibraryError, OrderMap};
+
+/// Where a replace keeps the archive's files, inside the library folder, between
+/// reading them all and clearing the library.
+const STAGING_DIR: &str = "import-staging";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportMode {
+    /// Keep the current library and add what's new from the archive.
+    Merge,
+    /// Wipe the library first, then restore the archive as-is.
+    Replace,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub added: Vec<String>,
+    pub skipped: Vec<SkippedCard>,
+    pub conflicts: Vec<ConflictCard>,
+    pub custom_collections_added: Vec<String>,
+    pub saved_queries_added: Vec<String>,
+    pub smart_collections_added: Vec<String>,
+    pub checklist_matches_added: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedCard {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "reason",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SkipReason {
+    /// The same card, with the same file, is already in the library.
+    AlreadyInLibrary,
+    /// Another card in the library already has byte-identical content.
+    DuplicateOf { existing_id: String },
+}
+
+/// A card whose id is already used by a different file in the library. The library's
+/// copy is kept.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictCard {
+    pub id: String,
+    pub name: String,
+    pub existing_name: String,
+}
+
+/// Restores a library archive of any supported schema version. The archive is fully
+/// validated, checksums included, before anything in the library is touched, so a bad
+/// file never leaves a half-replaced library behind.
+///
+/// A merge that fails or is cancelled part-way removes the cards it already added. A
+/// replace reads every file out of the archive into a staging folder before it clears
+/// the library, so a bad entry or a cancel up to then leaves the library as it was;
+/// once cleared it runs to the end, since stopping would leave half a library.
+pub fn import_archive(
+    library: &Library,
+    path: &Path,
+    mode: ImportMode,
+    job: &mut Job<'_>,
+) -> Result<ImportReport> {
+    let limits = ExtractLimits::default();
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let mut manifest_budget = Budget::new(&zip, &limits)?;
+    let manifest = read_manifest(&mut zip, &mut manifest_budget)?;
+    // Checksumming decompresses every file once more, so it gets a budget of its own.
+    let mut check_budget = Budget::new(&zip, &limits)?;
+    validate(&mut zip, &manifest.cards, &mut check_budget, job)?;
+    job.set_totals(
+        manifest.cards.len(),
+        manifest
+            .cards
+            .iter()
+            .flat_map(|card| std::iter::once(&card.file).chain(&card.face_files))
+            .map(|file| file.size)
+            .sum(),
+    );
+
+    let mut report = ImportReport::default();
+    match mode {
+        ImportMode::Merge => {
+            let mut budget = Budget::new(&zip, &limits)?;
+            let read = |path: &str| budget.read(&mut zip.by_name(path)?);
+            let imported = import_cards(library, manifest.cards, mode, job, &mut report, read);
+            if let Err(e) = imported {
+                for id in &report.added {
+                    let _ = library.delete_card(id);
+                }
+                return Err(e);
+            }
+        }
+        ImportMode::Replace => {
+            let staging = library.root().join(STAGING_DIR);
+            let result =
+                stage(&mut zip, &manifest.cards, &limits, &staging, job).and_then(|staged| {
+                    job.check()?;
+                    clear(library)?;
+                    let read = |path: &str| Ok(fs::read(&staged[path])?);
+                    import_cards(library, manifest.cards, mode, job, &mut report, read)
+                });
+            let _ = fs::remove_dir_all(&staging);
+            result?;
+        }
+    }
+
+    if !manifest.order_map.is_empty() {
+        let mut order = library.order_map()?;
+        merge_order_map(&mut order, manifest.order_map);
+        library.restore_order_map(&order)?;
+    }
+
+    if !manifest.custom_collections.is_empty() {
+        let mut names = library.custom_collections()?;
+        for name in manifest.custom_collections {
+            let name = name.trim().to_string();
+            if !name.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                names.push(name.clone());
+                report.custom_collections_added.push(name);
+            }
+        }
+        names.sort_by_key(|n| n.to_lowercase());
+        library.restore_custom_collections(&names)?;
+    }
+
+    // Queries whose name is taken keep the library's version, like conflicting cards
+    if !manifest.saved_queries.is_empty() {
+        let mut queries = library.saved_queries()?;
+        for query in manifest.saved_queries {
+            if !queries
+                .iter()
+                .any(|q| q.name.eq_ignore_ascii_case(&query.name))
+            {
+                report.saved_queries_added.push(query.name.clone());
+                queries.push(query);
+            }
+        }
+        library.set_saved_queries(&queries)?;
+    }
+
+    if !manifest.smart_collections.is_empty() {
+        let existing = library.smart_collections()?;
+        for collection in manifest.smart_collections {
+            if !existing.iter().any(|c| c.id == collection.id) {
+                library.add_smart_collection(&collection)?;
+                report.smart_collections_added.push(collection.name);
+            }
+        }
+    }
+
+    // A match follows its card to wherever it landed: the added card, or the library's
+    // copy of a skipped one. Conflicts keep the library's card, which isn't the one the
+    // match was made for, and cards the user already matched by hand keep their match.
+    if !manifest.checklist_matches.is_empty() {
+        let mut landed: HashMap<&str, &str> = report
+            .added
+            .iter()
+            .map(|id| (id.as_str(), id.as_str()))
+            .collect();
+        for card in &report.skipped {
+            let existing = match &card.reason {
+                SkipReason::AlreadyInLibrary => &card.id,
+                SkipReason::DuplicateOf { existing_id } => existing_id,
+            };
+            landed.insert(&card.id, existing);
+        }
+        let mut matched: HashSet<String> = library.checklist_matches()?.into_keys().collect();
+        for (card_id, entry) in &manifest.checklist_matches {
+            let Some(&id) = landed.get(card_id.as_str()) else {
+                continue;
+            };
+            if matched.insert(id.to_string()) {
+                library.set_checklist_match(id, Some(entry))?;
+                report.checklist_matches_added += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Empties the library for a replace: cards, history, order, collections and queries.
+fn clear(library: &Library) -> Result<()> {
+    library.clear()?;
+    library.restore_order_map(&OrderMap::new())?;
+    library.restore_custom_collections(&[])?;
+    library.set_saved_queries(&[])?;
+    for collection in library.smart_collections()? {
+        library.delete_smart_collection(&collection.id)?;
+    }
+    Ok(())
+}
+
+/// Copies every card's file, and those of its faces, out of the archive into `dir`.
+/// Returns where each archive path went. Progress is reported here, since this is
+/// where a replace spends its time decompressing.
+fn stage<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    cards: &[ManifestCard],
+    limits: &ExtractLimits,
+    dir: &Path,
+    job: &mut Job<'_>,
+) -> Result<HashMap<String, PathBuf>> {
+    // Left over from an import that was killed
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir)?;
+    let mut budget = Budget::new(zip, limits)?;
+    let mut staged = HashMap::new();
+    for ManifestCard {
+        meta,
+        file,
+        face_files,
+    } in cards
+    {
+        job.start_entry(&meta.name)?;
+        let mut size = 0;
+        for file in std::iter::once(file).chain(face_files) {
+            if staged.contains_key(&file.path) {
+                continue;
+            }
+            let path = dir.join(format!("{}.bin", staged.len()));
+            size += budget.copy(&mut zip.by_name(&file.path)?, &mut File::create(&path)?)?;
+            staged.insert(file.path.clone(), path);
+        }
+        job.finish_entry(size);
+    }
+    Ok(staged)
+}
+
+/// Adds the cards, reading each file through `read`. A merge reports progress here; a
+/// replace already did while staging, and can no longer be cancelled.
+fn import_cards(
+    library: &Library,
+    cards: Vec<ManifestCard>,
+    mode: ImportMode,
+    job: &mut Job<'_>,
+    report: &mut ImportReport,
+    mut read: impl FnMut(&str) -> Result<Vec<u8>>,
+) -> Result<()> {
+    for ManifestCard {
+        mut meta,
+        file,
+        face_files,
+    } in cards
+    {
+        match mode {
+            ImportMode::Merge => job.start_entry(&meta.name)?,
+            ImportMode::Replace => job.set_entry(&meta.name),
+        }
+        let bytes = read(&file.path)?;
+        if meta.mime.is_empty() {
+            meta.mime = file.mime;
+        }
+        if meta.orig_ext.is_empty() {
+            meta.orig_ext = file.orig_ext;
+        }
+        let faces = std::mem::take(&mut meta.faces);
+        let added = report.added.len();
+        match mode {
+            ImportMode::Replace => {
+                report.added.push(library.restore_card(meta, &bytes)?.id);
+            }
+            ImportMode::Merge => merge_card(library, meta, &bytes, report)?,
+        }
+        let mut size = bytes.len() as u64;
+        // Faces come along only with a card that was added
+        if let Some(id) = report.added.get(added) {
+            for (mut face, file) in faces.into_iter().zip(face_files) {
+                let bytes = read(&file.path)?;
+                if face.mime.is_empty() {
+                    face.mime = file.mime;
+                }
+                if face.orig_ext.is_empty() {
+                    face.orig_ext = file.orig_ext;
+                }
+                restore_face(library, id, face, &bytes, mode)?;
+                size += bytes.len() as u64;
+            }
+        }
+        if mode == ImportMode::Merge {
+            job.finish_entry(size);
+        }
+    }
+    Ok(())
+}
+
+fn restore_face(
+    library: &Library,
+    card_id: &str,
+    face: CardFace,
+    bytes: &[u8],
+    mode: ImportMode,
+) -> Result<()> {
+    match library.restore_face(card_id, face.clone(), bytes, None) {
+        // Only possible when merging; the face is new to this library, so give it a new id
+        Err(LibraryError::AlreadyExists(_)) if mode == ImportMode::Merge => {
+            let face = CardFace {
+                id: String::new(),
+                ..face
+            };
+            library.restore_face(card_id, face, bytes, None)?;
+        }
+        other => {
+            other?;
+        }
+    }
+    Ok(())
+}
+
+fn merge_card(
+    library: &Library,
+    meta: CardMeta,
+    bytes: &[u8],
+    report: &mut ImportReport,
+) -> Result<()> {
+    if let Some(existing) = library.get_card(&meta.id)? {
+        if library.file_hash(&meta.id)?.as_deref() == Some(hash_bytes(bytes).as_str()) {
+            report.skipped.push(SkippedCard {
+                id: meta.id,
+                name: meta.name,
+                reason: SkipReason::AlreadyInLibrary,
+            });
+        } else {
+            report.conflicts.push(ConflictCard {
+                id: meta.id,
+                name: meta.name,
+                existing_name: existing.name,
+            });
+        }
+        return Ok(());
+    }
+
+    let (id, name) = (meta.id.clone(), meta.name.clone());
+    match library.add_card(meta, bytes)? {
+        AddOutcome::Added { card } => report.added.push(card.id),
+        AddOutcome::AlreadyInLibrary { existing_id } => report.skipped.push(SkippedCard {
+            id,
+            name,
+            reason: SkipReason::DuplicateOf { existing_id },
+        }),
+    }
+    Ok(())
+}
+
+/// Appends ids the library doesn't have yet to each group, keeping the current order.
+fn merge_order_map(current: &mut OrderMap, incoming: OrderMap) {
+    for (key, ids) in incoming {
+        let group = current.entry(key).or_default();
+        for id in ids {
+            if !group.contains(&id) {
+                group.push(id);
+            }
+        }
+    }
+}
+
+/// Every card needs a unique, non-empty id and its file, and those of its faces, present
+/// in the archive, and every file has to match the checksum the manifest gives for it.
+fn validate<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    cards: &[ManifestCard],
+    budget: &mut Budget<'_>,
+    job: &Job<'_>,
+) -> Result<()> {
+    let entries: HashSet<String> = zip.file_names().map(str::to_string).collect();
+    let mut seen = HashSet::new();
+    let mut verified = HashSet::new();
+    for ManifestCard {
+        meta,
+        file,
+        face_files,
+    } in cards
+    {
+        if meta.id.is_empty() {
+            return Err(ArchiveError::Invalid("card without an id".into()));
+        }
+        if !seen.insert(meta.id.as_str()) {
+            return Err(ArchiveError::Invalid(format!(
+                "duplicate card id {}",
+                meta.id
+            )));
+        }
+        if meta.faces.len() != face_files.len() {
+            return Err(ArchiveError::Invalid(format!(
+                "card {} has {} faces but {} face files",
+                meta.id,
+                meta.faces.len(),
+                face_files.len()
+            )));
+        }
+        for file in std::iter::once(file).chain(face_files) {
+            if !entries.contains(&file.path) {
+                return Err(ArchiveError::Invalid(format!("missing {}", file.path)));
+            }
+            let Some(expected) = &file.sha256 else {
+                continue;
+            };
+            if !verified.insert(file.path.as_str()) {
+                continue;
+            }
+            job.check()?;
+            let mut hasher = HashWriter(Sha256::new());
+            budget.copy(&mut zip.by_name(&file.path)?, &mut hasher)?;
+            if !format!("{:x}", hasher.0.finalize()).eq_ignore_ascii_case(expected) {
+                return Err(ArchiveError::Invalid(format!(
+                    "{} does not match its checksum",
+                    file.path
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+struct HashWriter(Sha256);
+
+impl io::Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::archive::export_archive;
+    use crate::archive::manifest::MANIFEST_ENTRY;
+    use crate::jobs::CancelToken;
+    use crate::library::{test_card, CardKind};
+
+    fn library(path: &Path, cards: &[(&str, &str, &[u8])]) -> Library {
+        let library = Library::open(path).unwrap();
+        for (id, name, bytes) in cards {
+            let mut card = test_card(id);
+            card.name = name.to_string();
+            library.restore_card(card, bytes).unwrap();
+        }
+        library
+    }
+
+    fn ids(library: &Library) -> Vec<String> {
+        let mut ids: Vec<String> = library
+            .list_cards()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// An archive of cards `a`, `b`, `c` and `e`.
+    fn archive(dir: &Path) -> std::path::PathBuf {
+        let source = library(
+            &dir.join("source"),
+            &[
+                ("a", "A", b"a"),
+                ("b", "B", b"new b"),
+                ("c", "C", b"c"),
+                ("e", "E", b"e"),
+            ],
+        );
+        source
+            .set_custom_collections(&["Quiet Court".into()])
+            .unwrap();
+        let path = dir.join("cards.zip");
+        export_archive(&source, &path, &mut Job::detached()).unwrap();
+        path
+    }
+
+    /// Copies the archive at `from` to `to`, passing its manifest through `edit`.
+    fn rewrite_manifest(from: &Path, to: &Path, edit: impl FnOnce(&mut serde_json::Value)) {
+        let mut zip = ZipArchive::new(File::open(from).unwrap()).unwrap();
+        let mut out = zip::ZipWriter::new(File::create(to).unwrap());
+        let mut edit = Some(edit);
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).unwrap();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).unwrap();
+            if entry.name() == MANIFEST_ENTRY {
+                let mut manifest = serde_json::from_slice(&bytes).unwrap();
+                (edit.take().unwrap())(&mut manifest);
+                bytes = serde_json::to_vec(&manifest).unwrap();
+            }
+            out.start_file(entry.name(), zip::write::FileOptions::default())
+                .unwrap();
+            out.write_all(&bytes).unwrap();
+        }
+        out.finish().unwrap();
+    }
+
+    #[test]
+    fn merge_keeps_the_library_and_reports_what_it_left_out() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = archive(tmp.path());
+        let target = library(
+            &tmp.path().join("target"),
+            &[("a", "A", b"a"), ("b", "Old B", b"old b"), ("d", "D", b"c")],
+        );
+
+        let report =
+            import_archive(&target, &path, ImportMode::Merge, &mut Job::detached()).unwrap();
+        assert_eq!(report.added, ["e"]);
+        assert_eq!(report.skipped.len(), 2);
+        assert!(matches!(
+            &report.skipped[0],
+            SkippedCard { id, reason: SkipReason::AlreadyInLibrary, .. } if id == "a"
+        ));
+        assert!(matches!(
+            &report.skipped[1],
+            SkippedCard { id, reason: SkipReason::DuplicateOf { existing_id }, .. }
+                if id == "c" && existing_id == "d"
+        ));
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].id, "b");
+        assert_eq!(report.conflicts[0].existing_name, "Old B");
+        assert_eq!(report.custom_collections_added, ["Quiet Court"]);
+
+        // The library's own cards are kept as they were
+        assert_eq!(ids(&target), ["a", "b", "d", "e"]);
+        assert_eq!(target.read_card_file("b").unwrap(), b"old b");
+    }
+
+    #[test]
+    fn checklist_matches_follow_the_card_they_were_made_for() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = archive(tmp.path());
+        let matched = tmp.path().join("matched.zip");
+        rewrite_manifest(&path, &matched, |manifest| {
+            manifest["checklistMatches"] = serde_json::json!({
+                "a": "Entry A",
+                "b": "Entry B",
+                "c": "Entry C",
+                "e": "Entry E",
+            });
+        });
+        let target = library(
+            &tmp.path().join("target"),
+            &[("a", "A", b"a"), ("b", "Old B", b"old b"), ("d", "D", b"c")],
+        );
+
+        let report =
+            import_archive(&target, &matched, ImportMode::Merge, &mut Job::detached()).unwrap();
+        assert_eq!(report.checklist_matches_added, 3);
+        let matches = target.checklist_matches().unwrap();
+        assert_eq!(matches.get("a").map(String::as_str), Some("Entry A"));
+        // The conflicting b is a different card that happens to share the id
+        assert_eq!(matches.get("b"), None);
+        // c was skipped as a copy of d, so d gets its match
+        assert_eq!(matches.get("c"), None);
+        assert_eq!(matches.get("d").map(String::as_str), Some("Entry C"));
+        assert_eq!(matches.get("e").map(String::as_str), Some("Entry E"));
+    }
+
+    #[test]
+    fn replace_restores_only_the_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = archive(tmp.path());
+        let target = library(&tmp.path().join("target"), &[("x", "X", b"x")]);
+        target
+            .set_custom_collections(&["Loud Court".into()])
+            .unwrap();
+
+        let report =
+            import_archive(&target, &path, ImportMode::Replace, &mut Job::detached()).unwrap();
+        assert_eq!(report.added.len(), 4);
+        assert_eq!(ids(&target), ["a", "b", "c", "e"]);
+        assert_eq!(target.read_card_file("b").unwrap(), b"new b");
+        assert_eq!(target.custom_collections().unwrap(), ["Quiet Court"]);
+        assert!(target.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    fn a_bad_checksum_is_refused_before_anything_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = archive(tmp.path());
+        let tampered = tmp.path().join("tampered.zip");
+        rewrite_manifest(&path, &tampered, |manifest| {
+            manifest["cards"][1]["file"]["sha256"] = hash_bytes(b"something else").into();
+        });
+        let target = library(&tmp.path().join("target"), &[("x", "X", b"x")]);
+
+        let err = import_archive(
+            &target,
+            &tampered,
+            ImportMode::Replace,
+            &mut Job::detached(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&err, ArchiveError::Invalid(reason) if reason.contains("checksum")),
+            "{err}"
+        );
+        assert_eq!(ids(&target), ["x"]);
+    }
+
+    #[test]
+    fn a_merge_stopped_part_way_takes_back_what_it_added() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = archive(tmp.path());
+        let target = library(&tmp.path().join("target"), &[("x", "X", b"x")]);
+        let token = CancelToken::default();
+        let cancel = token.clone();
+        let mut job = Job::new(token, move |progress| {
+            if progress.files_done == 2 {
+                cancel.cancel();
+            }
+        });
+
+        let err = import_archive(&target, &path, ImportMode::Merge, &mut job).unwrap_err();
+        assert!(matches!(err, ArchiveError::Cancelled));
+        assert_eq!(ids(&target), ["x"]);
+        assert!(target.verify().unwrap().is_ok());
+        assert!(target.find_by_hash(&hash_bytes(b"a")).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_replace_that_fails_part_way_leaves_the_library_as_it_was() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = archive(tmp.path());
+        // Without a checksum, a damaged file is only found when the restore reads it
+        let damaged = tmp.path().join("damaged.zip");
+        let mut file_path = String::new();
+        rewrite_manifest(&path, &damaged, |manifest| {
+            let file = &mut manifest["cards"][2]["file"];
+            file_path = file["path"].as_str().unwrap().to_string();
+            file.as_object_mut().unwrap().remove("sha256");
+        });
+        let mut bytes = fs::read(&damaged).unwrap();
+        let name = bytes
+            .windows(file_path.len())
+            .position(|w| w == file_path.as_bytes())
+            .unwrap();
+        let extra = u16::from_le_bytes([bytes[name - 2], bytes[name - 1]]) as usize;
+        bytes[name + file_path.len() + extra] ^= 0xff;
+        fs::write(&damaged, bytes).unwrap();
+
+        let target = library(
+            &tmp.path().join("target"),
+            &[("x", "X", b"x"), ("y", "Y", b"y")],
+        );
+        target.delete_cards(&["y".into()]).unwrap();
+        import_archive(&target, &damaged, ImportMode::Replace, &mut Job::detached()).unwrap_err();
+        assert_eq!(ids(&target), ["x"]);
+        assert_eq!(target.history().unwrap().len(), 1);
+        target.undo().unwrap();
+        assert_eq!(ids(&target), ["x", "y"]);
+        assert!(target.verify().unwrap().is_ok());
+        assert!(!tmp.path().join("target").join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn faces_survive_a_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = Library::open(tmp.path().join("source")).unwrap();
+        let card = test_card("owl");
+        source.add_card(card, b"front").unwrap();
+        let back = CardFace {
+            id: "owl-back".into(),
+            label: "Back".into(),
+            kind: CardKind::Image,
+            orig_ext: "png".into(),
+            mime: "image/png".into(),
+        };
+        source
+            .attach_face("owl", back.clone(), b"back", None)
+            .unwrap();
+        let archive = tmp.path().join("cards.zip");
+        export_archive(&source, &archive, &mut Job::detached()).unwrap();
+
+        let restored = Library::open(tmp.path().join("restored")).unwrap();
+        let report = import_archive(
+            &restored,
+            &archive,
+            ImportMode::Replace,
+            &mut Job::detached(),
+        )
+        .unwrap();
+        assert_eq!(report.added, ["owl"]);
+        assert_eq!(restored.get_card("owl").unwrap().unwrap().faces, [back]);
+        assert_eq!(restored.read_face_file("owl-back").unwrap(), b"back");
+    }
+}
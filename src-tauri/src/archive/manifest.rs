@@ -0,0 +1,281 @@
+//! `manifest.json`, the table of contents of a library archive.
+//!
+//! # Layout (schema version 3)
+//!
+//! ```text
+//! manifest.json     ArchiveManifest, see below
+//! files/<sha256>    one entry per distinct card or face file, named by its SHA-256
+//! ```
+//!
+//! `manifest.json` is a camelCase JSON object:
+//!
+//! | field               | meaning                                                       |
+//! |---------------------|---------------------------------------------------------------|
+//! | `app`               | always `"empire-card-collection"`                             |
+//! | `schemaVersion`     | [`ARCHIVE_SCHEMA_VERSION`]; readers refuse newer versions     |
+//! | `appVersion`        | version of the app that wrote the archive                     |
+//! | `createdAt`         | milliseconds since the Unix epoch                             |
+//! | `cards`             | `CardMeta` objects, each with a `file` entry (below) and, if it has `faces`, a `faceFiles` array |
+//! | `orderMap`          | per-collection card order, as in the JSON backup              |
+//! | `customCollections` | user-added collection names                                   |
+//! | `savedQueries`      | `{ name, query }` filter queries; absent in older archives    |
+//! | `smartCollections`  | `{ id, name, filter }` smart collections; absent in older archives |
+//! | `checklistMatches`  | card id to the checklist entry the user matched it to; absent in older archives |
+//!
+//! Each card's `file` is `{ path, sha256, size, mime, origExt }`, and `faceFiles` holds
+//! one such entry per face, in the order of its `faces`. Files with identical content
+//! point at the same `path`.
+//!
+//! # Version 2
+//!
+//! The same, without faces. Read as-is.
+//!
+//! # Version 1
+//!
+//! The original `exportArchiveAll` layout has no manifest: `metadata.json` holds a bare
+//! array of `CardMeta` and each file is stored as `files/<id>.bin`. `read_manifest`
+//! upgrades it on the fly, leaving `sha256` unset since v1 carried no checksums.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+use serde::{Deserialize, Serialize};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use super::extract::Budget;
+use super::{ArchiveError, Result};
+use crate::library::{CardMeta, OrderMap, SavedQuery, SmartCollection};
+
+pub const ARCHIVE_APP: &str = "empire-card-collection";
+pub const ARCHIVE_SCHEMA_VERSION: u32 = 3;
+
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+const V1_METADATA_ENTRY: &str = "metadata.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub app: String,
+    pub schema_version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    pub cards: Vec<ManifestCard>,
+    #[serde(default)]
+    pub order_map: OrderMap,
+    #[serde(default)]
+    pub custom_collections: Vec<String>,
+    #[serde(default)]
+    pub saved_queries: Vec<SavedQuery>,
+    #[serde(default)]
+    pub smart_collections: Vec<SmartCollection>,
+    #[serde(default)]
+    pub checklist_matches: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestCard {
+    #[serde(flatten)]
+    pub meta: CardMeta,
+    pub file: ManifestFile,
+    /// The files of `meta.faces`, in the same order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub face_files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestFile {
+    /// Entry name inside the zip.
+    pub path: String,
+    /// Hex SHA-256 of the file. Only missing in archives upgraded from version 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub mime: String,
+    #[serde(default)]
+    pub orig_ext: String,
+}
+
+pub(super) fn file_entry_name(sha256: &str) -> String {
+    format!("files/{sha256}")
+}
+
+/// Just enough of the manifest to decide how to parse the rest.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionProbe {
+    app: Option<String>,
+    schema_version: Option<u32>,
+}
+
+/// Reads the archive's manifest, upgrading older layouts to the current one.
+pub(super) fn read_manifest<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    budget: &mut Budget<'_>,
+) -> Result<ArchiveManifest> {
+    if !zip.file_names().any(|name| name == MANIFEST_ENTRY) {
+        return read_v1(zip, budget);
+    }
+    let bytes = budget.read(&mut zip.by_name(MANIFEST_ENTRY)?)?;
+
+    let probe: VersionProbe = serde_json::from_slice(&bytes).map_err(invalid_manifest)?;
+    if probe.app.as_deref() != Some(ARCHIVE_APP) {
+        return Err(ArchiveError::Invalid(format!(
+            "{MANIFEST_ENTRY} is not from Empire Card Collection"
+        )));
+    }
+    match probe.schema_version {
+        Some(2..=ARCHIVE_SCHEMA_VERSION) => {
+            serde_json::from_slice(&bytes).map_err(invalid_manifest)
+        }
+        Some(v) if v > ARCHIVE_SCHEMA_VERSION => Err(ArchiveError::Invalid(format!(
+            "archive schema version {v} is newer than this app supports \
+             ({ARCHIVE_SCHEMA_VERSION}); please update the app"
+        ))),
+        other => Err(ArchiveError::Invalid(format!(
+            "unknown archive schema version {other:?}"
+        ))),
+    }
+}
+
+fn invalid_manifest(e: serde_json::Error) -> ArchiveError {
+    ArchiveError::Invalid(format!("unreadable {MANIFEST_ENTRY}: {e}"))
+}
+
+/// `metadata.json` is a bare array of metas in v1 archives; we also accept the
+/// JSON-backup shape, which carries the order map and custom collections.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum V1Metadata {
+    Cards(Vec<CardMeta>),
+    Backup {
+        #[serde(default)]
+        metas: Vec<CardMeta>,
+        #[serde(default, rename = "orderMap")]
+        order_map: OrderMap,
+        #[serde(default, rename = "customCollections")]
+        custom_collections: Vec<String>,
+    },
+}
+
+fn read_v1<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    budget: &mut Budget<'_>,
+) -> Result<ArchiveManifest> {
+    let mut entry = match zip.by_name(V1_METADATA_ENTRY) {
+        Err(ZipError::FileNotFound) => {
+            return Err(ArchiveError::Invalid(format!(
+                "missing {MANIFEST_ENTRY} and {V1_METADATA_ENTRY}"
+            )));
+        }
+        other => other?,
+    };
+    let bytes = budget.read(&mut entry)?;
+    let metadata: V1Metadata = serde_json::from_slice(&bytes)
+        .map_err(|e| ArchiveError::Invalid(format!("unreadable {V1_METADATA_ENTRY}: {e}")))?;
+    Ok(upgrade_v1(metadata))
+}
+
+fn upgrade_v1(metadata: V1Metadata) -> ArchiveManifest {
+    let (metas, order_map, custom_collections) = match metadata {
+        V1Metadata::Cards(metas) => (metas, OrderMap::new(), Vec::new()),
+        V1Metadata::Backup {
+            metas,
+            order_map,
+            custom_collections,
+        } => (metas, order_map, custom_collections),
+    };
+    let cards = metas
+        .into_iter()
+        .map(|meta| ManifestCard {
+            file: ManifestFile {
+                path: format!("files/{}.bin", meta.id),
+                sha256: None,
+                size: 0,
+                mime: meta.mime.clone(),
+                orig_ext: meta.orig_ext.clone(),
+            },
+            face_files: Vec::new(),
+            meta,
+        })
+        .collect();
+    ArchiveManifest {
+        app: ARCHIVE_APP.to_string(),
+        schema_version: 1,
+        app_version: String::new(),
+        created_at: 0,
+        cards,
+        order_map,
+        custom_collections,
+        saved_queries: Vec::new(),
+        smart_collections: Vec::new(),
+        checklist_matches: BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+    use crate::archive::ExtractLimits;
+
+    fn zip_of(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut out = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            out.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            out.write_all(contents.as_bytes()).unwrap();
+        }
+        ZipArchive::new(out.finish().unwrap()).unwrap()
+    }
+
+    fn read(entries: &[(&str, &str)]) -> Result<ArchiveManifest> {
+        let mut zip = zip_of(entries);
+        let limits = ExtractLimits::default();
+        let mut budget = Budget::new(&zip, &limits)?;
+        read_manifest(&mut zip, &mut budget)
+    }
+
+    #[test]
+    fn version_1_archives_are_upgraded() {
+        let manifest = read(&[
+            (
+                "metadata.json",
+                r#"{
+                    "metas": [{ "id": "moth", "name": "Moth", "mime": "application/pdf" }],
+                    "orderMap": { "(none)": ["moth"] },
+                    "customCollections": ["Quiet Court"]
+                }"#,
+            ),
+            ("files/moth.bin", "%PDF"),
+        ])
+        .unwrap();
+        assert_eq!(manifest.schema_version, 1);
+        assert_eq!(manifest.cards.len(), 1);
+        let card = &manifest.cards[0];
+        assert_eq!(card.meta.name, "Moth");
+        assert_eq!(card.file.path, "files/moth.bin");
+        assert_eq!(card.file.mime, "application/pdf");
+        assert!(card.file.sha256.is_none());
+        assert_eq!(manifest.order_map["(none)"], ["moth"]);
+        assert_eq!(manifest.custom_collections, ["Quiet Court"]);
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let newer = format!(
+            r#"{{ "app": "{ARCHIVE_APP}", "schemaVersion": {}, "cards": [] }}"#,
+            ARCHIVE_SCHEMA_VERSION + 1
+        );
+        let err = read(&[(MANIFEST_ENTRY, &newer)]).unwrap_err();
+        assert!(
+            matches!(&err, ArchiveError::Invalid(reason) if reason.contains("newer")),
+            "{err}"
+        );
+    }
+}
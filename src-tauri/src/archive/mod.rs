@@ -0,0 +1,49 @@
+//! Library archives: exporting the [`Library`](crate::library::Library) to a zip and
+//! restoring it again. The format is documented in [`manifest`].
+//!
+//! Archives are traded between community members, so everything here treats them as
+//! untrusted input: see [`ExtractLimits`] and [`extract_to_dir`].
+
+mod export;
+mod extract;
+mod import;
+pub mod manifest;
+
+pub use export::{export_archive, ExportSummary};
+pub use extract::{extract_to_dir, ExtractLimits};
+pub use import::{import_archive, ConflictCard, ImportMode, ImportReport, SkipReason, SkippedCard};
+
+use crate::jobs::Cancelled;
+use crate::library::LibraryError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("invalid archive: {0}")]
+    Invalid(String),
+    #[error("archive entry {0:?} would be written outside the destination")]
+    UnsafePath(String),
+    #[error("archive entry {0:?} is a symlink")]
+    Symlink(String),
+    #[error("archive has more than {limit} entries")]
+    TooManyEntries { limit: usize },
+    #[error("archive expands to more than {limit} bytes")]
+    TooLarge { limit: u64 },
+    #[error("archive entry {name:?} is compressed more than {limit}:1")]
+    CompressionRatio { name: String, limit: u64 },
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl From<Cancelled> for ArchiveError {
+    fn from(_: Cancelled) -> Self {
+        ArchiveError::Cancelled
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ArchiveError>;
@@ -0,0 +1,454 @@
+//! Printable binder pages: cards laid out nine to a page, in a 3×3 grid of standard
+//! 63×88 mm pockets, on A4 or Letter. Each card is scaled to fill its pocket plus the
+//! bleed; PDF cards go in as vector forms copied from their first page, images as
+//! they are. Crop marks sit in the space around the grid, in line with every cut.
+
+use std::collections::HashSet;
+
+use pdfium_render::prelude::{
+    PdfColor, PdfDocument, PdfFontToken, PdfPage, PdfPageImageObject, PdfPageObjectsCommon,
+    PdfPagePaperSize, PdfPoints, PdfiumError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::jobs::{Cancelled, Job};
+use crate::library::{collection_key, CardKind, CardMeta, Library, LibraryError};
+use crate::render::{RenderError, Renderer};
+
+/// Pockets per row and per column.
+pub const GRID: usize = 3;
+
+/// A standard trading card, in millimetres.
+const CARD_MM: (f32, f32) = (63.0, 88.0);
+
+/// Height of the caption strip under each card, and the caption's font size, in
+/// points.
+const CAPTION_HEIGHT: f32 = 10.0;
+const CAPTION_FONT_SIZE: f32 = 7.0;
+
+/// Crop marks stop this far short of the bleed, and are at most this long, in mm.
+const CROP_MARK_GAP_MM: f32 = 1.0;
+const CROP_MARK_MM: f32 = 5.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BinderError {
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+    #[error(transparent)]
+    Render(#[from] RenderError),
+    #[error("could not write the PDF: {0}")]
+    Pdf(#[from] PdfiumError),
+    #[error("{0}")]
+    Layout(String),
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl From<Cancelled> for BinderError {
+    fn from(_: Cancelled) -> Self {
+        BinderError::Cancelled
+    }
+}
+
+pub type Result<T> = std::result::Result<T, BinderError>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Paper {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl Paper {
+    /// Width and height in points.
+    fn size(self) -> (f32, f32) {
+        match self {
+            Paper::A4 => (mm(210.0), mm(297.0)),
+            Paper::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BinderOptions {
+    pub paper: Paper,
+    /// How far each card's picture runs past where it's cut, in mm.
+    pub bleed_mm: f32,
+    /// The least space left blank at each edge of the paper, in mm.
+    pub margin_mm: f32,
+    pub crop_marks: bool,
+    /// Prints each card's name and tier under it.
+    pub captions: bool,
+}
+
+impl Default for BinderOptions {
+    fn default() -> Self {
+        Self {
+            paper: Paper::A4,
+            bleed_mm: 0.0,
+            margin_mm: 5.0,
+            crop_marks: true,
+            captions: false,
+        }
+    }
+}
+
+/// A rectangle on the page, in points from the bottom left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    left: f32,
+    bottom: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Rect {
+    fn right(&self) -> f32 {
+        self.left + self.width
+    }
+
+    fn top(&self) -> f32 {
+        self.bottom + self.height
+    }
+
+    fn grow(&self, by: f32) -> Rect {
+        Rect {
+            left: self.left - by,
+            bottom: self.bottom - by,
+            width: self.width + 2.0 * by,
+            height: self.height + 2.0 * by,
+        }
+    }
+}
+
+/// Where everything goes on a page; the same for every page.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    page: (f32, f32),
+    bleed: f32,
+    /// The trimmed card in each pocket, left to right then top to bottom.
+    cards: Vec<Rect>,
+    /// The pockets, bleed and caption included, as one block.
+    grid: Rect,
+}
+
+/// What [`export`] wrote.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinderSummary {
+    pub cards: usize,
+    pub pages: usize,
+}
+
+fn mm(value: f32) -> f32 {
+    value * 72.0 / 25.4
+}
+
+fn layout(options: &BinderOptions) -> Result<Layout> {
+    if !(0.0..=10.0).contains(&options.bleed_mm) || !(0.0..=50.0).contains(&options.margin_mm) {
+        return Err(BinderError::Layout(
+            "bleed must be 0–10 mm and margins 0–50 mm".into(),
+        ));
+    }
+    let page = options.paper.size();
+    let (card_width, card_height) = (mm(CARD_MM.0), mm(CARD_MM.1));
+    let bleed = mm(options.bleed_mm);
+    let caption = if options.captions {
+        CAPTION_HEIGHT
+    } else {
+        0.0
+    };
+    let cell = (
+        card_width + 2.0 * bleed,
+        card_height + 2.0 * bleed + caption,
+    );
+    let size = (cell.0 * GRID as f32, cell.1 * GRID as f32);
+    let margin = mm(options.margin_mm);
+    if size.0 > page.0 - 2.0 * margin || size.1 > page.1 - 2.0 * margin {
+        return Err(BinderError::Layout(format!(
+            "nine cards don't fit on {} paper with {} mm margins and {} mm bleed",
+            match options.paper {
+                Paper::A4 => "A4",
+                Paper::Letter => "Letter",
+            },
+            options.margin_mm,
+            options.bleed_mm
+        )));
+    }
+    let grid = Rect {
+        left: (page.0 - size.0) / 2.0,
+        bottom: (page.1 - size.1) / 2.0,
+        width: size.0,
+        height: size.1,
+    };
+    let cards = (0..GRID * GRID)
+        .map(|i| {
+            let (column, row) = ((i % GRID) as f32, (i / GRID) as f32);
+            Rect {
+                left: grid.left + column * cell.0 + bleed,
+                bottom: grid.top() - (row + 1.0) * cell.1 + caption + bleed,
+                width: card_width,
+                height: card_height,
+            }
+        })
+        .collect();
+    Ok(Layout {
+        page,
+        bleed,
+        cards,
+        grid,
+    })
+}
+
+/// The cards of `collection` in the order the user arranged them, then any they
+/// haven't placed yet oldest first, like `orderItemsInGroup` in App.jsx.
+pub fn collection_cards(
+    library: &Library,
+    collection: &str,
+) -> std::result::Result<Vec<CardMeta>, LibraryError> {
+    let key = collection_key(collection);
+    let mut cards: Vec<CardMeta> = library
+        .list_cards()?
+        .into_iter()
+        .filter(|card| collection_key(&card.collection) == key)
+        .collect();
+    let order = library.order_map()?.remove(&key).unwrap_or_default();
+    let mut ordered = Vec::with_capacity(cards.len());
+    for id in order {
+        if let Some(at) = cards.iter().position(|card| card.id == id) {
+            ordered.push(cards.remove(at));
+        }
+    }
+    // `list_cards` is already oldest first
+    ordered.extend(cards);
+    Ok(ordered)
+}
+
+/// The cards with these ids, in that order, each once.
+pub fn cards(
+    library: &Library,
+    ids: &[String],
+) -> std::result::Result<Vec<CardMeta>, LibraryError> {
+    let mut seen = HashSet::new();
+    ids.iter()
+        .filter(|id| seen.insert(id.as_str()))
+        .map(|id| {
+            library
+                .get_card(id)?
+                .ok_or_else(|| LibraryError::NotFound(id.clone()))
+        })
+        .collect()
+}
+
+/// Lays `cards` out in that order and returns the PDF. Each card is a step of `job`;
+/// cancelling stops between cards.
+pub fn export(
+    library: &Library,
+    renderer: &Renderer,
+    cards: &[CardMeta],
+    options: &BinderOptions,
+    job: &mut Job<'_>,
+) -> Result<(Vec<u8>, BinderSummary)> {
+    let layout = layout(options)?;
+    if cards.is_empty() {
+        return Err(BinderError::Layout("there are no cards to print".into()));
+    }
+    job.set_totals(cards.len(), 0);
+
+    let pdfium = renderer.pdfium()?;
+    let mut document = pdfium.create_new_pdf()?;
+    let font = document.fonts_mut().helvetica();
+    let paper =
+        PdfPagePaperSize::new_custom(PdfPoints::new(layout.page.0), PdfPoints::new(layout.page.1));
+    for sheet in cards.chunks(GRID * GRID) {
+        let mut page = document.pages_mut().create_page_at_end(paper)?;
+        for (card, trim) in sheet.iter().zip(&layout.cards) {
+            job.start_entry(&card.name)?;
+            let bytes = library.read_card_file(&card.id)?;
+            place_card(
+                &mut document,
+                &mut page,
+                renderer,
+                card.kind,
+                bytes,
+                trim.grow(layout.bleed),
+            )?;
+            if options.captions {
+                caption(&mut page, font, card, trim, layout.bleed)?;
+            }
+            job.finish_entry(0);
+        }
+        if options.crop_marks {
+            crop_marks(&mut page, &layout)?;
+        }
+    }
+    let bytes = document.save_to_bytes()?;
+    let summary = BinderSummary {
+        cards: cards.len(),
+        pages: cards.len().div_ceil(GRID * GRID),
+    };
+    Ok((bytes, summary))
+}
+
+/// Scales the card to fill `area` without distorting it, centred.
+fn place_card<'a>(
+    document: &mut PdfDocument<'a>,
+    page: &mut PdfPage<'a>,
+    renderer: &'a Renderer,
+    kind: CardKind,
+    bytes: Vec<u8>,
+    area: Rect,
+) -> Result<()> {
+    let fit = |width: f32, height: f32| {
+        let scale = (area.width / width).min(area.height / height);
+        let (width, height) = (width * scale, height * scale);
+        (
+            scale,
+            area.left + (area.width - width) / 2.0,
+            area.bottom + (area.height - height) / 2.0,
+        )
+    };
+    match kind {
+        CardKind::Pdf => {
+            let source = renderer.pdfium()?.load_pdf_from_byte_vec(bytes, None)?;
+            let first = source.pages().get(0)?;
+            let (scale, x, y) = fit(first.width().value, first.height().value);
+            let mut form = first.objects().copy_into_x_object_form_object(document)?;
+            form.scale(scale, scale)?;
+            form.translate(PdfPoints::new(x), PdfPoints::new(y))?;
+            page.objects_mut().add_object(form)?;
+        }
+        CardKind::Gif | CardKind::Image => {
+            let image = renderer.render(kind, &bytes, u32::MAX)?;
+            let (scale, x, y) = fit(image.width() as f32, image.height() as f32);
+            let mut object = PdfPageImageObject::new_with_size(
+                document,
+                &image,
+                PdfPoints::new(image.width() as f32 * scale),
+                PdfPoints::new(image.height() as f32 * scale),
+            )?;
+            object.translate(PdfPoints::new(x), PdfPoints::new(y))?;
+            page.objects_mut().add_image_object(object)?;
+        }
+    }
+    Ok(())
+}
+
+fn caption(
+    page: &mut PdfPage<'_>,
+    font: PdfFontToken,
+    card: &CardMeta,
+    trim: &Rect,
+    bleed: f32,
+) -> Result<()> {
+    let text = match card.tier.trim() {
+        "" => card.name.trim().to_string(),
+        tier => format!("{} - {tier}", card.name.trim()),
+    };
+    let text = fit_text(&text, trim.width, CAPTION_FONT_SIZE);
+    page.objects_mut().create_text_object(
+        PdfPoints::new(trim.left),
+        PdfPoints::new(trim.bottom - bleed - CAPTION_HEIGHT + 2.5),
+        text,
+        font,
+        PdfPoints::new(CAPTION_FONT_SIZE),
+    )?;
+    Ok(())
+}
+
+/// Shortens `text` with an ellipsis to about `width` points at `size`; Helvetica
+/// averages about half an em per character.
+pub(crate) fn fit_text(text: &str, width: f32, size: f32) -> String {
+    let fits = (width / (size * 0.5)) as usize;
+    if text.chars().count() <= fits {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(fits.saturating_sub(3)).collect();
+    format!("{}...", cut.trim_end())
+}
+
+/// Short hairlines outside the grid at every card edge.
+fn crop_marks(page: &mut PdfPage<'_>, layout: &Layout) -> Result<()> {
+    let gap = mm(CROP_MARK_GAP_MM);
+    let horizontal = (layout.page.0 - layout.grid.width) / 2.0 - gap;
+    let vertical = (layout.page.1 - layout.grid.height) / 2.0 - gap;
+    let length = (
+        mm(CROP_MARK_MM).min(horizontal),
+        mm(CROP_MARK_MM).min(vertical),
+    );
+    let mut xs: Vec<f32> = layout
+        .cards
+        .iter()
+        .flat_map(|c| [c.left, c.right()])
+        .collect();
+    let mut ys: Vec<f32> = layout
+        .cards
+        .iter()
+        .flat_map(|c| [c.bottom, c.top()])
+        .collect();
+    for values in [&mut xs, &mut ys] {
+        values.sort_by(f32::total_cmp);
+        values.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+    }
+    let grid = layout.grid;
+    let mut line = |x1: f32, y1: f32, x2: f32, y2: f32| {
+        page.objects_mut().create_path_object_line(
+            PdfPoints::new(x1),
+            PdfPoints::new(y1),
+            PdfPoints::new(x2),
+            PdfPoints::new(y2),
+            PdfColor::BLACK,
+            PdfPoints::new(0.25),
+        )
+    };
+    if length.1 > 0.0 {
+        for &x in &xs {
+            line(x, grid.top() + gap, x, grid.top() + gap + length.1)?;
+            line(x, grid.bottom - gap, x, grid.bottom - gap - length.1)?;
+        }
+    }
+    if length.0 > 0.0 {
+        for &y in &ys {
+            line(grid.left - gap, y, grid.left - gap - length.0, y)?;
+            line(grid.right() + gap, y, grid.right() + gap + length.0, y)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_nine_pockets_or_says_why_not() {
+        let options = BinderOptions {
+            bleed_mm: 1.0,
+            captions: true,
+            ..BinderOptions::default()
+        };
+        let layout = layout(&options).unwrap();
+        assert_eq!(layout.cards.len(), GRID * GRID);
+        // Centred on the page, first pocket top left, captions under the cards
+        assert!((layout.grid.left + layout.grid.right() - layout.page.0).abs() < 0.01);
+        let (first, last) = (layout.cards[0], layout.cards[8]);
+        assert!(first.left < last.left && first.bottom > last.bottom);
+        assert!((first.top() + layout.bleed - layout.grid.top()).abs() < 0.01);
+        assert!((last.bottom - layout.bleed - CAPTION_HEIGHT - layout.grid.bottom).abs() < 0.01);
+        // Neighbours are a bleed apart either side
+        let gap = layout.cards[1].left - first.right();
+        assert!((gap - 2.0 * layout.bleed).abs() < 0.01);
+
+        // Letter is shorter than A4
+        let too_tall = BinderOptions {
+            paper: Paper::Letter,
+            ..options
+        };
+        assert_eq!(
+            super::layout(&too_tall).unwrap_err().to_string(),
+            "nine cards don't fit on Letter paper with 5 mm margins and 1 mm bleed"
+        );
+    }
+}
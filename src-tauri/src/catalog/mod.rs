@@ -0,0 +1,359 @@
+//! The catalog of tiers and collections: which tiers exist and in what order, which
+//! collections the app suggests, and the tier and NSFW flag a card gets when it's
+//! put in one of them. It lives in `catalog.json` in the app data dir so a new set of
+//! cards doesn't need a new release; until that file exists the catalog bundled with
+//! the app (`src-tauri/catalog.json`, also read by App.jsx) is used. Signed updates
+//! can be merged in from the web; see [`remote`].
+//!
+//! A collection may also list the cards it's made of, its checklist; see
+//! [`crate::checklist`] for how owned cards are matched against it.
+
+pub mod remote;
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::library::{collection_key, write_atomic};
+
+/// Catalog file format version. Bump when a change would be misread by older apps.
+pub const CATALOG_VERSION: u32 = 1;
+
+/// Name of the catalog file in the app data dir.
+pub const CATALOG_FILE: &str = "catalog.json";
+
+const BUILTIN: &str = include_str!("../../catalog.json");
+
+#[derive(Debug, thiserror::Error)]
+pub enum CatalogError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid catalog: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("catalog version {0} is newer than this app understands")]
+    UnsupportedVersion(u32),
+    #[error("invalid catalog: {0}")]
+    Invalid(String),
+    #[error("could not download the catalog: {0}")]
+    Fetch(String),
+    #[error("the catalog's signature doesn't check out: {0}")]
+    Signature(String),
+    #[error("there's no catalog update to apply")]
+    NoUpdate,
+    #[error("the catalog update is older than the one already applied")]
+    Outdated,
+}
+
+pub type Result<T> = std::result::Result<T, CatalogError>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Catalog {
+    pub version: u32,
+    /// Tiers from lowest to highest.
+    pub tiers: Vec<String>,
+    pub collections: Vec<CatalogCollection>,
+    /// Where to check for catalog updates, instead of the app's default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_url: Option<String>,
+    /// When the last update merged in was signed, in seconds since the epoch. The
+    /// store keeps it: edits and imported files don't change it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_update: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogCollection {
+    pub name: String,
+    /// Tier suggested for cards put in this collection; empty for none.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub default_tier: String,
+    /// Whether cards put in this collection are marked NSFW.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub nsfw: bool,
+    /// Every card in the collection, for completion tracking; empty if unknown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<ChecklistEntry>,
+}
+
+/// One card a collection is expected to contain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistEntry {
+    /// The card's number within its collection, e.g. `"012"`; may be empty.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub number: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tier: String,
+    /// Distinguishes printings of the same card, e.g. `"Holo"`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub variant: String,
+}
+
+impl ChecklistEntry {
+    /// Identifies the entry within its collection: the number if it has one, else the
+    /// name and variant.
+    pub fn key(&self) -> String {
+        if self.number.is_empty() {
+            format!("{} {}", self.name, self.variant)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        } else {
+            format!("#{}", self.number.to_lowercase())
+        }
+    }
+}
+
+impl Catalog {
+    /// The catalog shipped with the app.
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN.as_bytes()).expect("the bundled catalog.json is valid")
+    }
+
+    /// Reads and checks a catalog file, e.g. one shared by another user.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut catalog: Catalog = serde_json::from_slice(bytes)?;
+        catalog.check()?;
+        Ok(catalog)
+    }
+
+    /// Loads `path`, or the built-in catalog if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => Self::parse(&bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::builtin()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Trims names and rejects catalogs the app can't use: an unknown version, no
+    /// tiers, duplicate tiers, collections or checklist entries, or a tier that isn't
+    /// one.
+    pub fn check(&mut self) -> Result<()> {
+        if self.version > CATALOG_VERSION {
+            return Err(CatalogError::UnsupportedVersion(self.version));
+        }
+        let invalid = |reason: String| Err(CatalogError::Invalid(reason));
+        if self.tiers.is_empty() {
+            return invalid("it has no tiers".into());
+        }
+        let mut tiers = HashSet::new();
+        for tier in &mut self.tiers {
+            *tier = tier.trim().to_string();
+            if tier.is_empty() {
+                return invalid("a tier has no name".into());
+            }
+            if !tiers.insert(tier.to_lowercase()) {
+                return invalid(format!("tier `{tier}` is listed twice"));
+            }
+        }
+        let mut seen = HashSet::new();
+        for collection in &mut self.collections {
+            collection.name = collection.name.trim().to_string();
+            collection.default_tier = collection.default_tier.trim().to_string();
+            if collection.name.is_empty() {
+                return invalid("a collection has no name".into());
+            }
+            if !seen.insert(collection_key(&collection.name)) {
+                return invalid(format!("collection `{}` is listed twice", collection.name));
+            }
+            if !collection.default_tier.is_empty()
+                && !tiers.contains(&collection.default_tier.to_lowercase())
+            {
+                return invalid(format!(
+                    "collection `{}` defaults to unknown tier `{}`",
+                    collection.name, collection.default_tier
+                ));
+            }
+            let mut keys = HashSet::new();
+            for entry in &mut collection.checklist {
+                for field in [
+                    &mut entry.number,
+                    &mut entry.name,
+                    &mut entry.tier,
+                    &mut entry.variant,
+                ] {
+                    *field = field.trim().to_string();
+                }
+                if entry.name.is_empty() {
+                    return invalid(format!(
+                        "a card in the `{}` checklist has no name",
+                        collection.name
+                    ));
+                }
+                if !keys.insert(entry.key()) {
+                    return invalid(format!(
+                        "`{}` is listed twice in the `{}` checklist",
+                        entry.name, collection.name
+                    ));
+                }
+                if !entry.tier.is_empty() && !tiers.contains(&entry.tier.to_lowercase()) {
+                    return invalid(format!(
+                        "`{}` in the `{}` checklist has unknown tier `{}`",
+                        entry.name, collection.name, entry.tier
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Position of a tier in tier order, ignoring case; `None` for no tier or an
+    /// unknown one.
+    pub fn tier_rank(&self, tier: &str) -> Option<usize> {
+        self.tiers
+            .iter()
+            .position(|t| t.eq_ignore_ascii_case(tier.trim()))
+    }
+
+    /// The collection of that name (case and spacing don't matter).
+    pub fn collection(&self, name: &str) -> Option<&CatalogCollection> {
+        let key = collection_key(name);
+        self.collections
+            .iter()
+            .find(|c| collection_key(&c.name) == key)
+    }
+
+    /// The tier suggested for cards put in `collection`, or `""`.
+    pub fn default_tier(&self, collection: &str) -> &str {
+        self.collection(collection)
+            .map_or("", |c| c.default_tier.as_str())
+    }
+
+    /// Whether cards put in `collection` are marked NSFW.
+    pub fn default_nsfw(&self, collection: &str) -> bool {
+        self.collection(collection).is_some_and(|c| c.nsfw)
+    }
+}
+
+/// The catalog in use, kept in step with `catalog.json`.
+pub struct CatalogStore {
+    path: PathBuf,
+    catalog: RwLock<Catalog>,
+    /// A verified update waiting for the user's approval.
+    pending: Mutex<Option<remote::CatalogUpdate>>,
+}
+
+impl CatalogStore {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        Ok(Self {
+            catalog: RwLock::new(Catalog::load(&path)?),
+            path,
+            pending: Mutex::new(None),
+        })
+    }
+
+    pub fn catalog(&self) -> Catalog {
+        self.catalog
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Checks and saves a new catalog, returning it as stored.
+    pub fn set_catalog(&self, mut catalog: Catalog) -> Result<Catalog> {
+        catalog.last_update = self.catalog().last_update;
+        self.save(catalog)
+    }
+
+    fn save(&self, mut catalog: Catalog) -> Result<Catalog> {
+        catalog.check()?;
+        catalog.version = CATALOG_VERSION;
+        write_atomic(&self.path, &serde_json::to_vec_pretty(&catalog)?)?;
+        *self.catalog.write().unwrap_or_else(|e| e.into_inner()) = catalog.clone();
+        Ok(catalog)
+    }
+
+    /// Holds a verified update and returns what merging it would change, or `None`
+    /// (dropping it) if it wouldn't change anything or is the one last applied. One
+    /// signed before that is refused.
+    pub fn offer_update(
+        &self,
+        update: remote::CatalogUpdate,
+    ) -> Result<Option<remote::CatalogDiff>> {
+        let current = self.catalog();
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        *pending = None;
+        match current.last_update {
+            Some(last) if update.signed_at < last => return Err(CatalogError::Outdated),
+            Some(last) if update.signed_at == last => return Ok(None),
+            _ => {}
+        }
+        let diff = remote::diff(&current, &remote::merge(&current, &update.catalog));
+        *pending = (!diff.is_empty()).then_some(update);
+        Ok(pending.is_some().then_some(diff))
+    }
+
+    /// Merges the update held by [`offer_update`](Self::offer_update) into the
+    /// catalog, as it is now, and saves the result.
+    pub fn apply_update(&self) -> Result<Catalog> {
+        let update = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or(CatalogError::NoUpdate)?;
+        let mut catalog = remote::merge(&self.catalog(), &update.catalog);
+        catalog.last_update = Some(update.signed_at);
+        self.save(catalog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_catalog_answers_lookups() {
+        let catalog = Catalog::builtin();
+        assert_eq!(catalog.tier_rank("dawn"), Some(0));
+        assert_eq!(catalog.tier_rank("Immortal"), Some(catalog.tiers.len() - 1));
+        assert_eq!(catalog.tier_rank(""), None);
+        assert_eq!(catalog.default_tier("  celestial   oath "), "Ascendant");
+        assert_eq!(catalog.default_tier("Hairpins"), "");
+        assert!(catalog.default_nsfw("Obsidian Veil"));
+        assert!(!catalog.default_nsfw("Aurora Seal"));
+    }
+
+    #[test]
+    fn rejects_catalogs_the_app_cannot_use() {
+        let error = |json: &str| Catalog::parse(json.as_bytes()).unwrap_err().to_string();
+        assert_eq!(
+            error(r#"{"version":2,"tiers":["Dawn"],"collections":[]}"#),
+            "catalog version 2 is newer than this app understands"
+        );
+        assert_eq!(
+            error(r#"{"version":1,"tiers":["Dawn"," dawn"],"collections":[]}"#),
+            "invalid catalog: tier `dawn` is listed twice"
+        );
+        assert_eq!(
+            error(
+                r#"{"version":1,"tiers":["Dawn"],"collections":[{"name":"Quiet Court","defaultTier":"Lotus"}]}"#
+            ),
+            "invalid catalog: collection `Quiet Court` defaults to unknown tier `Lotus`"
+        );
+        assert!(error(r#"{"tiers":["Dawn"],"collections":[]}"#).starts_with("invalid catalog"));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let store = CatalogStore::open(tmp.path().join(CATALOG_FILE)).unwrap();
+        assert_eq!(store.catalog(), Catalog::builtin());
+        let mut catalog = store.catalog();
+        catalog.collections.push(CatalogCollection {
+            name: " Aurora  seal ".into(),
+            default_tier: String::new(),
+            nsfw: false,
+            checklist: Vec::new(),
+        });
+        assert!(store.set_catalog(catalog).is_err());
+        assert_eq!(store.catalog(), Catalog::builtin());
+    }
+}
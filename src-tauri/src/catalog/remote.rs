@@ -0,0 +1,323 @@
+//! Catalog updates published alongside app releases. The document at the update URL
+//! is a full catalog, signed with minisign like the updater's artifacts: the
+//! signature sits next to it at `<url>.sig`, both it and the public key are base64
+//! of the minisign text, and nothing is used unless it verifies.
+//!
+//! Signatures carry the time they were made in their trusted comment, as the tauri
+//! signer writes them. The store remembers that of the last update merged in and
+//! refuses older ones, so a stale catalog, however well signed, can't roll it back.
+//!
+//! An update only ever adds: new tiers, new collections, and new defaults and
+//! checklists for collections the user already has. Anything the user added locally
+//! stays.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+use super::{Catalog, CatalogCollection, CatalogError, Result};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where updates come from: `plugins.catalog` in `tauri.conf.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateSource {
+    /// URL of the signed catalog, unless the catalog names its own `updateUrl`.
+    pub endpoint: String,
+    /// Base64 minisign public key the catalog must be signed with.
+    pub pubkey: String,
+}
+
+/// A verified catalog from the update URL.
+#[derive(Debug, Clone)]
+pub struct CatalogUpdate {
+    pub catalog: Catalog,
+    /// When it was signed, in seconds since the epoch.
+    pub signed_at: u64,
+}
+
+/// What merging an update would change, for the user to approve.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogDiff {
+    pub added_tiers: Vec<String>,
+    /// Whether tiers the user already has would be ranked differently.
+    pub reordered_tiers: bool,
+    pub added_collections: Vec<CatalogCollection>,
+    pub changed_collections: Vec<CollectionChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionChange {
+    pub before: CatalogCollection,
+    pub after: CatalogCollection,
+}
+
+impl CatalogDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_tiers.is_empty()
+            && !self.reordered_tiers
+            && self.added_collections.is_empty()
+            && self.changed_collections.is_empty()
+    }
+}
+
+/// Downloads the catalog at `url` and its signature, and checks one against the other.
+pub fn fetch(url: &str, pubkey: &str) -> Result<CatalogUpdate> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(fetch_error)?;
+    let get = |url: &str| -> Result<Vec<u8>> {
+        let response = client
+            .get(url)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .map_err(fetch_error)?;
+        Ok(response.bytes().map_err(fetch_error)?.to_vec())
+    };
+    let bytes = get(url)?;
+    let signature = get(&format!("{url}.sig"))?;
+    let signed_at = verify(&bytes, &String::from_utf8_lossy(&signature), pubkey)?;
+    Ok(CatalogUpdate {
+        catalog: Catalog::parse(&bytes)?,
+        signed_at,
+    })
+}
+
+fn fetch_error(e: reqwest::Error) -> CatalogError {
+    CatalogError::Fetch(e.without_url().to_string())
+}
+
+/// Checks `data` against a base64 minisign signature and public key, the way the
+/// updater checks its downloads. Returns the `timestamp:` from the signature's
+/// trusted comment.
+pub fn verify(data: &[u8], signature: &str, pubkey: &str) -> Result<u64> {
+    let decode = |what: &str, text: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(text.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| CatalogError::Signature(format!("the {what} isn't valid base64")))
+    };
+    let bad = |e: minisign_verify::Error| CatalogError::Signature(e.to_string());
+    let pubkey = PublicKey::decode(&decode("public key", pubkey)?).map_err(bad)?;
+    let signature = Signature::decode(&decode("signature", signature)?).map_err(bad)?;
+    pubkey.verify(data, &signature, true).map_err(bad)?;
+    signature
+        .trusted_comment()
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("timestamp:"))
+        .and_then(|time| time.parse().ok())
+        .ok_or_else(|| CatalogError::Signature("it doesn't say when it was made".into()))
+}
+
+/// `local` with `update` merged in. The update's tiers come first in its order,
+/// followed by any tiers only the user has; collections keep the user's order with
+/// new ones at the end.
+pub fn merge(local: &Catalog, update: &Catalog) -> Catalog {
+    let update_tiers: HashSet<String> = update.tiers.iter().map(|t| t.to_lowercase()).collect();
+    let mut tiers = update.tiers.clone();
+    tiers.extend(
+        local
+            .tiers
+            .iter()
+            .filter(|t| !update_tiers.contains(&t.to_lowercase()))
+            .cloned(),
+    );
+
+    let mut collections: Vec<CatalogCollection> = local
+        .collections
+        .iter()
+        .map(|c| match update.collection(&c.name) {
+            // An update without a checklist doesn't take away the user's
+            Some(new) if new.checklist.is_empty() => CatalogCollection {
+                checklist: c.checklist.clone(),
+                ..new.clone()
+            },
+            Some(new) => new.clone(),
+            None => c.clone(),
+        })
+        .collect();
+    collections.extend(
+        update
+            .collections
+            .iter()
+            .filter(|c| local.collection(&c.name).is_none())
+            .cloned(),
+    );
+
+    Catalog {
+        tiers,
+        collections,
+        ..local.clone()
+    }
+}
+
+/// What changes between `before` and the merged catalog `after`.
+pub fn diff(before: &Catalog, after: &Catalog) -> CatalogDiff {
+    let added_tiers: Vec<String> = after
+        .tiers
+        .iter()
+        .filter(|t| before.tier_rank(t).is_none())
+        .cloned()
+        .collect();
+    let kept: Vec<String> = after
+        .tiers
+        .iter()
+        .filter(|t| before.tier_rank(t).is_some())
+        .map(|t| t.to_lowercase())
+        .collect();
+    let before_order: Vec<String> = before.tiers.iter().map(|t| t.to_lowercase()).collect();
+
+    let mut diff = CatalogDiff {
+        added_tiers,
+        reordered_tiers: kept != before_order,
+        ..CatalogDiff::default()
+    };
+    for collection in &after.collections {
+        match before.collection(&collection.name) {
+            None => diff.added_collections.push(collection.clone()),
+            Some(old)
+                if old.nsfw != collection.nsfw
+                    || old.name != collection.name
+                    || !old
+                        .default_tier
+                        .eq_ignore_ascii_case(&collection.default_tier)
+                    || old.checklist != collection.checklist =>
+            {
+                diff.changed_collections.push(CollectionChange {
+                    before: old.clone(),
+                    after: collection.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::catalog::{CatalogStore, CATALOG_FILE};
+
+    /// A throwaway key, used only to sign [`UPDATE`].
+    const PUBKEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXk6IDg4RkE2RDI4QTBGNzhEODEKUldTQmpmZWdLRzM2aUUwK2FqMTRlcjljRHUrdEJDbHhkbnF6SS9TUHBlYTRHUit3ZjhSL0NNamkK";
+    const UPDATE: &str = concat!(
+        r#"{"version":1,"tiers":["Dawn","Lotus","Jade","Radiant"],"collections":[{"name":"Quiet Court","defaultTier":"Jade"},{"name":"Sunrise Tokens","defaultTier":"Radiant","nsfw":true}]}"#,
+        "\n"
+    );
+    const SIGNATURE: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIHRhdXJpIHNlY3JldCBrZXkKUlVTQmpmZWdLRzM2aUxITXBtWkFEUTdQdmZuc0Vyb1lYL2RuTlJOaWE1ejFCTGIzbFcrdTlVWCtsdE1udVlvaUNwWXRkQ1pHek42WUw2YTRMU0hmUlpKYWZGeWk0Q05pMkFJPQp0cnVzdGVkIGNvbW1lbnQ6IHRpbWVzdGFtcDoxNzYwNzQ1NjAwCWZpbGU6Y2F0YWxvZy5qc29uCjBOdHVHMDcyblhXeVNydEVBanJjcjR3ZVdxcSs4RWR2WnhtVGJ3Rm92blJDWklYY20zamw3U2tLUDRPNm5BWWszTGJoa29JNEl2c0U5U25SU1V5TEFRPT0K";
+
+    /// Serves `catalog` at `/catalog.json` and `signature` next to it, then returns
+    /// the catalog's URL.
+    fn serve(catalog: &'static str, signature: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/catalog.json", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request).unwrap();
+                let body = if request.contains(".sig ") {
+                    signature
+                } else {
+                    catalog
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    fn catalog(json: &str) -> Catalog {
+        Catalog::parse(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn fetches_only_signed_catalogs() {
+        let update = fetch(&serve(UPDATE, SIGNATURE), PUBKEY).unwrap();
+        assert_eq!(
+            update.catalog.tiers.last().map(String::as_str),
+            Some("Radiant")
+        );
+        assert_eq!(update.signed_at, 1760745600);
+
+        let tampered: &'static str = UPDATE.replace("\"nsfw\":true", "\"nsfw\":false").leak();
+        assert!(matches!(
+            fetch(&serve(tampered, SIGNATURE), PUBKEY),
+            Err(CatalogError::Signature(_))
+        ));
+        assert!(matches!(
+            fetch(&serve(UPDATE, "not a signature"), PUBKEY),
+            Err(CatalogError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn merging_adds_without_dropping_local_entries() {
+        let local = catalog(
+            r#"{"version":1,"tiers":["Dawn","Jade","Homebrew"],"collections":[
+                {"name":"My Binder"},{"name":"quiet  court","defaultTier":"Dawn"}]}"#,
+        );
+        let merged = merge(&local, &catalog(UPDATE));
+        assert_eq!(
+            merged.tiers,
+            ["Dawn", "Lotus", "Jade", "Radiant", "Homebrew"]
+        );
+        let names: Vec<&str> = merged.collections.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["My Binder", "Quiet Court", "Sunrise Tokens"]);
+        assert_eq!(merged.default_tier("Quiet Court"), "Jade");
+
+        let diff = diff(&local, &merged);
+        assert_eq!(diff.added_tiers, ["Lotus", "Radiant"]);
+        assert!(!diff.reordered_tiers);
+        assert_eq!(diff.added_collections.len(), 1);
+        assert_eq!(diff.changed_collections[0].before.default_tier, "Dawn");
+        assert!(super::diff(&merged, &merge(&merged, &catalog(UPDATE))).is_empty());
+    }
+
+    #[test]
+    fn updates_no_newer_than_the_last_one_applied_are_not_offered() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(CATALOG_FILE);
+        let store = CatalogStore::open(&path).unwrap();
+        let update = |signed_at| CatalogUpdate {
+            catalog: catalog(UPDATE),
+            signed_at,
+        };
+        assert!(store.offer_update(update(1000)).unwrap().is_some());
+        let applied = store.apply_update().unwrap();
+        assert_eq!(applied.last_update, Some(1000));
+
+        // Edits and imports don't reset it, and it's saved with the catalog
+        store
+            .set_catalog(Catalog {
+                last_update: None,
+                ..applied
+            })
+            .unwrap();
+        let store = CatalogStore::open(&path).unwrap();
+        assert_eq!(store.catalog().last_update, Some(1000));
+
+        // The same update again would bring back what the user removed since
+        assert_eq!(store.offer_update(update(1000)).unwrap(), None);
+        assert!(matches!(
+            store.offer_update(update(999)),
+            Err(CatalogError::Outdated)
+        ));
+        assert!(matches!(store.apply_update(), Err(CatalogError::NoUpdate)));
+    }
+}
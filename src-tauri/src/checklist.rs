@@ -0,0 +1,291 @@
+//! Completion tracking: owned cards matched against the checklists in the catalog.
+//!
+//! A card only counts towards the collection it's in. The user's own match wins;
+//! otherwise a card matches the entry whose name (plus variant, from the name or a
+//! tag) is its name, with the entry's number allowed anywhere in it, or failing that
+//! the one entry whose number appears in its name. Cards that match nothing, or two
+//! entries equally well, are left for the user to match by hand.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::catalog::{Catalog, CatalogCollection, ChecklistEntry};
+use crate::library::{collection_key, CardMeta, Library, LibraryError};
+
+/// Completion of the collections that have a checklist, and of all of them together.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistReport {
+    pub collections: Vec<CollectionProgress>,
+    #[serde(flatten)]
+    pub totals: Progress,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    /// Entries in the checklists.
+    pub expected: usize,
+    /// Entries with at least one card.
+    pub owned: usize,
+    /// Entries with no card.
+    pub missing: usize,
+    /// Cards beyond the first for an entry.
+    pub duplicates: usize,
+    /// `owned` out of `expected`, from 0 to 100.
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionProgress {
+    /// The collection's name as the catalog spells it.
+    pub collection: String,
+    #[serde(flatten)]
+    pub progress: Progress,
+    /// Every entry in checklist order.
+    pub entries: Vec<EntryStatus>,
+    /// Cards in the collection that match no entry.
+    pub unmatched: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryStatus {
+    #[serde(flatten)]
+    pub entry: ChecklistEntry,
+    /// What [`Library::set_checklist_match`] takes to match a card to this entry.
+    pub key: String,
+    /// Cards matched to the entry, in library order.
+    pub card_ids: Vec<String>,
+    /// Those of `card_ids` the user matched by hand.
+    pub manual: Vec<String>,
+}
+
+impl Progress {
+    fn add(&mut self, other: &Progress) {
+        self.expected += other.expected;
+        self.owned += other.owned;
+        self.missing += other.missing;
+        self.duplicates += other.duplicates;
+        self.percent = percent(self.owned, self.expected);
+    }
+}
+
+fn percent(owned: usize, expected: usize) -> f64 {
+    if expected == 0 {
+        0.0
+    } else {
+        owned as f64 * 100.0 / expected as f64
+    }
+}
+
+/// Matches the library against every checklist in `catalog`, or only `collection`'s.
+pub fn report(
+    library: &Library,
+    catalog: &Catalog,
+    collection: Option<&str>,
+) -> Result<ChecklistReport, LibraryError> {
+    let cards = library.list_cards()?;
+    let manual = library.checklist_matches()?;
+    let wanted = collection.map(collection_key);
+    let mut report = ChecklistReport::default();
+    for checklist in catalog.collections.iter().filter(|c| {
+        !c.checklist.is_empty()
+            && wanted
+                .as_ref()
+                .is_none_or(|key| *key == collection_key(&c.name))
+    }) {
+        let key = collection_key(&checklist.name);
+        let members = cards
+            .iter()
+            .filter(|card| collection_key(&card.collection) == key);
+        let progress = progress(checklist, members, &manual);
+        report.totals.add(&progress.progress);
+        report.collections.push(progress);
+    }
+    Ok(report)
+}
+
+fn progress<'a>(
+    collection: &CatalogCollection,
+    cards: impl Iterator<Item = &'a CardMeta>,
+    manual: &HashMap<String, String>,
+) -> CollectionProgress {
+    let mut entries: Vec<EntryStatus> = collection
+        .checklist
+        .iter()
+        .map(|entry| EntryStatus {
+            key: entry.key(),
+            entry: entry.clone(),
+            card_ids: Vec::new(),
+            manual: Vec::new(),
+        })
+        .collect();
+    let mut unmatched = Vec::new();
+    for card in cards {
+        let by_hand = manual
+            .get(&card.id)
+            .and_then(|key| entries.iter().position(|e| e.key == *key));
+        match by_hand.or_else(|| best_match(card, &collection.checklist)) {
+            Some(i) => {
+                entries[i].card_ids.push(card.id.clone());
+                if by_hand.is_some() {
+                    entries[i].manual.push(card.id.clone());
+                }
+            }
+            None => unmatched.push(card.id.clone()),
+        }
+    }
+    let expected = entries.len();
+    let owned = entries.iter().filter(|e| !e.card_ids.is_empty()).count();
+    CollectionProgress {
+        collection: collection.name.clone(),
+        progress: Progress {
+            expected,
+            owned,
+            missing: expected - owned,
+            duplicates: entries
+                .iter()
+                .map(|e| e.card_ids.len().saturating_sub(1))
+                .sum(),
+            percent: percent(owned, expected),
+        },
+        entries,
+        unmatched,
+    }
+}
+
+/// Index of the entry `card` matches best, if exactly one does.
+fn best_match(card: &CardMeta, checklist: &[ChecklistEntry]) -> Option<usize> {
+    let name = words(&card.name);
+    let has_tag = |variant: &str| {
+        card.tags
+            .iter()
+            .any(|t| t.trim().eq_ignore_ascii_case(variant))
+    };
+    let mut best = None;
+    let mut tied = false;
+    for (i, entry) in checklist.iter().enumerate() {
+        let number = words(&entry.number);
+        let (by_number, rest) = match find(&name, &number) {
+            Some(at) => (true, [&name[..at], &name[at + number.len()..]].concat()),
+            None => (false, name.clone()),
+        };
+        let entry_name = words(&entry.name);
+        let variant = words(&entry.variant);
+        let by_name = rest == [entry_name.as_slice(), &variant].concat()
+            || (rest == entry_name && (variant.is_empty() || has_tag(&entry.variant)));
+        if !by_name && !by_number {
+            continue;
+        }
+        // A name match beats a number match, and a matching variant beats none
+        let score = (by_name, by_number, by_name && !variant.is_empty());
+        match best {
+            Some((top, _)) if top > score => {}
+            Some((top, _)) if top == score => tied = true,
+            _ => {
+                best = Some((score, i));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|_| !tied).map(|(_, i)| i)
+}
+
+/// Lowercase words of `text`, split at anything not a letter or digit, with leading
+/// zeros dropped from numbers so `#007` and `7` are the same.
+pub(crate) fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let w = w.to_lowercase();
+            match w.trim_start_matches('0') {
+                "" => "0".to_string(),
+                trimmed if w.chars().all(|c| c.is_ascii_digit()) => trimmed.to_string(),
+                _ => w,
+            }
+        })
+        .collect()
+}
+
+/// Where `needle` first appears in `haystack`; never for an empty needle.
+fn find(haystack: &[String], needle: &[String]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_card;
+
+    fn entry(number: &str, name: &str, variant: &str) -> ChecklistEntry {
+        ChecklistEntry {
+            number: number.into(),
+            name: name.into(),
+            tier: String::new(),
+            variant: variant.into(),
+        }
+    }
+
+    fn card(id: &str, name: &str, tags: &[&str]) -> CardMeta {
+        let mut card = test_card(id);
+        card.name = name.into();
+        card.collection = "Quiet Court".into();
+        card.tags = tags.iter().map(|t| t.to_string()).collect();
+        card
+    }
+
+    #[test]
+    fn matches_by_name_variant_and_number() {
+        let collection = CatalogCollection {
+            name: "Quiet Court".into(),
+            default_tier: String::new(),
+            nsfw: false,
+            checklist: vec![
+                entry("001", "Lady Ash", ""),
+                entry("002", "Lady Ash", "Holo"),
+                entry("003", "The Warden", ""),
+                entry("", "Moth Queen", ""),
+            ],
+        };
+        let cards = [
+            card("a", "Lady Ash", &[]),
+            card("b", "lady-ash (HOLO)", &[]),
+            card("c", "Lady Ash", &["holo"]),
+            card("d", "QC #3 scan", &[]),
+            card("e", "Lady  Ash", &[]),
+            card("f", "Moth Queen", &[]),
+            card("g", "Unknown 4", &[]),
+            card("h", "002 - Lady Ash", &[]),
+        ];
+        let manual = HashMap::from([("f".to_string(), "#003".to_string())]);
+        let progress = progress(&collection, cards.iter(), &manual);
+
+        let ids: Vec<Vec<&str>> = progress
+            .entries
+            .iter()
+            .map(|e| e.card_ids.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            ids,
+            [vec!["a", "e"], vec!["b", "c", "h"], vec!["d", "f"], vec![]]
+        );
+        assert_eq!(progress.entries[2].manual, ["f"]);
+        assert_eq!(progress.unmatched, ["g"]);
+        assert_eq!(
+            progress.progress,
+            Progress {
+                expected: 4,
+                owned: 3,
+                missing: 1,
+                duplicates: 4,
+                percent: 75.0,
+            }
+        );
+    }
+}
@@ -0,0 +1,564 @@
+//! Headless command line. `empire_card_app <command>` works on the same library as the
+//! GUI without opening a window, so backups and bulk imports can be scripted.
+//!
+//! Results go to stdout, as text or with `--format json` as JSON; progress and errors
+//! go to stderr. Exit codes:
+//!
+//! - 0: success
+//! - 1: the command failed (the error is on stderr)
+//! - 2: bad arguments
+//! - 3: the command ran but hit problems: some files failed to import, or `verify`
+//!   found missing or damaged files
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::archive::{self, ImportMode};
+use crate::catalog::{Catalog, CATALOG_FILE};
+use crate::error::{CommandError, CommandResult};
+use crate::ingest::{self, FileOutcome};
+use crate::jobs::{CancelToken, Job, Progress};
+use crate::library::{collection_key, CardMeta, Library};
+use crate::query::Query;
+use crate::render::Renderer;
+use crate::similar;
+
+/// Exit code for a command that completed but hit problems along the way.
+const EXIT_PROBLEMS: u8 = 3;
+
+#[derive(Parser)]
+#[command(name = "empire_card_app", version, about = "Empire Card Collection")]
+struct Cli {
+    /// Library directory [default: the one the app uses]
+    #[arg(long, global = true, env = "EMPIRE_CARD_LIBRARY", value_name = "DIR")]
+    library: Option<PathBuf>,
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Import PDFs and images; a folder imports every card file directly inside it
+    Import(ImportArgs),
+    /// List cards
+    List {
+        /// Only cards in this collection (case and spacing don't matter)
+        #[arg(long)]
+        collection: Option<String>,
+        /// Only cards of this tier
+        #[arg(long)]
+        tier: Option<String>,
+        /// Only cards matching a filter query, e.g. 'tier>=Phoenix -tag:dupe fav:yes'
+        #[arg(long, short)]
+        query: Option<String>,
+    },
+    /// Write the whole library to a card archive
+    Export {
+        #[arg(long, short, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Check every card's file against its checksum
+    Verify,
+    /// Restore a card archive into the library
+    Restore {
+        archive: PathBuf,
+        /// Replace the library with the archive instead of merging it in
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Put the new cards in this collection, with its default tier and NSFW flag from
+    /// the catalog
+    #[arg(long)]
+    collection: Option<String>,
+    /// Give the new cards this tier instead
+    #[arg(long)]
+    tier: Option<String>,
+    /// Mark the new cards NSFW
+    #[arg(long)]
+    nsfw: bool,
+}
+
+enum Status {
+    Ok,
+    Problems,
+}
+
+/// Whether the arguments ask for the command line rather than the GUI. The OS may
+/// pass arguments of its own when launching the app, so only a known subcommand or
+/// one of our flags counts.
+pub fn requested(args: &[OsString]) -> bool {
+    let Some(first) = args.get(1).and_then(|arg| arg.to_str()) else {
+        return false;
+    };
+    matches!(first, "help" | "-h" | "--help" | "-V" | "--version")
+        || first.starts_with("--library")
+        || first.starts_with("--format")
+        || Cli::command().find_subcommand(first).is_some()
+}
+
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    attach_console();
+    let cli = Cli::parse_from(args);
+    let result = run(&cli, &mut std::io::stdout().lock());
+    ExitCode::from(exit_code(cli.format, result))
+}
+
+/// Maps the outcome to the exit codes above, reporting an error on stderr. Bad
+/// arguments never get here: clap exits with 2 itself.
+fn exit_code(format: Format, result: CommandResult<Status>) -> u8 {
+    match result {
+        Ok(Status::Ok) => 0,
+        Ok(Status::Problems) => EXIT_PROBLEMS,
+        Err(error) => {
+            match format {
+                Format::Text => eprintln!("error: {error}"),
+                Format::Json => eprintln!("{}", json(&error)),
+            }
+            1
+        }
+    }
+}
+
+fn run(cli: &Cli, out: &mut impl Write) -> CommandResult<Status> {
+    let context = crate::context();
+    let data_dir = dirs::data_dir().map(|dir| dir.join(&context.config().identifier));
+    let root = match (&cli.library, &data_dir) {
+        (Some(root), _) => root.clone(),
+        (None, Some(data_dir)) => data_dir.join(crate::LIBRARY_DIR),
+        (None, None) => {
+            return Err(CommandError::Internal {
+                message: "could not find the app data directory".into(),
+            })
+        }
+    };
+    let library = Library::open(&root).map_err(|e| CommandError::from(e).at(&root))?;
+    // The catalog the app uses, even with --library
+    let catalog = match &data_dir {
+        Some(data_dir) => {
+            let path = data_dir.join(CATALOG_FILE);
+            Catalog::load(&path).map_err(|e| CommandError::from(e).at(&path))?
+        }
+        None => Catalog::builtin(),
+    };
+
+    let resource_dir =
+        tauri::utils::platform::resource_dir(context.package_info(), &tauri::Env::default())
+            .unwrap_or_default();
+    let renderer = crate::renderer(resource_dir);
+    execute(cli, &library, &renderer, &catalog, out)
+}
+
+/// Runs the command against an open library, writing its results to `out`.
+fn execute(
+    cli: &Cli,
+    library: &Library,
+    renderer: &Renderer,
+    catalog: &Catalog,
+    out: &mut impl Write,
+) -> CommandResult<Status> {
+    match &cli.command {
+        Command::Import(args) => import(library, renderer, catalog, cli.format, args, out),
+
+        Command::List {
+            collection,
+            tier,
+            query,
+        } => {
+            let key = collection.as_deref().map(collection_key);
+            let query = match query {
+                Some(query) => Some(parse_query(query, catalog, cli.format)?),
+                None => None,
+            };
+            let cards: Vec<CardMeta> = library
+                .list_cards()?
+                .into_iter()
+                .filter(|card| key.is_none() || key == Some(collection_key(&card.collection)))
+                .filter(|card| tier.is_none() || tier.as_ref() == Some(&card.tier))
+                .filter(|card| query.as_ref().is_none_or(|query| query.matches(card)))
+                .collect();
+            match cli.format {
+                Format::Text => {
+                    for card in &cards {
+                        writeln!(
+                            out,
+                            "{}\t{}\t{}\t{}",
+                            card.id, card.name, card.collection, card.tier
+                        )?;
+                    }
+                }
+                Format::Json => writeln!(out, "{}", json(&cards))?,
+            }
+            Ok(Status::Ok)
+        }
+
+        Command::Export { out: dest } => {
+            let summary = archive::export_archive(library, dest, &mut progress_job())
+                .map_err(|e| CommandError::from(e).at(dest))?;
+            match cli.format {
+                Format::Text => writeln!(
+                    out,
+                    "exported {} cards ({} files, {} bytes) to {}",
+                    summary.cards,
+                    summary.files,
+                    summary.bytes,
+                    summary.path.display()
+                )?,
+                Format::Json => writeln!(out, "{}", json(&summary))?,
+            }
+            Ok(Status::Ok)
+        }
+
+        Command::Verify => {
+            let report = library.verify()?;
+            match cli.format {
+                Format::Text => {
+                    for id in &report.missing {
+                        writeln!(out, "missing\t{id}")?;
+                    }
+                    for id in &report.corrupt {
+                        writeln!(out, "corrupt\t{id}")?;
+                    }
+                    eprintln!(
+                        "checked {} cards: {} missing, {} corrupt",
+                        report.checked,
+                        report.missing.len(),
+                        report.corrupt.len()
+                    );
+                }
+                Format::Json => writeln!(out, "{}", json(&report))?,
+            }
+            Ok(if report.is_ok() {
+                Status::Ok
+            } else {
+                Status::Problems
+            })
+        }
+
+        Command::Restore {
+            archive: path,
+            replace,
+        } => {
+            let mode = if *replace {
+                ImportMode::Replace
+            } else {
+                ImportMode::Merge
+            };
+            let report = archive::import_archive(library, path, mode, &mut progress_job())
+                .map_err(|e| CommandError::from(e).at(path))?;
+            match cli.format {
+                Format::Text => writeln!(
+                    out,
+                    "restored {} cards ({} skipped, {} conflicting ids kept)",
+                    report.added.len(),
+                    report.skipped.len(),
+                    report.conflicts.len()
+                )?,
+                Format::Json => writeln!(out, "{}", json(&report))?,
+            }
+            Ok(Status::Ok)
+        }
+    }
+}
+
+fn import(
+    library: &Library,
+    renderer: &Renderer,
+    catalog: &Catalog,
+    format: Format,
+    args: &ImportArgs,
+    out: &mut impl Write,
+) -> CommandResult<Status> {
+    let mut failed = false;
+    let mut results = Vec::new();
+    for path in expand_paths(&args.paths)? {
+        let result = import_file(library, renderer, &path, |card| {
+            if let Some(collection) = &args.collection {
+                card.collection = collection.clone();
+                card.tier = catalog.default_tier(collection).to_string();
+                if catalog.default_nsfw(collection) {
+                    card.nsfw = Some(true);
+                }
+            }
+            if let Some(tier) = &args.tier {
+                card.tier = tier.clone();
+            }
+            if args.nsfw {
+                card.nsfw = Some(true);
+            }
+        });
+        let outcome = FileOutcome::new(result, &path);
+        failed |= matches!(outcome, FileOutcome::Failed { .. });
+        let path = path.display().to_string();
+        match format {
+            Format::Text => match &outcome {
+                FileOutcome::Added { card } => writeln!(out, "added\t{}\t{path}", card.id)?,
+                FileOutcome::AlreadyInLibrary { existing_id } => {
+                    writeln!(out, "exists\t{existing_id}\t{path}")?
+                }
+                FileOutcome::Failed { error } => {
+                    writeln!(out, "failed\t\t{path}")?;
+                    eprintln!("error: {error}");
+                }
+            },
+            Format::Json => results.push(ImportedFile { path, outcome }),
+        }
+    }
+    if format == Format::Json {
+        writeln!(out, "{}", json(&results))?;
+    }
+    // Hash the new files for near-duplicate detection now; if this fails, the app
+    // catches up when it next opens
+    let _ = similar::sync(library, renderer);
+    Ok(if failed { Status::Problems } else { Status::Ok })
+}
+
+#[derive(Serialize)]
+struct ImportedFile {
+    path: String,
+    #[serde(flatten)]
+    outcome: FileOutcome,
+}
+
+/// Files named on the command line, with folders replaced by the card files in them.
+fn expand_paths(paths: &[PathBuf]) -> CommandResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(ingest::card_files(path).map_err(|e| CommandError::from(e).at(path))?);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn import_file(
+    library: &Library,
+    renderer: &Renderer,
+    path: &Path,
+    rules: impl FnOnce(&mut CardMeta),
+) -> CommandResult<crate::library::AddOutcome> {
+    let kind = ingest::card_kind(path).ok_or_else(|| {
+        CommandError::invalid_request(format!("{} is not a PDF, GIF, PNG or JPEG", path.display()))
+    })?;
+    let bytes = fs::read(path)?;
+    let mut card = ingest::card_meta(renderer, path, kind, &bytes);
+    rules(&mut card);
+    Ok(library.add_card(card, &bytes)?)
+}
+
+/// In text mode the error quotes the query and underlines the part at fault.
+fn parse_query(query: &str, catalog: &Catalog, format: Format) -> CommandResult<Query> {
+    Query::parse(query, &catalog.tiers).map_err(|e| {
+        let mut error = CommandError::from(e.clone());
+        if let (Format::Text, CommandError::InvalidQuery { message, .. }) = (format, &mut error) {
+            let underline = "^".repeat(e.end.saturating_sub(e.start).max(1));
+            *message = format!(
+                "{}\n  {query}\n  {}{underline}",
+                e.message,
+                " ".repeat(e.start)
+            );
+        }
+        error
+    })
+}
+
+/// A job that draws a progress line on stderr, if stderr is a terminal.
+fn progress_job() -> Job<'static> {
+    if !std::io::stderr().is_terminal() {
+        return Job::detached();
+    }
+    Job::new(CancelToken::default(), |progress: &Progress| {
+        let mut stderr = std::io::stderr();
+        let _ = write!(
+            stderr,
+            "\r\x1b[K{}/{} files",
+            progress.files_done, progress.files_total
+        );
+        if progress.files_total > 0 && progress.files_done == progress.files_total {
+            let _ = writeln!(stderr);
+        }
+    })
+}
+
+fn json(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Release builds on Windows are GUI-subsystem programs with no console of their own;
+/// borrow the one we were started from so output shows up in the terminal.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // Fails harmlessly when there is no parent console (or we already have one)
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        std::iter::once("empire_card_app")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect()
+    }
+
+    /// Runs a command against `library`, returning the exit code and stdout.
+    fn exec(library: &Library, args: &[&str]) -> (u8, String) {
+        let cli = Cli::try_parse_from(argv(args)).unwrap();
+        let mut out = Vec::new();
+        let renderer = Renderer::new(Vec::new());
+        let result = execute(&cli, library, &renderer, &Catalog::builtin(), &mut out);
+        (
+            exit_code(cli.format, result),
+            String::from_utf8(out).unwrap(),
+        )
+    }
+
+    fn json_out(library: &Library, args: &[&str]) -> (u8, Value) {
+        let (code, out) = exec(library, args);
+        (code, serde_json::from_str(&out).unwrap())
+    }
+
+    #[test]
+    fn only_our_commands_and_flags_ask_for_the_command_line() {
+        assert!(!requested(&argv(&[])));
+        assert!(requested(&argv(&["list"])));
+        assert!(requested(&argv(&["--help"])));
+        assert!(requested(&argv(&["-V"])));
+        assert!(requested(&argv(&["--library=/tmp/cards", "verify"])));
+        assert!(requested(&argv(&["--format", "json", "list"])));
+        // What an OS or a file association hands the GUI
+        assert!(!requested(&argv(&["-psn_0_12345"])));
+        assert!(!requested(&argv(&["/home/me/cards/moth.pdf"])));
+        assert!(!requested(&argv(&["lsit"])));
+    }
+
+    #[test]
+    fn bad_arguments_exit_with_2() {
+        for args in [
+            &["list", "--colection", "x"][..],
+            &["import"],
+            &["list", "--format", "yaml"],
+            &["frobnicate"],
+        ] {
+            let error = Cli::try_parse_from(argv(args)).err().unwrap();
+            assert_eq!(error.exit_code(), 2, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn exit_codes_follow_the_outcome() {
+        assert_eq!(exit_code(Format::Text, Ok(Status::Ok)), 0);
+        assert_eq!(exit_code(Format::Json, Ok(Status::Problems)), EXIT_PROBLEMS);
+        let error = CommandError::invalid_request("no");
+        assert_eq!(exit_code(Format::Json, Err(error)), 1);
+    }
+
+    #[test]
+    fn import_list_and_verify_against_a_library() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        let inbox = tmp.path().join("inbox");
+        fs::create_dir(&inbox).unwrap();
+        fs::write(inbox.join("moth.png"), b"moth").unwrap();
+        fs::write(inbox.join("notes.txt"), b"not a card").unwrap();
+
+        // A file that can't be imported finishes with problems, not a failure
+        let moth = inbox.join("moth.png");
+        let notes = inbox.join("notes.txt");
+        let (code, imported) = json_out(
+            &library,
+            &[
+                "--format",
+                "json",
+                "import",
+                moth.to_str().unwrap(),
+                notes.to_str().unwrap(),
+                "--collection",
+                "Celestial Oath",
+            ],
+        );
+        assert_eq!(code, EXIT_PROBLEMS);
+        assert_eq!(imported[0]["status"], "added");
+        assert_eq!(imported[0]["path"], moth.display().to_string());
+        assert_eq!(imported[0]["card"]["tier"], "Ascendant");
+        assert_eq!(imported[1]["status"], "failed");
+        assert_eq!(imported[1]["error"]["kind"], "invalidRequest");
+        let id = imported[0]["card"]["id"].as_str().unwrap().to_string();
+
+        let (code, out) = exec(&library, &["import", moth.to_str().unwrap()]);
+        assert_eq!(code, 0);
+        assert_eq!(out, format!("exists\t{id}\t{}\n", moth.display()));
+
+        let (code, cards) = json_out(
+            &library,
+            &[
+                "list",
+                "--collection",
+                "celestial  oath",
+                "--format",
+                "json",
+            ],
+        );
+        assert_eq!(code, 0);
+        assert_eq!(cards.as_array().unwrap().len(), 1);
+        assert_eq!(cards[0]["id"], id.as_str());
+        assert_eq!(cards[0]["name"], "moth");
+        let (_, text) = exec(&library, &["list", "--tier", "Dawn"]);
+        assert!(text.is_empty());
+
+        let (code, report) = json_out(&library, &["verify", "--format", "json"]);
+        assert_eq!(code, 0);
+        assert_eq!(
+            report,
+            serde_json::json!({ "checked": 1, "missing": [], "corrupt": [] })
+        );
+        let hash = library.file_hash(&id).unwrap().unwrap();
+        let blob = tmp
+            .path()
+            .join("library/blobs")
+            .join(&hash[..2])
+            .join(&hash);
+        fs::write(blob, b"moth, but not").unwrap();
+        let (code, out) = exec(&library, &["verify"]);
+        assert_eq!(code, EXIT_PROBLEMS);
+        assert_eq!(out, format!("corrupt\t{id}\n"));
+    }
+
+    #[test]
+    fn a_bad_query_fails() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path()).unwrap();
+        let (code, out) = exec(&library, &["list", "--query", "tier>=Nonsense"]);
+        assert_eq!(code, 1);
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use tauri::{Manager, Window};
+
+use crate::archive::{self, ExtractLimits, ImportMode};
+use crate::error::CommandError;
+use crate::jobs::JobId;
+use crate::library::Library;
+
+use super::jobs::spawn_job;
+
+/// Writes the whole library to `dest` (picked by the user in a save dialog) as a
+/// manifest-based archive. Runs as a job; the result is an `ExportSummary`.
+#[tauri::command]
+pub fn export_archive(window: Window, dest: String) -> JobId {
+    spawn_job(window, move |window, job| {
+        let dest = Path::new(&dest);
+        archive::export_archive(&window.state::<Library>(), dest, job)
+            .map_err(|e| CommandError::from(e).at(dest))
+    })
+}
+
+/// Restores a library archive, current or legacy format, into the library. Runs as a
+/// job; the result is an `ImportReport`.
+#[tauri::command]
+pub fn import_archive(window: Window, path: String, mode: ImportMode) -> JobId {
+    spawn_job(window, move |window, job| {
+        let path = Path::new(&path);
+        archive::import_archive(&window.state::<Library>(), path, mode, job)
+            .map_err(|e| CommandError::from(e).at(path))
+    })
+}
+
+/// Extracts an archive into `dest`, refusing entries that escape it, symlinks and
+/// zip bombs (see `ExtractLimits`). Runs as a job; the result is `dest`.
+#[tauri::command]
+pub fn unzip_to_dir(window: Window, zip_path: String, dest: String) -> JobId {
+    spawn_job(window, move |_, job| {
+        archive::extract_to_dir(
+            Path::new(&zip_path),
+            Path::new(&dest),
+            &ExtractLimits::default(),
+            job,
+        )
+        .map_err(|e| CommandError::from(e).at(Path::new(&dest)))?;
+        Ok(dest)
+    })
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::Path;
+
+use tauri::{Manager, Window};
+
+use crate::binder::{self, BinderOptions};
+use crate::error::CommandError;
+use crate::jobs::JobId;
+use crate::library::Library;
+use crate::thumbnails::ThumbnailService;
+
+use super::jobs::spawn_job;
+
+/// Writes binder pages for `card_ids`, in that order, or for the whole of
+/// `collection` in the user's order, to `dest` as a PDF. Runs as a job; the result
+/// is a `BinderSummary`.
+#[tauri::command]
+pub fn export_binder_pages(
+    window: Window,
+    card_ids: Option<Vec<String>>,
+    collection: Option<String>,
+    options: BinderOptions,
+    dest: String,
+) -> JobId {
+    spawn_job(window, move |window, job| {
+        let library = window.state::<Library>();
+        let cards = match (card_ids, collection) {
+            (Some(ids), _) => binder::cards(&library, &ids)?,
+            (None, Some(collection)) => binder::collection_cards(&library, &collection)?,
+            (None, None) => {
+                return Err(CommandError::invalid_request(
+                    "pick cards or a collection to print",
+                ))
+            }
+        };
+        let thumbnails = window.state::<ThumbnailService>();
+        let (pdf, summary) =
+            binder::export(&library, thumbnails.renderer(), &cards, &options, job)?;
+        write_output(Path::new(&dest), &pdf)?;
+        Ok(summary)
+    })
+}
+
+/// Writes to `dest.part` and renames it into place, as archive exports do, so a failed
+/// write never leaves a truncated file where the old one was.
+pub(super) fn write_output(dest: &Path, bytes: &[u8]) -> Result<(), CommandError> {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    let partial = dest.with_file_name(name);
+    let result = fs::write(&partial, bytes).and_then(|()| fs::rename(&partial, dest));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.map_err(|e| CommandError::from(e).at(dest))
+}
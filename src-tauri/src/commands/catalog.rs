@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::catalog::remote::{self, CatalogDiff, UpdateSource};
+use crate::catalog::{Catalog, CatalogStore};
+use crate::error::{CommandError, CommandResult};
+
+/// Emitted to every window with the new catalog after it changes.
+const CATALOG_EVENT: &str = "catalog-changed";
+
+#[tauri::command]
+pub fn get_catalog(catalog: State<'_, CatalogStore>) -> Catalog {
+    catalog.catalog()
+}
+
+/// Replaces the catalog after checking it. Returns it as saved (names trimmed).
+#[tauri::command]
+pub fn set_catalog(
+    app: AppHandle,
+    store: State<'_, CatalogStore>,
+    catalog: Catalog,
+) -> CommandResult<Catalog> {
+    let catalog = store.set_catalog(catalog)?;
+    changed(&app, &catalog);
+    Ok(catalog)
+}
+
+/// Replaces the catalog with a catalog file, e.g. one shared by another user.
+#[tauri::command]
+pub fn import_catalog(
+    app: AppHandle,
+    store: State<'_, CatalogStore>,
+    path: String,
+) -> CommandResult<Catalog> {
+    let path = Path::new(&path);
+    let bytes = fs::read(path).map_err(|e| CommandError::from(e).at(path))?;
+    let catalog = store.set_catalog(Catalog::parse(&bytes)?)?;
+    changed(&app, &catalog);
+    Ok(catalog)
+}
+
+/// Writes the catalog to a file that `import_catalog` can read.
+#[tauri::command]
+pub fn export_catalog(store: State<'_, CatalogStore>, dest: String) -> CommandResult<()> {
+    let dest = Path::new(&dest);
+    let json = serde_json::to_vec_pretty(&store.catalog()).map_err(|e| CommandError::Internal {
+        message: e.to_string(),
+    })?;
+    fs::write(dest, json).map_err(|e| CommandError::from(e).at(dest))
+}
+
+/// The tier suggested for cards put in a collection, or `""`.
+#[tauri::command]
+pub fn default_tier_for_collection(store: State<'_, CatalogStore>, name: String) -> String {
+    store.catalog().default_tier(&name).to_string()
+}
+
+/// Whether cards put in a collection are marked NSFW.
+#[tauri::command]
+pub fn default_nsfw_for_collection(store: State<'_, CatalogStore>, name: String) -> bool {
+    store.catalog().default_nsfw(&name)
+}
+
+/// Downloads the signed catalog from the update URL and returns what merging it would
+/// change, or `None` if nothing. The update is held until `apply_catalog_update`.
+#[tauri::command]
+pub async fn check_catalog_update(app: AppHandle) -> CommandResult<Option<CatalogDiff>> {
+    let source = update_source(&app)?;
+    let url = app
+        .state::<CatalogStore>()
+        .catalog()
+        .update_url
+        .unwrap_or(source.endpoint);
+    let update = tauri::async_runtime::spawn_blocking(move || remote::fetch(&url, &source.pubkey))
+        .await
+        .map_err(|e| CommandError::Internal {
+            message: e.to_string(),
+        })??;
+    Ok(app.state::<CatalogStore>().offer_update(update)?)
+}
+
+/// Merges the update found by `check_catalog_update` into the catalog.
+#[tauri::command]
+pub fn apply_catalog_update(
+    app: AppHandle,
+    store: State<'_, CatalogStore>,
+) -> CommandResult<Catalog> {
+    let catalog = store.apply_update()?;
+    changed(&app, &catalog);
+    Ok(catalog)
+}
+
+/// `plugins.catalog` from `tauri.conf.json`.
+fn update_source(app: &AppHandle) -> CommandResult<UpdateSource> {
+    let config = app
+        .config()
+        .plugins
+        .0
+        .get("catalog")
+        .cloned()
+        .ok_or_else(|| {
+            CommandError::invalid_request("catalog updates aren't set up in this build")
+        })?;
+    serde_json::from_value(config).map_err(|e| CommandError::Internal {
+        message: format!("invalid catalog update settings: {e}"),
+    })
+}
+
+fn changed(app: &AppHandle, catalog: &Catalog) {
+    let _ = app.emit(CATALOG_EVENT, catalog);
+    // Smart collections with a tier range depend on the tier order
+    crate::commands::smart::refresh(app);
+}
@@ -0,0 +1,53 @@
+use tauri::State;
+
+use crate::catalog::CatalogStore;
+use crate::checklist::{self, ChecklistReport};
+use crate::error::{CommandError, CommandResult};
+use crate::library::Library;
+
+/// How complete each collection with a checklist is, or only `collection`.
+#[tauri::command]
+pub fn checklist_report(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    collection: Option<String>,
+) -> CommandResult<ChecklistReport> {
+    Ok(checklist::report(
+        &library,
+        &catalog.catalog(),
+        collection.as_deref(),
+    )?)
+}
+
+/// Matches a card to a checklist entry by its key, or with `None` lets it be matched
+/// automatically again. Returns the card's collection's report.
+#[tauri::command]
+pub fn match_checklist_entry(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    card_id: String,
+    entry: Option<String>,
+) -> CommandResult<ChecklistReport> {
+    let card = library
+        .get_card(&card_id)?
+        .ok_or_else(|| CommandError::NotFound {
+            what: format!("card {card_id}"),
+        })?;
+    let catalog = catalog.catalog();
+    if let Some(key) = &entry {
+        let listed = catalog
+            .collection(&card.collection)
+            .is_some_and(|c| c.checklist.iter().any(|e| e.key() == *key));
+        if !listed {
+            return Err(CommandError::invalid_request(format!(
+                "`{key}` isn't in the checklist of the card's collection"
+            )));
+        }
+    }
+    library.set_checklist_match(&card_id, entry.as_deref())?;
+    Ok(checklist::report(
+        &library,
+        &catalog,
+        Some(&card.collection),
+    )?)
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use tauri::{Manager, Window};
+
+use crate::binder;
+use crate::catalog::CatalogStore;
+use crate::contact_sheet::{self, SheetOptions};
+use crate::error::CommandError;
+use crate::jobs::JobId;
+use crate::library::Library;
+use crate::thumbnails::ThumbnailService;
+
+use super::binder::write_output;
+use super::jobs::spawn_job;
+
+/// Writes a contact sheet of `card_ids`, in that order, or of the whole of
+/// `collection` in the user's order, to `dest` as a PNG or WebP. `title` defaults to
+/// the collection's name. Runs as a job; the result is a `SheetSummary`.
+#[tauri::command]
+pub fn export_contact_sheet(
+    window: Window,
+    card_ids: Option<Vec<String>>,
+    collection: Option<String>,
+    title: Option<String>,
+    options: SheetOptions,
+    dest: String,
+) -> JobId {
+    spawn_job(window, move |window, job| {
+        let library = window.state::<Library>();
+        let catalog = window.state::<CatalogStore>().catalog();
+        let cards = match (card_ids, &collection) {
+            (Some(ids), _) => binder::cards(&library, &ids)?,
+            (None, Some(collection)) => binder::collection_cards(&library, collection)?,
+            (None, None) => {
+                return Err(CommandError::invalid_request(
+                    "pick cards or a collection to show",
+                ))
+            }
+        };
+        let title = title
+            .or_else(|| collection.clone())
+            .unwrap_or_else(|| "Cards".into());
+        let subtitle =
+            contact_sheet::completion(&library, &catalog, collection.as_deref(), cards.len())?;
+        let (image, summary) = contact_sheet::render(
+            &library,
+            &window.state::<ThumbnailService>(),
+            &catalog,
+            &cards,
+            &title,
+            &subtitle,
+            &options,
+            job,
+        )?;
+        write_output(Path::new(&dest), &image)?;
+        Ok(summary)
+    })
+}
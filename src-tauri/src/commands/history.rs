@@ -0,0 +1,22 @@
+use tauri::State;
+
+use crate::error::CommandResult;
+use crate::library::{HistoryStep, Library};
+
+/// Steps that can be undone and redone, newest first.
+#[tauri::command]
+pub fn history(library: State<'_, Library>) -> CommandResult<Vec<HistoryStep>> {
+    Ok(library.history()?)
+}
+
+/// Reverses the newest step; `null` if there's nothing to undo.
+#[tauri::command]
+pub fn undo(library: State<'_, Library>) -> CommandResult<Option<HistoryStep>> {
+    Ok(library.undo()?)
+}
+
+/// Makes the last undone step again; `null` if there's nothing to redo.
+#[tauri::command]
+pub fn redo(library: State<'_, Library>) -> CommandResult<Option<HistoryStep>> {
+    Ok(library.redo()?)
+}
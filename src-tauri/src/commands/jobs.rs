@@ -0,0 +1,78 @@
+use serde::Serialize;
+use tauri::{Emitter, Manager, State, Window};
+
+use crate::error::CommandError;
+use crate::jobs::{Job, JobId, JobRegistry, Progress};
+
+/// Emitted to the window that started a job, with a [`ProgressEvent`] payload.
+const PROGRESS_EVENT: &str = "progress";
+/// Emitted once per job when it ends, with a [`JobFinished`] payload.
+const JOB_FINISHED_EVENT: &str = "job-finished";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    job_id: JobId,
+    #[serde(flatten)]
+    progress: &'a Progress,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobFinished {
+    job_id: JobId,
+    #[serde(flatten)]
+    outcome: JobOutcome,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+enum JobOutcome {
+    Done { result: serde_json::Value },
+    Failed { error: CommandError },
+    Cancelled,
+}
+
+/// Runs `work` on the blocking pool and returns its job id straight away; progress and
+/// the outcome reach the calling window as events.
+pub(crate) fn spawn_job<T, F>(window: Window, work: F) -> JobId
+where
+    T: Serialize,
+    F: FnOnce(&Window, &mut Job<'_>) -> Result<T, CommandError> + Send + 'static,
+{
+    let (job_id, token) = window.state::<JobRegistry>().register();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut job = Job::new(token, |progress| {
+            let payload = ProgressEvent { job_id, progress };
+            let _ = window.emit_to(window.label(), PROGRESS_EVENT, payload);
+        });
+        let outcome = match work(&window, &mut job) {
+            Ok(result) => match serde_json::to_value(result) {
+                Ok(result) => JobOutcome::Done { result },
+                Err(e) => JobOutcome::Failed {
+                    error: CommandError::Internal {
+                        message: e.to_string(),
+                    },
+                },
+            },
+            Err(CommandError::Cancelled) => JobOutcome::Cancelled,
+            Err(error) => JobOutcome::Failed { error },
+        };
+        drop(job);
+        window.state::<JobRegistry>().finish(job_id);
+        let _ = window.emit_to(
+            window.label(),
+            JOB_FINISHED_EVENT,
+            JobFinished { job_id, outcome },
+        );
+    });
+    job_id
+}
+
+/// Asks a running job to stop. It winds down at the next entry, removes its partial
+/// output and finishes with `status: "cancelled"`. Returns false if the job had
+/// already finished.
+#[tauri::command]
+pub fn cancel_job(jobs: State<'_, JobRegistry>, job_id: JobId) -> bool {
+    jobs.cancel(job_id)
+}
@@ -0,0 +1,216 @@
+use percent_encoding::percent_decode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tauri::ipc::{InvokeBody, Request, Response};
+use tauri::State;
+
+use crate::error::{CommandError, CommandResult};
+use crate::library::{AddOutcome, CardFace, CardMeta, CardPatch, Library, LibraryError, OrderMap};
+
+/// Header carrying the URI-encoded `CardMeta` JSON for uploads, whose body is the raw file.
+const CARD_META_HEADER: &str = "card-meta";
+/// Header carrying the URI-encoded [`FaceUpload`] JSON for `attach_face` and `restore_face`.
+const FACE_META_HEADER: &str = "face-meta";
+
+/// A face to attach: the `CardFace` fields, plus the card and where among its faces.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FaceUpload {
+    card_id: String,
+    #[serde(default)]
+    position: Option<usize>,
+    #[serde(flatten)]
+    face: CardFace,
+}
+
+#[tauri::command]
+pub fn list_cards(library: State<'_, Library>) -> CommandResult<Vec<CardMeta>> {
+    library.list_cards().map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_card(library: State<'_, Library>, id: String) -> CommandResult<Option<CardMeta>> {
+    library.get_card(&id).map_err(Into::into)
+}
+
+/// Imports a card. Call with the file bytes as the raw invoke body so large PDFs
+/// don't get JSON-encoded: `invoke("add_card", bytes, { headers: { "card-meta": ... } })`.
+/// Reports `alreadyInLibrary` with the existing id when the same file was imported before.
+#[tauri::command]
+pub fn add_card(library: State<'_, Library>, request: Request<'_>) -> CommandResult<AddOutcome> {
+    let (meta, bytes) = upload(&request, CARD_META_HEADER)?;
+    library.add_card(meta, bytes).map_err(Into::into)
+}
+
+/// Like `add_card`, but keeps content duplicates. Used when restoring backups.
+#[tauri::command]
+pub fn restore_card(library: State<'_, Library>, request: Request<'_>) -> CommandResult<CardMeta> {
+    let (meta, bytes) = upload(&request, CARD_META_HEADER)?;
+    library.restore_card(meta, bytes).map_err(Into::into)
+}
+
+/// Adds a face (a back, alternate art, ...) to a card. Like `add_card`, the file is the
+/// raw invoke body, with `{ cardId, position?, id?, label, kind, origExt, mime }` in a
+/// `face-meta` header. Returns the card with its faces.
+#[tauri::command]
+pub fn attach_face(library: State<'_, Library>, request: Request<'_>) -> CommandResult<CardMeta> {
+    let (upload, bytes): (FaceUpload, _) = upload(&request, FACE_META_HEADER)?;
+    library
+        .attach_face(&upload.card_id, upload.face, bytes, upload.position)
+        .map_err(Into::into)
+}
+
+/// Like `attach_face`, but not a step of the undo history. Used when restoring backups.
+#[tauri::command]
+pub fn restore_face(library: State<'_, Library>, request: Request<'_>) -> CommandResult<CardMeta> {
+    let (upload, bytes): (FaceUpload, _) = upload(&request, FACE_META_HEADER)?;
+    library
+        .restore_face(&upload.card_id, upload.face, bytes, upload.position)
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn detach_face(
+    library: State<'_, Library>,
+    id: String,
+    face_id: String,
+) -> CommandResult<CardMeta> {
+    library.detach_face(&id, &face_id).map_err(|e| match e {
+        // A missing card comes back under its own id; anything else missing is the face
+        LibraryError::NotFound(ref missing) if *missing == id => e.into(),
+        e => not_found_as_face(e, &face_id),
+    })
+}
+
+/// Returns a face's file as an `ArrayBuffer`, like `read_card_file`.
+#[tauri::command]
+pub fn read_face_file(library: State<'_, Library>, face_id: String) -> CommandResult<Response> {
+    library
+        .read_face_file(&face_id)
+        .map(Response::new)
+        .map_err(|e| not_found_as_face(e, &face_id))
+}
+
+fn not_found_as_face(e: LibraryError, id: &str) -> CommandError {
+    match e {
+        LibraryError::NotFound(_) => CommandError::NotFound {
+            what: format!("face {id}"),
+        },
+        e => e.into(),
+    }
+}
+
+fn upload<'a, T: DeserializeOwned>(
+    request: &'a Request<'_>,
+    header: &str,
+) -> CommandResult<(T, &'a [u8])> {
+    let InvokeBody::Raw(bytes) = request.body() else {
+        return Err(CommandError::invalid_request(
+            "expected the card file as a raw body",
+        ));
+    };
+    let value = request
+        .headers()
+        .get(header)
+        .ok_or_else(|| CommandError::invalid_request(format!("missing {header} header")))?;
+    let json = percent_decode(value.as_bytes())
+        .decode_utf8()
+        .map_err(|e| CommandError::invalid_request(e.to_string()))?;
+    let meta = serde_json::from_str(&json)
+        .map_err(|e| CommandError::invalid_request(format!("bad {header} header: {e}")))?;
+    Ok((meta, bytes))
+}
+
+/// One card's part of an `edit_cards` call.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardUpdate {
+    id: String,
+    patch: CardPatch,
+}
+
+/// Applies a patch to each card, recorded as one step of the undo history. Returns the
+/// cards as they are now.
+#[tauri::command]
+pub fn edit_cards(
+    library: State<'_, Library>,
+    updates: Vec<CardUpdate>,
+) -> CommandResult<Vec<CardMeta>> {
+    let updates = updates.into_iter().map(|u| (u.id, u.patch)).collect();
+    library.edit_cards(updates).map_err(Into::into)
+}
+
+/// Deletes cards as one step of the undo history.
+#[tauri::command]
+pub fn delete_cards(library: State<'_, Library>, ids: Vec<String>) -> CommandResult<()> {
+    library.delete_cards(&ids).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn clear_library(library: State<'_, Library>) -> CommandResult<()> {
+    library.clear().map_err(Into::into)
+}
+
+/// Returns the card's file as an `ArrayBuffer` rather than a JSON number array.
+#[tauri::command]
+pub fn read_card_file(library: State<'_, Library>, id: String) -> CommandResult<Response> {
+    library
+        .read_card_file(&id)
+        .map(Response::new)
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_order_map(library: State<'_, Library>) -> CommandResult<OrderMap> {
+    library.order_map().map_err(Into::into)
+}
+
+/// Replaces the order map as one step of the undo history.
+#[tauri::command]
+pub fn set_order_map(library: State<'_, Library>, map: OrderMap) -> CommandResult<()> {
+    library.set_order_map(&map).map_err(Into::into)
+}
+
+/// Like `set_order_map`, but not a step of the undo history. Used when restoring backups.
+#[tauri::command]
+pub fn restore_order_map(library: State<'_, Library>, map: OrderMap) -> CommandResult<()> {
+    library.restore_order_map(&map).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_custom_collections(library: State<'_, Library>) -> CommandResult<Vec<String>> {
+    library.custom_collections().map_err(Into::into)
+}
+
+/// Replaces the custom collection list as one step of the undo history.
+#[tauri::command]
+pub fn set_custom_collections(
+    library: State<'_, Library>,
+    names: Vec<String>,
+) -> CommandResult<()> {
+    library.set_custom_collections(&names).map_err(Into::into)
+}
+
+/// Like `set_custom_collections`, but not a step of the undo history. Used when
+/// restoring backups.
+#[tauri::command]
+pub fn restore_custom_collections(
+    library: State<'_, Library>,
+    names: Vec<String>,
+) -> CommandResult<()> {
+    library
+        .restore_custom_collections(&names)
+        .map_err(Into::into)
+}
+
+/// Removes custom collections from the list, their cards and the saved order, as one
+/// step of the undo history. Returns the cards that changed.
+#[tauri::command]
+pub fn delete_custom_collections(
+    library: State<'_, Library>,
+    names: Vec<String>,
+) -> CommandResult<Vec<CardMeta>> {
+    library
+        .delete_custom_collections(&names)
+        .map_err(Into::into)
+}
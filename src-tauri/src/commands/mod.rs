@@ -0,0 +1,19 @@
+//! Tauri command handlers. Each submodule is a thin layer over the matching domain
+//! module and is registered in `run()` via `generate_handler!`.
+
+pub mod archive;
+pub mod binder;
+pub mod catalog;
+pub mod checklist;
+pub mod contact_sheet;
+pub mod history;
+pub mod jobs;
+pub mod library;
+pub mod pdf_edit;
+pub mod query;
+pub mod search;
+pub mod similar;
+pub mod smart;
+pub mod thumbnails;
+pub mod trade;
+pub mod watch;
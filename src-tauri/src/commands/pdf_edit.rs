@@ -0,0 +1,26 @@
+use tauri::State;
+
+use crate::error::CommandResult;
+use crate::library::Library;
+use crate::pdf_edit::{self, EditOutcome};
+use crate::thumbnails::ThumbnailService;
+
+/// Replaces a multi-page PDF card with a card per page (see [`pdf_edit::split`]).
+#[tauri::command]
+pub fn split_card(
+    library: State<'_, Library>,
+    thumbnails: State<'_, ThumbnailService>,
+    id: String,
+) -> CommandResult<EditOutcome> {
+    Ok(pdf_edit::split(&library, thumbnails.renderer(), &id)?)
+}
+
+/// Replaces `ids` with one multi-page PDF card (see [`pdf_edit::merge`]).
+#[tauri::command]
+pub fn merge_cards_into_pdf(
+    library: State<'_, Library>,
+    thumbnails: State<'_, ThumbnailService>,
+    ids: Vec<String>,
+) -> CommandResult<EditOutcome> {
+    Ok(pdf_edit::merge(&library, thumbnails.renderer(), &ids)?)
+}
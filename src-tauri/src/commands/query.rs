@@ -0,0 +1,54 @@
+use tauri::State;
+
+use crate::catalog::CatalogStore;
+use crate::error::{CommandError, CommandResult};
+use crate::library::{Library, LibraryError, SavedQuery};
+use crate::query::Query;
+
+/// Ids of the cards matching a filter query (see [`crate::query`]), in library order.
+/// A query that doesn't parse fails with `invalidQuery` and the span at fault.
+#[tauri::command]
+pub fn filter_cards(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    query: String,
+) -> CommandResult<Vec<String>> {
+    let query = Query::parse(&query, &catalog.catalog().tiers)?;
+    Ok(library
+        .list_cards()?
+        .into_iter()
+        .filter(|card| query.matches(card))
+        .map(|card| card.id)
+        .collect())
+}
+
+#[tauri::command]
+pub fn list_saved_queries(library: State<'_, Library>) -> CommandResult<Vec<SavedQuery>> {
+    library.saved_queries().map_err(Into::into)
+}
+
+/// Saves a query under a name, replacing one of the same name. Only queries that
+/// parse can be saved.
+#[tauri::command]
+pub fn save_query(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    mut query: SavedQuery,
+) -> CommandResult<()> {
+    query.name = query.name.trim().to_string();
+    if query.name.is_empty() {
+        return Err(CommandError::invalid_request("a saved query needs a name"));
+    }
+    Query::parse(&query.query, &catalog.catalog().tiers)?;
+    Ok(library.save_query(&query)?)
+}
+
+#[tauri::command]
+pub fn delete_saved_query(library: State<'_, Library>, name: String) -> CommandResult<()> {
+    library.delete_saved_query(&name).map_err(|e| match e {
+        LibraryError::NotFound(_) => CommandError::NotFound {
+            what: format!("saved query {name}"),
+        },
+        e => e.into(),
+    })
+}
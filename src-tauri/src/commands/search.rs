@@ -0,0 +1,44 @@
+use std::sync::mpsc;
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::CommandResult;
+use crate::library::Library;
+use crate::search::{SearchHit, SearchIndex};
+use crate::thumbnails::ThumbnailService;
+
+const DEFAULT_LIMIT: usize = 500;
+
+/// Keeps the search index in step with the library on a background thread, starting
+/// with a catch-up sync for anything changed while the app was closed.
+pub(crate) fn start_indexing(app: &AppHandle) {
+    let (changed, changes) = mpsc::channel();
+    let _ = changed.send(());
+    app.state::<Library>().on_change(move || {
+        let _ = changed.send(());
+    });
+    let app = app.clone();
+    std::thread::spawn(move || {
+        while changes.recv().is_ok() {
+            // One sync covers every change that piled up meanwhile (e.g. a bulk import)
+            while changes.try_recv().is_ok() {}
+            let library = app.state::<Library>();
+            let thumbnails = app.state::<ThumbnailService>();
+            // A failed sync leaves the index behind; the next change retries it
+            let _ = app
+                .state::<SearchIndex>()
+                .sync(&library, thumbnails.renderer());
+        }
+    });
+}
+
+/// Full-text search over names, tags and PDF text; see [`crate::search`] for the
+/// query syntax. Best matches first.
+#[tauri::command]
+pub fn search_cards(
+    search: State<'_, SearchIndex>,
+    query: String,
+    limit: Option<usize>,
+) -> CommandResult<Vec<SearchHit>> {
+    Ok(search.search(&query, limit.unwrap_or(DEFAULT_LIMIT))?)
+}
@@ -0,0 +1,40 @@
+use std::sync::mpsc;
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::CommandResult;
+use crate::library::Library;
+use crate::similar::{self, SimilarCluster, DEFAULT_THRESHOLD};
+use crate::thumbnails::ThumbnailService;
+
+/// Hashes newly imported files on a background thread, starting with any left over
+/// from before.
+pub(crate) fn start_hashing(app: &AppHandle) {
+    let (changed, changes) = mpsc::channel();
+    let _ = changed.send(());
+    app.state::<Library>().on_change(move || {
+        let _ = changed.send(());
+    });
+    let app = app.clone();
+    std::thread::spawn(move || {
+        while changes.recv().is_ok() {
+            // One pass covers every change that piled up meanwhile (e.g. a bulk import)
+            while changes.try_recv().is_ok() {}
+            let thumbnails = app.state::<ThumbnailService>();
+            // A failed pass leaves files unhashed; the next change retries them
+            let _ = similar::sync(&app.state::<Library>(), thumbnails.renderer());
+        }
+    });
+}
+
+/// Clusters of cards that look alike, at least `threshold` (0 to 1) similar.
+#[tauri::command]
+pub fn similar_cards(
+    library: State<'_, Library>,
+    threshold: Option<f64>,
+) -> CommandResult<Vec<SimilarCluster>> {
+    Ok(similar::clusters(
+        &library,
+        threshold.unwrap_or(DEFAULT_THRESHOLD),
+    )?)
+}
@@ -0,0 +1,118 @@
+use std::sync::mpsc;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::catalog::{Catalog, CatalogStore};
+use crate::error::{CommandError, CommandResult};
+use crate::library::{Library, LibraryError, SmartCollection};
+use crate::smart::{self, Matcher, SmartGroup};
+
+/// Emitted to every window with the re-evaluated smart collections after the library
+/// changes.
+const SMART_EVENT: &str = "smart-collections-changed";
+
+/// Re-evaluates smart collections on a background thread whenever a card changes.
+pub(crate) fn start_evaluating(app: &AppHandle) {
+    let (changed, changes) = mpsc::channel();
+    app.state::<Library>().on_change(move || {
+        let _ = changed.send(());
+    });
+    let app = app.clone();
+    std::thread::spawn(move || {
+        while changes.recv().is_ok() {
+            // One pass covers every change that piled up meanwhile (e.g. a bulk import)
+            while changes.try_recv().is_ok() {}
+            refresh(&app);
+        }
+    });
+}
+
+/// Re-evaluates smart collections now and broadcasts them, e.g. after the catalog's
+/// tier order changes.
+pub(crate) fn refresh(app: &AppHandle) {
+    let catalog = app.state::<CatalogStore>().catalog();
+    if let Ok(groups) = smart::evaluate(&app.state::<Library>(), &catalog) {
+        let _ = app.emit(SMART_EVENT, groups);
+    }
+}
+
+/// Smart collections in display order, each with the ids of the cards in it.
+#[tauri::command]
+pub fn list_smart_collections(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+) -> CommandResult<Vec<SmartGroup>> {
+    Ok(smart::evaluate(&library, &catalog.catalog())?)
+}
+
+/// Adds a smart collection after the others. Returns every smart collection, as
+/// `list_smart_collections` does.
+#[tauri::command]
+pub fn add_smart_collection(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    mut collection: SmartCollection,
+) -> CommandResult<Vec<SmartGroup>> {
+    let catalog = catalog.catalog();
+    check(&mut collection, &catalog)?;
+    collection.id = uuid::Uuid::new_v4().to_string();
+    library.add_smart_collection(&collection)?;
+    Ok(smart::evaluate(&library, &catalog)?)
+}
+
+#[tauri::command]
+pub fn update_smart_collection(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    mut collection: SmartCollection,
+) -> CommandResult<Vec<SmartGroup>> {
+    let catalog = catalog.catalog();
+    check(&mut collection, &catalog)?;
+    library
+        .update_smart_collection(&collection)
+        .map_err(|e| not_found_as_smart(e, &collection.id))?;
+    Ok(smart::evaluate(&library, &catalog)?)
+}
+
+#[tauri::command]
+pub fn delete_smart_collection(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    id: String,
+) -> CommandResult<Vec<SmartGroup>> {
+    library
+        .delete_smart_collection(&id)
+        .map_err(|e| not_found_as_smart(e, &id))?;
+    Ok(smart::evaluate(&library, &catalog.catalog())?)
+}
+
+/// Sets the display order of smart collections by id.
+#[tauri::command]
+pub fn reorder_smart_collections(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    ids: Vec<String>,
+) -> CommandResult<Vec<SmartGroup>> {
+    library.reorder_smart_collections(&ids)?;
+    Ok(smart::evaluate(&library, &catalog.catalog())?)
+}
+
+fn check(collection: &mut SmartCollection, catalog: &Catalog) -> CommandResult<()> {
+    collection.name = collection.name.trim().to_string();
+    if collection.name.is_empty() {
+        return Err(CommandError::invalid_request(
+            "a smart collection needs a name",
+        ));
+    }
+    Matcher::new(&collection.filter, catalog)?;
+    Ok(())
+}
+
+fn not_found_as_smart(e: LibraryError, id: &str) -> CommandError {
+    match e {
+        LibraryError::NotFound(_) => CommandError::NotFound {
+            what: format!("smart collection {id}"),
+        },
+        e => e.into(),
+    }
+}
@@ -0,0 +1,40 @@
+use std::fs;
+
+use tauri::ipc::Response;
+use tauri::State;
+
+use crate::error::CommandResult;
+use crate::library::Library;
+use crate::thumbnails::{SizePreset, ThumbnailConfig, ThumbnailService, ThumbnailSize};
+
+/// Returns a card's thumbnail (WebP or PNG, per the settings) as an `ArrayBuffer`.
+/// `size` is `"grid"`, `"hover"` or a width in pixels; it defaults to the grid size.
+#[tauri::command]
+pub fn card_thumbnail(
+    thumbnails: State<'_, ThumbnailService>,
+    library: State<'_, Library>,
+    id: String,
+    size: Option<ThumbnailSize>,
+) -> CommandResult<Response> {
+    let size = size.unwrap_or(ThumbnailSize::Preset(SizePreset::Grid));
+    let thumbnail = thumbnails.thumbnail(&library, &id, size)?;
+    Ok(Response::new(fs::read(&thumbnail.path)?))
+}
+
+#[tauri::command]
+pub fn get_thumbnail_config(thumbnails: State<'_, ThumbnailService>) -> ThumbnailConfig {
+    thumbnails.config()
+}
+
+#[tauri::command]
+pub fn set_thumbnail_config(
+    thumbnails: State<'_, ThumbnailService>,
+    config: ThumbnailConfig,
+) -> CommandResult<()> {
+    Ok(thumbnails.set_config(config)?)
+}
+
+#[tauri::command]
+pub fn clear_thumbnail_cache(thumbnails: State<'_, ThumbnailService>) -> CommandResult<()> {
+    Ok(thumbnails.clear_cache()?)
+}
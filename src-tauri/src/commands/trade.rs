@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::Path;
+
+use tauri::State;
+
+use crate::catalog::CatalogStore;
+use crate::error::{CommandError, CommandResult};
+use crate::library::{CardMeta, Library};
+use crate::trade::{self, DuplicateGroup, TradeFormat};
+
+/// Sets of cards that are copies of each other: the same file, the same checklist
+/// entry, or the same name in a collection.
+#[tauri::command]
+pub fn duplicate_groups(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+) -> CommandResult<Vec<DuplicateGroup>> {
+    Ok(trade::duplicate_groups(&library, &catalog.catalog())?)
+}
+
+/// Merges cards that are copies of each other into `keep`, deleting the rest (see
+/// [`trade::merge`]).
+#[tauri::command]
+pub fn merge_cards(
+    library: State<'_, Library>,
+    keep: String,
+    others: Vec<String>,
+) -> CommandResult<CardMeta> {
+    Ok(trade::merge(&library, &keep, &others)?)
+}
+
+/// The have/want list as text, CSV or JSON, also written to `dest` if given.
+#[tauri::command]
+pub fn trade_list(
+    library: State<'_, Library>,
+    catalog: State<'_, CatalogStore>,
+    format: TradeFormat,
+    dest: Option<String>,
+) -> CommandResult<String> {
+    let list = trade::trade_list(&library, &catalog.catalog())?.render(format);
+    if let Some(dest) = dest {
+        let dest = Path::new(&dest);
+        fs::write(dest, &list).map_err(|e| CommandError::from(e).at(dest))?;
+    }
+    Ok(list)
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{CommandError, CommandResult};
+use crate::ingest::FileOutcome;
+use crate::library::{Library, LibraryError, WatchFolder};
+use crate::thumbnails::ThumbnailService;
+use crate::watch::{WatchReport, WatchService};
+
+/// Emitted to every window for each file a watch folder imports (or fails to).
+const WATCH_EVENT: &str = "watch-imported";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchEvent {
+    folder: String,
+    path: String,
+    #[serde(flatten)]
+    outcome: FileOutcome,
+}
+
+impl From<WatchReport> for WatchEvent {
+    fn from(report: WatchReport) -> Self {
+        WatchEvent {
+            outcome: FileOutcome::new(report.result, &report.path),
+            folder: report.folder,
+            path: report.path.display().to_string(),
+        }
+    }
+}
+
+/// Imports whatever in `paths` is new, telling the frontend about each file.
+pub(crate) fn ingest_paths(app: &AppHandle, paths: Vec<PathBuf>) {
+    let library = app.state::<Library>();
+    let thumbnails = app.state::<ThumbnailService>();
+    let watch = app.state::<WatchService>();
+    for path in paths {
+        if let Some(report) = watch.ingest(&library, thumbnails.renderer(), &path) {
+            let _ = app.emit(WATCH_EVENT, WatchEvent::from(report));
+        }
+    }
+}
+
+/// Scans folders on the blocking pool, catching files added while we weren't watching.
+fn rescan(app: AppHandle, folders: Vec<PathBuf>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        for folder in folders {
+            if let Ok(paths) = WatchService::scan(&folder) {
+                ingest_paths(&app, paths);
+            }
+        }
+    });
+}
+
+/// Watches every registered folder and imports what arrived since the last run.
+/// Folders that are missing right now (an unplugged drive) are skipped until restart.
+pub(crate) fn start_watching(app: &AppHandle) -> CommandResult<()> {
+    let watch = app.state::<WatchService>();
+    let folders: Vec<PathBuf> = app
+        .state::<Library>()
+        .watch_folders()?
+        .into_iter()
+        .map(|folder| PathBuf::from(folder.path))
+        .filter(|path| watch.watch(path).is_ok())
+        .collect();
+    rescan(app.clone(), folders);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_watch_folders(library: State<'_, Library>) -> CommandResult<Vec<WatchFolder>> {
+    library.watch_folders().map_err(Into::into)
+}
+
+/// Starts watching a folder and imports what's already in it. Returns the folder
+/// with its path canonicalized, which is how it's identified from then on.
+#[tauri::command]
+pub fn add_watch_folder(
+    app: AppHandle,
+    library: State<'_, Library>,
+    watch: State<'_, WatchService>,
+    mut folder: WatchFolder,
+) -> CommandResult<WatchFolder> {
+    let path = fs::canonicalize(&folder.path)
+        .map_err(|e| CommandError::from(e).at(folder.path.as_ref()))?;
+    if !path.is_dir() {
+        return Err(CommandError::invalid_request(format!(
+            "{} is not a folder",
+            path.display()
+        )));
+    }
+    folder.path = path.display().to_string();
+    match library.add_watch_folder(&folder) {
+        Err(LibraryError::AlreadyExists(path)) => {
+            return Err(CommandError::invalid_request(format!(
+                "{path} is already being watched"
+            )))
+        }
+        other => other?,
+    }
+    if let Err(e) = watch.watch(&path) {
+        let _ = library.remove_watch_folder(&folder.path);
+        return Err(CommandError::from(e).at(&path));
+    }
+    rescan(app, vec![path]);
+    Ok(folder)
+}
+
+/// Changes a folder's collection, tier and NSFW rules for files imported from now on.
+#[tauri::command]
+pub fn update_watch_folder(library: State<'_, Library>, folder: WatchFolder) -> CommandResult<()> {
+    library
+        .update_watch_folder(&folder)
+        .map_err(|e| not_found_as_folder(e, &folder.path))
+}
+
+/// Stops watching a folder. Cards it imported stay in the library.
+#[tauri::command]
+pub fn remove_watch_folder(
+    library: State<'_, Library>,
+    watch: State<'_, WatchService>,
+    path: String,
+) -> CommandResult<()> {
+    library
+        .remove_watch_folder(&path)
+        .map_err(|e| not_found_as_folder(e, &path))?;
+    // Fails if the folder has disappeared, in which case nothing is watching it anyway
+    let _ = watch.unwatch(path.as_ref());
+    Ok(())
+}
+
+fn not_found_as_folder(e: LibraryError, path: &str) -> CommandError {
+    match e {
+        LibraryError::NotFound(_) => CommandError::NotFound {
+            what: format!("watch folder {path}"),
+        },
+        e => e.into(),
+    }
+}
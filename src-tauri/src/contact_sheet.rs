@@ -0,0 +1,396 @@
+//! Contact sheets: one picture of a collection, or of any list of cards, to share
+//! where a PDF won't do. A header with the title and how complete the collection is,
+//! then the cards' grid thumbnails in rows, in the given order or in sections by tier,
+//! on the app's light or dark background.
+//!
+//! The sheet is drawn as a PDF page through Pdfium, which also sets the text, and
+//! rendered to PNG or WebP, so it needs Pdfium even when every card is an image.
+
+use std::collections::BTreeMap;
+
+use image::{imageops, DynamicImage};
+use pdfium_render::prelude::{
+    PdfColor, PdfDocument, PdfFontToken, PdfPage, PdfPageImageObject, PdfPageObjectCommon,
+    PdfPageObjectsCommon, PdfPagePaperSize, PdfPageTextObject, PdfPoints, PdfRect, PdfRenderConfig,
+    PdfiumError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::binder::fit_text;
+use crate::catalog::Catalog;
+use crate::checklist;
+use crate::jobs::{Cancelled, Job};
+use crate::library::{CardMeta, Library, LibraryError};
+use crate::render::RenderError;
+use crate::thumbnails::{
+    self, SizePreset, ThumbnailError, ThumbnailFormat, ThumbnailService, ThumbnailSize,
+};
+
+/// Size of a card's picture on the sheet, in pixels; pictures keep their shape
+/// within it.
+const CELL: (f32, f32) = (160.0, 224.0);
+/// Under each picture, for the card's name.
+const NAME_HEIGHT: f32 = 18.0;
+const GAP: f32 = 12.0;
+const PADDING: f32 = 24.0;
+const HEADER_HEIGHT: f32 = 64.0;
+const SECTION_HEIGHT: f32 = 28.0;
+/// Pdfium won't make a page taller than 200 inches.
+const MAX_HEIGHT: f32 = 14_400.0;
+/// How much flagged cards are blurred, as a Gaussian sigma in pixels.
+const NSFW_BLUR: f32 = 14.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SheetError {
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+    #[error(transparent)]
+    Thumbnail(#[from] ThumbnailError),
+    #[error("could not draw the contact sheet: {0}")]
+    Pdf(#[from] PdfiumError),
+    #[error("{0}")]
+    Layout(String),
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl From<Cancelled> for SheetError {
+    fn from(_: Cancelled) -> Self {
+        SheetError::Cancelled
+    }
+}
+
+impl From<RenderError> for SheetError {
+    fn from(e: RenderError) -> Self {
+        SheetError::Thumbnail(e.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, SheetError>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arrange {
+    /// In the order given.
+    #[default]
+    Custom,
+    /// In sections from the highest tier down, each in the order given.
+    Tier,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SheetOptions {
+    pub format: ThumbnailFormat,
+    pub theme: Theme,
+    pub arrange: Arrange,
+    /// Cards per row.
+    pub columns: u32,
+    pub blur_nsfw: bool,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            format: ThumbnailFormat::Png,
+            theme: Theme::Light,
+            arrange: Arrange::Custom,
+            columns: 6,
+            blur_nsfw: true,
+        }
+    }
+}
+
+/// The slate shades App.jsx uses for the page background and text.
+struct Palette {
+    background: PdfColor,
+    text: PdfColor,
+    muted: PdfColor,
+}
+
+impl Theme {
+    fn palette(self) -> Palette {
+        match self {
+            Theme::Light => Palette {
+                background: PdfColor::new(255, 255, 255, 255),
+                text: PdfColor::new(15, 23, 42, 255),
+                muted: PdfColor::new(100, 116, 139, 255),
+            },
+            Theme::Dark => Palette {
+                background: PdfColor::new(15, 23, 42, 255),
+                text: PdfColor::new(241, 245, 249, 255),
+                muted: PdfColor::new(148, 163, 184, 255),
+            },
+        }
+    }
+}
+
+/// A run of cards under one heading; the heading is empty when not arranged by tier.
+#[derive(Debug, PartialEq)]
+struct Section<'c> {
+    heading: String,
+    cards: Vec<&'c CardMeta>,
+}
+
+/// What [`render`] drew, in pixels.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetSummary {
+    pub cards: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn sections<'c>(cards: &'c [CardMeta], arrange: Arrange, catalog: &Catalog) -> Vec<Section<'c>> {
+    if arrange == Arrange::Custom {
+        return vec![Section {
+            heading: String::new(),
+            cards: cards.iter().collect(),
+        }];
+    }
+    // Known tiers highest first, then unknown ones by name, then no tier
+    let mut by_tier: BTreeMap<(usize, String), Vec<&CardMeta>> = BTreeMap::new();
+    for card in cards {
+        let tier = card.tier.trim();
+        let key = match catalog.tier_rank(tier) {
+            Some(rank) => (catalog.tiers.len() - 1 - rank, catalog.tiers[rank].clone()),
+            None if tier.is_empty() => (usize::MAX, "No tier".to_string()),
+            None => (catalog.tiers.len(), tier.to_string()),
+        };
+        by_tier.entry(key).or_default().push(card);
+    }
+    by_tier
+        .into_iter()
+        .map(|((_, heading), cards)| Section { heading, cards })
+        .collect()
+}
+
+/// The line under the title: how much of the checklist is owned when `collection`
+/// has one, else how many cards there are.
+pub fn completion(
+    library: &Library,
+    catalog: &Catalog,
+    collection: Option<&str>,
+    cards: usize,
+) -> Result<String> {
+    let plural = if cards == 1 { "" } else { "s" };
+    let Some(collection) = collection else {
+        return Ok(format!("{cards} card{plural}"));
+    };
+    let report = checklist::report(library, catalog, Some(collection))?;
+    Ok(match report.collections.first() {
+        Some(progress) => format!(
+            "{} of {} collected ({:.0}%) - {cards} card{plural}",
+            progress.progress.owned, progress.progress.expected, progress.progress.percent
+        ),
+        None => format!("{cards} card{plural}"),
+    })
+}
+
+/// Draws the sheet and returns it encoded as `options.format`. Each card is a step
+/// of `job`; cancelling stops between cards.
+#[allow(clippy::too_many_arguments)]
+pub fn render(
+    library: &Library,
+    thumbnails: &ThumbnailService,
+    catalog: &Catalog,
+    cards: &[CardMeta],
+    title: &str,
+    subtitle: &str,
+    options: &SheetOptions,
+    job: &mut Job<'_>,
+) -> Result<(Vec<u8>, SheetSummary)> {
+    if cards.is_empty() {
+        return Err(SheetError::Layout("there are no cards to show".into()));
+    }
+    let columns = options.columns.clamp(1, 12) as usize;
+    let sections = sections(cards, options.arrange, catalog);
+    let row_height = CELL.1 + NAME_HEIGHT + GAP;
+    let width = 2.0 * PADDING + columns as f32 * (CELL.0 + GAP) - GAP;
+    let height = PADDING
+        + HEADER_HEIGHT
+        + sections
+            .iter()
+            .map(|s| {
+                let heading = if s.heading.is_empty() {
+                    0.0
+                } else {
+                    SECTION_HEIGHT
+                };
+                heading + s.cards.len().div_ceil(columns) as f32 * row_height
+            })
+            .sum::<f32>()
+        + PADDING
+        - GAP;
+    if height > MAX_HEIGHT {
+        return Err(SheetError::Layout(
+            "too many cards for one sheet; try more columns or fewer cards".into(),
+        ));
+    }
+    job.set_totals(cards.len(), 0);
+
+    let palette = options.theme.palette();
+    let pdfium = thumbnails.renderer().pdfium()?;
+    let mut document = pdfium.create_new_pdf()?;
+    let regular = document.fonts_mut().helvetica();
+    let bold = document.fonts_mut().helvetica_bold();
+    let page = document
+        .pages_mut()
+        .create_page_at_end(PdfPagePaperSize::new_custom(
+            PdfPoints::new(width),
+            PdfPoints::new(height),
+        ))?;
+    let mut sheet = Sheet {
+        document: &document,
+        page,
+        height,
+    };
+    sheet.page.objects_mut().create_path_object_rect(
+        PdfRect::new_from_values(0.0, 0.0, height, width),
+        None,
+        None,
+        Some(palette.background),
+    )?;
+
+    let mut top = PADDING;
+    sheet.text(title, bold, 24.0, palette.text, PADDING, top + 24.0)?;
+    sheet.text(subtitle, regular, 13.0, palette.muted, PADDING, top + 46.0)?;
+    top += HEADER_HEIGHT;
+    for section in &sections {
+        if !section.heading.is_empty() {
+            let heading = format!("{} ({})", section.heading, section.cards.len());
+            sheet.text(&heading, bold, 14.0, palette.text, PADDING, top + 18.0)?;
+            top += SECTION_HEIGHT;
+        }
+        for (i, card) in section.cards.iter().enumerate() {
+            job.start_entry(&card.name)?;
+            let left = PADDING + (i % columns) as f32 * (CELL.0 + GAP);
+            let cell_top = top + (i / columns) as f32 * row_height;
+            let thumbnail =
+                thumbnails.thumbnail(library, &card.id, ThumbnailSize::Preset(SizePreset::Grid))?;
+            let mut image = image::open(&thumbnail.path).map_err(RenderError::from)?;
+            if options.blur_nsfw && card.nsfw == Some(true) {
+                image = DynamicImage::ImageRgba8(imageops::blur(&image, NSFW_BLUR));
+            }
+            sheet.picture(&image, left, cell_top)?;
+            let name = fit_text(card.name.trim(), CELL.0, 10.0);
+            sheet.text(
+                &name,
+                regular,
+                10.0,
+                palette.muted,
+                left,
+                cell_top + CELL.1 + 13.0,
+            )?;
+            job.finish_entry(0);
+        }
+        top += section.cards.len().div_ceil(columns) as f32 * row_height;
+    }
+
+    let image = sheet
+        .page
+        .render_with_config(&PdfRenderConfig::new().set_target_width(width as i32))?
+        .as_image();
+    let summary = SheetSummary {
+        cards: cards.len(),
+        width: image.width(),
+        height: image.height(),
+    };
+    Ok((thumbnails::encode(&image, options.format)?, summary))
+}
+
+/// The page being drawn. Positions are measured down from the top of the sheet, as
+/// it's laid out; Pdfium's go up from the bottom.
+struct Sheet<'d, 'a> {
+    document: &'d PdfDocument<'a>,
+    page: PdfPage<'a>,
+    height: f32,
+}
+
+impl Sheet<'_, '_> {
+    /// Left-aligned text with its baseline at `baseline`.
+    fn text(
+        &mut self,
+        text: &str,
+        font: PdfFontToken,
+        size: f32,
+        color: PdfColor,
+        left: f32,
+        baseline: f32,
+    ) -> std::result::Result<(), PdfiumError> {
+        let mut object = PdfPageTextObject::new(self.document, text, font, PdfPoints::new(size))?;
+        object.set_fill_color(color)?;
+        object.translate(PdfPoints::new(left), PdfPoints::new(self.height - baseline))?;
+        self.page.objects_mut().add_text_object(object)?;
+        Ok(())
+    }
+
+    /// `image` fitted into the cell at `(left, top)` without changing its shape.
+    fn picture(
+        &mut self,
+        image: &DynamicImage,
+        left: f32,
+        top: f32,
+    ) -> std::result::Result<(), PdfiumError> {
+        let scale = (CELL.0 / image.width() as f32).min(CELL.1 / image.height() as f32);
+        let (width, height) = (image.width() as f32 * scale, image.height() as f32 * scale);
+        let mut object = PdfPageImageObject::new_with_size(
+            self.document,
+            image,
+            PdfPoints::new(width),
+            PdfPoints::new(height),
+        )?;
+        object.translate(
+            PdfPoints::new(left + (CELL.0 - width) / 2.0),
+            PdfPoints::new(self.height - top - (CELL.1 + height) / 2.0),
+        )?;
+        self.page.objects_mut().add_image_object(object)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_card;
+
+    #[test]
+    fn tier_sections_run_from_the_highest_tier_down() {
+        let catalog = Catalog::builtin();
+        let highest = catalog.tiers.last().unwrap().clone();
+        let card = |id: &str, tier: &str| {
+            let mut card = test_card(id);
+            card.tier = tier.into();
+            card
+        };
+        let cards = [
+            card("a", ""),
+            card("b", "dawn"),
+            card("c", "Homebrew"),
+            card("d", &highest),
+            card("e", "Dawn"),
+        ];
+        let headings: Vec<(String, Vec<&str>)> = sections(&cards, Arrange::Tier, &catalog)
+            .into_iter()
+            .map(|s| (s.heading, s.cards.iter().map(|c| c.id.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            headings,
+            [
+                (highest, vec!["d"]),
+                ("Dawn".to_string(), vec!["b", "e"]),
+                ("Homebrew".to_string(), vec!["c"]),
+                ("No tier".to_string(), vec!["a"]),
+            ]
+        );
+        assert_eq!(sections(&cards, Arrange::Custom, &catalog).len(), 1);
+    }
+}
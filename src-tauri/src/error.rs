@@ -0,0 +1,386 @@
+//! The error every Tauri command returns. It reaches the frontend as
+//! `{ kind, message, ...fields }`, so the UI can branch on `kind` and show something
+//! more useful than "see console".
+
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::archive::ArchiveError;
+use crate::binder::BinderError;
+use crate::catalog::CatalogError;
+use crate::contact_sheet::SheetError;
+use crate::library::LibraryError;
+use crate::pdf_edit::PdfEditError;
+use crate::query::QueryError;
+use crate::render::RenderError;
+use crate::search::SearchError;
+use crate::smart::SmartError;
+use crate::thumbnails::ThumbnailError;
+use crate::watch::WatchError;
+
+#[derive(Debug, Clone, thiserror::Error, Serialize)]
+#[serde(into = "ErrorPayload")]
+pub enum CommandError {
+    #[error("{what} not found")]
+    NotFound { what: String },
+    #[error("card {id} already exists")]
+    AlreadyExists { id: String },
+    #[error("permission denied{}", describe_path(.path))]
+    PermissionDenied { path: Option<String> },
+    #[error("invalid archive: {reason}")]
+    InvalidArchive { reason: String },
+    #[error("not enough disk space{}", describe_path(.path))]
+    QuotaExceeded { path: Option<String> },
+    #[error("{message}{}", describe_path(.path))]
+    Io {
+        path: Option<String>,
+        message: String,
+    },
+    #[error("cancelled")]
+    Cancelled,
+    /// The card's file couldn't be rendered (corrupt file, or no PDF renderer).
+    #[error("{reason}")]
+    Render { reason: String },
+    /// A filter query that doesn't parse; `start..end` is the offending text, in
+    /// characters.
+    #[error("invalid query: {message}")]
+    InvalidQuery {
+        message: String,
+        start: usize,
+        end: usize,
+    },
+    /// The frontend sent something the command can't use.
+    #[error("invalid request: {reason}")]
+    InvalidRequest { reason: String },
+    /// Anything the user can't act on (database corruption, serialization bugs).
+    #[error("{message}")]
+    Internal { message: String },
+}
+
+pub type CommandResult<T> = Result<T, CommandError>;
+
+fn describe_path(path: &Option<String>) -> String {
+    path.as_deref()
+        .map(|p| format!(" ({p})"))
+        .unwrap_or_default()
+}
+
+impl CommandError {
+    pub fn invalid_request(reason: impl Into<String>) -> Self {
+        CommandError::InvalidRequest {
+            reason: reason.into(),
+        }
+    }
+
+    /// Attaches the path the command was working on to file-system errors that don't
+    /// carry one yet.
+    pub fn at(mut self, at: &Path) -> Self {
+        match &mut self {
+            CommandError::PermissionDenied { path }
+            | CommandError::QuotaExceeded { path }
+            | CommandError::Io { path, .. } => {
+                path.get_or_insert_with(|| at.display().to_string());
+            }
+            _ => {}
+        }
+        self
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CommandError::NotFound { .. } => "notFound",
+            CommandError::AlreadyExists { .. } => "alreadyExists",
+            CommandError::PermissionDenied { .. } => "permissionDenied",
+            CommandError::InvalidArchive { .. } => "invalidArchive",
+            CommandError::QuotaExceeded { .. } => "quotaExceeded",
+            CommandError::Io { .. } => "io",
+            CommandError::Cancelled => "cancelled",
+            CommandError::Render { .. } => "render",
+            CommandError::InvalidQuery { .. } => "invalidQuery",
+            CommandError::InvalidRequest { .. } => "invalidRequest",
+            CommandError::Internal { .. } => "internal",
+        }
+    }
+}
+
+/// What actually goes over IPC: the variant's fields plus a readable `message`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorPayload {
+    kind: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<usize>,
+}
+
+impl From<CommandError> for ErrorPayload {
+    fn from(error: CommandError) -> Self {
+        let (path, id) = match &error {
+            CommandError::PermissionDenied { path }
+            | CommandError::QuotaExceeded { path }
+            | CommandError::Io { path, .. } => (path.clone(), None),
+            CommandError::AlreadyExists { id } => (None, Some(id.clone())),
+            _ => (None, None),
+        };
+        let (start, end) = match &error {
+            CommandError::InvalidQuery { start, end, .. } => (Some(*start), Some(*end)),
+            _ => (None, None),
+        };
+        ErrorPayload {
+            kind: error.kind(),
+            message: error.to_string(),
+            path,
+            id,
+            start,
+            end,
+        }
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::PermissionDenied => CommandError::PermissionDenied { path: None },
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => {
+                CommandError::QuotaExceeded { path: None }
+            }
+            _ => CommandError::Io {
+                path: None,
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<LibraryError> for CommandError {
+    fn from(e: LibraryError) -> Self {
+        match e {
+            LibraryError::NotFound(id) => CommandError::NotFound {
+                what: format!("card {id}"),
+            },
+            LibraryError::AlreadyExists(id) => CommandError::AlreadyExists { id },
+            LibraryError::Io(e) => e.into(),
+            LibraryError::Db(rusqlite::Error::SqliteFailure(code, _))
+                if code.code == rusqlite::ErrorCode::DiskFull =>
+            {
+                CommandError::QuotaExceeded { path: None }
+            }
+            e @ (LibraryError::Db(_) | LibraryError::Json(_)) => CommandError::Internal {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<ArchiveError> for CommandError {
+    fn from(e: ArchiveError) -> Self {
+        match e {
+            ArchiveError::Io(e) | ArchiveError::Zip(zip::result::ZipError::Io(e)) => e.into(),
+            ArchiveError::Library(e) => e.into(),
+            ArchiveError::Cancelled => CommandError::Cancelled,
+            ArchiveError::Invalid(reason) => CommandError::InvalidArchive { reason },
+            e @ (ArchiveError::UnsafePath(_)
+            | ArchiveError::Symlink(_)
+            | ArchiveError::TooManyEntries { .. }
+            | ArchiveError::TooLarge { .. }
+            | ArchiveError::CompressionRatio { .. }
+            | ArchiveError::Zip(_)) => CommandError::InvalidArchive {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<QueryError> for CommandError {
+    fn from(e: QueryError) -> Self {
+        CommandError::InvalidQuery {
+            message: e.message,
+            start: e.start,
+            end: e.end,
+        }
+    }
+}
+
+impl From<SmartError> for CommandError {
+    fn from(e: SmartError) -> Self {
+        match e {
+            SmartError::Query(e) => e.into(),
+            e @ (SmartError::UnknownTier(_) | SmartError::EmptyTierRange) => {
+                CommandError::invalid_request(e.to_string())
+            }
+        }
+    }
+}
+
+impl From<CatalogError> for CommandError {
+    fn from(e: CatalogError) -> Self {
+        match e {
+            CatalogError::Io(e) => e.into(),
+            e @ (CatalogError::Fetch(_) | CatalogError::Signature(_) | CatalogError::Outdated) => {
+                CommandError::Io {
+                    path: None,
+                    message: e.to_string(),
+                }
+            }
+            e @ (CatalogError::Json(_)
+            | CatalogError::UnsupportedVersion(_)
+            | CatalogError::Invalid(_)
+            | CatalogError::NoUpdate) => CommandError::invalid_request(e.to_string()),
+        }
+    }
+}
+
+impl From<RenderError> for CommandError {
+    fn from(e: RenderError) -> Self {
+        CommandError::Render {
+            reason: e.to_string(),
+        }
+    }
+}
+
+impl From<BinderError> for CommandError {
+    fn from(e: BinderError) -> Self {
+        match e {
+            BinderError::Library(e) => e.into(),
+            BinderError::Render(e) => e.into(),
+            BinderError::Cancelled => CommandError::Cancelled,
+            BinderError::Layout(reason) => CommandError::InvalidRequest { reason },
+            e @ BinderError::Pdf(_) => CommandError::Render {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<SheetError> for CommandError {
+    fn from(e: SheetError) -> Self {
+        match e {
+            SheetError::Library(e) => e.into(),
+            SheetError::Thumbnail(e) => e.into(),
+            SheetError::Cancelled => CommandError::Cancelled,
+            SheetError::Layout(reason) => CommandError::InvalidRequest { reason },
+            e @ SheetError::Pdf(_) => CommandError::Render {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<PdfEditError> for CommandError {
+    fn from(e: PdfEditError) -> Self {
+        match e {
+            PdfEditError::Library(e) => e.into(),
+            PdfEditError::Render(e) => e.into(),
+            PdfEditError::Invalid(reason) => CommandError::InvalidRequest { reason },
+            e @ PdfEditError::Pdf(_) => CommandError::Render {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<ThumbnailError> for CommandError {
+    fn from(e: ThumbnailError) -> Self {
+        match e {
+            ThumbnailError::Render(e) => e.into(),
+            ThumbnailError::Io(e) => e.into(),
+            ThumbnailError::Library(e) => e.into(),
+            e @ (ThumbnailError::Encode(_) | ThumbnailError::Config(_)) => CommandError::Internal {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<WatchError> for CommandError {
+    fn from(e: WatchError) -> Self {
+        use notify_debouncer_mini::notify;
+        match e {
+            WatchError::Io(e)
+            | WatchError::Notify(notify::Error {
+                kind: notify::ErrorKind::Io(e),
+                ..
+            }) => e.into(),
+            WatchError::Library(e) => e.into(),
+            WatchError::Notify(e) => CommandError::Io {
+                path: None,
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<SearchError> for CommandError {
+    fn from(e: SearchError) -> Self {
+        match e {
+            SearchError::Io(e) => e.into(),
+            SearchError::Library(e) => e.into(),
+            SearchError::Render(e) => e.into(),
+            e @ SearchError::Index(_) => CommandError::Internal {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_keep_their_kind_and_take_a_path() {
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        let error = CommandError::from(ArchiveError::Io(denied)).at(Path::new("/out.zip"));
+        assert!(matches!(
+            &error,
+            CommandError::PermissionDenied { path: Some(p) } if p == "/out.zip"
+        ));
+
+        let full = io::Error::from(io::ErrorKind::StorageFull);
+        assert!(matches!(
+            CommandError::from(LibraryError::Io(full)),
+            CommandError::QuotaExceeded { path: None }
+        ));
+    }
+
+    #[test]
+    fn archive_errors_map_to_invalid_archive() {
+        let error = CommandError::from(ArchiveError::Invalid("missing manifest.json".into()));
+        assert_eq!(error.to_string(), "invalid archive: missing manifest.json");
+
+        let error = CommandError::from(ArchiveError::Symlink("files/link".into()));
+        assert!(matches!(error, CommandError::InvalidArchive { .. }));
+        assert!(matches!(
+            CommandError::from(ArchiveError::Cancelled),
+            CommandError::Cancelled
+        ));
+    }
+
+    #[test]
+    fn serializes_kind_message_and_fields() {
+        let error = CommandError::Io {
+            path: Some("/cards".into()),
+            message: "disk on fire".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(error).unwrap(),
+            serde_json::json!({
+                "kind": "io",
+                "message": "disk on fire (/cards)",
+                "path": "/cards",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(CommandError::Cancelled).unwrap(),
+            serde_json::json!({ "kind": "cancelled", "message": "cancelled" })
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Turns files on disk into new cards, the way `importFiles` in App.jsx does for files
+//! dropped on the window. Shared by watch folders and the command line.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::CommandError;
+use crate::library::{AddOutcome, CardKind, CardMeta};
+use crate::render::Renderer;
+
+/// What became of one imported file, as the UI and the command line report it:
+/// `{ "status": "added", "card" }`, `{ "status": "alreadyInLibrary", "existingId" }`
+/// or `{ "status": "failed", "error" }`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "status",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum FileOutcome {
+    Added { card: Box<CardMeta> },
+    AlreadyInLibrary { existing_id: String },
+    Failed { error: CommandError },
+}
+
+impl FileOutcome {
+    pub fn new<E: Into<CommandError>>(result: Result<AddOutcome, E>, path: &Path) -> Self {
+        match result {
+            Ok(AddOutcome::Added { card }) => FileOutcome::Added { card },
+            Ok(AddOutcome::AlreadyInLibrary { existing_id }) => {
+                FileOutcome::AlreadyInLibrary { existing_id }
+            }
+            Err(e) => FileOutcome::Failed {
+                error: e.into().at(path),
+            },
+        }
+    }
+}
+
+/// The card kind for an importable file, judged by extension as `importFiles` does.
+/// Hidden files (editor and sync-client temporaries) are never imported.
+pub fn card_kind(path: &Path) -> Option<CardKind> {
+    let name = path.file_name()?.to_string_lossy();
+    if name.starts_with('.') {
+        return None;
+    }
+    match extension(path).as_str() {
+        "pdf" => Some(CardKind::Pdf),
+        "gif" => Some(CardKind::Gif),
+        "png" | "jpg" | "jpeg" => Some(CardKind::Image),
+        _ => None,
+    }
+}
+
+/// Importable files directly inside `dir`, sorted by path.
+pub fn card_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && card_kind(&path).is_some() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Metadata for a new card made from `path`: a fresh id, the file name as its name
+/// and no collection, tier or NSFW flag yet.
+pub fn card_meta(renderer: &Renderer, path: &Path, kind: CardKind, bytes: &[u8]) -> CardMeta {
+    let ext = extension(path);
+    let pages = match kind {
+        // Like the frontend, a PDF we can't count (or can't load pdfium for) is one page
+        CardKind::Pdf => renderer.page_count(bytes).map_or(1, u32::from),
+        CardKind::Gif | CardKind::Image => 1,
+    };
+    let mime = match ext.as_str() {
+        "pdf" => "application/pdf",
+        "gif" => "image/gif",
+        "png" => "image/png",
+        _ => "image/jpeg",
+    };
+    CardMeta {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        pages,
+        tags: Vec::new(),
+        collection: String::new(),
+        thumbnail_data_url: String::new(),
+        created_at: 0,
+        updated_at: 0,
+        tier: String::new(),
+        favorite: false,
+        kind,
+        nsfw: None,
+        orig_ext: ext,
+        mime: mime.to_string(),
+        quantity: 1,
+        condition: String::new(),
+        faces: Vec::new(),
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
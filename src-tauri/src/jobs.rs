@@ -0,0 +1,140 @@
+//! Long-running work (archive export, import, extraction) runs as a job: it reports
+//! progress as it goes and can be cancelled through its id. Nothing here knows about
+//! Tauri; the command layer turns progress into window events.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Archive entry or card currently being worked on.
+    pub current_entry: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("cancelled")]
+pub struct Cancelled;
+
+/// Handed to the work function: tracks progress, forwards it to the listener and
+/// answers whether the job has been cancelled.
+pub struct Job<'a> {
+    token: CancelToken,
+    progress: Progress,
+    on_progress: Box<dyn FnMut(&Progress) + 'a>,
+}
+
+impl<'a> Job<'a> {
+    pub fn new(token: CancelToken, on_progress: impl FnMut(&Progress) + 'a) -> Self {
+        Self {
+            token,
+            progress: Progress::default(),
+            on_progress: Box::new(on_progress),
+        }
+    }
+
+    /// A job nobody watches or cancels, for callers outside the UI.
+    pub fn detached() -> Job<'static> {
+        Job::new(CancelToken::default(), |_| {})
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.token.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn set_totals(&mut self, files: usize, bytes: u64) {
+        self.progress.files_total = files;
+        self.progress.bytes_total = bytes;
+        self.report();
+    }
+
+    /// Marks the start of the next entry. This is the cancellation point between
+    /// entries, so work functions call it before touching anything.
+    pub fn start_entry(&mut self, name: &str) -> Result<(), Cancelled> {
+        self.check()?;
+        self.set_entry(name);
+        Ok(())
+    }
+
+    /// [`start_entry`](Self::start_entry) without the cancellation check, for stretches
+    /// of work that must not be interrupted.
+    pub fn set_entry(&mut self, name: &str) {
+        self.progress.current_entry = Some(name.to_string());
+        self.report();
+    }
+
+    pub fn finish_entry(&mut self, bytes: u64) {
+        self.progress.files_done += 1;
+        self.progress.bytes_done += bytes;
+        self.report();
+    }
+
+    fn report(&mut self) {
+        (self.on_progress)(&self.progress);
+    }
+}
+
+/// Jobs that are still running, by id, so `cancel_job` can reach them.
+#[derive(Debug, Default)]
+pub struct JobRegistry {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<JobId, CancelToken>>,
+}
+
+impl JobRegistry {
+    pub fn register(&self) -> (JobId, CancelToken) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let token = CancelToken::default();
+        self.running().insert(id, token.clone());
+        (id, token)
+    }
+
+    /// Asks a job to stop. Returns false if it already finished (or never existed).
+    pub fn cancel(&self, id: JobId) -> bool {
+        match self.running().get(&id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, id: JobId) {
+        self.running().remove(&id);
+    }
+
+    fn running(&self) -> std::sync::MutexGuard<'_, HashMap<JobId, CancelToken>> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -4,63 +4,50 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-use std::fs::{File, create_dir_all};
-use std::io::copy;
-use std::path::Path;
-use walkdir::WalkDir;
-use zip::write::FileOptions;
+pub mod archive;
+pub mod binder;
+pub mod catalog;
+pub mod checklist;
+pub mod cli;
+mod commands;
+pub mod contact_sheet;
+pub mod error;
+pub mod ingest;
+pub mod jobs;
+pub mod library;
+pub mod pdf_edit;
+pub mod protocol;
+pub mod query;
+pub mod render;
+pub mod search;
+pub mod similar;
+pub mod smart;
+pub mod thumbnails;
+pub mod trade;
+pub mod watch;
 
-#[tauri::command]
-fn zip_dir(src: String, dest: String) -> Result<String, String> {
-    let src_path = Path::new(&src);
-    let dest_path = Path::new(&dest);
-    if !src_path.exists() {
-        return Err(format!("source path does not exist: {}", src));
-    }
+use std::path::PathBuf;
 
-    let file = File::create(&dest_path).map_err(|e| e.to_string())?;
-    let mut zip = zip::ZipWriter::new(file);
-    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+use tauri::Manager;
 
-    for entry in WalkDir::new(&src_path) {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        let name = path.strip_prefix(&src_path).map_err(|e| e.to_string())?.to_str().ok_or("invalid path")?;
-        let name = name.replace("\\", "/");
-        if path.is_file() {
-            zip.start_file(name, options).map_err(|e| e.to_string())?;
-            let mut f = File::open(path).map_err(|e| e.to_string())?;
-            copy(&mut f, &mut zip).map_err(|e| e.to_string())?;
-        } else if !name.is_empty() {
-            zip.add_directory(name, options).map_err(|e| e.to_string())?;
-        }
-    }
+use catalog::{CatalogStore, CATALOG_FILE};
+use jobs::JobRegistry;
+use library::Library;
+use render::Renderer;
+use search::SearchIndex;
+use thumbnails::ThumbnailService;
+use watch::WatchService;
 
-    zip.finish().map_err(|e| e.to_string())?;
-    Ok(dest)
-}
+/// Where the library lives inside the app data dir.
+const LIBRARY_DIR: &str = "library";
 
-#[tauri::command]
-fn unzip_to_dir(zip_path: String, dest: String) -> Result<String, String> {
-    let file = File::open(&zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
-    let dest_path = Path::new(&dest);
-    create_dir_all(&dest_path).map_err(|e| e.to_string())?;
+fn context() -> tauri::Context {
+    tauri::generate_context!()
+}
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-        let outpath = dest_path.join(file.name());
-        if file.name().ends_with('/') {
-            create_dir_all(&outpath).map_err(|e| e.to_string())?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                create_dir_all(p).map_err(|e| e.to_string())?;
-            }
-            let mut outfile = File::create(&outpath).map_err(|e| e.to_string())?;
-            copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-        }
-    }
-    Ok(dest)
+/// A bundled pdfium sits in the resource dir; otherwise the system one is used.
+fn renderer(resource_dir: PathBuf) -> Renderer {
+    Renderer::new(vec![resource_dir.join("pdfium"), resource_dir])
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -70,7 +57,104 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![greet, zip_dir, unzip_to_dir])
-        .run(tauri::generate_context!())
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, |ctx, request, responder| {
+            // Thumbnails may need rendering, so keep the work off the webview's thread
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let library = app.state::<Library>();
+                let thumbnails = app.state::<ThumbnailService>();
+                responder.respond(protocol::handle(&library, &thumbnails, &request));
+            });
+        })
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(Library::open(data_dir.join(LIBRARY_DIR))?);
+            app.manage(JobRegistry::default());
+            app.manage(CatalogStore::open(data_dir.join(CATALOG_FILE))?);
+
+            app.manage(ThumbnailService::open(
+                data_dir.join("thumbnails"),
+                data_dir.join("thumbnails.json"),
+                renderer(app.path().resource_dir()?),
+            )?);
+
+            app.manage(SearchIndex::open(&data_dir.join("search"))?);
+            commands::search::start_indexing(app.handle());
+            commands::similar::start_hashing(app.handle());
+            commands::smart::start_evaluating(app.handle());
+
+            let handle = app.handle().clone();
+            app.manage(WatchService::new(move |paths| {
+                commands::watch::ingest_paths(&handle, paths)
+            })?);
+            commands::watch::start_watching(app.handle())?;
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            commands::archive::unzip_to_dir,
+            commands::library::list_cards,
+            commands::library::get_card,
+            commands::library::add_card,
+            commands::library::restore_card,
+            commands::library::edit_cards,
+            commands::library::delete_cards,
+            commands::library::clear_library,
+            commands::library::read_card_file,
+            commands::library::attach_face,
+            commands::library::restore_face,
+            commands::library::detach_face,
+            commands::library::read_face_file,
+            commands::library::get_order_map,
+            commands::library::set_order_map,
+            commands::library::restore_order_map,
+            commands::library::get_custom_collections,
+            commands::library::set_custom_collections,
+            commands::library::restore_custom_collections,
+            commands::library::delete_custom_collections,
+            commands::history::history,
+            commands::history::undo,
+            commands::history::redo,
+            commands::archive::export_archive,
+            commands::archive::import_archive,
+            commands::binder::export_binder_pages,
+            commands::contact_sheet::export_contact_sheet,
+            commands::pdf_edit::split_card,
+            commands::pdf_edit::merge_cards_into_pdf,
+            commands::jobs::cancel_job,
+            commands::thumbnails::card_thumbnail,
+            commands::thumbnails::get_thumbnail_config,
+            commands::thumbnails::set_thumbnail_config,
+            commands::thumbnails::clear_thumbnail_cache,
+            commands::watch::list_watch_folders,
+            commands::watch::add_watch_folder,
+            commands::watch::update_watch_folder,
+            commands::watch::remove_watch_folder,
+            commands::search::search_cards,
+            commands::catalog::get_catalog,
+            commands::catalog::set_catalog,
+            commands::catalog::import_catalog,
+            commands::catalog::export_catalog,
+            commands::catalog::default_tier_for_collection,
+            commands::catalog::default_nsfw_for_collection,
+            commands::catalog::check_catalog_update,
+            commands::catalog::apply_catalog_update,
+            commands::checklist::checklist_report,
+            commands::checklist::match_checklist_entry,
+            commands::trade::duplicate_groups,
+            commands::trade::trade_list,
+            commands::trade::merge_cards,
+            commands::similar::similar_cards,
+            commands::query::filter_cards,
+            commands::query::list_saved_queries,
+            commands::query::save_query,
+            commands::query::delete_saved_query,
+            commands::smart::list_smart_collections,
+            commands::smart::add_smart_collection,
+            commands::smart::update_smart_collection,
+            commands::smart::delete_smart_collection,
+            commands::smart::reorder_smart_collections,
+        ])
+        .run(context())
         .expect("error while running tauri application");
 }
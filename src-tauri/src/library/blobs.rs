@@ -0,0 +1,79 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::write_atomic;
+
+/// Content-addressed file storage: each distinct file is stored once as
+/// `blobs/<first two hex chars>/<sha256>`. Reference counts live in the `blobs` table;
+/// this type only deals with the bytes on disk.
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(&hash[..2]).join(hash)
+    }
+
+    /// Writes `bytes` under `hash` unless a blob with that hash is already on disk.
+    pub fn put(&self, hash: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(path.parent().expect("blob path has a parent"))?;
+        write_atomic(&path, bytes)
+    }
+
+    pub fn read(&self, hash: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(hash))
+    }
+
+    pub fn remove(&self, hash: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(hash)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Lowercase hex SHA-256 of `bytes`.
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blobs_are_stored_once_under_their_hash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(tmp.path().join("blobs"));
+        let hash = hash_bytes(b"moth");
+        assert_eq!(
+            store.path(&hash),
+            tmp.path().join("blobs").join(&hash[..2]).join(&hash)
+        );
+
+        store.put(&hash, b"moth").unwrap();
+        // Already there, so not rewritten
+        store.put(&hash, b"not moth").unwrap();
+        assert_eq!(store.read(&hash).unwrap(), b"moth");
+
+        store.remove(&hash).unwrap();
+        assert!(!store.path(&hash).exists());
+        store.remove(&hash).unwrap();
+    }
+}
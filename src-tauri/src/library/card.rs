@@ -0,0 +1,173 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// What kind of file backs a card. Mirrors `CardMeta.kind` in App.jsx.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CardKind {
+    #[default]
+    Pdf,
+    Gif,
+    Image,
+}
+
+impl CardKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CardKind::Pdf => "pdf",
+            CardKind::Gif => "gif",
+            CardKind::Image => "image",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "gif" => CardKind::Gif,
+            "image" => CardKind::Image,
+            _ => CardKind::Pdf,
+        }
+    }
+}
+
+/// Card metadata, field-for-field compatible with the `CardMeta` typedef in App.jsx
+/// so records can round-trip through the frontend and the JSON backups unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardMeta {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_pages")]
+    pub pages: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub collection: String,
+    #[serde(default)]
+    pub thumbnail_data_url: String,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+    #[serde(default)]
+    pub tier: String,
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub favorite: bool,
+    #[serde(default)]
+    pub kind: CardKind,
+    /// `None` means "never set", which the auto-NSFW logic treats differently from `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nsfw: Option<bool>,
+    #[serde(default)]
+    pub orig_ext: String,
+    #[serde(default)]
+    pub mime: String,
+    /// How many copies of the card the user has; at least 1.
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    /// Free-form grade of the user's copy, e.g. `"Near Mint"`; empty if not graded.
+    #[serde(default)]
+    pub condition: String,
+    /// The card's other faces (back, alternate art, ...) in order, after its own file,
+    /// which is the front. Changed only through `attach_face` and `detach_face`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faces: Vec<CardFace>,
+}
+
+/// One more face of a card, stored as a file of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardFace {
+    pub id: String,
+    /// What the face shows, e.g. `"Back"`; empty for a plain back.
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub kind: CardKind,
+    #[serde(default)]
+    pub orig_ext: String,
+    #[serde(default)]
+    pub mime: String,
+}
+
+fn default_pages() -> u32 {
+    1
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+/// Older records sometimes stored `favorite` as `"true"` or `1` (see `isFav` in App.jsx).
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(match value {
+        serde_json::Value::Bool(b) => b,
+        serde_json::Value::String(s) => s == "true",
+        serde_json::Value::Number(n) => n.as_i64() == Some(1),
+        _ => false,
+    })
+}
+
+/// A partial update to a card, as sent by `updateMeta`. Absent fields are left untouched;
+/// `id`, `createdAt` and `updatedAt` are managed by the library and ignored if present.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardPatch {
+    pub name: Option<String>,
+    pub pages: Option<u32>,
+    pub tags: Option<Vec<String>>,
+    pub collection: Option<String>,
+    pub thumbnail_data_url: Option<String>,
+    pub tier: Option<String>,
+    pub favorite: Option<bool>,
+    pub kind: Option<CardKind>,
+    pub nsfw: Option<bool>,
+    pub orig_ext: Option<String>,
+    pub mime: Option<String>,
+    pub quantity: Option<u32>,
+    pub condition: Option<String>,
+}
+
+impl CardPatch {
+    pub fn apply(self, meta: &mut CardMeta) {
+        if let Some(v) = self.name {
+            meta.name = v;
+        }
+        if let Some(v) = self.pages {
+            meta.pages = v;
+        }
+        if let Some(v) = self.tags {
+            meta.tags = v;
+        }
+        if let Some(v) = self.collection {
+            meta.collection = v;
+        }
+        if let Some(v) = self.thumbnail_data_url {
+            meta.thumbnail_data_url = v;
+        }
+        if let Some(v) = self.tier {
+            meta.tier = v;
+        }
+        if let Some(v) = self.favorite {
+            meta.favorite = v;
+        }
+        if let Some(v) = self.kind {
+            meta.kind = v;
+        }
+        if let Some(v) = self.nsfw {
+            meta.nsfw = Some(v);
+        }
+        if let Some(v) = self.orig_ext {
+            meta.orig_ext = v;
+        }
+        if let Some(v) = self.mime {
+            meta.mime = v;
+        }
+        if let Some(v) = self.quantity {
+            meta.quantity = v.max(1);
+        }
+        if let Some(v) = self.condition {
+            meta.condition = v.trim().to_string();
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, OptionalExtension};
+
+use super::{Library, LibraryError, Result};
+
+impl Library {
+    /// Cards matched to a checklist entry by hand: card id to entry key (see
+    /// `ChecklistEntry::key`).
+    pub fn checklist_matches(&self) -> Result<HashMap<String, String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT card_id, entry FROM checklist_matches")?;
+        let matches = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(matches.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Matches a card to a checklist entry of its collection, or with `None` goes back
+    /// to matching it automatically.
+    pub fn set_checklist_match(&self, card_id: &str, entry: Option<&str>) -> Result<()> {
+        let conn = self.conn();
+        conn.query_row("SELECT 1 FROM cards WHERE id = ?1", [card_id], |_| Ok(()))
+            .optional()?
+            .ok_or_else(|| LibraryError::NotFound(card_id.to_string()))?;
+        match entry {
+            Some(entry) => conn.execute(
+                "INSERT OR REPLACE INTO checklist_matches (card_id, entry) VALUES (?1, ?2)",
+                params![card_id, entry],
+            )?,
+            None => conn.execute(
+                "DELETE FROM checklist_matches WHERE card_id = ?1",
+                [card_id],
+            )?,
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::history::{record, Operation};
+use super::{Library, Result};
+
+/// Persisted per-group card order, keyed like `keyForCollection` in App.jsx
+/// (normalized, lowercased collection name).
+pub type OrderMap = BTreeMap<String, Vec<String>>;
+
+/// The key a collection is grouped under: `keyForCollection` in App.jsx, i.e. the
+/// trimmed, whitespace-collapsed, lowercased name, with no collection as `(none)`.
+pub fn collection_key(name: &str) -> String {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        "(none)".to_string()
+    } else {
+        name.to_lowercase()
+    }
+}
+
+impl Library {
+    pub fn order_map(&self) -> Result<OrderMap> {
+        read_order_map(&self.conn())
+    }
+
+    /// Replaces the whole order map, mirroring `orderStore.setItem("map", next)`, as one
+    /// step of the history. Saving the order it already has records nothing.
+    pub fn set_order_map(&self, map: &OrderMap) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let before = read_order_map(&tx)?;
+        let keys: BTreeSet<&String> = before.keys().chain(map.keys()).collect();
+        let ops: Vec<Operation> = keys
+            .into_iter()
+            .filter(|key| before.get(*key) != map.get(*key))
+            .map(|key| Operation::SetOrder {
+                key: key.clone(),
+                before: before.get(key).cloned(),
+                after: map.get(key).cloned(),
+            })
+            .collect();
+        if ops.is_empty() {
+            return Ok(());
+        }
+        write_order_map(&tx, map)?;
+        let (_, orphaned) = record(&tx, "Reorder cards", &ops)?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)
+    }
+
+    /// Like [`Library::set_order_map`], but not a step of the history. Used when
+    /// restoring a backup.
+    pub fn restore_order_map(&self, map: &OrderMap) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        write_order_map(&tx, map)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// User-added collections, in the order they were last saved.
+    pub fn custom_collections(&self) -> Result<Vec<String>> {
+        read_custom_collections(&self.conn())
+    }
+
+    /// Replaces the custom collection list as one step of the history. Names differing
+    /// only by case are stored once; saving the list it already has records nothing.
+    pub fn set_custom_collections(&self, names: &[String]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let before = read_custom_collections(&tx)?;
+        write_custom_collections(&tx, names)?;
+        let after = read_custom_collections(&tx)?;
+        if after == before {
+            return Ok(());
+        }
+        let added: Vec<&String> = after.iter().filter(|name| !before.contains(name)).collect();
+        let removed: Vec<&String> = before.iter().filter(|name| !after.contains(name)).collect();
+        let description = match (added.as_slice(), removed.as_slice()) {
+            ([name], []) => format!("Add collection \"{name}\""),
+            ([], [name]) => format!("Remove collection \"{name}\""),
+            _ => "Change custom collections".to_string(),
+        };
+        let (_, orphaned) = record(
+            &tx,
+            &description,
+            &[Operation::SetCustomCollections { before, after }],
+        )?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)
+    }
+
+    /// Like [`Library::set_custom_collections`], but not a step of the history. Used
+    /// when restoring a backup.
+    pub fn restore_custom_collections(&self, names: &[String]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        write_custom_collections(&tx, names)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn read_order_map(conn: &Connection) -> Result<OrderMap> {
+    let mut stmt = conn.prepare("SELECT collection_key, card_ids FROM collection_order")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut map = OrderMap::new();
+    for row in rows {
+        let (key, ids) = row?;
+        map.insert(key, serde_json::from_str(&ids)?);
+    }
+    Ok(map)
+}
+
+fn write_order_map(conn: &Connection, map: &OrderMap) -> Result<()> {
+    conn.execute("DELETE FROM collection_order", [])?;
+    for (key, ids) in map {
+        conn.execute(
+            "INSERT INTO collection_order (collection_key, card_ids) VALUES (?1, ?2)",
+            params![key, serde_json::to_string(ids)?],
+        )?;
+    }
+    Ok(())
+}
+
+pub(super) fn read_custom_collections(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM custom_collections ORDER BY position")?;
+    let names = stmt.query_map([], |row| row.get(0))?;
+    Ok(names.collect::<rusqlite::Result<_>>()?)
+}
+
+pub(super) fn write_custom_collections(conn: &Connection, names: &[String]) -> Result<()> {
+    conn.execute("DELETE FROM custom_collections", [])?;
+    for (position, name) in names.iter().enumerate() {
+        conn.execute(
+            "INSERT OR IGNORE INTO custom_collections (name, position) VALUES (?1, ?2)",
+            params![name, position],
+        )?;
+    }
+    Ok(())
+}
+
+/// The saved card order of one group, if it has one.
+pub(super) fn order_of(conn: &Connection, key: &str) -> Result<Option<Vec<String>>> {
+    let ids: Option<String> = conn
+        .query_row(
+            "SELECT card_ids FROM collection_order WHERE collection_key = ?1",
+            [key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(ids.map(|ids| serde_json::from_str(&ids)).transpose()?)
+}
+
+/// Sets or, with `None`, forgets the saved card order of one group.
+pub(super) fn write_order(conn: &Connection, key: &str, ids: Option<&[String]>) -> Result<()> {
+    conn.execute(
+        "DELETE FROM collection_order WHERE collection_key = ?1",
+        [key],
+    )?;
+    if let Some(ids) = ids {
+        conn.execute(
+            "INSERT INTO collection_order (collection_key, card_ids) VALUES (?1, ?2)",
+            params![key, serde_json::to_string(ids)?],
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::history::{label, read_step, record, Operation, StoredCard};
+use super::{
+    hash_bytes, insert_card, now_millis, CardMeta, HistoryStep, Library, LibraryError, Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditKind {
+    /// One card became one card per page.
+    Split,
+    /// Several cards became one.
+    Merge,
+}
+
+impl Library {
+    /// Replaces the `originals` with `created` (cards and their files) in one go,
+    /// recorded as one step of the history. The originals' files, faces included, stay
+    /// in the blob store, held by the step, until it's forgotten. The created cards
+    /// start without faces.
+    pub fn replace_cards(
+        &self,
+        kind: EditKind,
+        originals: &[String],
+        created: Vec<(CardMeta, Vec<u8>)>,
+    ) -> Result<HistoryStep> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = now_millis();
+        let mut removed = Vec::with_capacity(originals.len());
+        for id in originals {
+            let card =
+                StoredCard::read(&tx, id)?.ok_or_else(|| LibraryError::NotFound(id.clone()))?;
+            tx.execute("DELETE FROM cards WHERE id = ?1", [id])?;
+            removed.push(card);
+        }
+        let mut added = Vec::with_capacity(created.len());
+        for (mut meta, bytes) in created {
+            meta.created_at = now;
+            meta.updated_at = now;
+            let hash = hash_bytes(&bytes);
+            self.retain_blob(&tx, &hash, &bytes)?;
+            insert_card(&tx, &meta, &hash)?;
+            added.push(StoredCard {
+                meta,
+                file_hash: hash,
+                face_hashes: Vec::new(),
+                checklist_match: None,
+            });
+        }
+        let description = match (kind, removed.as_slice(), added.as_slice()) {
+            (EditKind::Split, [card], _) => {
+                format!("Split {} into {} cards", label(&card.meta), added.len())
+            }
+            (_, _, [card]) => format!("Merge {} cards into {}", removed.len(), label(&card.meta)),
+            _ => format!("Replace {} cards", removed.len()),
+        };
+        let ops = [
+            Operation::DeleteCards { cards: removed },
+            Operation::AddCards { cards: added },
+        ];
+        let (step, orphaned) = record(&tx, &description, &ops)?;
+        let step = read_step(&tx, step)?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(step)
+    }
+}
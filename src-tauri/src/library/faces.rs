@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use super::history::{label, record, Operation, StoredFace};
+use super::{
+    hash_bytes, now_millis, select_card, CardFace, CardKind, CardMeta, Library, LibraryError,
+    Result,
+};
+
+const FACE_COLUMNS: &str = "id, label, kind, orig_ext, mime";
+
+impl Library {
+    /// Adds a face to a card, storing `bytes` as its file, as one step of the history.
+    /// It goes at `position` among the card's faces, or after the last one. A face
+    /// without an id is given one.
+    pub fn attach_face(
+        &self,
+        card_id: &str,
+        face: CardFace,
+        bytes: &[u8],
+        position: Option<usize>,
+    ) -> Result<CardMeta> {
+        self.add_face(card_id, face, bytes, position, true)
+    }
+
+    /// Like [`Library::attach_face`], but not a step of the history. Used when restoring
+    /// backups, like [`Library::restore_card`].
+    pub fn restore_face(
+        &self,
+        card_id: &str,
+        face: CardFace,
+        bytes: &[u8],
+        position: Option<usize>,
+    ) -> Result<CardMeta> {
+        self.add_face(card_id, face, bytes, position, false)
+    }
+
+    fn add_face(
+        &self,
+        card_id: &str,
+        mut face: CardFace,
+        bytes: &[u8],
+        position: Option<usize>,
+        undoable: bool,
+    ) -> Result<CardMeta> {
+        if face.id.is_empty() {
+            face.id = uuid::Uuid::new_v4().to_string();
+        }
+        let hash = hash_bytes(bytes);
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let card_exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM cards WHERE id = ?1)",
+            [card_id],
+            |row| row.get(0),
+        )?;
+        if !card_exists {
+            return Err(LibraryError::NotFound(card_id.to_string()));
+        }
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM card_faces WHERE id = ?1)",
+            [&face.id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(LibraryError::AlreadyExists(face.id));
+        }
+        self.retain_blob(&tx, &hash, bytes)?;
+        let position = place_face(&tx, card_id, position, &face, &hash)?;
+        let card = touch(&tx, card_id)?;
+        let orphaned = if undoable {
+            let description = format!("Add {} to {}", face_label(&face), label(&card));
+            let face = Box::new(StoredFace {
+                card_id: card_id.to_string(),
+                position,
+                face,
+                file_hash: hash,
+            });
+            record(&tx, &description, &[Operation::AttachFace { face }])?.1
+        } else {
+            Vec::new()
+        };
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(card)
+    }
+
+    /// Removes a face from a card as one step of the history, which keeps its file
+    /// until the step is forgotten. A missing card is `NotFound` under the card's id, and
+    /// a face it doesn't have under the face's.
+    pub fn detach_face(&self, card_id: &str, face_id: &str) -> Result<CardMeta> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        select_card(&tx, card_id)?.ok_or_else(|| LibraryError::NotFound(card_id.to_string()))?;
+        let face = tx
+            .query_row(
+                &format!("SELECT {FACE_COLUMNS} FROM card_faces WHERE id = ?1 AND card_id = ?2"),
+                [face_id, card_id],
+                face_from_row,
+            )
+            .optional()?
+            .ok_or_else(|| LibraryError::NotFound(face_id.to_string()))?;
+        let (position, file_hash) = remove_face(&tx, face_id)?
+            .ok_or_else(|| LibraryError::NotFound(face_id.to_string()))?;
+        let card = touch(&tx, card_id)?;
+        let description = format!("Remove {} from {}", face_label(&face), label(&card));
+        let face = Box::new(StoredFace {
+            card_id: card_id.to_string(),
+            position,
+            face,
+            file_hash,
+        });
+        let (_, orphaned) = record(&tx, &description, &[Operation::DetachFace { face }])?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(card)
+    }
+
+    pub fn get_face(&self, face_id: &str) -> Result<Option<CardFace>> {
+        let face = self
+            .conn()
+            .query_row(
+                &format!("SELECT {FACE_COLUMNS} FROM card_faces WHERE id = ?1"),
+                [face_id],
+                face_from_row,
+            )
+            .optional()?;
+        Ok(face)
+    }
+
+    pub fn face_hash(&self, face_id: &str) -> Result<String> {
+        let hash = self
+            .conn()
+            .query_row(
+                "SELECT file_hash FROM card_faces WHERE id = ?1",
+                [face_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| LibraryError::NotFound(face_id.to_string()))?;
+        Ok(hash)
+    }
+
+    /// Where a face's file lives on disk, like [`Library::card_file_path`].
+    pub fn face_file_path(&self, face_id: &str) -> Result<PathBuf> {
+        Ok(self.blobs.path(&self.face_hash(face_id)?))
+    }
+
+    pub fn read_face_file(&self, face_id: &str) -> Result<Vec<u8>> {
+        match self.blobs.read(&self.face_hash(face_id)?) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(LibraryError::NotFound(face_id.to_string()))
+            }
+            other => Ok(other?),
+        }
+    }
+}
+
+/// A card's faces, in order.
+pub(super) fn faces_of(conn: &Connection, card_id: &str) -> Result<Vec<CardFace>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {FACE_COLUMNS} FROM card_faces WHERE card_id = ?1 ORDER BY position"
+    ))?;
+    let faces = stmt.query_map([card_id], face_from_row)?;
+    Ok(faces.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Every card's faces, in order, by card id.
+pub(super) fn all_faces(conn: &Connection) -> Result<HashMap<String, Vec<CardFace>>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {FACE_COLUMNS}, card_id FROM card_faces ORDER BY card_id, position"
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(5)?, face_from_row(row)?))
+    })?;
+    let mut faces: HashMap<String, Vec<CardFace>> = HashMap::new();
+    for row in rows {
+        let (card_id, face) = row?;
+        faces.entry(card_id).or_default().push(face);
+    }
+    Ok(faces)
+}
+
+/// The file hashes of a card's faces, in the same order as [`faces_of`]. The faces go
+/// when their card is deleted; release these afterwards.
+pub(super) fn face_hashes(conn: &Connection, card_id: &str) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT file_hash FROM card_faces WHERE card_id = ?1 ORDER BY position")?;
+    let hashes = stmt.query_map([card_id], |row| row.get(0))?;
+    Ok(hashes.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Inserts a face at `position` among the card's faces (after the last one if `None` or
+/// past the end), moving those after it along. The caller has already taken the
+/// reference to `file_hash`. Returns where it went.
+pub(super) fn place_face(
+    conn: &Connection,
+    card_id: &str,
+    position: Option<usize>,
+    face: &CardFace,
+    file_hash: &str,
+) -> Result<usize> {
+    let count: usize = conn.query_row(
+        "SELECT COUNT(*) FROM card_faces WHERE card_id = ?1",
+        [card_id],
+        |row| row.get(0),
+    )?;
+    let position = position.unwrap_or(count).min(count);
+    conn.execute(
+        "UPDATE card_faces SET position = position + 1 WHERE card_id = ?1 AND position >= ?2",
+        params![card_id, position],
+    )?;
+    insert_face(conn, card_id, position, face, file_hash)?;
+    Ok(position)
+}
+
+/// Deletes a face row, closing the gap it leaves. Returns where it was and its file
+/// hash, whose reference passes to the caller, or `None` if there's no such face.
+pub(super) fn remove_face(conn: &Connection, face_id: &str) -> Result<Option<(usize, String)>> {
+    let face: Option<(String, usize, String)> = conn
+        .query_row(
+            "SELECT card_id, position, file_hash FROM card_faces WHERE id = ?1",
+            [face_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let Some((card_id, position, file_hash)) = face else {
+        return Ok(None);
+    };
+    conn.execute("DELETE FROM card_faces WHERE id = ?1", [face_id])?;
+    conn.execute(
+        "UPDATE card_faces SET position = position - 1 WHERE card_id = ?1 AND position > ?2",
+        params![card_id, position],
+    )?;
+    Ok(Some((position, file_hash)))
+}
+
+/// Adds a face row. The caller has already taken the reference to `file_hash`.
+pub(super) fn insert_face(
+    conn: &Connection,
+    card_id: &str,
+    position: usize,
+    face: &CardFace,
+    file_hash: &str,
+) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO card_faces (card_id, position, file_hash, {FACE_COLUMNS})
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+        ),
+        params![
+            card_id,
+            position,
+            file_hash,
+            face.id,
+            face.label.trim(),
+            face.kind.as_str(),
+            face.orig_ext,
+            face.mime,
+        ],
+    )?;
+    Ok(())
+}
+
+/// How history descriptions name a face: `"Back"`, or `a face` if it has no label.
+fn face_label(face: &CardFace) -> String {
+    match face.label.trim() {
+        "" => "a face".to_string(),
+        label => format!("face \"{label}\""),
+    }
+}
+
+fn face_from_row(row: &Row<'_>) -> rusqlite::Result<CardFace> {
+    let kind: String = row.get(2)?;
+    Ok(CardFace {
+        id: row.get(0)?,
+        label: row.get(1)?,
+        kind: CardKind::parse(&kind),
+        orig_ext: row.get(3)?,
+        mime: row.get(4)?,
+    })
+}
+
+/// Bumps the card's `updated_at` after a change to its faces and returns it.
+pub(super) fn touch(conn: &Connection, card_id: &str) -> Result<CardMeta> {
+    conn.execute(
+        "UPDATE cards SET updated_at = ?2 WHERE id = ?1",
+        params![card_id, now_millis()],
+    )?;
+    select_card(conn, card_id)?.ok_or_else(|| LibraryError::NotFound(card_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_card;
+
+    fn face(label: &str) -> CardFace {
+        CardFace {
+            id: String::new(),
+            label: label.into(),
+            kind: CardKind::Image,
+            orig_ext: "png".into(),
+            mime: "image/png".into(),
+        }
+    }
+
+    #[test]
+    fn faces_keep_their_order_and_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(test_card("moth"), b"front").unwrap();
+
+        library
+            .attach_face("moth", face("Back"), b"back", None)
+            .unwrap();
+        let card = library
+            .attach_face("moth", face("Foil"), b"foil", Some(0))
+            .unwrap();
+        let labels: Vec<&str> = card.faces.iter().map(|f| f.label.as_str()).collect();
+        assert_eq!(labels, ["Foil", "Back"]);
+        assert_eq!(library.list_cards().unwrap()[0].faces, card.faces);
+        assert_eq!(library.read_face_file(&card.faces[1].id).unwrap(), b"back");
+
+        let back = library.face_file_path(&card.faces[1].id).unwrap();
+        let card = library.detach_face("moth", &card.faces[0].id).unwrap();
+        assert_eq!(card.faces.len(), 1);
+        assert!(library.verify().unwrap().is_ok());
+
+        assert!(matches!(
+            library.detach_face("moth", "nope"),
+            Err(LibraryError::NotFound(id)) if id == "nope"
+        ));
+        library.delete_card("moth").unwrap();
+        assert!(!back.exists());
+        assert!(matches!(
+            library.detach_face("moth", &card.faces[0].id),
+            Err(LibraryError::NotFound(id)) if id == "moth"
+        ));
+        assert!(matches!(
+            library.attach_face("moth", face("Back"), b"back", None),
+            Err(LibraryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn attaching_and_detaching_faces_can_be_undone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(test_card("moth"), b"front").unwrap();
+        library
+            .attach_face("moth", face("Back"), b"back", None)
+            .unwrap();
+        let card = library
+            .attach_face("moth", face("Foil"), b"foil", Some(0))
+            .unwrap();
+        let foil = card.faces[0].clone();
+        let foil_path = library.face_file_path(&foil.id).unwrap();
+
+        library.detach_face("moth", &foil.id).unwrap();
+        let step = library.undo().unwrap().unwrap();
+        assert_eq!(
+            step.description,
+            "Remove face \"Foil\" from an untitled card"
+        );
+        // Back where it was, with its file
+        let card = library.get_card("moth").unwrap().unwrap();
+        assert_eq!(card.faces[0], foil);
+        assert_eq!(library.read_face_file(&foil.id).unwrap(), b"foil");
+
+        // Undoing the attach takes it out again; the step keeps the file for a redo
+        let step = library.undo().unwrap().unwrap();
+        assert_eq!(step.description, "Add face \"Foil\" to an untitled card");
+        assert_eq!(library.get_card("moth").unwrap().unwrap().faces.len(), 1);
+        assert!(foil_path.exists());
+        library.redo().unwrap();
+        assert_eq!(library.get_card("moth").unwrap().unwrap().faces[0], foil);
+
+        // Once the redo is forgotten, so is the file
+        library.undo().unwrap();
+        library.detach_face("moth", &card.faces[1].id).unwrap();
+        assert!(!foil_path.exists());
+        assert!(library.verify().unwrap().is_ok());
+    }
+}
@@ -0,0 +1,950 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use super::collections::{
+    order_of, read_custom_collections, write_custom_collections, write_order,
+};
+use super::faces::{face_hashes, insert_face, place_face, remove_face, touch};
+use super::{
+    collection_key, insert_card, now_millis, release_blob, select_card, write_card, CardFace,
+    CardMeta, CardPatch, Library, LibraryError, Result,
+};
+
+/// How many steps the history keeps. Past this the oldest are forgotten, along with
+/// the files of the cards they deleted.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// One step of the history, as listed by [`Library::history`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStep {
+    pub id: i64,
+    /// What the step did, e.g. `Change tier on 300 cards`.
+    pub description: String,
+    pub created_at: i64,
+    /// Undone steps can be redone until the next edit, which forgets them.
+    pub undone: bool,
+}
+
+/// A change a step makes, stored so it can be made again or reversed.
+///
+/// A step holds the blob references of whatever it took out of the library: the cards
+/// and faces a done `DeleteCards` or `DetachFace` removed, and those an undone
+/// `AddCards` or `AttachFace` removed. They're listed in `history_files`, under the card
+/// or face id, until the step puts them back or is forgotten.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub(super) enum Operation {
+    EditCards {
+        changes: Vec<CardChange>,
+    },
+    /// Cards a split or merge made: the reverse of `DeleteCards`.
+    AddCards {
+        cards: Vec<StoredCard>,
+    },
+    DeleteCards {
+        cards: Vec<StoredCard>,
+    },
+    AttachFace {
+        face: Box<StoredFace>,
+    },
+    DetachFace {
+        face: Box<StoredFace>,
+    },
+    SetCustomCollections {
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+    SetOrder {
+        key: String,
+        before: Option<Vec<String>>,
+        after: Option<Vec<String>>,
+    },
+}
+
+/// A card's details either side of an edit. Thumbnails are data URLs and most edits
+/// leave them alone, so they're only kept when the edit changed them.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct CardChange {
+    before: CardMeta,
+    after: CardMeta,
+    #[serde(default)]
+    thumbnail_changed: bool,
+}
+
+impl CardChange {
+    pub(super) fn new(mut before: CardMeta, mut after: CardMeta) -> Self {
+        before.faces.clear();
+        after.faces.clear();
+        let thumbnail_changed = before.thumbnail_data_url != after.thumbnail_data_url;
+        if !thumbnail_changed {
+            before.thumbnail_data_url.clear();
+            after.thumbnail_data_url.clear();
+        }
+        CardChange {
+            before,
+            after,
+            thumbnail_changed,
+        }
+    }
+}
+
+/// A card a step added or deleted, with the hashes of its file and faces.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct StoredCard {
+    pub meta: CardMeta,
+    pub file_hash: String,
+    pub face_hashes: Vec<String>,
+    /// The checklist entry it was matched to by hand, as of when it was last taken out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checklist_match: Option<String>,
+}
+
+impl StoredCard {
+    /// The card as it is in the library now, or `None` if there's no such card.
+    pub(super) fn read(conn: &Connection, id: &str) -> Result<Option<Self>> {
+        let Some(meta) = select_card(conn, id)? else {
+            return Ok(None);
+        };
+        let (file_hash, face_hashes) =
+            hashes_of(conn, id)?.ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+        Ok(Some(StoredCard {
+            meta,
+            file_hash,
+            face_hashes,
+            checklist_match: checklist_match(conn, id)?,
+        }))
+    }
+
+    fn hashes(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.file_hash).chain(&self.face_hashes)
+    }
+}
+
+/// A face a step attached or detached, and where it was among the card's faces.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct StoredFace {
+    pub card_id: String,
+    pub position: usize,
+    pub face: CardFace,
+    pub file_hash: String,
+}
+
+impl Library {
+    /// The steps that can be undone and, before them, those that can be redone; newest
+    /// first.
+    pub fn history(&self) -> Result<Vec<HistoryStep>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT seq, description, created_at, undone FROM history ORDER BY seq DESC",
+        )?;
+        let steps = stmt.query_map([], step_from_row)?;
+        Ok(steps.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Applies a patch to each card, as one step. Cards the patch leaves as they were
+    /// aren't part of it, and nor are new thumbnails: they're baked from the file, so
+    /// there's nothing to undo. Returns the cards as they are now.
+    pub fn edit_cards(&self, updates: Vec<(String, CardPatch)>) -> Result<Vec<CardMeta>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = now_millis();
+        let mut cards = Vec::with_capacity(updates.len());
+        let mut changes = Vec::new();
+        let mut fields = Vec::new();
+        let mut rethumbnailed = false;
+        for (id, patch) in updates {
+            let before = select_card(&tx, &id)?.ok_or(LibraryError::NotFound(id))?;
+            let mut after = before.clone();
+            patch.apply(&mut after);
+            let changed = changed_fields(&before, &after);
+            if changed.is_empty() {
+                cards.push(after);
+                continue;
+            }
+            after.updated_at = now;
+            write_card(&tx, &after)?;
+            cards.push(after.clone());
+            if changed == ["thumbnail"] {
+                rethumbnailed = true;
+                continue;
+            }
+            for field in changed {
+                if !fields.contains(&field) {
+                    fields.push(field);
+                }
+            }
+            changes.push(CardChange::new(before, after));
+        }
+        if changes.is_empty() {
+            tx.commit()?;
+            drop(conn);
+            if rethumbnailed {
+                self.changed();
+            }
+            return Ok(cards);
+        }
+        let what = join(&fields);
+        let description = match changes.as_slice() {
+            [change] => format!("Change {what} of {}", label(&change.after)),
+            _ => format!("Change {what} on {} cards", changes.len()),
+        };
+        let (_, orphaned) = record(&tx, &description, &[Operation::EditCards { changes }])?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(cards)
+    }
+
+    /// Deletes cards as one step. Their files stay in the blob store until the step is
+    /// forgotten, so undoing it brings them back exactly.
+    pub fn delete_cards(&self, ids: &[String]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut cards: Vec<StoredCard> = Vec::new();
+        for id in ids {
+            if cards.iter().any(|card| &card.meta.id == id) {
+                continue;
+            }
+            let card =
+                StoredCard::read(&tx, id)?.ok_or_else(|| LibraryError::NotFound(id.clone()))?;
+            tx.execute("DELETE FROM cards WHERE id = ?1", [id])?;
+            cards.push(card);
+        }
+        let description = match cards.as_slice() {
+            [] => return Ok(()),
+            [card] => format!("Delete {}", label(&card.meta)),
+            _ => format!("Delete {} cards", cards.len()),
+        };
+        let (_, orphaned) = record(&tx, &description, &[Operation::DeleteCards { cards }])?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(())
+    }
+
+    /// Folds `others` into `keep` with `fold` and deletes them, all as one step.
+    /// Returns `keep` as it is now.
+    pub fn merge_cards(
+        &self,
+        keep: &str,
+        others: &[String],
+        mut fold: impl FnMut(&mut CardMeta, &CardMeta),
+    ) -> Result<CardMeta> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let before =
+            select_card(&tx, keep)?.ok_or_else(|| LibraryError::NotFound(keep.to_string()))?;
+        let mut after = before.clone();
+        let mut cards: Vec<StoredCard> = Vec::new();
+        for id in others {
+            if id == keep || cards.iter().any(|card| &card.meta.id == id) {
+                continue;
+            }
+            let card =
+                StoredCard::read(&tx, id)?.ok_or_else(|| LibraryError::NotFound(id.clone()))?;
+            fold(&mut after, &card.meta);
+            tx.execute("DELETE FROM cards WHERE id = ?1", [id])?;
+            cards.push(card);
+        }
+        if cards.is_empty() {
+            return Ok(before);
+        }
+        after.updated_at = now_millis();
+        write_card(&tx, &after)?;
+        let description = format!("Merge {} copies of {}", cards.len() + 1, label(&after));
+        let ops = [
+            Operation::EditCards {
+                changes: vec![CardChange::new(before, after.clone())],
+            },
+            Operation::DeleteCards { cards },
+        ];
+        let (_, orphaned) = record(&tx, &description, &ops)?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(after)
+    }
+
+    /// Removes custom collections as one step: from the list, from the cards in them
+    /// (which are left with no collection) and from the saved card order. Names match
+    /// like [`collection_key`]. Returns the cards that changed.
+    pub fn delete_custom_collections(&self, names: &[String]) -> Result<Vec<CardMeta>> {
+        let keys: Vec<String> = names
+            .iter()
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| collection_key(name))
+            .collect();
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut ops = Vec::new();
+
+        let before = read_custom_collections(&tx)?;
+        let (removed, after): (Vec<String>, Vec<String>) = before
+            .iter()
+            .cloned()
+            .partition(|name| keys.contains(&collection_key(name)));
+        if !removed.is_empty() {
+            write_custom_collections(&tx, &after)?;
+            ops.push(Operation::SetCustomCollections { before, after });
+        }
+
+        let now = now_millis();
+        let ids = {
+            let mut stmt =
+                tx.prepare("SELECT id, collection FROM cards ORDER BY created_at, id")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        let mut cards = Vec::new();
+        let mut changes = Vec::new();
+        for (id, collection) in ids {
+            if collection.trim().is_empty() || !keys.contains(&collection_key(&collection)) {
+                continue;
+            }
+            let Some(before) = select_card(&tx, &id)? else {
+                continue;
+            };
+            let after = CardMeta {
+                collection: String::new(),
+                updated_at: now,
+                ..before.clone()
+            };
+            write_card(&tx, &after)?;
+            cards.push(after.clone());
+            changes.push(CardChange::new(before, after));
+        }
+        if !changes.is_empty() {
+            ops.push(Operation::EditCards { changes });
+        }
+
+        for key in &keys {
+            if let Some(ids) = order_of(&tx, key)? {
+                write_order(&tx, key, None)?;
+                ops.push(Operation::SetOrder {
+                    key: key.clone(),
+                    before: Some(ids),
+                    after: None,
+                });
+            }
+        }
+
+        if ops.is_empty() {
+            return Ok(cards);
+        }
+        let description = match (removed.as_slice(), keys.len()) {
+            ([name], _) => format!("Delete collection \"{name}\""),
+            (_, 1) => format!("Delete collection \"{}\"", names[0].trim()),
+            (_, n) => format!("Delete {n} collections"),
+        };
+        let (_, orphaned) = record(&tx, &description, &ops)?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(cards)
+    }
+
+    /// Reverses the newest step that's done. Returns it, or `None` if there was nothing
+    /// to undo.
+    pub fn undo(&self) -> Result<Option<HistoryStep>> {
+        self.replay(false)
+    }
+
+    /// Makes the oldest undone step again. Returns it, or `None` if there was nothing
+    /// to redo.
+    pub fn redo(&self) -> Result<Option<HistoryStep>> {
+        self.replay(true)
+    }
+
+    fn replay(&self, forward: bool) -> Result<Option<HistoryStep>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let sql = if forward {
+            "SELECT seq, operations FROM history WHERE undone = 1 ORDER BY seq LIMIT 1"
+        } else {
+            "SELECT seq, operations FROM history WHERE undone = 0 ORDER BY seq DESC LIMIT 1"
+        };
+        let Some((step, ops)) = tx
+            .query_row(sql, [], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let mut ops: Vec<Operation> = serde_json::from_str(&ops)?;
+        let mut orphaned = Vec::new();
+        if forward {
+            for op in &mut ops {
+                orphaned.extend(op.apply(&tx, step, true)?);
+            }
+        } else {
+            for op in ops.iter_mut().rev() {
+                orphaned.extend(op.apply(&tx, step, false)?);
+            }
+        }
+        // Taking cards out notes what they were matched to since
+        tx.execute(
+            "UPDATE history SET undone = ?2, operations = ?3 WHERE seq = ?1",
+            params![step, !forward, serde_json::to_string(&ops)?],
+        )?;
+        let step = read_step(&tx, step)?;
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(Some(step))
+    }
+}
+
+impl Operation {
+    /// Makes the change or, with `forward` false, reverses it. Cards and faces that are
+    /// gone, or (when taking them out again) have changed files since, are left alone.
+    /// Returns the hashes of blobs nothing uses any more.
+    fn apply(&mut self, conn: &Connection, step: i64, forward: bool) -> Result<Vec<String>> {
+        let mut orphaned = Vec::new();
+        let adds = matches!(
+            self,
+            Operation::AddCards { .. } | Operation::AttachFace { .. }
+        );
+        match self {
+            Operation::EditCards { changes } => {
+                for change in changes {
+                    let target = if forward {
+                        &change.after
+                    } else {
+                        &change.before
+                    };
+                    let Some(current) = select_card(conn, &target.id)? else {
+                        continue;
+                    };
+                    let mut meta = target.clone();
+                    if !change.thumbnail_changed {
+                        meta.thumbnail_data_url = current.thumbnail_data_url;
+                    }
+                    write_card(conn, &meta)?;
+                }
+            }
+            Operation::AddCards { cards } | Operation::DeleteCards { cards } => {
+                for card in cards {
+                    if forward == adds {
+                        orphaned.extend(put_back_card(conn, step, card)?);
+                    } else {
+                        take_out_card(conn, step, card)?;
+                    }
+                }
+            }
+            Operation::AttachFace { face } | Operation::DetachFace { face } => {
+                if forward == adds {
+                    orphaned.extend(put_back_face(conn, step, face)?);
+                } else {
+                    take_out_face(conn, step, face)?;
+                }
+            }
+            Operation::SetCustomCollections { before, after } => {
+                write_custom_collections(conn, if forward { after } else { before })?;
+            }
+            Operation::SetOrder { key, before, after } => {
+                let ids = if forward { after } else { before };
+                write_order(conn, key, ids.as_deref())?;
+            }
+        }
+        Ok(orphaned)
+    }
+}
+
+/// Adds a step after the newest done one, holding the files of whatever it took out of
+/// the library, and forgets any undone steps and the oldest steps past
+/// [`HISTORY_LIMIT`]. Returns the new step's id and the hashes of blobs nothing uses any
+/// more.
+pub(super) fn record(
+    conn: &Connection,
+    description: &str,
+    ops: &[Operation],
+) -> Result<(i64, Vec<String>)> {
+    let undone = {
+        let mut stmt = conn.prepare("SELECT seq FROM history WHERE undone = 1")?;
+        let seqs = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        seqs.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    let mut orphaned = forget(conn, &undone)?;
+
+    conn.execute(
+        "INSERT INTO history (description, created_at, operations) VALUES (?1, ?2, ?3)",
+        params![description, now_millis(), serde_json::to_string(ops)?],
+    )?;
+    let step = conn.last_insert_rowid();
+    for op in ops {
+        match op {
+            Operation::DeleteCards { cards } => {
+                for card in cards {
+                    hold(conn, step, &card.meta.id, card.hashes())?;
+                }
+            }
+            Operation::DetachFace { face } => {
+                hold(conn, step, &face.face.id, [&face.file_hash])?;
+            }
+            _ => {}
+        }
+    }
+
+    let stale = {
+        let mut stmt =
+            conn.prepare("SELECT seq FROM history ORDER BY seq DESC LIMIT -1 OFFSET ?1")?;
+        let seqs = stmt.query_map([HISTORY_LIMIT], |row| row.get::<_, i64>(0))?;
+        seqs.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    orphaned.extend(forget(conn, &stale)?);
+    Ok((step, orphaned))
+}
+
+/// Drops steps, releasing the references they hold. Returns the hashes of blobs nothing
+/// uses any more.
+fn forget(conn: &Connection, steps: &[i64]) -> Result<Vec<String>> {
+    let mut orphaned = Vec::new();
+    for seq in steps {
+        let hashes = {
+            let mut stmt = conn.prepare("SELECT file_hash FROM history_files WHERE step = ?1")?;
+            let hashes = stmt.query_map([seq], |row| row.get::<_, String>(0))?;
+            hashes.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        conn.execute("DELETE FROM history WHERE seq = ?1", [seq])?;
+        orphaned.extend(release_all(conn, &hashes)?);
+    }
+    Ok(orphaned)
+}
+
+/// Deletes a card the step puts back when made (or reversed), holding its references,
+/// unless its files have changed since.
+fn take_out_card(conn: &Connection, step: i64, card: &mut StoredCard) -> Result<()> {
+    let hashes = hashes_of(conn, &card.meta.id)?;
+    if hashes != Some((card.file_hash.clone(), card.face_hashes.clone())) {
+        return Ok(());
+    }
+    card.checklist_match = checklist_match(conn, &card.meta.id)?;
+    conn.execute("DELETE FROM cards WHERE id = ?1", [&card.meta.id])?;
+    hold(conn, step, &card.meta.id, card.hashes())
+}
+
+/// Puts back a card the step holds. If one with its id has turned up meanwhile, the
+/// step's references are released instead. Returns the hashes of blobs nothing uses
+/// any more.
+fn put_back_card(conn: &Connection, step: i64, card: &StoredCard) -> Result<Vec<String>> {
+    if !let_go(conn, step, &card.meta.id)? {
+        return Ok(Vec::new());
+    }
+    if select_card(conn, &card.meta.id)?.is_some() {
+        return release_all(conn, card.hashes());
+    }
+    // Takes over the references the step held
+    insert_card(conn, &card.meta, &card.file_hash)?;
+    for (position, (face, hash)) in card.meta.faces.iter().zip(&card.face_hashes).enumerate() {
+        insert_face(conn, &card.meta.id, position, face, hash)?;
+    }
+    if let Some(entry) = &card.checklist_match {
+        conn.execute(
+            "INSERT INTO checklist_matches (card_id, entry) VALUES (?1, ?2)",
+            params![card.meta.id, entry],
+        )?;
+    }
+    Ok(Vec::new())
+}
+
+fn checklist_match(conn: &Connection, card_id: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT entry FROM checklist_matches WHERE card_id = ?1",
+            [card_id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Like [`take_out_card`] for a face.
+fn take_out_face(conn: &Connection, step: i64, face: &StoredFace) -> Result<()> {
+    let current: Option<(String, String)> = conn
+        .query_row(
+            "SELECT card_id, file_hash FROM card_faces WHERE id = ?1",
+            [&face.face.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    if current != Some((face.card_id.clone(), face.file_hash.clone())) {
+        return Ok(());
+    }
+    remove_face(conn, &face.face.id)?;
+    touch(conn, &face.card_id)?;
+    hold(conn, step, &face.face.id, [&face.file_hash])
+}
+
+/// Like [`put_back_card`] for a face. It goes back where it was, or last if the card
+/// has fewer faces now; if the card is gone, its reference is released.
+fn put_back_face(conn: &Connection, step: i64, face: &StoredFace) -> Result<Vec<String>> {
+    if !let_go(conn, step, &face.face.id)? {
+        return Ok(Vec::new());
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM card_faces WHERE id = ?1)",
+        [&face.face.id],
+        |row| row.get(0),
+    )?;
+    if taken || select_card(conn, &face.card_id)?.is_none() {
+        return release_all(conn, [&face.file_hash]);
+    }
+    place_face(
+        conn,
+        &face.card_id,
+        Some(face.position),
+        &face.face,
+        &face.file_hash,
+    )?;
+    touch(conn, &face.card_id)?;
+    Ok(Vec::new())
+}
+
+/// Notes that the step holds the references of a card or face (`item`) it took out.
+fn hold<'a>(
+    conn: &Connection,
+    step: i64,
+    item: &str,
+    hashes: impl IntoIterator<Item = &'a String>,
+) -> Result<()> {
+    for hash in hashes {
+        conn.execute(
+            "INSERT INTO history_files (step, item_id, file_hash) VALUES (?1, ?2, ?3)",
+            params![step, item, hash],
+        )?;
+    }
+    Ok(())
+}
+
+/// Stops the step holding `item`'s references, which pass to the caller. Returns
+/// whether it held any.
+fn let_go(conn: &Connection, step: i64, item: &str) -> Result<bool> {
+    let held = conn.execute(
+        "DELETE FROM history_files WHERE step = ?1 AND item_id = ?2",
+        params![step, item],
+    )?;
+    Ok(held > 0)
+}
+
+/// Releases a reference to each blob. Returns the hashes of those nothing uses any more.
+fn release_all<'a>(
+    conn: &Connection,
+    hashes: impl IntoIterator<Item = &'a String>,
+) -> Result<Vec<String>> {
+    let mut orphaned = Vec::new();
+    for hash in hashes {
+        if release_blob(conn, hash)? {
+            orphaned.push(hash.clone());
+        }
+    }
+    Ok(orphaned)
+}
+
+/// The hashes of a card's file and faces, or `None` if there's no such card (or it has
+/// no file).
+fn hashes_of(conn: &Connection, id: &str) -> Result<Option<(String, Vec<String>)>> {
+    let hash: Option<Option<String>> = conn
+        .query_row("SELECT file_hash FROM cards WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    match hash.flatten() {
+        Some(hash) => Ok(Some((hash, face_hashes(conn, id)?))),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn read_step(conn: &Connection, step: i64) -> Result<HistoryStep> {
+    Ok(conn.query_row(
+        "SELECT seq, description, created_at, undone FROM history WHERE seq = ?1",
+        [step],
+        step_from_row,
+    )?)
+}
+
+fn step_from_row(row: &Row<'_>) -> rusqlite::Result<HistoryStep> {
+    Ok(HistoryStep {
+        id: row.get(0)?,
+        description: row.get(1)?,
+        created_at: row.get(2)?,
+        undone: row.get(3)?,
+    })
+}
+
+/// What an edit changed about a card, in words, in the order the card editor shows
+/// them.
+fn changed_fields(before: &CardMeta, after: &CardMeta) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    let mut check = |changed: bool, field| {
+        if changed {
+            fields.push(field);
+        }
+    };
+    check(before.name != after.name, "name");
+    check(before.favorite != after.favorite, "favorite");
+    check(before.collection != after.collection, "collection");
+    check(before.tier != after.tier, "tier");
+    check(before.quantity != after.quantity, "quantity");
+    check(before.condition != after.condition, "condition");
+    check(before.tags != after.tags, "tags");
+    check(before.nsfw != after.nsfw, "NSFW");
+    check(before.pages != after.pages, "page count");
+    check(
+        before.kind != after.kind || before.orig_ext != after.orig_ext || before.mime != after.mime,
+        "file type",
+    );
+    check(
+        before.thumbnail_data_url != after.thumbnail_data_url,
+        "thumbnail",
+    );
+    fields
+}
+
+/// `a`, `a and b`, `a, b and c`.
+fn join(words: &[&str]) -> String {
+    match words {
+        [] => String::new(),
+        [word] => word.to_string(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+pub(super) fn label(card: &CardMeta) -> String {
+    if card.name.trim().is_empty() {
+        "an untitled card".to_string()
+    } else {
+        format!("\"{}\"", card.name.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::{test_card, EditKind, OrderMap};
+
+    fn card(id: &str, collection: &str) -> CardMeta {
+        let mut card = test_card(id);
+        card.name = id.to_uppercase();
+        card.collection = collection.into();
+        card
+    }
+
+    fn tier(tier: &str) -> CardPatch {
+        serde_json::from_value(serde_json::json!({ "tier": tier })).unwrap()
+    }
+
+    #[test]
+    fn steps_undo_and_redo_in_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library
+            .add_card(card("moth", "Quiet Court"), b"moth")
+            .unwrap();
+        library
+            .add_card(card("wren", "Quiet Court"), b"wren")
+            .unwrap();
+        library
+            .restore_custom_collections(&["Quiet Court".into()])
+            .unwrap();
+
+        library
+            .edit_cards(vec![("moth".into(), tier("S")), ("wren".into(), tier("S"))])
+            .unwrap();
+        library.delete_cards(&["wren".into()]).unwrap();
+        library
+            .delete_custom_collections(&["quiet  court".into()])
+            .unwrap();
+        let descriptions: Vec<String> = library
+            .history()
+            .unwrap()
+            .into_iter()
+            .map(|step| step.description)
+            .collect();
+        assert_eq!(
+            descriptions,
+            [
+                "Delete collection \"Quiet Court\"",
+                "Delete \"WREN\"",
+                "Change tier on 2 cards"
+            ]
+        );
+        let moth = library.get_card("moth").unwrap().unwrap();
+        assert_eq!((moth.collection.as_str(), moth.tier.as_str()), ("", "S"));
+
+        for _ in 0..3 {
+            library.undo().unwrap().unwrap();
+        }
+        assert!(library.undo().unwrap().is_none());
+        let moth = library.get_card("moth").unwrap().unwrap();
+        assert_eq!(
+            (moth.collection.as_str(), moth.tier.as_str()),
+            ("Quiet Court", "")
+        );
+        assert_eq!(library.read_card_file("wren").unwrap(), b"wren");
+        assert_eq!(library.custom_collections().unwrap(), ["Quiet Court"]);
+
+        let redone = library.redo().unwrap().unwrap();
+        assert_eq!(redone.description, "Change tier on 2 cards");
+        library.redo().unwrap().unwrap();
+        assert!(library.get_card("wren").unwrap().is_none());
+        // A new edit forgets what could still have been redone
+        library
+            .edit_cards(vec![("moth".into(), tier("A"))])
+            .unwrap();
+        assert!(library.redo().unwrap().is_none());
+        assert_eq!(
+            library.history().unwrap()[0].description,
+            "Change tier of \"MOTH\""
+        );
+        assert!(library.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    fn forgotten_steps_let_go_of_deleted_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(card("moth", ""), b"moth").unwrap();
+        library.add_card(card("wren", ""), b"wren").unwrap();
+        let path = library.card_file_path("wren").unwrap();
+
+        library.delete_cards(&["wren".into()]).unwrap();
+        assert!(path.exists());
+        for i in 0..HISTORY_LIMIT {
+            let name = serde_json::from_value(serde_json::json!({ "name": i.to_string() }));
+            library
+                .edit_cards(vec![("moth".into(), name.unwrap())])
+                .unwrap();
+        }
+        assert_eq!(library.history().unwrap().len(), HISTORY_LIMIT);
+        assert!(!path.exists());
+        assert!(library.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    fn forgotten_redos_let_go_of_added_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(card("moth", ""), b"moth").unwrap();
+        library
+            .replace_cards(
+                EditKind::Split,
+                &["moth".into()],
+                vec![(card("moth-1", ""), b"page 1".to_vec())],
+            )
+            .unwrap();
+        let page = library.card_file_path("moth-1").unwrap();
+
+        library.undo().unwrap();
+        assert!(library.get_card("moth-1").unwrap().is_none());
+        assert!(page.exists());
+        library.add_card(card("wren", ""), b"wren").unwrap();
+        library.delete_cards(&["wren".into()]).unwrap();
+        assert!(!page.exists());
+        assert_eq!(library.read_card_file("moth").unwrap(), b"moth");
+        assert!(library.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    fn order_and_custom_collections_are_steps() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        let order = OrderMap::from([("quiet court".to_string(), vec!["moth".to_string()])]);
+        library.set_order_map(&order).unwrap();
+        library.set_order_map(&order).unwrap();
+        library
+            .set_custom_collections(&["Quiet Court".into()])
+            .unwrap();
+        let descriptions: Vec<String> = library
+            .history()
+            .unwrap()
+            .into_iter()
+            .map(|step| step.description)
+            .collect();
+        assert_eq!(
+            descriptions,
+            ["Add collection \"Quiet Court\"", "Reorder cards"]
+        );
+
+        library.undo().unwrap();
+        assert!(library.custom_collections().unwrap().is_empty());
+        library.undo().unwrap();
+        assert!(library.order_map().unwrap().is_empty());
+        // Restoring a backup isn't something to undo
+        library.restore_order_map(&order).unwrap();
+        assert_eq!(library.history().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn checklist_matches_come_back_with_their_cards() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(card("moth", ""), b"moth").unwrap();
+        library.set_checklist_match("moth", Some("qc-1")).unwrap();
+        library
+            .replace_cards(
+                EditKind::Split,
+                &["moth".into()],
+                vec![(card("moth-1", ""), b"page 1".to_vec())],
+            )
+            .unwrap();
+        library.set_checklist_match("moth-1", Some("qc-2")).unwrap();
+
+        library.undo().unwrap();
+        let matches = library.checklist_matches().unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches["moth"], "qc-1");
+        // Matched after the split, and still when it's redone
+        library.redo().unwrap();
+        let matches = library.checklist_matches().unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches["moth-1"], "qc-2");
+    }
+
+    #[test]
+    fn merging_copies_is_one_step() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(card("moth", ""), b"moth").unwrap();
+        library
+            .add_card(card("moth-2", "Quiet Court"), b"moth 2")
+            .unwrap();
+        let merged = library
+            .merge_cards("moth", &["moth-2".into()], |card, other| {
+                card.quantity += other.quantity;
+                card.collection.clone_from(&other.collection);
+            })
+            .unwrap();
+        assert_eq!(
+            (merged.quantity, merged.collection.as_str()),
+            (2, "Quiet Court")
+        );
+        assert!(library.get_card("moth-2").unwrap().is_none());
+        assert_eq!(
+            library.history().unwrap()[0].description,
+            "Merge 2 copies of \"MOTH\""
+        );
+
+        library.undo().unwrap();
+        let moth = library.get_card("moth").unwrap().unwrap();
+        assert_eq!((moth.quantity, moth.collection.as_str()), (1, ""));
+        assert_eq!(library.read_card_file("moth-2").unwrap(), b"moth 2");
+        assert!(matches!(
+            library.merge_cards("moth", &["wren".into()], |_, _| {}),
+            Err(LibraryError::NotFound(id)) if id == "wren"
+        ));
+        // Nothing changed, so nothing to undo
+        assert!(library.get_card("moth-2").unwrap().is_some());
+        assert!(library.undo().unwrap().is_none());
+    }
+}
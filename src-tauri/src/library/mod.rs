@@ -0,0 +1,598 @@
+//! On-disk card library.
+//!
+//! Card bytes live in a content-addressed blob store under the library root (see
+//! [`blobs`]) and metadata in an SQLite database next to them. This replaces the `pdf-card-binder-files` and
+//! `pdf-card-binder-meta` localforage instances, which hit WebView storage quotas on
+//! large binders and were lost whenever the WebView profile was reset.
+
+mod blobs;
+mod card;
+mod checklist;
+mod collections;
+mod edits;
+mod faces;
+mod history;
+mod perceptual;
+mod queries;
+mod schema;
+mod smart;
+mod watch;
+
+pub use blobs::hash_bytes;
+pub use card::{CardFace, CardKind, CardMeta, CardPatch};
+pub use collections::{collection_key, OrderMap};
+pub use edits::EditKind;
+pub use history::{HistoryStep, HISTORY_LIMIT};
+pub use perceptual::{PerceptualHash, UnhashedFile};
+pub use queries::SavedQuery;
+pub use smart::{SmartCollection, SmartFilter};
+pub use watch::{SeenFile, WatchFolder};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Row, TransactionBehavior};
+use serde::Serialize;
+
+use blobs::BlobStore;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    #[error("card not found: {0}")]
+    NotFound(String),
+    #[error("card already exists: {0}")]
+    AlreadyExists(String),
+    #[error("database error: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid metadata: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, LibraryError>;
+
+const DB_FILE: &str = "library.db";
+const BLOBS_DIR: &str = "blobs";
+
+const CARD_COLUMNS: &str = "id, name, pages, tags, collection, thumbnail_data_url, created_at, \
+    updated_at, tier, favorite, kind, nsfw, orig_ext, mime, quantity, condition";
+
+/// Result of [`Library::add_card`]. Serialized as `{ "status": "added", "card": ... }` or
+/// `{ "status": "alreadyInLibrary", "existingId": ... }`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "status",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AddOutcome {
+    Added { card: Box<CardMeta> },
+    AlreadyInLibrary { existing_id: String },
+}
+
+/// Result of [`Library::verify`]: cards whose file is gone or no longer matches its hash.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub missing: Vec<String>,
+    pub corrupt: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+type ChangeListener = Box<dyn Fn() + Send + Sync>;
+
+pub struct Library {
+    root: PathBuf,
+    blobs: BlobStore,
+    conn: Mutex<Connection>,
+    listeners: Mutex<Vec<ChangeListener>>,
+}
+
+impl Library {
+    /// Opens (creating if needed) the library rooted at `root`.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let blobs = BlobStore::new(root.join(BLOBS_DIR));
+        fs::create_dir_all(blobs.dir())?;
+        let mut conn = Connection::open(root.join(DB_FILE))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        // The GUI and the command line may have the library open at the same time
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        schema::migrate(&mut conn)?;
+        Ok(Self {
+            root,
+            blobs,
+            conn: Mutex::new(conn),
+            listeners: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Calls `listener` after every change to a card (added, updated, deleted or
+    /// cleared), for indexes kept alongside the library. It runs on the thread that made
+    /// the change, so it should hand any real work off elsewhere.
+    pub fn on_change(&self, listener: impl Fn() + Send + Sync + 'static) {
+        self.listeners
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(listener));
+    }
+
+    fn changed(&self) {
+        for listener in self
+            .listeners
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            listener();
+        }
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic while holding the lock can't leave SQLite in a torn state, so keep going.
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn list_cards(&self) -> Result<Vec<CardMeta>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {CARD_COLUMNS} FROM cards ORDER BY created_at, id"
+        ))?;
+        let mut cards = stmt
+            .query_map([], card_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut faces = faces::all_faces(&conn)?;
+        for card in &mut cards {
+            card.faces = faces.remove(&card.id).unwrap_or_default();
+        }
+        Ok(cards)
+    }
+
+    pub fn get_card(&self, id: &str) -> Result<Option<CardMeta>> {
+        select_card(&self.conn(), id)
+    }
+
+    /// Imports a new card. If a card with byte-identical content is already in the
+    /// library, nothing is stored and the existing card's id is reported instead. Any
+    /// `faces` in `meta` are ignored; attach them afterwards with their files.
+    pub fn add_card(&self, meta: CardMeta, bytes: &[u8]) -> Result<AddOutcome> {
+        let hash = hash_bytes(bytes);
+        let mut conn = self.conn();
+        if let Some(existing_id) = find_by_hash(&conn, &hash)? {
+            return Ok(AddOutcome::AlreadyInLibrary { existing_id });
+        }
+        let card = self.insert_card_with_blob(&mut conn, meta, &hash, bytes)?;
+        drop(conn);
+        self.changed();
+        Ok(AddOutcome::Added {
+            card: Box::new(card),
+        })
+    }
+
+    /// Stores a card even if another card already has the same content, sharing the
+    /// blob. Used when restoring backups, which may legitimately contain such twins.
+    pub fn restore_card(&self, meta: CardMeta, bytes: &[u8]) -> Result<CardMeta> {
+        let hash = hash_bytes(bytes);
+        let card = self.insert_card_with_blob(&mut self.conn(), meta, &hash, bytes)?;
+        self.changed();
+        Ok(card)
+    }
+
+    /// Id of the oldest card whose file has the given SHA-256, if any.
+    pub fn find_by_hash(&self, hash: &str) -> Result<Option<String>> {
+        find_by_hash(&self.conn(), hash)
+    }
+
+    pub fn file_hash(&self, id: &str) -> Result<Option<String>> {
+        let hash = self
+            .conn()
+            .query_row("SELECT file_hash FROM cards WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .optional()?
+            .ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+        Ok(hash)
+    }
+
+    /// Every card's file hash, by card id, for grouping identical files.
+    pub fn file_hashes(&self) -> Result<HashMap<String, String>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT id, file_hash FROM cards WHERE file_hash IS NOT NULL")?;
+        let hashes = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(hashes.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn insert_card_with_blob(
+        &self,
+        conn: &mut Connection,
+        mut meta: CardMeta,
+        hash: &str,
+        bytes: &[u8],
+    ) -> Result<CardMeta> {
+        let now = now_millis();
+        meta.faces.clear();
+        if meta.created_at == 0 {
+            meta.created_at = now;
+        }
+        if meta.updated_at == 0 {
+            meta.updated_at = now;
+        }
+
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM cards WHERE id = ?1)",
+            [&meta.id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(LibraryError::AlreadyExists(meta.id));
+        }
+
+        let tx = conn.transaction()?;
+        self.retain_blob(&tx, hash, bytes)?;
+        insert_card(&tx, &meta, hash)?;
+        tx.commit()?;
+        Ok(meta)
+    }
+
+    /// Takes a reference to the blob and makes sure it's on disk. The reference comes
+    /// first: once it's written the transaction holds SQLite's write lock, so a
+    /// [`Library::remove_orphans`] elsewhere can't unlink the file after `put` saw it.
+    fn retain_blob(&self, conn: &Connection, hash: &str, bytes: &[u8]) -> Result<()> {
+        conn.execute(
+            "INSERT INTO blobs (hash, size, refcount) VALUES (?1, ?2, 1)
+             ON CONFLICT (hash) DO UPDATE SET refcount = refcount + 1",
+            params![hash, bytes.len() as i64],
+        )?;
+        self.blobs.put(hash, bytes)?;
+        Ok(())
+    }
+
+    /// Deletes the files of blobs whose last reference went in a transaction that has
+    /// just committed. Runs with the connection still locked and re-checks each blob
+    /// under SQLite's write lock, so a card added with the same bytes in the meantime
+    /// (by this process or the command line) keeps its file.
+    fn remove_orphans(&self, conn: &mut Connection, hashes: Vec<String>) -> Result<()> {
+        if hashes.is_empty() {
+            return Ok(());
+        }
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for hash in hashes {
+            let referenced: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM blobs WHERE hash = ?1)",
+                [&hash],
+                |row| row.get(0),
+            )?;
+            if !referenced {
+                self.blobs.remove(&hash)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes a card with its faces, and their blobs once nothing else references them.
+    /// Not recorded in the history: it's for taking back a card a restore or import just
+    /// added. Edits the user makes go through [`Library::delete_cards`].
+    pub fn delete_card(&self, id: &str) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let hash: Option<String> = tx
+            .query_row("SELECT file_hash FROM cards WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .optional()?
+            .ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+        let face_hashes = faces::face_hashes(&tx, id)?;
+        tx.execute("DELETE FROM cards WHERE id = ?1", [id])?;
+        let mut orphaned = Vec::new();
+        for hash in hash.into_iter().chain(face_hashes) {
+            if release_blob(&tx, &hash)? {
+                orphaned.push(hash);
+            }
+        }
+        tx.commit()?;
+        self.remove_orphans(&mut conn, orphaned)?;
+        drop(conn);
+        self.changed();
+        Ok(())
+    }
+
+    /// Removes every card and file. Used by "replace" restores.
+    pub fn clear(&self) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM cards", [])?;
+        // History steps hold blob references too, and there's nothing left to undo into
+        tx.execute("DELETE FROM history", [])?;
+        tx.execute("DELETE FROM blobs", [])?;
+        tx.commit()?;
+        // Still locked, so no card added meanwhile loses its file to this
+        fs::remove_dir_all(self.blobs.dir())?;
+        fs::create_dir_all(self.blobs.dir())?;
+        drop(conn);
+        self.changed();
+        Ok(())
+    }
+
+    /// Where a card's file lives on disk, for callers that want to stream it.
+    pub fn card_file_path(&self, id: &str) -> Result<PathBuf> {
+        let hash = self
+            .file_hash(id)?
+            .ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+        Ok(self.blobs.path(&hash))
+    }
+
+    pub fn read_card_file(&self, id: &str) -> Result<Vec<u8>> {
+        let not_found = || LibraryError::NotFound(id.to_string());
+        let hash = self.file_hash(id)?.ok_or_else(not_found)?;
+        match self.blobs.read(&hash) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(not_found()),
+            other => Ok(other?),
+        }
+    }
+
+    /// Re-hashes every card's file and those of its faces, reporting the cards with any
+    /// that are missing or damaged.
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let conn = self.conn();
+        let cards = conn
+            .prepare("SELECT id, file_hash FROM cards ORDER BY created_at, id")?
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(String, Option<String>)>>>()?;
+        let faces = conn
+            .prepare("SELECT card_id, file_hash FROM card_faces ORDER BY card_id, position")?
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(String, Option<String>)>>>()?;
+        drop(conn);
+        let mut report = VerifyReport::default();
+        for (id, hash) in cards {
+            report.checked += 1;
+            self.verify_blob(&mut report, id, hash)?;
+        }
+        for (id, hash) in faces {
+            if !report.missing.contains(&id) && !report.corrupt.contains(&id) {
+                self.verify_blob(&mut report, id, hash)?;
+            }
+        }
+        Ok(report)
+    }
+
+    fn verify_blob(
+        &self,
+        report: &mut VerifyReport,
+        id: String,
+        hash: Option<String>,
+    ) -> Result<()> {
+        let Some(hash) = hash else {
+            report.missing.push(id);
+            return Ok(());
+        };
+        match self.blobs.read(&hash) {
+            Ok(bytes) if hash_bytes(&bytes) == hash => {}
+            Ok(_) => report.corrupt.push(id),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => report.missing.push(id),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+}
+
+/// Drops one reference to a blob, returning `true` if that was the last one. The
+/// caller removes the file with [`Library::remove_orphans`] after its transaction
+/// commits.
+fn release_blob(conn: &Connection, hash: &str) -> Result<bool> {
+    conn.execute(
+        "UPDATE blobs SET refcount = refcount - 1 WHERE hash = ?1",
+        [hash],
+    )?;
+    let removed = conn.execute(
+        "DELETE FROM blobs WHERE hash = ?1 AND refcount <= 0",
+        [hash],
+    )?;
+    Ok(removed > 0)
+}
+
+fn find_by_hash(conn: &Connection, hash: &str) -> Result<Option<String>> {
+    let id = conn
+        .query_row(
+            "SELECT id FROM cards WHERE file_hash = ?1 ORDER BY created_at, id LIMIT 1",
+            [hash],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(id)
+}
+
+fn select_card(conn: &Connection, id: &str) -> Result<Option<CardMeta>> {
+    let card = conn
+        .query_row(
+            &format!("SELECT {CARD_COLUMNS} FROM cards WHERE id = ?1"),
+            [id],
+            card_from_row,
+        )
+        .optional()?;
+    let Some(mut card) = card else {
+        return Ok(None);
+    };
+    card.faces = faces::faces_of(conn, id)?;
+    Ok(Some(card))
+}
+
+fn insert_card(conn: &Connection, meta: &CardMeta, file_hash: &str) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO cards ({CARD_COLUMNS}, file_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                ?17)"
+        ),
+        params![
+            meta.id,
+            meta.name,
+            meta.pages,
+            serde_json::to_string(&meta.tags)?,
+            meta.collection,
+            meta.thumbnail_data_url,
+            meta.created_at,
+            meta.updated_at,
+            meta.tier,
+            meta.favorite,
+            meta.kind.as_str(),
+            meta.nsfw,
+            meta.orig_ext,
+            meta.mime,
+            meta.quantity.max(1),
+            meta.condition,
+            file_hash,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Overwrites a card's details with `meta`'s. Its file and faces stay as they are.
+fn write_card(conn: &Connection, meta: &CardMeta) -> Result<()> {
+    conn.execute(
+        "UPDATE cards SET name = ?2, pages = ?3, tags = ?4, collection = ?5,
+            thumbnail_data_url = ?6, updated_at = ?7, tier = ?8, favorite = ?9, kind = ?10,
+            nsfw = ?11, orig_ext = ?12, mime = ?13, quantity = ?14, condition = ?15
+         WHERE id = ?1",
+        params![
+            meta.id,
+            meta.name,
+            meta.pages,
+            serde_json::to_string(&meta.tags)?,
+            meta.collection,
+            meta.thumbnail_data_url,
+            meta.updated_at,
+            meta.tier,
+            meta.favorite,
+            meta.kind.as_str(),
+            meta.nsfw,
+            meta.orig_ext,
+            meta.mime,
+            meta.quantity,
+            meta.condition,
+        ],
+    )?;
+    Ok(())
+}
+
+fn card_from_row(row: &Row<'_>) -> rusqlite::Result<CardMeta> {
+    let tags: String = row.get(3)?;
+    let kind: String = row.get(10)?;
+    Ok(CardMeta {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        pages: row.get(2)?,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        collection: row.get(4)?,
+        thumbnail_data_url: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+        tier: row.get(8)?,
+        favorite: row.get(9)?,
+        kind: CardKind::parse(&kind),
+        nsfw: row.get(11)?,
+        orig_ext: row.get(12)?,
+        mime: row.get(13)?,
+        quantity: row.get(14)?,
+        condition: row.get(15)?,
+        faces: Vec::new(),
+    })
+}
+
+/// Writes via a sibling temp file so a crash never leaves a half-written card behind.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut f = File::create(&tmp)?;
+    f.write_all(bytes)?;
+    f.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Milliseconds since the Unix epoch, matching `Date.now()` on the frontend.
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// A card with just an id, the rest as serde defaults it when a backup leaves it out.
+#[cfg(test)]
+pub(crate) fn test_card(id: &str) -> CardMeta {
+    serde_json::from_value(serde_json::json!({ "id": id })).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_files_are_stored_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path()).unwrap();
+        library.add_card(test_card("moth"), b"same bytes").unwrap();
+        match library.add_card(test_card("wren"), b"same bytes").unwrap() {
+            AddOutcome::AlreadyInLibrary { existing_id } => assert_eq!(existing_id, "moth"),
+            AddOutcome::Added { .. } => panic!("a duplicate was added"),
+        }
+        assert!(library.get_card("wren").unwrap().is_none());
+
+        // Restores keep twins, sharing the file until the last of them goes
+        library
+            .restore_card(test_card("wren"), b"same bytes")
+            .unwrap();
+        let path = library.card_file_path("moth").unwrap();
+        assert_eq!(path, library.card_file_path("wren").unwrap());
+        library.delete_card("moth").unwrap();
+        assert_eq!(library.read_card_file("wren").unwrap(), b"same bytes");
+        library.delete_card("wren").unwrap();
+        assert!(!path.exists());
+        assert!(matches!(
+            library.delete_card("wren"),
+            Err(LibraryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn orphans_taken_up_again_keep_their_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path()).unwrap();
+        library.add_card(test_card("moth"), b"moth").unwrap();
+        let hash = library.file_hash("moth").unwrap().unwrap();
+
+        let mut conn = library.conn();
+        let tx = conn.transaction().unwrap();
+        tx.execute("DELETE FROM cards WHERE id = 'moth'", [])
+            .unwrap();
+        assert!(release_blob(&tx, &hash).unwrap());
+        tx.commit().unwrap();
+        // The same bytes arrive before the file is unlinked
+        let tx = conn.transaction().unwrap();
+        library.retain_blob(&tx, &hash, b"moth").unwrap();
+        tx.commit().unwrap();
+        library
+            .remove_orphans(&mut conn, vec![hash.clone()])
+            .unwrap();
+        drop(conn);
+
+        assert!(library.blobs.path(&hash).exists());
+    }
+}
@@ -0,0 +1,84 @@
+use rusqlite::params;
+
+use super::{CardKind, Library, Result};
+
+/// Perceptual hashes of a file's rendered first page, 64 bits each (see
+/// `crate::similar`). Files that look alike have hashes that differ in few bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerceptualHash {
+    /// aHash: which pixels of an 8×8 thumbnail are brighter than average.
+    pub average: u64,
+    /// dHash: which pixels of a 9×8 thumbnail are brighter than their right neighbour.
+    pub difference: u64,
+    /// pHash: which low frequencies of a 32×32 thumbnail are above the median.
+    pub dct: u64,
+}
+
+/// A file with no perceptual hash yet, and a card to render it as.
+#[derive(Debug, Clone)]
+pub struct UnhashedFile {
+    pub hash: String,
+    pub card_id: String,
+    pub kind: CardKind,
+}
+
+impl Library {
+    /// Files that haven't been hashed (or found unrenderable) yet.
+    pub fn unhashed_files(&self) -> Result<Vec<UnhashedFile>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT c.file_hash, MIN(c.id), c.kind FROM cards c
+             LEFT JOIN perceptual_hashes p ON p.hash = c.file_hash
+             WHERE c.file_hash IS NOT NULL AND p.hash IS NULL
+             GROUP BY c.file_hash",
+        )?;
+        let files = stmt.query_map([], |row| {
+            Ok(UnhashedFile {
+                hash: row.get(0)?,
+                card_id: row.get(1)?,
+                kind: CardKind::parse(&row.get::<_, String>(2)?),
+            })
+        })?;
+        Ok(files.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Records a file's hashes, or `None` for a file that can't be rendered so it isn't
+    /// tried again. Does nothing if no card has the file any more.
+    pub fn set_perceptual_hash(&self, file_hash: &str, hash: Option<PerceptualHash>) -> Result<()> {
+        // SQLite integers are signed; the bits are what matter
+        let bits = |f: fn(&PerceptualHash) -> u64| hash.as_ref().map(|h| f(h) as i64);
+        self.conn().execute(
+            "INSERT OR REPLACE INTO perceptual_hashes (hash, average, difference, dct)
+             SELECT ?1, ?2, ?3, ?4 WHERE EXISTS (SELECT 1 FROM blobs WHERE hash = ?1)",
+            params![
+                file_hash,
+                bits(|h| h.average),
+                bits(|h| h.difference),
+                bits(|h| h.dct)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every hashed card's perceptual hash, in library order.
+    pub fn perceptual_hashes(&self) -> Result<Vec<(String, PerceptualHash)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT c.id, p.average, p.difference, p.dct FROM cards c
+             JOIN perceptual_hashes p ON p.hash = c.file_hash
+             WHERE p.average IS NOT NULL
+             ORDER BY c.created_at, c.id",
+        )?;
+        let hashes = stmt.query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                PerceptualHash {
+                    average: row.get::<_, i64>(1)? as u64,
+                    difference: row.get::<_, i64>(2)? as u64,
+                    dct: row.get::<_, i64>(3)? as u64,
+                },
+            ))
+        })?;
+        Ok(hashes.collect::<rusqlite::Result<_>>()?)
+    }
+}
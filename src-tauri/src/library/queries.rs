@@ -0,0 +1,62 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::{Library, LibraryError, Result};
+
+/// A filter query kept under a name (see `crate::query` for the language).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: String,
+}
+
+impl Library {
+    /// Saved queries, sorted by name.
+    pub fn saved_queries(&self) -> Result<Vec<SavedQuery>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT name, query FROM saved_queries ORDER BY name")?;
+        let queries = stmt.query_map([], |row| {
+            Ok(SavedQuery {
+                name: row.get(0)?,
+                query: row.get(1)?,
+            })
+        })?;
+        Ok(queries.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Saves a query, replacing any with the same name (names ignore case).
+    pub fn save_query(&self, query: &SavedQuery) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO saved_queries (name, query) VALUES (?1, ?2)
+             ON CONFLICT (name) DO UPDATE SET name = excluded.name, query = excluded.query",
+            params![query.name, query.query],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_saved_query(&self, name: &str) -> Result<()> {
+        let removed = self
+            .conn()
+            .execute("DELETE FROM saved_queries WHERE name = ?1", [name])?;
+        if removed == 0 {
+            return Err(LibraryError::NotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Replaces every saved query, as restoring a backup does.
+    pub fn set_saved_queries(&self, queries: &[SavedQuery]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM saved_queries", [])?;
+        for query in queries {
+            tx.execute(
+                "INSERT OR REPLACE INTO saved_queries (name, query) VALUES (?1, ?2)",
+                params![query.name, query.query],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use rusqlite::Connection;
+
+/// Schema migrations, applied in order. The index + 1 is stored in `PRAGMA user_version`,
+/// so only append to this list — never edit an entry that has shipped.
+const MIGRATIONS: &[&str] = &[
+    // 1: card metadata
+    "CREATE TABLE cards (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL DEFAULT '',
+        pages INTEGER NOT NULL DEFAULT 1,
+        tags TEXT NOT NULL DEFAULT '[]',
+        collection TEXT NOT NULL DEFAULT '',
+        thumbnail_data_url TEXT NOT NULL DEFAULT '',
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        tier TEXT NOT NULL DEFAULT '',
+        favorite INTEGER NOT NULL DEFAULT 0,
+        kind TEXT NOT NULL DEFAULT 'pdf',
+        nsfw INTEGER,
+        orig_ext TEXT NOT NULL DEFAULT '',
+        mime TEXT NOT NULL DEFAULT ''
+    );
+    CREATE INDEX cards_collection ON cards (collection);",
+    // 2: content-addressed files, shared between cards by reference count
+    "CREATE TABLE blobs (
+        hash TEXT PRIMARY KEY NOT NULL,
+        size INTEGER NOT NULL,
+        refcount INTEGER NOT NULL DEFAULT 0
+    );
+    ALTER TABLE cards ADD COLUMN file_hash TEXT REFERENCES blobs (hash);
+    CREATE INDEX cards_file_hash ON cards (file_hash);",
+    // 3: grouping state that used to live in the order/custom-collection localforage stores
+    "CREATE TABLE collection_order (
+        collection_key TEXT PRIMARY KEY NOT NULL,
+        card_ids TEXT NOT NULL DEFAULT '[]'
+    );
+    CREATE TABLE custom_collections (
+        name TEXT PRIMARY KEY NOT NULL COLLATE NOCASE,
+        position INTEGER NOT NULL
+    );",
+    // 4: watch folders and the ledger of files they have already imported
+    "CREATE TABLE watch_folders (
+        path TEXT PRIMARY KEY NOT NULL,
+        collection TEXT NOT NULL DEFAULT '',
+        tier TEXT NOT NULL DEFAULT '',
+        nsfw INTEGER
+    );
+    CREATE TABLE watch_seen (
+        path TEXT PRIMARY KEY NOT NULL,
+        folder TEXT NOT NULL REFERENCES watch_folders (path) ON DELETE CASCADE,
+        size INTEGER NOT NULL,
+        modified_at INTEGER NOT NULL,
+        hash TEXT NOT NULL
+    );
+    CREATE INDEX watch_seen_folder ON watch_seen (folder);",
+    // 5: named filter queries
+    "CREATE TABLE saved_queries (
+        name TEXT PRIMARY KEY NOT NULL COLLATE NOCASE,
+        query TEXT NOT NULL
+    );",
+    // 6: smart collections, with their filter as JSON
+    "CREATE TABLE smart_collections (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        filter TEXT NOT NULL DEFAULT '{}',
+        position INTEGER NOT NULL
+    );",
+    // 7: cards the user matched to a checklist entry by hand
+    "CREATE TABLE checklist_matches (
+        card_id TEXT PRIMARY KEY NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+        entry TEXT NOT NULL
+    );",
+    // 8: copies owned and their condition, for trading duplicates
+    "ALTER TABLE cards ADD COLUMN quantity INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE cards ADD COLUMN condition TEXT NOT NULL DEFAULT '';",
+    // 9: perceptual hashes of each file's first page; NULLs for files that can't be rendered
+    "CREATE TABLE perceptual_hashes (
+        hash TEXT PRIMARY KEY NOT NULL REFERENCES blobs (hash) ON DELETE CASCADE,
+        average INTEGER,
+        difference INTEGER,
+        dct INTEGER
+    );",
+    // 10: a card's other faces (back, alternate art), each with a blob reference that's
+    // released before the card is deleted
+    "CREATE TABLE card_faces (
+        id TEXT PRIMARY KEY NOT NULL,
+        card_id TEXT NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        label TEXT NOT NULL DEFAULT '',
+        kind TEXT NOT NULL DEFAULT 'image',
+        orig_ext TEXT NOT NULL DEFAULT '',
+        mime TEXT NOT NULL DEFAULT '',
+        file_hash TEXT NOT NULL REFERENCES blobs (hash)
+    );
+    CREATE INDEX card_faces_card ON card_faces (card_id, position);",
+    // 11: the undo/redo log of library edits; a step holds the blob references of the
+    // cards and faces it took out in history_files, under their id, until it puts them
+    // back or is forgotten
+    "CREATE TABLE history (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        description TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        operations TEXT NOT NULL,
+        undone INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE history_files (
+        step INTEGER NOT NULL REFERENCES history (seq) ON DELETE CASCADE,
+        item_id TEXT NOT NULL,
+        file_hash TEXT NOT NULL REFERENCES blobs (hash)
+    );
+    CREATE INDEX history_files_step ON history_files (step, item_id);",
+];
+
+pub fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let tx = conn.transaction()?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+    }
+    tx.commit()
+}
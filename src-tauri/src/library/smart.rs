@@ -0,0 +1,125 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::{CardKind, Library, LibraryError, Result};
+
+/// A collection whose cards are picked by a filter instead of by name. Cards keep
+/// their regular collection; a card can be in any number of smart collections.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartCollection {
+    /// Assigned by the library when the collection is added.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub filter: SmartFilter,
+}
+
+/// Conditions a card must all meet. Empty or `None` fields don't constrain anything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SmartFilter {
+    /// Lowest tier included, by name.
+    pub tier_min: String,
+    /// Highest tier included, by name.
+    pub tier_max: String,
+    /// Tags the card must all have (any case).
+    pub tags: Vec<String>,
+    /// Tags the card must have none of (any case).
+    pub exclude_tags: Vec<String>,
+    pub nsfw: Option<bool>,
+    pub favorite: Option<bool>,
+    /// Added at or after this time, in milliseconds since the Unix epoch.
+    pub added_from: Option<i64>,
+    /// Added before this time, in milliseconds since the Unix epoch.
+    pub added_until: Option<i64>,
+    /// Any of these kinds.
+    pub kinds: Vec<CardKind>,
+    /// Further conditions in the filter query language.
+    pub query: String,
+}
+
+impl Library {
+    /// Smart collections in their display order.
+    pub fn smart_collections(&self) -> Result<Vec<SmartCollection>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT id, name, filter FROM smart_collections ORDER BY position, name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut collections = Vec::new();
+        for row in rows {
+            let (id, name, filter) = row?;
+            collections.push(SmartCollection {
+                id,
+                name,
+                filter: serde_json::from_str(&filter)?,
+            });
+        }
+        Ok(collections)
+    }
+
+    /// Adds a smart collection after the existing ones. `collection.id` must be set.
+    pub fn add_smart_collection(&self, collection: &SmartCollection) -> Result<()> {
+        let inserted = self.conn().execute(
+            "INSERT OR IGNORE INTO smart_collections (id, name, filter, position)
+             VALUES (?1, ?2, ?3, (SELECT COALESCE(MAX(position), -1) + 1 FROM smart_collections))",
+            params![
+                collection.id,
+                collection.name,
+                serde_json::to_string(&collection.filter)?
+            ],
+        )?;
+        if inserted == 0 {
+            return Err(LibraryError::AlreadyExists(collection.id.clone()));
+        }
+        Ok(())
+    }
+
+    /// Renames a smart collection and replaces its filter.
+    pub fn update_smart_collection(&self, collection: &SmartCollection) -> Result<()> {
+        let updated = self.conn().execute(
+            "UPDATE smart_collections SET name = ?2, filter = ?3 WHERE id = ?1",
+            params![
+                collection.id,
+                collection.name,
+                serde_json::to_string(&collection.filter)?
+            ],
+        )?;
+        if updated == 0 {
+            return Err(LibraryError::NotFound(collection.id.clone()));
+        }
+        Ok(())
+    }
+
+    pub fn delete_smart_collection(&self, id: &str) -> Result<()> {
+        let removed = self
+            .conn()
+            .execute("DELETE FROM smart_collections WHERE id = ?1", [id])?;
+        if removed == 0 {
+            return Err(LibraryError::NotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Puts smart collections in the order of `ids`. Ones not listed go last.
+    pub fn reorder_smart_collections(&self, ids: &[String]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE smart_collections SET position = ?1", [ids.len()])?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE smart_collections SET position = ?2 WHERE id = ?1",
+                params![id, position],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::{Library, LibraryError, Result};
+
+/// A folder whose new files are imported automatically, and the metadata they get.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchFolder {
+    pub path: String,
+    #[serde(default)]
+    pub collection: String,
+    #[serde(default)]
+    pub tier: String,
+    /// `None` leaves the card's NSFW flag unset, so the collection default applies.
+    #[serde(default)]
+    pub nsfw: Option<bool>,
+}
+
+/// A ledger entry: what a watched file looked like when it was last imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeenFile {
+    pub size: u64,
+    pub modified_at: i64,
+    pub hash: String,
+}
+
+impl Library {
+    pub fn watch_folders(&self) -> Result<Vec<WatchFolder>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT path, collection, tier, nsfw FROM watch_folders ORDER BY path")?;
+        let folders = stmt.query_map([], |row| {
+            Ok(WatchFolder {
+                path: row.get(0)?,
+                collection: row.get(1)?,
+                tier: row.get(2)?,
+                nsfw: row.get(3)?,
+            })
+        })?;
+        Ok(folders.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn add_watch_folder(&self, folder: &WatchFolder) -> Result<()> {
+        let inserted = self.conn().execute(
+            "INSERT OR IGNORE INTO watch_folders (path, collection, tier, nsfw)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![folder.path, folder.collection, folder.tier, folder.nsfw],
+        )?;
+        if inserted == 0 {
+            return Err(LibraryError::AlreadyExists(folder.path.clone()));
+        }
+        Ok(())
+    }
+
+    /// Changes a folder's rules. Files it already imported keep the metadata they got.
+    pub fn update_watch_folder(&self, folder: &WatchFolder) -> Result<()> {
+        let updated = self.conn().execute(
+            "UPDATE watch_folders SET collection = ?2, tier = ?3, nsfw = ?4 WHERE path = ?1",
+            params![folder.path, folder.collection, folder.tier, folder.nsfw],
+        )?;
+        if updated == 0 {
+            return Err(LibraryError::NotFound(folder.path.clone()));
+        }
+        Ok(())
+    }
+
+    /// Stops tracking a folder and forgets which of its files were seen, so adding it
+    /// again rescans everything (content already in the library is still skipped).
+    pub fn remove_watch_folder(&self, path: &str) -> Result<()> {
+        let removed = self
+            .conn()
+            .execute("DELETE FROM watch_folders WHERE path = ?1", [path])?;
+        if removed == 0 {
+            return Err(LibraryError::NotFound(path.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn seen_file(&self, path: &str) -> Result<Option<SeenFile>> {
+        let seen = self
+            .conn()
+            .query_row(
+                "SELECT size, modified_at, hash FROM watch_seen WHERE path = ?1",
+                [path],
+                |row| {
+                    Ok(SeenFile {
+                        size: row.get(0)?,
+                        modified_at: row.get(1)?,
+                        hash: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(seen)
+    }
+
+    pub fn mark_seen(&self, folder: &str, path: &str, seen: &SeenFile) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO watch_seen (path, folder, size, modified_at, hash)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (path) DO UPDATE SET
+                folder = ?2, size = ?3, modified_at = ?4, hash = ?5",
+            params![path, folder, seen.size, seen.modified_at, seen.hash],
+        )?;
+        Ok(())
+    }
+}
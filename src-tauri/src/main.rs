@@ -1,6 +1,15 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-fn main() {
-    empire_card_collection::run()
+use std::process::ExitCode;
+
+use empire_card_collection::cli;
+
+fn main() -> ExitCode {
+    let args: Vec<_> = std::env::args_os().collect();
+    if cli::requested(&args) {
+        return cli::main(args);
+    }
+    empire_card_collection::run();
+    ExitCode::SUCCESS
 }
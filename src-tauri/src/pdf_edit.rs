@@ -0,0 +1,231 @@
+//! Reshaping PDF cards: a drop that arrived as one PDF of several cards can be split
+//! into a card per page, and cards that belong together (a front and a back) merged
+//! into one multi-page PDF card. Image cards can be merged too; each becomes a page
+//! at [`IMAGE_DPI`].
+//!
+//! Either way the edit is a step of the library's history, which keeps the original
+//! cards and their files (see [`Library::replace_cards`]), so undoing it brings them
+//! back exactly.
+
+use pdfium_render::prelude::{
+    PdfDocument, PdfPageImageObject, PdfPageObjectsCommon, PdfPagePaperSize, PdfPoints, PdfiumError,
+};
+use serde::Serialize;
+
+use crate::library::{CardKind, CardMeta, EditKind, HistoryStep, Library, LibraryError};
+use crate::render::{RenderError, Renderer};
+
+/// Resolution image cards are placed at when merged into a PDF: a 750×1050 scan
+/// comes out at the size of a trading card.
+pub const IMAGE_DPI: f32 = 300.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PdfEditError {
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+    #[error(transparent)]
+    Render(#[from] RenderError),
+    #[error("could not write the PDF: {0}")]
+    Pdf(#[from] PdfiumError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, PdfEditError>;
+
+/// A split or merge that was made: its step of the history, the ids of the cards it
+/// replaced and the new cards.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditOutcome {
+    pub step: HistoryStep,
+    pub replaced: Vec<String>,
+    pub cards: Vec<CardMeta>,
+}
+
+/// Replaces a multi-page PDF card with one card per page, each with the original's
+/// collection, tier, tags and so on.
+pub fn split(library: &Library, renderer: &Renderer, id: &str) -> Result<EditOutcome> {
+    let card = library
+        .get_card(id)?
+        .ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+    if card.kind != CardKind::Pdf {
+        return Err(PdfEditError::Invalid(format!(
+            "\"{}\" isn't a PDF, so it has no pages to split",
+            card.name
+        )));
+    }
+    let pdfium = renderer.pdfium()?;
+    let source = pdfium.load_pdf_from_byte_vec(library.read_card_file(id)?, None)?;
+    let pages = source.pages().len();
+    if pages < 2 {
+        return Err(PdfEditError::Invalid(format!(
+            "\"{}\" has only one page",
+            card.name
+        )));
+    }
+    let mut cards = Vec::with_capacity(usize::from(pages));
+    for page in 0..pages {
+        let mut document = pdfium.create_new_pdf()?;
+        document
+            .pages_mut()
+            .copy_page_from_document(&source, page, 0)?;
+        let meta = CardMeta {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: format!("{} (page {})", card.name, page + 1),
+            pages: 1,
+            thumbnail_data_url: String::new(),
+            ..card.clone()
+        };
+        cards.push((meta, document.save_to_bytes()?));
+    }
+    finish(library, EditKind::Split, &[card.id], cards)
+}
+
+/// Replaces `ids` with one PDF card holding all their pages in that order. It takes
+/// the first card's details, with every card's tags, and is a favorite if any was.
+pub fn merge(library: &Library, renderer: &Renderer, ids: &[String]) -> Result<EditOutcome> {
+    if ids.len() < 2 {
+        return Err(PdfEditError::Invalid(
+            "pick at least two cards to merge".into(),
+        ));
+    }
+    let cards = crate::binder::cards(library, ids)?;
+    if cards.len() < 2 {
+        return Err(PdfEditError::Invalid(
+            "pick at least two different cards".into(),
+        ));
+    }
+    let pdfium = renderer.pdfium()?;
+    let mut document = pdfium.create_new_pdf()?;
+    for card in &cards {
+        let bytes = library.read_card_file(&card.id)?;
+        match card.kind {
+            CardKind::Pdf => {
+                let source = pdfium.load_pdf_from_byte_vec(bytes, None)?;
+                document.pages_mut().append(&source)?;
+            }
+            CardKind::Gif | CardKind::Image => {
+                append_image(&mut document, renderer, card.kind, &bytes)?
+            }
+        }
+    }
+
+    let first = &cards[0];
+    let mut tags = first.tags.clone();
+    for tag in cards.iter().flat_map(|c| &c.tags) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+    let meta = CardMeta {
+        id: uuid::Uuid::new_v4().to_string(),
+        pages: u32::from(document.pages().len()),
+        tags,
+        thumbnail_data_url: String::new(),
+        favorite: cards.iter().any(|c| c.favorite),
+        kind: CardKind::Pdf,
+        orig_ext: "pdf".into(),
+        mime: "application/pdf".into(),
+        ..first.clone()
+    };
+    let bytes = document.save_to_bytes()?;
+    let ids: Vec<String> = cards.into_iter().map(|c| c.id).collect();
+    finish(library, EditKind::Merge, &ids, vec![(meta, bytes)])
+}
+
+fn append_image(
+    document: &mut PdfDocument<'_>,
+    renderer: &Renderer,
+    kind: CardKind,
+    bytes: &[u8],
+) -> Result<()> {
+    let image = renderer.render(kind, bytes, u32::MAX)?;
+    let points = |pixels: u32| PdfPoints::new(pixels as f32 * 72.0 / IMAGE_DPI);
+    let (width, height) = (points(image.width()), points(image.height()));
+    let object = PdfPageImageObject::new_with_size(document, &image, width, height)?;
+    document
+        .pages_mut()
+        .create_page_at_end(PdfPagePaperSize::new_custom(width, height))?
+        .objects_mut()
+        .add_image_object(object)?;
+    Ok(())
+}
+
+fn finish(
+    library: &Library,
+    kind: EditKind,
+    originals: &[String],
+    created: Vec<(CardMeta, Vec<u8>)>,
+) -> Result<EditOutcome> {
+    let ids: Vec<String> = created.iter().map(|(meta, _)| meta.id.clone()).collect();
+    let step = library.replace_cards(kind, originals, created)?;
+    let cards = ids
+        .iter()
+        .filter_map(|id| library.get_card(id).transpose())
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(EditOutcome {
+        step,
+        replaced: originals.to_vec(),
+        cards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_card;
+
+    fn card(id: &str) -> CardMeta {
+        let mut card = test_card(id);
+        card.kind = CardKind::Image;
+        card.collection = "Quiet Court".into();
+        card
+    }
+
+    #[test]
+    fn undo_brings_back_the_original_cards_and_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        library.add_card(card("front"), b"front bytes").unwrap();
+        library.add_card(card("back"), b"back bytes").unwrap();
+
+        let merged = CardMeta {
+            pages: 2,
+            ..card("merged")
+        };
+        let step = library
+            .replace_cards(
+                EditKind::Merge,
+                &["front".into(), "back".into()],
+                vec![(merged, b"both".to_vec())],
+            )
+            .unwrap();
+        assert_eq!(step.description, "Merge 2 cards into an untitled card");
+        let ids: Vec<String> = library
+            .list_cards()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, ["merged"]);
+
+        assert_eq!(
+            library.undo().unwrap(),
+            Some(HistoryStep {
+                undone: true,
+                ..step
+            })
+        );
+        assert!(library.get_card("merged").unwrap().is_none());
+        assert_eq!(library.read_card_file("front").unwrap(), b"front bytes");
+        assert_eq!(library.read_card_file("back").unwrap(), b"back bytes");
+        assert!(library.verify().unwrap().is_ok());
+
+        // Redoing it takes the originals out again; the merged card's file was kept
+        library.redo().unwrap();
+        assert_eq!(library.read_card_file("merged").unwrap(), b"both");
+        assert!(library.get_card("front").unwrap().is_none());
+        assert!(library.verify().unwrap().is_ok());
+    }
+}
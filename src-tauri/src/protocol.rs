@@ -0,0 +1,255 @@
+//! The `card://` URI scheme, which serves card files and thumbnails straight from disk
+//! so `<img>` tags and pdf.js can load them without copying bytes through IPC.
+//!
+//! - `card://localhost/file/<id>` is the card's file, with its MIME type.
+//! - `card://localhost/face/<face id>` is the file of one of a card's other faces.
+//! - `card://localhost/thumb/<id>?w=360` (or `?size=grid` / `?size=hover`) is a cached
+//!   thumbnail, rendered on first request.
+//!
+//! `card://file/<id>` works too. Windows webviews spell the scheme
+//! `http://card.localhost/...`; `convertFileSrc(path, "card")` builds the right form.
+//! Single `Range` requests are honoured so pdf.js can fetch large PDFs in chunks.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use http::header::{
+    ACCEPT_RANGES, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, CONTENT_LENGTH,
+    CONTENT_RANGE, CONTENT_TYPE, RANGE,
+};
+use http::{Request, Response, StatusCode, Uri};
+use percent_encoding::percent_decode_str;
+
+use crate::error::{CommandError, CommandResult};
+use crate::library::{CardKind, Library, LibraryError};
+use crate::thumbnails::{SizePreset, ThumbnailService, ThumbnailSize};
+
+pub const SCHEME: &str = "card";
+
+#[derive(Debug, PartialEq)]
+enum Target {
+    File(String),
+    Face(String),
+    Thumb(String, ThumbnailSize),
+}
+
+/// Answers one `card://` request. Failures come back as the same JSON error the
+/// commands return, with a matching HTTP status.
+pub fn handle(
+    library: &Library,
+    thumbnails: &ThumbnailService,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    serve(library, thumbnails, request).unwrap_or_else(error_response)
+}
+
+fn serve(
+    library: &Library,
+    thumbnails: &ThumbnailService,
+    request: &Request<Vec<u8>>,
+) -> CommandResult<Response<Vec<u8>>> {
+    let target = parse_target(request.uri()).ok_or_else(|| CommandError::NotFound {
+        what: request.uri().to_string(),
+    })?;
+    let range = request
+        .headers()
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok());
+    match target {
+        Target::File(id) => {
+            let card = library
+                .get_card(&id)?
+                .ok_or(LibraryError::NotFound(id.clone()))?;
+            let mime = mime_for(card.kind, &card.mime, &card.orig_ext);
+            serve_file(&library.card_file_path(&id)?, mime, range)
+        }
+        Target::Face(id) => {
+            let face = library
+                .get_face(&id)?
+                .ok_or(LibraryError::NotFound(id.clone()))?;
+            let mime = mime_for(face.kind, &face.mime, &face.orig_ext);
+            serve_file(&library.face_file_path(&id)?, mime, range)
+        }
+        Target::Thumb(id, size) => {
+            let thumbnail = thumbnails.thumbnail(library, &id, size)?;
+            serve_file(&thumbnail.path, thumbnail.mime, range)
+        }
+    }
+}
+
+fn parse_target(uri: &Uri) -> Option<Target> {
+    let path = percent_decode_str(uri.path()).decode_utf8().ok()?;
+    let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    // `card://file/<id>` carries the kind in the host
+    if let Some(host @ ("file" | "face" | "thumb")) = uri.host() {
+        segments.insert(0, host);
+    }
+    match segments.as_slice() {
+        ["file", id] => Some(Target::File(id.to_string())),
+        ["face", id] => Some(Target::Face(id.to_string())),
+        ["thumb", id] => Some(Target::Thumb(id.to_string(), thumb_size(uri.query()))),
+        _ => None,
+    }
+}
+
+fn thumb_size(query: Option<&str>) -> ThumbnailSize {
+    let mut size = ThumbnailSize::Preset(SizePreset::Grid);
+    for pair in query.unwrap_or_default().split('&') {
+        match pair.split_once('=') {
+            Some(("w", width)) => {
+                if let Ok(width) = width.parse() {
+                    size = ThumbnailSize::Width(width);
+                }
+            }
+            Some(("size", "hover")) => size = ThumbnailSize::Preset(SizePreset::Hover),
+            Some(("size", "grid")) => size = ThumbnailSize::Preset(SizePreset::Grid),
+            _ => {}
+        }
+    }
+    size
+}
+
+fn mime_for<'a>(kind: CardKind, mime: &'a str, orig_ext: &str) -> &'a str {
+    if !mime.is_empty() {
+        return mime;
+    }
+    match (kind, orig_ext) {
+        (CardKind::Pdf, _) => "application/pdf",
+        (CardKind::Gif, _) => "image/gif",
+        (CardKind::Image, "jpg" | "jpeg") => "image/jpeg",
+        (CardKind::Image, "webp") => "image/webp",
+        (CardKind::Image, _) => "image/png",
+    }
+}
+
+/// Parses a `bytes=` range header against a body of `len` bytes into an inclusive
+/// `(start, end)`. `None` means serve the whole body (no header, or a form we don't
+/// support such as multiple ranges); `Some(Err(()))` means the range can't be satisfied.
+fn parse_range(header: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.min(len.saturating_sub(1)))
+        }
+    };
+    if len == 0 || start > end || start >= len {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end)))
+}
+
+fn serve_file(path: &Path, mime: &str, range: Option<&str>) -> CommandResult<Response<Vec<u8>>> {
+    let mut file = File::open(path).map_err(|e| CommandError::from(e).at(path))?;
+    let len = file.metadata()?.len();
+    let builder = Response::builder()
+        .header(CONTENT_TYPE, mime)
+        .header(ACCEPT_RANGES, "bytes")
+        // pdf.js fetches from the app's own origin, which differs from card://
+        .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(
+            ACCESS_CONTROL_EXPOSE_HEADERS,
+            "Accept-Ranges, Content-Range, Content-Length",
+        );
+
+    let response = match range.and_then(|header| parse_range(header, len)) {
+        None => {
+            let mut body = Vec::with_capacity(len as usize);
+            file.read_to_end(&mut body)?;
+            builder.header(CONTENT_LENGTH, body.len()).body(body)
+        }
+        Some(Ok((start, end))) => {
+            let mut body = Vec::with_capacity((end - start + 1) as usize);
+            file.seek(SeekFrom::Start(start))?;
+            file.take(end - start + 1).read_to_end(&mut body)?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))
+                .header(CONTENT_LENGTH, body.len())
+                .body(body)
+        }
+        Some(Err(())) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(CONTENT_RANGE, format!("bytes */{len}"))
+            .body(Vec::new()),
+    };
+    response.map_err(|e| CommandError::Internal {
+        message: e.to_string(),
+    })
+}
+
+fn error_response(error: CommandError) -> Response<Vec<u8>> {
+    let status = match &error {
+        CommandError::NotFound { .. } => StatusCode::NOT_FOUND,
+        CommandError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        CommandError::Render { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let mut response = Response::new(serde_json::to_vec(&error).unwrap_or_default());
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    response.headers_mut().insert(
+        ACCESS_CONTROL_ALLOW_ORIGIN,
+        http::HeaderValue::from_static("*"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_url_forms() {
+        let target = |uri: &str| parse_target(&uri.parse().unwrap());
+        assert_eq!(
+            target("card://localhost/file/abc"),
+            Some(Target::File("abc".into()))
+        );
+        assert_eq!(
+            target("http://card.localhost/file%2Fabc"),
+            Some(Target::File("abc".into()))
+        );
+        assert_eq!(target("card://file/abc"), Some(Target::File("abc".into())));
+        assert_eq!(
+            target("card://localhost/face/def"),
+            Some(Target::Face("def".into()))
+        );
+        assert!(matches!(
+            target("card://localhost/thumb/abc?w=360"),
+            Some(Target::Thumb(id, ThumbnailSize::Width(360))) if id == "abc"
+        ));
+        assert!(matches!(
+            target("card://thumb/abc?size=hover"),
+            Some(Target::Thumb(_, ThumbnailSize::Preset(SizePreset::Hover)))
+        ));
+        assert_eq!(target("card://localhost/other/abc"), None);
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(parse_range("bytes=900-", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=500-5000", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+    }
+}
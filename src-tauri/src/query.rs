@@ -0,0 +1,647 @@
+//! The card filter language, e.g.
+//! `tier>=Phoenix collection:"Quiet Court" -tag:dupe fav:yes added:<2026-01-01`.
+//!
+//! | term                               | matches cards                                      |
+//! |------------------------------------|----------------------------------------------------|
+//! | `moon`, `"moon hare"`              | whose name or a tag contains the text (any case)   |
+//! | `name:moon`                        | whose name contains the text                       |
+//! | `tag:dupe`                         | with that tag (any case); `tag:""` has no tags     |
+//! | `collection:"Quiet Court"`         | in that collection (case and spacing don't matter); `collection:""` has none |
+//! | `tier:Phoenix`, `tier>=Phoenix`    | at, above or below a tier in the catalog's tier order; `tier:""` has none |
+//! | `fav:yes`, `nsfw:no`               | favorite / NSFW or not (`yes`, `no`, `true`, `false`) |
+//! | `kind:pdf`                         | of kind `pdf`, `gif` or `image`                    |
+//! | `pages>1`                          | by page count                                      |
+//! | `qty>1`                            | by number of copies owned                          |
+//! | `condition:"near mint"`            | in that condition (any case); `condition:""` is ungraded |
+//! | `added:2026-01`, `added:<2026-01-01` | added on a day, in a month or year, or before / after it (UTC) |
+//!
+//! Comparisons are `=`, `<`, `<=`, `>`, `>=`, written `tier>=Phoenix` or `tier:>=Phoenix`.
+//! Terms must all match; `OR` allows either side, `-` excludes, parentheses group.
+//! Errors carry the span of the text at fault, in characters, so the UI can point at it.
+
+use std::cmp::Ordering;
+
+use serde::Serialize;
+
+use crate::library::{collection_key, CardKind, CardMeta};
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[error("{message}")]
+pub struct QueryError {
+    pub message: String,
+    /// Character offset where the offending text starts.
+    pub start: usize,
+    /// Character offset just past its end.
+    pub end: usize,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, start: usize, end: usize) -> Self {
+        Self {
+            message: message.into(),
+            start,
+            end,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, QueryError>;
+
+/// A parsed query. The empty query matches every card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Expr);
+
+impl Query {
+    /// `tiers` are the tier names from lowest to highest, as in the
+    /// [catalog](crate::catalog::Catalog).
+    pub fn parse(input: &str, tiers: &[String]) -> Result<Self> {
+        let tokens = lex(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            tiers,
+        };
+        let expr = parser.or()?;
+        match parser.next() {
+            None => Ok(Query(expr)),
+            Some(token) => Err(QueryError::new("unmatched `)`", token.start, token.end)),
+        }
+    }
+
+    pub fn matches(&self, card: &CardMeta) -> bool {
+        self.0.matches(card)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+impl Expr {
+    fn matches(&self, card: &CardMeta) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|e| e.matches(card)),
+            Expr::Or(exprs) => exprs.iter().any(|e| e.matches(card)),
+            Expr::Not(expr) => !expr.matches(card),
+            Expr::Term(term) => term.matches(card),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Cmp::Eq => ordering.is_eq(),
+            Cmp::Lt => ordering.is_lt(),
+            Cmp::Le => ordering.is_le(),
+            Cmp::Gt => ordering.is_gt(),
+            Cmp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Lowercased.
+    Text(String),
+    /// Lowercased.
+    Name(String),
+    /// Lowercased; empty means no tags at all.
+    Tag(String),
+    /// A [`collection_key`].
+    Collection(String),
+    /// The lowercased tiers that match; `""` is no tier, which only `=` can ask for.
+    Tier(Vec<String>),
+    Favorite(bool),
+    Nsfw(bool),
+    Kind(CardKind),
+    Pages(Cmp, u32),
+    Quantity(Cmp, u32),
+    /// Lowercased and trimmed; empty means ungraded.
+    Condition(String),
+    /// A UTC day, month or year as `[start, end)` in milliseconds.
+    Added(Cmp, i64, i64),
+}
+
+impl Term {
+    fn matches(&self, card: &CardMeta) -> bool {
+        match self {
+            Term::Text(text) => {
+                card.name.to_lowercase().contains(text)
+                    || card.tags.iter().any(|t| t.to_lowercase().contains(text))
+            }
+            Term::Name(text) => card.name.to_lowercase().contains(text),
+            Term::Tag(tag) if tag.is_empty() => card.tags.is_empty(),
+            Term::Tag(tag) => card.tags.iter().any(|t| t.to_lowercase() == *tag),
+            Term::Collection(key) => collection_key(&card.collection) == *key,
+            Term::Tier(tiers) => tiers.contains(&card.tier.trim().to_lowercase()),
+            Term::Favorite(favorite) => card.favorite == *favorite,
+            Term::Nsfw(nsfw) => card.nsfw.unwrap_or(false) == *nsfw,
+            Term::Kind(kind) => card.kind == *kind,
+            Term::Pages(cmp, pages) => cmp.holds(card.pages.cmp(pages)),
+            Term::Quantity(cmp, quantity) => cmp.holds(card.quantity.cmp(quantity)),
+            Term::Condition(condition) => card.condition.trim().to_lowercase() == *condition,
+            Term::Added(cmp, start, end) => {
+                let at = card.created_at;
+                match cmp {
+                    Cmp::Eq => *start <= at && at < *end,
+                    Cmp::Lt => at < *start,
+                    Cmp::Le => at < *end,
+                    Cmp::Gt => at >= *end,
+                    Cmp::Ge => at >= *start,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Open,
+    Close,
+    Not,
+    Or,
+    Word(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// Splits a query into words, parentheses, `-` and `OR`. Quoted stretches stay inside
+/// their word, spaces and parentheses included.
+fn lex(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let kind = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            '-' if chars
+                .get(i + 1)
+                .is_some_and(|c| !c.is_whitespace() && *c != ')') =>
+            {
+                TokenKind::Not
+            }
+            _ => {
+                let mut quote = None;
+                while i < chars.len() {
+                    match chars[i] {
+                        '"' if quote.is_some() => quote = None,
+                        '"' => quote = Some(i),
+                        c if quote.is_none() && (c.is_whitespace() || c == '(' || c == ')') => {
+                            break
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                if let Some(quote) = quote {
+                    return Err(QueryError::new("unclosed quote", quote, chars.len()));
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(Token {
+                    kind: match word.as_str() {
+                        "OR" => TokenKind::Or,
+                        _ => TokenKind::Word(word),
+                    },
+                    start,
+                    end: i,
+                });
+                continue;
+            }
+        };
+        i += 1;
+        tokens.push(Token {
+            kind,
+            start,
+            end: i,
+        });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    tiers: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// `and (OR and)*`
+    fn or(&mut self) -> Result<Expr> {
+        let mut alternatives = vec![self.and()?];
+        while let Some(or) = self.peek().filter(|t| t.kind == TokenKind::Or).cloned() {
+            self.pos += 1;
+            let rhs = self.and()?;
+            if rhs == Expr::And(Vec::new()) || alternatives.last() == Some(&Expr::And(Vec::new())) {
+                return Err(QueryError::new(
+                    "`OR` needs a term on each side",
+                    or.start,
+                    or.end,
+                ));
+            }
+            alternatives.push(rhs);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Expr::Or(alternatives),
+        })
+    }
+
+    /// Terms up to the next `OR` or `)`. Empty if there are none.
+    fn and(&mut self) -> Result<Expr> {
+        let mut terms = Vec::new();
+        while self
+            .peek()
+            .is_some_and(|t| !matches!(t.kind, TokenKind::Or | TokenKind::Close))
+        {
+            terms.push(self.unary()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Expr::And(terms),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let Some(token) = self.next() else {
+            unreachable!("and() only calls unary() with a token left");
+        };
+        match token.kind {
+            TokenKind::Not => match self.peek().map(|t| &t.kind) {
+                None | Some(TokenKind::Or | TokenKind::Close) => Err(QueryError::new(
+                    "nothing to exclude after `-`",
+                    token.start,
+                    token.end,
+                )),
+                Some(_) => Ok(Expr::Not(Box::new(self.unary()?))),
+            },
+            TokenKind::Open => {
+                let inner = self.or()?;
+                if inner == Expr::And(Vec::new()) {
+                    let end = self.peek().map_or(token.end, |t| t.end);
+                    return Err(QueryError::new("empty parentheses", token.start, end));
+                }
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => Ok(inner),
+                    _ => Err(QueryError::new("unclosed `(`", token.start, token.end)),
+                }
+            }
+            TokenKind::Word(word) => Ok(Expr::Term(term(&word, token.start, self.tiers)?)),
+            TokenKind::Or | TokenKind::Close => unreachable!("and() stops at these"),
+        }
+    }
+}
+
+/// Parses one word, `start` being its offset in the query.
+fn term(word: &str, start: usize, tiers: &[String]) -> Result<Term> {
+    let chars: Vec<char> = word.chars().collect();
+    let name_len = chars.iter().take_while(|c| c.is_ascii_alphabetic()).count();
+    let name: String = chars[..name_len].iter().collect();
+    let mut i = name_len;
+    let colon = chars.get(i) == Some(&':');
+    if colon {
+        i += 1;
+    }
+    let op_start = i;
+    let cmp = match (chars.get(i), chars.get(i + 1)) {
+        (Some('<'), Some('=')) => Some(Cmp::Le),
+        (Some('>'), Some('=')) => Some(Cmp::Ge),
+        (Some('<'), _) => Some(Cmp::Lt),
+        (Some('>'), _) => Some(Cmp::Gt),
+        (Some('='), _) => Some(Cmp::Eq),
+        _ => None,
+    };
+    i += match cmp {
+        Some(Cmp::Le | Cmp::Ge) => 2,
+        Some(_) => 1,
+        None => 0,
+    };
+
+    if name_len == 0 || (!colon && cmp.is_none()) {
+        return Ok(Term::Text(unquote(&chars).to_lowercase()));
+    }
+    let field = Field::parse(&name).ok_or_else(|| {
+        QueryError::new(format!("unknown field `{name}`"), start, start + name_len)
+    })?;
+    let cmp = cmp.unwrap_or(Cmp::Eq);
+    let at = |message: String| QueryError::new(message, start + i, start + chars.len());
+    if cmp != Cmp::Eq && !field.ordered() {
+        return Err(QueryError::new(
+            format!(
+                "`{name}` can't be compared with `{}`",
+                chars[op_start..i].iter().collect::<String>()
+            ),
+            start + op_start,
+            start + i,
+        ));
+    }
+    let value = unquote(&chars[i..]);
+    if value.is_empty() && !field.allows_empty() {
+        return Err(QueryError::new(
+            format!("missing value for `{name}`"),
+            start,
+            start + chars.len(),
+        ));
+    }
+
+    Ok(match field {
+        Field::Name => Term::Name(value.to_lowercase()),
+        Field::Tag => Term::Tag(value.trim().to_lowercase()),
+        Field::Collection => Term::Collection(collection_key(&value)),
+        Field::Tier if value.trim().is_empty() => {
+            if cmp != Cmp::Eq {
+                return Err(at(format!("compare `{name}` with a tier, not nothing")));
+            }
+            Term::Tier(vec![String::new()])
+        }
+        Field::Tier => {
+            let Some(rank) = tiers
+                .iter()
+                .position(|t| t.eq_ignore_ascii_case(value.trim()))
+            else {
+                return Err(at(format!(
+                    "unknown tier `{value}`; tiers are {}",
+                    tiers.join(", ")
+                )));
+            };
+            Term::Tier(
+                tiers
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| cmp.holds(i.cmp(&rank)))
+                    .map(|(_, t)| t.to_lowercase())
+                    .collect(),
+            )
+        }
+        Field::Favorite | Field::Nsfw => {
+            let flag = match value.to_lowercase().as_str() {
+                "yes" | "true" => true,
+                "no" | "false" => false,
+                _ => return Err(at(format!("expected yes or no, not `{value}`"))),
+            };
+            match field {
+                Field::Favorite => Term::Favorite(flag),
+                _ => Term::Nsfw(flag),
+            }
+        }
+        Field::Kind => match value.to_lowercase().as_str() {
+            "pdf" => Term::Kind(CardKind::Pdf),
+            "gif" => Term::Kind(CardKind::Gif),
+            "image" => Term::Kind(CardKind::Image),
+            _ => return Err(at(format!("expected pdf, gif or image, not `{value}`"))),
+        },
+        Field::Pages => match value.parse() {
+            Ok(pages) => Term::Pages(cmp, pages),
+            Err(_) => return Err(at(format!("expected a number of pages, not `{value}`"))),
+        },
+        Field::Quantity => match value.parse() {
+            Ok(quantity) => Term::Quantity(cmp, quantity),
+            Err(_) => return Err(at(format!("expected a number of copies, not `{value}`"))),
+        },
+        Field::Condition => Term::Condition(value.trim().to_lowercase()),
+        Field::Added => match date_range(&value) {
+            Some((from, to)) => Term::Added(cmp, from, to),
+            None => {
+                return Err(at(format!(
+                    "expected a date like 2026-01-31, 2026-01 or 2026, not `{value}`"
+                )))
+            }
+        },
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Tag,
+    Collection,
+    Tier,
+    Favorite,
+    Nsfw,
+    Kind,
+    Pages,
+    Quantity,
+    Condition,
+    Added,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "name" => Field::Name,
+            "tag" => Field::Tag,
+            "collection" => Field::Collection,
+            "tier" => Field::Tier,
+            "fav" | "favorite" => Field::Favorite,
+            "nsfw" => Field::Nsfw,
+            "kind" => Field::Kind,
+            "pages" => Field::Pages,
+            "qty" | "quantity" => Field::Quantity,
+            "condition" => Field::Condition,
+            "added" => Field::Added,
+            _ => return None,
+        })
+    }
+
+    fn ordered(self) -> bool {
+        matches!(
+            self,
+            Field::Tier | Field::Pages | Field::Quantity | Field::Added
+        )
+    }
+
+    fn allows_empty(self) -> bool {
+        matches!(
+            self,
+            Field::Tag | Field::Collection | Field::Tier | Field::Condition
+        )
+    }
+}
+
+/// The text with its quotes removed, so `name:"moon hare"` and `name:moon" "hare` agree.
+fn unquote(chars: &[char]) -> String {
+    chars.iter().filter(|c| **c != '"').collect()
+}
+
+/// `YYYY-MM-DD`, `YYYY-MM` or `YYYY` as a UTC `[start, end)` in milliseconds.
+fn date_range(value: &str) -> Option<(i64, i64)> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    let number = |s: &str, digits: usize| -> Option<i64> {
+        (s.len() == digits && s.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| s.parse().ok())
+            .flatten()
+    };
+    let year = number(parts.first()?, 4)?;
+    let (start, end) = match parts[1..] {
+        [] => (days_from_civil(year, 1, 1), days_from_civil(year + 1, 1, 1)),
+        [month] => {
+            let month = number(month, 2).filter(|m| (1..=12).contains(m))?;
+            let (next_year, next_month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+            (
+                days_from_civil(year, month, 1),
+                days_from_civil(next_year, next_month, 1),
+            )
+        }
+        [month, day] => {
+            let month = number(month, 2).filter(|m| (1..=12).contains(m))?;
+            let day = number(day, 2).filter(|d| *d >= 1)?;
+            let start = days_from_civil(year, month, day);
+            // Reject days past the end of the month rather than rolling over
+            let next_month = if month == 12 {
+                days_from_civil(year + 1, 1, 1)
+            } else {
+                days_from_civil(year, month + 1, 1)
+            };
+            if start >= next_month {
+                return None;
+            }
+            (start, start + 1)
+        }
+        _ => return None,
+    };
+    Some((start * MS_PER_DAY, end * MS_PER_DAY))
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar (Howard Hinnant's
+/// `days_from_civil`).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+
+    fn parse(query: &str) -> Result<Query> {
+        Query::parse(query, &Catalog::builtin().tiers)
+    }
+
+    fn card(name: &str, tier: &str, tags: &[&str]) -> CardMeta {
+        let mut card: CardMeta = serde_json::from_str(r#"{"id":"x"}"#).unwrap();
+        card.name = name.into();
+        card.tier = tier.into();
+        card.tags = tags.iter().map(|t| t.to_string()).collect();
+        card
+    }
+
+    #[test]
+    fn evaluates_the_example_query() {
+        let query =
+            parse(r#"tier>=Phoenix collection:"Quiet Court" -tag:dupe fav:yes added:<2026-01-01"#)
+                .unwrap();
+        let mut hare = card("Moon Hare", "jade", &["Spring"]);
+        hare.collection = "quiet  court".into();
+        hare.favorite = true;
+        hare.created_at = date_range("2025-12-31").unwrap().0;
+        assert!(query.matches(&hare));
+
+        let mut dupe = hare.clone();
+        dupe.tags.push("DUPE".into());
+        let mut low = hare.clone();
+        low.tier = "Lotus".into();
+        let mut late = hare.clone();
+        late.created_at = date_range("2026-01-01").unwrap().0;
+        for other in [dupe, low, late] {
+            assert!(!query.matches(&other), "{other:?}");
+        }
+
+        assert!(parse("").unwrap().matches(&hare));
+        let either = parse("(tier:lotus OR name:moon) -nsfw:yes").unwrap();
+        assert!(either.matches(&hare));
+        assert!(!parse("tier:\"\"").unwrap().matches(&hare));
+        assert!(parse("spr").unwrap().matches(&hare));
+        assert!(parse("condition:\"\" qty:1").unwrap().matches(&hare));
+        hare.quantity = 3;
+        hare.condition = "Near Mint".into();
+        assert!(parse("qty>=2 condition:\"near mint\"")
+            .unwrap()
+            .matches(&hare));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_text() {
+        let error = |query: &str| {
+            let e = parse(query).unwrap_err();
+            let at: String = query.chars().skip(e.start).take(e.end - e.start).collect();
+            (e.message, at)
+        };
+        assert_eq!(
+            error("fav:yes colour:red"),
+            ("unknown field `colour`".into(), "colour".into())
+        );
+        assert_eq!(error("tier>=Bronze").1, "Bronze");
+        assert_eq!(error("tag<dupe").1, "<");
+        assert_eq!(error("added:2026-02-30").1, "2026-02-30");
+        assert_eq!(error("name:\"moon").1, "\"moon");
+        assert_eq!(error("(fav:yes").1, "(");
+        assert_eq!(error("fav:yes )").1, ")");
+        assert_eq!(error("fav:yes OR").1, "OR");
+        assert_eq!(error("kind:").1, "kind:");
+    }
+
+    #[test]
+    fn dates_are_utc_days_months_and_years() {
+        assert_eq!(date_range("1970-01-01"), Some((0, MS_PER_DAY)));
+        assert_eq!(
+            date_range("2024-02"),
+            Some((19_754 * MS_PER_DAY, 19_783 * MS_PER_DAY))
+        );
+        assert_eq!(
+            date_range("2024").map(|(a, b)| (b - a) / MS_PER_DAY),
+            Some(366)
+        );
+        assert_eq!(date_range("2024-13"), None);
+        assert_eq!(date_range("24-01-01"), None);
+    }
+}
@@ -0,0 +1,102 @@
+//! Rasterizes cards: page 1 of a PDF through Pdfium, or the first frame of a GIF, PNG,
+//! JPEG or WebP through `image`.
+//!
+//! Pdfium is a shared library loaded at runtime. We look for it in the directories
+//! given to [`Renderer::new`] (the app's resource dir when bundled) and then wherever
+//! the system loader finds it. Without it PDFs can't be rendered and callers get
+//! [`RenderError::PdfiumUnavailable`]; images still work.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium, PdfiumError};
+
+use crate::library::CardKind;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("the PDF renderer (pdfium) is not available")]
+    PdfiumUnavailable,
+    #[error("could not render PDF: {0}")]
+    Pdf(#[from] PdfiumError),
+    #[error("could not decode image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+pub type Result<T> = std::result::Result<T, RenderError>;
+
+pub struct Renderer {
+    pdfium_dirs: Vec<PathBuf>,
+    pdfium: OnceLock<Option<Pdfium>>,
+}
+
+impl Renderer {
+    pub fn new(pdfium_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            pdfium_dirs,
+            pdfium: OnceLock::new(),
+        }
+    }
+
+    /// Renders the card's first page or frame, scaled to `width` pixels wide. Images
+    /// narrower than `width` are left at their own size rather than blown up.
+    pub fn render(&self, kind: CardKind, bytes: &[u8], width: u32) -> Result<DynamicImage> {
+        match kind {
+            CardKind::Pdf => self.render_pdf_page(bytes, 0, width),
+            CardKind::Gif | CardKind::Image => {
+                let image = image::load_from_memory(bytes)?;
+                Ok(if image.width() > width {
+                    image.resize(width, u32::MAX, FilterType::Triangle)
+                } else {
+                    image
+                })
+            }
+        }
+    }
+
+    /// Renders one page of a PDF (0-based) at `width` pixels wide.
+    pub fn render_pdf_page(&self, bytes: &[u8], page: u16, width: u32) -> Result<DynamicImage> {
+        let document = self.pdfium()?.load_pdf_from_byte_slice(bytes, None)?;
+        let page = document.pages().get(page)?;
+        let config = PdfRenderConfig::new().set_target_width(width as i32);
+        let image = page.render_with_config(&config)?.as_image();
+        Ok(image)
+    }
+
+    /// Number of pages in a PDF.
+    pub fn page_count(&self, bytes: &[u8]) -> Result<u16> {
+        let document = self.pdfium()?.load_pdf_from_byte_slice(bytes, None)?;
+        Ok(document.pages().len())
+    }
+
+    /// The text of every page of a PDF, one page per line.
+    pub fn extract_text(&self, bytes: &[u8]) -> Result<String> {
+        let document = self.pdfium()?.load_pdf_from_byte_slice(bytes, None)?;
+        let mut text = String::new();
+        for page in document.pages().iter() {
+            text.push_str(&page.text()?.all());
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// The bound Pdfium library, for callers that build PDFs rather than render them.
+    pub(crate) fn pdfium(&self) -> Result<&Pdfium> {
+        self.pdfium
+            .get_or_init(|| self.bind_pdfium())
+            .as_ref()
+            .ok_or(RenderError::PdfiumUnavailable)
+    }
+
+    fn bind_pdfium(&self) -> Option<Pdfium> {
+        self.pdfium_dirs
+            .iter()
+            .map(Pdfium::pdfium_platform_library_name_at_path)
+            .filter(|path| path.is_file())
+            .find_map(|path| Pdfium::bind_to_library(path).ok())
+            .or_else(|| Pdfium::bind_to_system_library().ok())
+            .map(Pdfium::new)
+    }
+}
@@ -0,0 +1,628 @@
+//! Full-text search over card names, tags and the text inside PDF cards.
+//!
+//! The index is a tantivy index on disk. It's a cache of the library: [`SearchIndex::sync`]
+//! brings it up to date (the app runs it whenever the library changes) and an index
+//! that can't be opened is simply rebuilt. Text is extracted once per file content, so
+//! renaming or tagging a card only re-indexes its metadata.
+//!
+//! Query syntax, as typed in the search box:
+//!
+//! - `moon hare`: every word must match the name, the PDF text or a tag
+//! - `"jade palace"`: a phrase
+//! - `jad*`, `"jade pal"*`: a word or phrase prefix
+//! - `collection:"Quiet Court"`, `tier:phoenix`, `tag:dupe`: exact field filters
+//! - `-tag:dupe`, `-moon`: exclude anything matching
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{
+    AllQuery, BooleanQuery, EmptyQuery, Occur, PhrasePrefixQuery, PhraseQuery, Query, TermQuery,
+};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING, TEXT,
+};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::{LowerCaser, RawTokenizer, TextAnalyzer};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, Searcher, TantivyDocument, Term};
+
+use crate::library::{collection_key, CardKind, CardMeta, Library, LibraryError};
+use crate::render::{RenderError, Renderer};
+
+/// Tokenizer for the filter fields: the whole value, lowercased.
+const KEY_TOKENIZER: &str = "key";
+const WRITER_MEMORY: usize = 15_000_000;
+/// How many distinct words a `prefix*` expands to at most.
+const MAX_PREFIX_TERMS: usize = 64;
+const SNIPPET_CHARS: usize = 160;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("search index error: {0}")]
+    Index(#[from] tantivy::TantivyError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+    #[error(transparent)]
+    Render(#[from] RenderError),
+}
+
+pub type Result<T> = std::result::Result<T, SearchError>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub id: String,
+    pub score: f32,
+    /// HTML-escaped excerpt of the card's text with the matches in `<b>`, or empty if
+    /// the text didn't match (e.g. only the name did).
+    pub snippet: String,
+}
+
+struct Fields {
+    id: Field,
+    name: Field,
+    body: Field,
+    tag: Field,
+    collection: Field,
+    tier: Field,
+    updated_at: Field,
+    hash: Field,
+}
+
+/// What a card looked like when it was indexed.
+struct Indexed {
+    updated_at: i64,
+    hash: String,
+}
+
+struct IndexState {
+    writer: IndexWriter,
+    indexed: HashMap<String, Indexed>,
+}
+
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+    state: Mutex<IndexState>,
+}
+
+impl SearchIndex {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let (schema, fields) = schema();
+        fs::create_dir_all(dir)?;
+        let index = match open_index(dir, &schema) {
+            Ok(index) => index,
+            // Corrupt, or written with an older schema: it's only a cache, so start over
+            Err(_) => {
+                fs::remove_dir_all(dir)?;
+                fs::create_dir_all(dir)?;
+                Index::create_in_dir(dir, schema)?
+            }
+        };
+        index.tokenizers().register(
+            KEY_TOKENIZER,
+            TextAnalyzer::builder(RawTokenizer::default())
+                .filter(LowerCaser)
+                .build(),
+        );
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let indexed = read_indexed(&reader.searcher(), &fields)?;
+        Ok(Self {
+            index,
+            reader,
+            fields,
+            state: Mutex::new(IndexState { writer, indexed }),
+        })
+    }
+
+    /// Indexes cards added or changed since the last sync and drops deleted ones.
+    pub fn sync(&self, library: &Library, renderer: &Renderer) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stale: HashSet<String> = state.indexed.keys().cloned().collect();
+        let mut changed = false;
+        let pdfium = renderer.pdfium().is_ok();
+        for card in library.list_cards()? {
+            stale.remove(&card.id);
+            let hash = match library.file_hash(&card.id) {
+                // Deleted since we listed it; the next sync drops it
+                Err(LibraryError::NotFound(_)) => continue,
+                other => other?.unwrap_or_default(),
+            };
+            let previous = state.indexed.get(&card.id);
+            // An empty hash means the text couldn't be read yet; retry once pdfium can
+            if previous.is_some_and(|p| {
+                p.updated_at == card.updated_at && (p.hash == hash || p.hash.is_empty() && !pdfium)
+            }) {
+                continue;
+            }
+            let (hash, body) = match previous {
+                Some(p) if p.hash == hash => (hash, self.stored_body(&card.id)?),
+                _ => match extract_text(library, renderer, &card) {
+                    Ok(body) => (hash, body),
+                    Err(SearchError::Library(LibraryError::NotFound(_))) => continue,
+                    // A PDF pdfium can't parse won't parse next time either
+                    Err(SearchError::Render(RenderError::Pdf(_))) => (hash, String::new()),
+                    // No pdfium, or the file can't be read: searchable by name and tags
+                    // until then, without caching the missing text under the file's hash
+                    Err(_) => (String::new(), String::new()),
+                },
+            };
+            self.write(&mut state, &card, hash, &body)?;
+            changed = true;
+        }
+        for id in stale {
+            state
+                .writer
+                .delete_term(Term::from_field_text(self.fields.id, &id));
+            state.indexed.remove(&id);
+            changed = true;
+        }
+        if changed {
+            state.writer.commit()?;
+            self.reader.reload()?;
+        }
+        Ok(())
+    }
+
+    /// Best matches first, at most `limit` of them.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let searcher = self.reader.searcher();
+        let Some(query) = self.build_query(&searcher, &parse(query))? else {
+            return Ok(Vec::new());
+        };
+        let top = searcher.search(&query, &TopDocs::with_limit(limit.max(1)))?;
+        let mut snippets = SnippetGenerator::create(&searcher, &*query, self.fields.body)?;
+        snippets.set_max_num_chars(SNIPPET_CHARS);
+        top.into_iter()
+            .map(|(score, address)| {
+                let doc: TantivyDocument = searcher.doc(address)?;
+                let snippet = snippets.snippet_from_doc(&doc);
+                Ok(SearchHit {
+                    id: stored_text(&doc, self.fields.id),
+                    score,
+                    snippet: if snippet.is_empty() {
+                        String::new()
+                    } else {
+                        snippet.to_html()
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn write(
+        &self,
+        state: &mut IndexState,
+        card: &CardMeta,
+        hash: String,
+        body: &str,
+    ) -> Result<()> {
+        let fields = &self.fields;
+        let mut doc = TantivyDocument::default();
+        doc.add_text(fields.id, &card.id);
+        doc.add_text(fields.name, &card.name);
+        doc.add_text(fields.body, body);
+        doc.add_text(fields.collection, collection_key(&card.collection));
+        doc.add_text(fields.tier, &card.tier);
+        for tag in &card.tags {
+            doc.add_text(fields.tag, tag);
+        }
+        doc.add_i64(fields.updated_at, card.updated_at);
+        doc.add_text(fields.hash, &hash);
+
+        state
+            .writer
+            .delete_term(Term::from_field_text(fields.id, &card.id));
+        state.writer.add_document(doc)?;
+        state.indexed.insert(
+            card.id.clone(),
+            Indexed {
+                updated_at: card.updated_at,
+                hash,
+            },
+        );
+        Ok(())
+    }
+
+    /// The text indexed for a card last time, so metadata edits don't re-extract it.
+    fn stored_body(&self, id: &str) -> Result<String> {
+        let searcher = self.reader.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(self.fields.id, id),
+            IndexRecordOption::Basic,
+        );
+        Ok(
+            match searcher.search(&query, &TopDocs::with_limit(1))?.first() {
+                Some((_, address)) => stored_text(&searcher.doc(*address)?, self.fields.body),
+                None => String::new(),
+            },
+        )
+    }
+
+    /// `None` if the query has nothing to search for.
+    fn build_query(&self, searcher: &Searcher, parts: &[Part]) -> Result<Option<Box<dyn Query>>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for part in parts {
+            let query = match &part.clause {
+                Clause::Text { text, prefix } => self.text_query(searcher, text, *prefix)?,
+                Clause::Filter(field, value) => {
+                    let (field, value) = match field {
+                        FilterField::Collection => (self.fields.collection, collection_key(value)),
+                        FilterField::Tier => (self.fields.tier, value.trim().to_lowercase()),
+                        FilterField::Tag => (self.fields.tag, value.trim().to_lowercase()),
+                    };
+                    Some(Box::new(TermQuery::new(
+                        Term::from_field_text(field, &value),
+                        IndexRecordOption::Basic,
+                    )) as Box<dyn Query>)
+                }
+            };
+            if let Some(query) = query {
+                let occur = if part.negated {
+                    Occur::MustNot
+                } else {
+                    Occur::Must
+                };
+                clauses.push((occur, query));
+            }
+        }
+        if clauses.is_empty() {
+            return Ok(None);
+        }
+        // Exclusions alone match nothing; exclude them from everything instead
+        if clauses.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+            clauses.push((Occur::Must, Box::new(AllQuery)));
+        }
+        Ok(Some(Box::new(BooleanQuery::new(clauses))))
+    }
+
+    /// Words or a phrase, matched against the name, the text and the tags.
+    fn text_query(
+        &self,
+        searcher: &Searcher,
+        text: &str,
+        prefix: bool,
+    ) -> Result<Option<Box<dyn Query>>> {
+        let mut tokenizer = self.index.tokenizer_for_field(self.fields.name)?;
+        let mut words = Vec::new();
+        tokenizer
+            .token_stream(text)
+            .process(&mut |token| words.push(token.text.clone()));
+        if words.is_empty() {
+            return Ok(None);
+        }
+
+        let mut alternatives: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for field in [self.fields.name, self.fields.body] {
+            let terms: Vec<Term> = words
+                .iter()
+                .map(|word| Term::from_field_text(field, word))
+                .collect();
+            alternatives.push((Occur::Should, words_query(searcher, terms, prefix)?));
+        }
+        let tag = vec![Term::from_field_text(
+            self.fields.tag,
+            &text.trim().to_lowercase(),
+        )];
+        alternatives.push((Occur::Should, words_query(searcher, tag, prefix)?));
+        Ok(Some(Box::new(BooleanQuery::new(alternatives))))
+    }
+}
+
+fn words_query(searcher: &Searcher, mut terms: Vec<Term>, prefix: bool) -> Result<Box<dyn Query>> {
+    Ok(match (terms.len(), prefix) {
+        (1, false) => Box::new(TermQuery::new(
+            terms.remove(0),
+            IndexRecordOption::WithFreqs,
+        )),
+        // Expanded into the words it matches, so snippets can highlight them
+        (1, true) => {
+            let expanded: Vec<(Occur, Box<dyn Query>)> = expand_prefix(searcher, &terms[0])?
+                .into_iter()
+                .map(|term| {
+                    let query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+                    (Occur::Should, Box::new(query) as Box<dyn Query>)
+                })
+                .collect();
+            if expanded.is_empty() {
+                Box::new(EmptyQuery)
+            } else {
+                Box::new(BooleanQuery::new(expanded))
+            }
+        }
+        (_, false) => Box::new(PhraseQuery::new(terms)),
+        (_, true) => Box::new(PhrasePrefixQuery::new(terms)),
+    })
+}
+
+/// Indexed words starting with `prefix`'s text, in the same field.
+fn expand_prefix(searcher: &Searcher, prefix: &Term) -> Result<Vec<Term>> {
+    let field = prefix.field();
+    let start = prefix.serialized_value_bytes();
+    // No UTF-8 text contains 0xff, so this bounds every word with the prefix
+    let mut end = start.to_vec();
+    end.push(0xff);
+    let mut words = BTreeSet::new();
+    for segment in searcher.segment_readers() {
+        let inverted = segment.inverted_index(field)?;
+        let mut stream = inverted.terms().range().ge(start).lt(&end).into_stream()?;
+        while words.len() < MAX_PREFIX_TERMS && stream.advance() {
+            words.insert(String::from_utf8_lossy(stream.key()).into_owned());
+        }
+    }
+    Ok(words
+        .into_iter()
+        .map(|word| Term::from_field_text(field, &word))
+        .collect())
+}
+
+/// The text inside a PDF card; other kinds have none.
+fn extract_text(library: &Library, renderer: &Renderer, card: &CardMeta) -> Result<String> {
+    if card.kind != CardKind::Pdf {
+        return Ok(String::new());
+    }
+    let bytes = library.read_card_file(&card.id)?;
+    Ok(renderer.extract_text(&bytes)?)
+}
+
+fn schema() -> (Schema, Fields) {
+    let key = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(KEY_TOKENIZER)
+            .set_index_option(IndexRecordOption::Basic),
+    );
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        id: builder.add_text_field("id", STRING | STORED),
+        name: builder.add_text_field("name", TEXT | STORED),
+        body: builder.add_text_field("body", TEXT | STORED),
+        tag: builder.add_text_field("tag", key.clone()),
+        collection: builder.add_text_field("collection", key.clone()),
+        tier: builder.add_text_field("tier", key),
+        updated_at: builder.add_i64_field("updated_at", STORED),
+        hash: builder.add_text_field("hash", STORED),
+    };
+    (builder.build(), fields)
+}
+
+fn open_index(dir: &Path, schema: &Schema) -> tantivy::Result<Index> {
+    Index::open_or_create(MmapDirectory::open(dir)?, schema.clone())
+}
+
+fn read_indexed(searcher: &Searcher, fields: &Fields) -> Result<HashMap<String, Indexed>> {
+    let mut indexed = HashMap::new();
+    for segment in searcher.segment_readers() {
+        let store = segment.get_store_reader(0)?;
+        for doc_id in segment.doc_ids_alive() {
+            let doc: TantivyDocument = store.get(doc_id)?;
+            let updated_at = doc
+                .get_first(fields.updated_at)
+                .and_then(|value| value.as_i64())
+                .unwrap_or_default();
+            indexed.insert(
+                stored_text(&doc, fields.id),
+                Indexed {
+                    updated_at,
+                    hash: stored_text(&doc, fields.hash),
+                },
+            );
+        }
+    }
+    Ok(indexed)
+}
+
+fn stored_text(doc: &TantivyDocument, field: Field) -> String {
+    doc.get_first(field)
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[derive(Debug, PartialEq)]
+struct Part {
+    negated: bool,
+    clause: Clause,
+}
+
+#[derive(Debug, PartialEq)]
+enum Clause {
+    Text { text: String, prefix: bool },
+    Filter(FilterField, String),
+}
+
+#[derive(Debug, PartialEq)]
+enum FilterField {
+    Collection,
+    Tier,
+    Tag,
+}
+
+/// Splits a search-box query into its parts. Anything unrecognised is searched for
+/// as text, so a half-typed query never fails.
+fn parse(query: &str) -> Vec<Part> {
+    tokens(query)
+        .into_iter()
+        .map(|token| {
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token),
+            };
+            let filter = token.split_once(':').and_then(|(name, value)| {
+                let field = match name.to_lowercase().as_str() {
+                    "collection" => FilterField::Collection,
+                    "tier" => FilterField::Tier,
+                    "tag" => FilterField::Tag,
+                    _ => return None,
+                };
+                Some(Clause::Filter(field, unquote(value).0.to_string()))
+            });
+            let clause = filter.unwrap_or_else(|| {
+                let (text, prefix) = unquote(token);
+                Clause::Text {
+                    text: text.to_string(),
+                    prefix,
+                }
+            });
+            Part { negated, clause }
+        })
+        .collect()
+}
+
+/// Whitespace-separated tokens, keeping quoted stretches (`tag:"two words"`) whole. An
+/// unclosed quote runs to the end.
+fn tokens(query: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (i, c) in query.char_indices() {
+        if c.is_whitespace() && !quoted {
+            if let Some(start) = start.take() {
+                tokens.push(&query[start..i]);
+            }
+            continue;
+        }
+        if c == '"' {
+            quoted = !quoted;
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(start) = start {
+        tokens.push(&query[start..]);
+    }
+    tokens
+}
+
+/// Strips surrounding quotes and a trailing `*`, reporting whether there was one.
+fn unquote(value: &str) -> (&str, bool) {
+    let (value, prefix) = match value.strip_suffix('*') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    (value.trim_matches('"'), prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_card;
+
+    fn text(text: &str, prefix: bool) -> Clause {
+        Clause::Text {
+            text: text.into(),
+            prefix,
+        }
+    }
+
+    #[test]
+    fn parses_phrases_prefixes_and_filters() {
+        let parts = parse(r#"moon "jade pal"* jad* collection:"Quiet Court" -tag:dupe"#);
+        let clauses: Vec<_> = parts.iter().map(|p| (p.negated, &p.clause)).collect();
+        assert_eq!(
+            clauses,
+            [
+                (false, &text("moon", false)),
+                (false, &text("jade pal", true)),
+                (false, &text("jad", true)),
+                (
+                    false,
+                    &Clause::Filter(FilterField::Collection, "Quiet Court".into())
+                ),
+                (true, &Clause::Filter(FilterField::Tag, "dupe".into())),
+            ]
+        );
+        // Unknown fields, a lone dash and an unclosed quote are just text
+        assert_eq!(parse("foo:bar")[0].clause, text("foo:bar", false));
+        assert_eq!(
+            parse("-")[0],
+            Part {
+                negated: false,
+                clause: text("-", false)
+            }
+        );
+        assert_eq!(parse(r#""jade pal"#)[0].clause, text("jade pal", false));
+    }
+
+    #[test]
+    fn searches_text_names_and_filters() {
+        let tmp = tempfile::tempdir().unwrap();
+        let index = SearchIndex::open(tmp.path()).unwrap();
+        let card = |id: &str, name: &str, collection: &str, tags: &[&str]| {
+            let mut card = test_card(id);
+            card.name = name.into();
+            card.collection = collection.into();
+            card.tier = "Phoenix".into();
+            card.tags = tags.iter().map(|t| t.to_string()).collect();
+            card
+        };
+        {
+            let mut state = index.state.lock().unwrap();
+            let body = "The moon hare guards the jade palace. Card No. 042";
+            let a = card("a", "Moon Hare", "Quiet Court", &["dupe"]);
+            index.write(&mut state, &a, "h1".into(), body).unwrap();
+            let b = card("b", "Sun Fox", "Quiet Court Deluxe", &[]);
+            index
+                .write(&mut state, &b, "h2".into(), "A fox of the sun")
+                .unwrap();
+            state.writer.commit().unwrap();
+        }
+        index.reader.reload().unwrap();
+
+        let ids = |query: &str| -> Vec<String> {
+            let mut ids: Vec<_> = index
+                .search(query, 10)
+                .unwrap()
+                .into_iter()
+                .map(|h| h.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("\"jade palace\""), ["a"]);
+        assert_eq!(ids("jad*"), ["a"]);
+        assert_eq!(ids("\"jade pal\"*"), ["a"]);
+        assert_eq!(ids("042"), ["a"]);
+        assert_eq!(ids("fox"), ["b"]);
+        assert_eq!(ids("collection:\"quiet  court\""), ["a"]);
+        assert_eq!(ids("tier:PHOENIX"), ["a", "b"]);
+        assert_eq!(ids("-tag:dupe"), ["b"]);
+        assert_eq!(ids("palace -moon"), Vec::<String>::new());
+        assert!(ids("").is_empty());
+
+        let hit = &index.search("jad*", 10).unwrap()[0];
+        assert!(hit.snippet.contains("<b>jade</b>"), "{}", hit.snippet);
+    }
+
+    #[test]
+    fn text_is_only_cached_once_pdfium_has_read_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        let mut card = test_card("a");
+        card.name = "Moon Hare".into();
+        card.kind = CardKind::Pdf;
+        library.restore_card(card, b"not a pdf").unwrap();
+        let index = SearchIndex::open(&tmp.path().join("index")).unwrap();
+        let renderer = Renderer::new(Vec::new());
+        index.sync(&library, &renderer).unwrap();
+
+        assert_eq!(index.search("hare", 10).unwrap()[0].id, "a");
+        let hash = &index.state.lock().unwrap().indexed["a"].hash;
+        if renderer.pdfium().is_ok() {
+            // pdfium looked and found no text: that holds for as long as the file does
+            assert_eq!(*hash, library.file_hash("a").unwrap().unwrap());
+        } else {
+            assert!(hash.is_empty());
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Near-duplicate detection. The same card often arrives twice in different files, a
+//! PNG screenshot and the official PDF, or one image re-encoded at another size, and
+//! the content hash can't tell. Each file's first page is rendered once, right after
+//! import, and reduced to three 64-bit perceptual hashes (aHash, dHash and pHash);
+//! cards whose hashes differ in few bits are clustered for the user to review.
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use serde::Serialize;
+
+use crate::library::{Library, LibraryError, PerceptualHash};
+use crate::render::{RenderError, Renderer};
+
+/// How alike two cards must be, from 0 to 1, to be clustered unless the caller says
+/// otherwise.
+pub const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// Pages are rendered at this width to hash them; the hashes only look at a 32×32
+/// thumbnail, so more would be wasted.
+const RENDER_WIDTH: u32 = 256;
+
+/// Cards that probably show the same thing, oldest first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarCluster {
+    pub card_ids: Vec<String>,
+    /// The similarity of the least alike pair that put a card in the cluster.
+    pub similarity: f64,
+}
+
+/// Hashes every file that doesn't have perceptual hashes yet. Returns how many were
+/// hashed. Files that can't be rendered are recorded as such, except PDFs while
+/// Pdfium is missing, which are tried again next time.
+pub fn sync(library: &Library, renderer: &Renderer) -> Result<usize, LibraryError> {
+    let mut hashed = 0;
+    for file in library.unhashed_files()? {
+        let bytes = match library.read_card_file(&file.card_id) {
+            // Deleted since we listed it
+            Err(LibraryError::NotFound(_)) => continue,
+            other => other?,
+        };
+        let hash = match renderer.render(file.kind, &bytes, RENDER_WIDTH) {
+            Ok(image) => Some(hash_image(&image)),
+            Err(RenderError::PdfiumUnavailable) => continue,
+            Err(_) => None,
+        };
+        library.set_perceptual_hash(&file.hash, hash)?;
+        hashed += 1;
+    }
+    Ok(hashed)
+}
+
+/// Clusters of cards at least `threshold` alike (see [`similarity`]).
+pub fn clusters(library: &Library, threshold: f64) -> Result<Vec<SimilarCluster>, LibraryError> {
+    Ok(cluster(&library.perceptual_hashes()?, threshold))
+}
+
+/// How alike two hashed images are, from 0 (every bit differs) to 1 (same hashes).
+pub fn similarity(a: &PerceptualHash, b: &PerceptualHash) -> f64 {
+    let differing = (a.average ^ b.average).count_ones()
+        + (a.difference ^ b.difference).count_ones()
+        + (a.dct ^ b.dct).count_ones();
+    1.0 - f64::from(differing) / 192.0
+}
+
+pub fn hash_image(image: &DynamicImage) -> PerceptualHash {
+    PerceptualHash {
+        average: average_hash(&gray(image, 8, 8)),
+        difference: difference_hash(&gray(image, 9, 8)),
+        dct: dct_hash(&gray(image, 32, 32)),
+    }
+}
+
+fn gray(image: &DynamicImage, width: u32, height: u32) -> GrayImage {
+    image
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_luma8()
+}
+
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.fold(0, |hash, bit| (hash << 1) | u64::from(bit))
+}
+
+fn average_hash(image: &GrayImage) -> u64 {
+    let mean = image.pixels().map(|p| u32::from(p.0[0])).sum::<u32>() / 64;
+    bits(image.pixels().map(|p| u32::from(p.0[0]) > mean))
+}
+
+fn difference_hash(image: &GrayImage) -> u64 {
+    bits(
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .map(|(x, y)| image.get_pixel(x, y).0[0] > image.get_pixel(x + 1, y).0[0]),
+    )
+}
+
+/// The top-left 8×8 of the image's 2-D DCT-II, each compared with the median of the
+/// 63 that aren't the DC term.
+fn dct_hash(image: &GrayImage) -> u64 {
+    const N: usize = 32;
+    let cosines: Vec<[f64; N]> = (0..8)
+        .map(|u| {
+            std::array::from_fn(|x| {
+                ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * N) as f64).cos()
+            })
+        })
+        .collect();
+    let pixel = |x: usize, y: usize| f64::from(image.get_pixel(x as u32, y as u32).0[0]);
+    let mut coefficients = [0.0; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            let mut sum = 0.0;
+            for y in 0..N {
+                for x in 0..N {
+                    sum += pixel(x, y) * cosines[u][x] * cosines[v][y];
+                }
+            }
+            coefficients[v * 8 + u] = sum;
+        }
+    }
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    bits(coefficients.iter().map(|&c| c > median))
+}
+
+/// Groups cards linked by a chain of pairs at least `threshold` alike.
+fn cluster(hashes: &[(String, PerceptualHash)], threshold: f64) -> Vec<SimilarCluster> {
+    let mut cluster_of: Vec<Option<usize>> = vec![None; hashes.len()];
+    let mut clusters: Vec<(Vec<usize>, f64)> = Vec::new();
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            let alike = similarity(&hashes[i].1, &hashes[j].1);
+            if alike < threshold {
+                continue;
+            }
+            match (cluster_of[i], cluster_of[j]) {
+                (Some(a), Some(b)) if a == b => clusters[a].1 = clusters[a].1.min(alike),
+                (Some(a), Some(b)) => {
+                    let (keep, gone) = (a.min(b), a.max(b));
+                    let (members, lowest) = std::mem::take(&mut clusters[gone]);
+                    for &m in &members {
+                        cluster_of[m] = Some(keep);
+                    }
+                    clusters[keep].0.extend(members);
+                    clusters[keep].1 = clusters[keep].1.min(lowest).min(alike);
+                }
+                (Some(a), None) | (None, Some(a)) => {
+                    let new = if cluster_of[i].is_none() { i } else { j };
+                    cluster_of[new] = Some(a);
+                    clusters[a].0.push(new);
+                    clusters[a].1 = clusters[a].1.min(alike);
+                }
+                (None, None) => {
+                    cluster_of[i] = Some(clusters.len());
+                    cluster_of[j] = Some(clusters.len());
+                    clusters.push((vec![i, j], alike));
+                }
+            }
+        }
+    }
+    clusters
+        .into_iter()
+        .filter(|(members, _)| !members.is_empty())
+        .map(|(mut members, similarity)| {
+            members.sort_unstable();
+            SimilarCluster {
+                card_ids: members.iter().map(|&m| hashes[m].0.clone()).collect(),
+                similarity,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    /// A card-like picture: a light background with a dark disc at `(cx, cy)`.
+    fn picture(width: u32, height: u32, cx: f64, cy: f64) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x as f64 / width as f64, y as f64 / height as f64);
+            let inside = (fx - cx).powi(2) + (fy - cy).powi(2) < 0.04;
+            let shade = if inside { 30 } else { 200 - (fx * 80.0) as u8 };
+            Rgb([shade, shade, shade.saturating_add(20)])
+        }))
+    }
+
+    #[test]
+    fn resized_copies_cluster_and_different_cards_do_not() {
+        let original = hash_image(&picture(600, 840, 0.3, 0.4));
+        let smaller = hash_image(&picture(150, 210, 0.3, 0.4));
+        let other = hash_image(&picture(600, 840, 0.7, 0.7));
+        assert!(similarity(&original, &smaller) >= DEFAULT_THRESHOLD);
+        assert!(similarity(&original, &other) < DEFAULT_THRESHOLD);
+
+        let hashes = vec![
+            ("a".to_string(), original),
+            ("b".to_string(), other),
+            ("c".to_string(), smaller),
+            ("d".to_string(), original),
+        ];
+        let clusters = cluster(&hashes, DEFAULT_THRESHOLD);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].card_ids, ["a", "c", "d"]);
+    }
+}
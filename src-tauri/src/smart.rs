@@ -0,0 +1,179 @@
+//! Smart collections: saved filters whose members are worked out from the library
+//! rather than assigned. The app re-evaluates them whenever a card changes, so the
+//! grouped view and the counts stay current.
+
+use serde::Serialize;
+
+use crate::catalog::Catalog;
+use crate::library::{CardMeta, Library, LibraryError, SmartCollection, SmartFilter};
+use crate::query::{Query, QueryError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SmartError {
+    #[error("unknown tier `{0}`")]
+    UnknownTier(String),
+    #[error("the lowest tier is above the highest")]
+    EmptyTierRange,
+    #[error(transparent)]
+    Query(#[from] QueryError),
+}
+
+/// A smart collection with the ids of the cards currently in it, in library order.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartGroup {
+    #[serde(flatten)]
+    pub collection: SmartCollection,
+    pub card_ids: Vec<String>,
+}
+
+/// A [`SmartFilter`] with its tiers and query resolved, ready to test cards.
+pub struct Matcher<'a> {
+    filter: &'a SmartFilter,
+    /// Lowercased names of the tiers in range, if the filter has one.
+    tiers: Option<Vec<String>>,
+    query: Query,
+}
+
+impl<'a> Matcher<'a> {
+    /// Fails if the filter names a tier that doesn't exist or has a query that doesn't
+    /// parse, which is how filters are checked before they're saved.
+    /// Tiers are looked up in `catalog`.
+    pub fn new(filter: &'a SmartFilter, catalog: &Catalog) -> Result<Self, SmartError> {
+        let tier = |name: &str| match name.trim() {
+            "" => Ok(None),
+            name => catalog
+                .tier_rank(name)
+                .map(Some)
+                .ok_or_else(|| SmartError::UnknownTier(name.to_string())),
+        };
+        let tiers = match (tier(&filter.tier_min)?, tier(&filter.tier_max)?) {
+            (None, None) => None,
+            (Some(min), Some(max)) if min > max => return Err(SmartError::EmptyTierRange),
+            (min, max) => Some(
+                catalog.tiers[min.unwrap_or(0)..=max.unwrap_or(catalog.tiers.len() - 1)]
+                    .iter()
+                    .map(|t| t.to_lowercase())
+                    .collect(),
+            ),
+        };
+        Ok(Self {
+            filter,
+            tiers,
+            query: Query::parse(&filter.query, &catalog.tiers)?,
+        })
+    }
+
+    pub fn matches(&self, card: &CardMeta) -> bool {
+        let filter = self.filter;
+        let has_tag = |tag: &String| card.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()));
+        self.tiers
+            .as_ref()
+            .is_none_or(|tiers| tiers.contains(&card.tier.trim().to_lowercase()))
+            && filter.tags.iter().all(has_tag)
+            && !filter.exclude_tags.iter().any(has_tag)
+            && filter
+                .nsfw
+                .is_none_or(|nsfw| card.nsfw.unwrap_or(false) == nsfw)
+            && filter
+                .favorite
+                .is_none_or(|favorite| card.favorite == favorite)
+            && filter.added_from.is_none_or(|from| card.created_at >= from)
+            && filter
+                .added_until
+                .is_none_or(|until| card.created_at < until)
+            && (filter.kinds.is_empty() || filter.kinds.contains(&card.kind))
+            && self.query.matches(card)
+    }
+}
+
+/// Every smart collection with its current members. A collection whose filter no
+/// longer checks out (e.g. restored from a newer app's backup, or naming a tier since
+/// dropped from the catalog) comes back empty.
+pub fn evaluate(library: &Library, catalog: &Catalog) -> Result<Vec<SmartGroup>, LibraryError> {
+    let cards = library.list_cards()?;
+    Ok(library
+        .smart_collections()?
+        .into_iter()
+        .map(|collection| {
+            let card_ids = match Matcher::new(&collection.filter, catalog) {
+                Ok(matcher) => cards
+                    .iter()
+                    .filter(|card| matcher.matches(card))
+                    .map(|card| card.id.clone())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            SmartGroup {
+                collection,
+                card_ids,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::CardKind;
+
+    #[test]
+    fn filters_combine_every_condition() {
+        let filter = SmartFilter {
+            tier_min: "Phoenix".into(),
+            tier_max: "jade".into(),
+            tags: vec!["Spring".into()],
+            exclude_tags: vec!["dupe".into()],
+            nsfw: Some(false),
+            added_from: Some(1_000),
+            kinds: vec![CardKind::Pdf, CardKind::Image],
+            query: "-name:draft".into(),
+            ..SmartFilter::default()
+        };
+        let catalog = Catalog::builtin();
+        let matcher = Matcher::new(&filter, &catalog).unwrap();
+        let mut card: CardMeta = serde_json::from_str(
+            r#"{"id":"a","name":"Moon Hare","tier":"Sovereign","tags":["spring"],"createdAt":5000}"#,
+        )
+        .unwrap();
+        assert!(matcher.matches(&card));
+
+        let variants: [fn(&mut CardMeta); 6] = [
+            |c| c.tier = "Lotus".into(),
+            |c| c.tier = String::new(),
+            |c| c.tags.push("DUPE".into()),
+            |c| c.nsfw = Some(true),
+            |c| c.kind = CardKind::Gif,
+            |c| c.name = "Draft hare".into(),
+        ];
+        for change in variants {
+            let mut other = card.clone();
+            change(&mut other);
+            assert!(!matcher.matches(&other), "{other:?}");
+        }
+        card.created_at = 999;
+        assert!(!matcher.matches(&card));
+
+        assert!(matches!(
+            Matcher::new(
+                &SmartFilter {
+                    tier_min: "Bronze".into(),
+                    ..SmartFilter::default()
+                },
+                &catalog
+            ),
+            Err(SmartError::UnknownTier(_))
+        ));
+        assert!(matches!(
+            Matcher::new(
+                &SmartFilter {
+                    tier_min: "Jade".into(),
+                    tier_max: "Dawn".into(),
+                    ..SmartFilter::default()
+                },
+                &catalog
+            ),
+            Err(SmartError::EmptyTierRange)
+        ));
+    }
+}
@@ -0,0 +1,391 @@
+//! Thumbnails rendered in Rust and cached on disk, replacing the base64
+//! `thumbnailDataUrl` the frontend used to bake into every meta record.
+//!
+//! Cache entries are keyed by content hash and width (`<hash[..2]>/<hash>-<width>.webp`),
+//! so twins share thumbnails and a card whose file changes never serves a stale one.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::library::{write_atomic, CardKind, Library, LibraryError};
+use crate::render::{RenderError, Renderer};
+
+const MIN_WIDTH: u32 = 64;
+const MAX_WIDTH: u32 = 2048;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThumbnailError {
+    #[error(transparent)]
+    Render(#[from] RenderError),
+    #[error("could not encode thumbnail: {0}")]
+    Encode(image::ImageError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid thumbnail settings: {0}")]
+    Config(#[from] serde_json::Error),
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+}
+
+pub type Result<T> = std::result::Result<T, ThumbnailError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Webp,
+    Png,
+}
+
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "webp",
+            ThumbnailFormat::Png => "png",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "image/webp",
+            ThumbnailFormat::Png => "image/png",
+        }
+    }
+}
+
+/// Persisted in `thumbnails.json` next to the cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ThumbnailConfig {
+    /// Width of the thumbnails in the card grid.
+    pub grid_width: u32,
+    /// Width of the larger preview shown on hover.
+    pub hover_width: u32,
+    pub format: ThumbnailFormat,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            grid_width: 360,
+            hover_width: 720,
+            format: ThumbnailFormat::Webp,
+        }
+    }
+}
+
+/// Either one of the configured sizes or an explicit width in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ThumbnailSize {
+    Preset(SizePreset),
+    Width(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SizePreset {
+    Grid,
+    Hover,
+}
+
+#[derive(Debug)]
+pub struct Thumbnail {
+    pub path: PathBuf,
+    pub mime: &'static str,
+}
+
+pub struct ThumbnailService {
+    cache_dir: PathBuf,
+    config_path: PathBuf,
+    config: RwLock<ThumbnailConfig>,
+    renderer: Renderer,
+    /// One lock per cache entry being rendered, so two requests for the same thumbnail
+    /// don't both do the work (and race on the temp file) while different ones render
+    /// side by side.
+    rendering: Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+    /// Read by every render, written by [`clear_cache`](Self::clear_cache), so the cache
+    /// isn't removed under a thumbnail being written.
+    cache: RwLock<()>,
+}
+
+impl ThumbnailService {
+    pub fn open(
+        cache_dir: impl Into<PathBuf>,
+        config_path: impl Into<PathBuf>,
+        renderer: Renderer,
+    ) -> Result<Self> {
+        let cache_dir = cache_dir.into();
+        let config_path = config_path.into();
+        fs::create_dir_all(&cache_dir)?;
+        let config = match fs::read(&config_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ThumbnailConfig::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            cache_dir,
+            config_path,
+            config: RwLock::new(config),
+            renderer,
+            rendering: Mutex::default(),
+            cache: RwLock::new(()),
+        })
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    pub fn config(&self) -> ThumbnailConfig {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Saves new settings. Thumbnails already cached at other sizes stay until
+    /// [`clear_cache`](Self::clear_cache).
+    pub fn set_config(&self, config: ThumbnailConfig) -> Result<()> {
+        write_atomic(&self.config_path, &serde_json::to_vec_pretty(&config)?)?;
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+        Ok(())
+    }
+
+    /// Returns the cached thumbnail for a card, rendering it first if needed.
+    pub fn thumbnail(&self, library: &Library, id: &str, size: ThumbnailSize) -> Result<Thumbnail> {
+        let config = self.config();
+        let width = match size {
+            ThumbnailSize::Preset(SizePreset::Grid) => config.grid_width,
+            ThumbnailSize::Preset(SizePreset::Hover) => config.hover_width,
+            ThumbnailSize::Width(width) => width,
+        }
+        .clamp(MIN_WIDTH, MAX_WIDTH);
+
+        let card = library
+            .get_card(id)?
+            .ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+        let hash = library
+            .file_hash(id)?
+            .ok_or_else(|| LibraryError::NotFound(id.to_string()))?;
+        let path = self.cache_path(&hash, width, config.format);
+        let thumbnail = Thumbnail {
+            path,
+            mime: config.format.mime(),
+        };
+        if thumbnail.path.is_file() {
+            return Ok(thumbnail);
+        }
+
+        let _cache = self.cache.read().unwrap_or_else(|e| e.into_inner());
+        let lock = self.render_lock(&thumbnail.path);
+        let rendered = {
+            let _rendering = lock.lock().unwrap_or_else(|e| e.into_inner());
+            if thumbnail.path.is_file() {
+                Ok(())
+            } else {
+                self.render(
+                    library,
+                    id,
+                    card.kind,
+                    width,
+                    config.format,
+                    &thumbnail.path,
+                )
+            }
+        };
+        self.release_render_lock(&thumbnail.path, lock);
+        rendered.map(|()| thumbnail)
+    }
+
+    pub fn clear_cache(&self) -> Result<()> {
+        let _cache = self.cache.write().unwrap_or_else(|e| e.into_inner());
+        fs::remove_dir_all(&self.cache_dir)?;
+        fs::create_dir_all(&self.cache_dir)?;
+        Ok(())
+    }
+
+    fn render(
+        &self,
+        library: &Library,
+        id: &str,
+        kind: CardKind,
+        width: u32,
+        format: ThumbnailFormat,
+        path: &Path,
+    ) -> Result<()> {
+        let bytes = library.read_card_file(id)?;
+        let image = self.renderer.render(kind, &bytes, width)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, &encode(&image, format)?)?;
+        Ok(())
+    }
+
+    fn render_lock(&self, path: &Path) -> Arc<Mutex<()>> {
+        let mut locks = self.rendering.lock().unwrap_or_else(|e| e.into_inner());
+        locks.entry(path.to_path_buf()).or_default().clone()
+    }
+
+    /// Drops the lock for `path` once nobody else is waiting on it. Clones only happen
+    /// under the map's lock, so the count can't change while we look at it.
+    fn release_render_lock(&self, path: &Path, lock: Arc<Mutex<()>>) {
+        let mut locks = self.rendering.lock().unwrap_or_else(|e| e.into_inner());
+        drop(lock);
+        if locks
+            .get(path)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(path);
+        }
+    }
+
+    fn cache_path(&self, hash: &str, width: u32, format: ThumbnailFormat) -> PathBuf {
+        self.cache_dir
+            .join(&hash[..2])
+            .join(format!("{hash}-{width}.{}", format.extension()))
+    }
+}
+
+pub(crate) fn encode(image: &DynamicImage, format: ThumbnailFormat) -> Result<Vec<u8>> {
+    // Both encoders only take 8-bit RGB(A); PDFs render to RGBA anyway.
+    let image = DynamicImage::ImageRgba8(image.to_rgba8());
+    let mut out = Cursor::new(Vec::new());
+    let result = match format {
+        ThumbnailFormat::Webp => image.write_with_encoder(WebPEncoder::new_lossless(&mut out)),
+        ThumbnailFormat::Png => image.write_with_encoder(PngEncoder::new(&mut out)),
+    };
+    result.map_err(ThumbnailError::Encode)?;
+    Ok(out.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::{test_card, CardKind};
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, Rgba([180, 40, 90, 255]));
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    fn setup(dir: &Path) -> (Library, ThumbnailService) {
+        let library = Library::open(dir.join("library")).unwrap();
+        let mut card = test_card("moth");
+        card.name = "Moth".into();
+        card.kind = CardKind::Image;
+        library.restore_card(card, &png(1000, 1400)).unwrap();
+        let thumbnails = ThumbnailService::open(
+            dir.join("thumbnails"),
+            dir.join("thumbnails.json"),
+            Renderer::new(Vec::new()),
+        )
+        .unwrap();
+        (library, thumbnails)
+    }
+
+    #[test]
+    fn thumbnails_are_cached_under_hash_and_width() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (library, thumbnails) = setup(tmp.path());
+        let hash = library.file_hash("moth").unwrap().unwrap();
+
+        let grid = ThumbnailSize::Preset(SizePreset::Grid);
+        let first = thumbnails.thumbnail(&library, "moth", grid).unwrap();
+        assert_eq!(
+            first.path,
+            tmp.path()
+                .join("thumbnails")
+                .join(&hash[..2])
+                .join(format!("{hash}-360.webp"))
+        );
+        assert_eq!(first.mime, "image/webp");
+
+        // A second request is served from disk, not rendered again
+        let rendered_at = fs::metadata(&first.path).unwrap().modified().unwrap();
+        let second = thumbnails.thumbnail(&library, "moth", grid).unwrap();
+        assert_eq!(second.path, first.path);
+        assert_eq!(
+            fs::metadata(&second.path).unwrap().modified().unwrap(),
+            rendered_at
+        );
+        assert!(thumbnails.rendering.lock().unwrap().is_empty());
+
+        thumbnails.clear_cache().unwrap();
+        assert!(!first.path.exists());
+    }
+
+    #[test]
+    fn sizes_follow_the_presets_and_are_clamped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (library, thumbnails) = setup(tmp.path());
+        thumbnails
+            .set_config(ThumbnailConfig {
+                grid_width: 200,
+                hover_width: 500,
+                format: ThumbnailFormat::Webp,
+            })
+            .unwrap();
+
+        let width_of = |size| {
+            let thumbnail = thumbnails.thumbnail(&library, "moth", size).unwrap();
+            image::open(&thumbnail.path).unwrap().width()
+        };
+        assert_eq!(width_of(ThumbnailSize::Preset(SizePreset::Grid)), 200);
+        assert_eq!(width_of(ThumbnailSize::Preset(SizePreset::Hover)), 500);
+        assert_eq!(width_of(ThumbnailSize::Width(10)), MIN_WIDTH);
+        // Wider than the card itself: left at its own size
+        assert_eq!(width_of(ThumbnailSize::Width(5000)), 1000);
+
+        let presets: Vec<ThumbnailSize> =
+            serde_json::from_str(r#"["grid", "hover", 640]"#).unwrap();
+        assert_eq!(
+            presets,
+            [
+                ThumbnailSize::Preset(SizePreset::Grid),
+                ThumbnailSize::Preset(SizePreset::Hover),
+                ThumbnailSize::Width(640),
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_webp_and_png() {
+        let image = image::load_from_memory(&png(40, 56)).unwrap();
+        for (format, expected) in [
+            (ThumbnailFormat::Webp, ImageFormat::WebP),
+            (ThumbnailFormat::Png, ImageFormat::Png),
+        ] {
+            let bytes = encode(&image, format).unwrap();
+            assert_eq!(image::guess_format(&bytes).unwrap(), expected);
+            let decoded = image::load_from_memory(&bytes).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (40, 56));
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let (library, thumbnails) = setup(tmp.path());
+        thumbnails
+            .set_config(ThumbnailConfig {
+                format: ThumbnailFormat::Png,
+                ..ThumbnailConfig::default()
+            })
+            .unwrap();
+        let thumbnail = thumbnails
+            .thumbnail(&library, "moth", ThumbnailSize::Width(300))
+            .unwrap();
+        assert_eq!(thumbnail.mime, "image/png");
+        assert!(thumbnail.path.to_string_lossy().ends_with("-300.png"));
+    }
+}
@@ -0,0 +1,427 @@
+//! Duplicates and the have/want list for trading them.
+//!
+//! Cards are copies of the same card when they share a file, when they're matched to
+//! the same checklist entry, or, outside checklists, when they're in the same
+//! collection under the same name give or take a download suffix like `(1)` or
+//! `- Copy`. Each card also counts its own `quantity`. Copies can be merged into one
+//! card (see [`merge`]); `crate::similar` finds more candidates by their pictures.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::{Catalog, ChecklistEntry};
+use crate::checklist::{self, words, ChecklistReport};
+use crate::library::{collection_key, CardMeta, Library, LibraryError};
+
+/// Cards that are copies of the same card, oldest first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// Whether every card in the group has the same file.
+    pub identical: bool,
+    pub card_ids: Vec<String>,
+    /// Copies across the group, counting each card's quantity.
+    pub copies: u32,
+}
+
+/// Spare copies to offer and missing checklist entries to ask for.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeList {
+    pub have: Vec<TradeItem>,
+    pub want: Vec<TradeItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeItem {
+    pub collection: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub number: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub variant: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tier: String,
+    /// Conditions of the copies on offer, comma-separated.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub condition: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+/// Groups of two or more cards that are copies of each other.
+pub fn duplicate_groups(
+    library: &Library,
+    catalog: &Catalog,
+) -> Result<Vec<DuplicateGroup>, LibraryError> {
+    let cards = library.list_cards()?;
+    let hashes = library.file_hashes()?;
+    let entries = matched_entries(&checklist::report(library, catalog, None)?);
+    Ok(group(&cards, &hashes, &entries)
+        .into_iter()
+        .filter(|g| g.len() > 1)
+        .map(|g| DuplicateGroup {
+            identical: g.iter().all(|&i| {
+                let hash = hashes.get(&cards[i].id);
+                hash.is_some() && hash == hashes.get(&cards[g[0]].id)
+            }),
+            card_ids: g.iter().map(|&i| cards[i].id.clone()).collect(),
+            copies: g.iter().map(|&i| cards[i].quantity).sum(),
+        })
+        .collect())
+}
+
+/// Every card the user has more than one copy of, with one copy kept back, and every
+/// checklist entry they have none of.
+pub fn trade_list(library: &Library, catalog: &Catalog) -> Result<TradeList, LibraryError> {
+    let cards = library.list_cards()?;
+    let hashes = library.file_hashes()?;
+    let report = checklist::report(library, catalog, None)?;
+    let entries = matched_entries(&report);
+    let mut list = TradeList::default();
+    for group in group(&cards, &hashes, &entries) {
+        let copies: u32 = group.iter().map(|&i| cards[i].quantity).sum();
+        if copies < 2 {
+            continue;
+        }
+        let first = &cards[group[0]];
+        let mut conditions: Vec<&str> = Vec::new();
+        for &i in &group {
+            let condition = cards[i].condition.trim();
+            if !condition.is_empty() && !conditions.contains(&condition) {
+                conditions.push(condition);
+            }
+        }
+        let (collection, entry) = match entries.get(&first.id) {
+            Some((collection, entry)) => (collection.clone(), Some(entry)),
+            None => (first.collection.trim().to_string(), None),
+        };
+        list.have.push(TradeItem {
+            collection,
+            number: entry.map_or_else(String::new, |e| e.number.clone()),
+            name: entry.map_or_else(|| first.name.clone(), |e| e.name.clone()),
+            variant: entry.map_or_else(String::new, |e| e.variant.clone()),
+            tier: match entry {
+                Some(e) if !e.tier.is_empty() => e.tier.clone(),
+                _ => first.tier.clone(),
+            },
+            condition: conditions.join(", "),
+            count: copies - 1,
+        });
+    }
+    for collection in report.collections {
+        for status in collection.entries {
+            if status.card_ids.is_empty() {
+                list.want.push(TradeItem {
+                    collection: collection.collection.clone(),
+                    number: status.entry.number,
+                    name: status.entry.name,
+                    variant: status.entry.variant,
+                    tier: status.entry.tier,
+                    condition: String::new(),
+                    count: 1,
+                });
+            }
+        }
+    }
+    Ok(list)
+}
+
+/// Folds `others` into `keep` and deletes them, as one step of the library's history:
+/// their copies add to its quantity, their tags join its tags, it's a favorite if any
+/// of them was, and it takes their collection, tier and condition where it has none.
+/// Returns `keep` as merged.
+pub fn merge(library: &Library, keep: &str, others: &[String]) -> Result<CardMeta, LibraryError> {
+    library.merge_cards(keep, others, |card, other| {
+        card.quantity = card.quantity.saturating_add(other.quantity);
+        for tag in &other.tags {
+            if !card.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                card.tags.push(tag.clone());
+            }
+        }
+        card.favorite |= other.favorite;
+        for (mine, theirs) in [
+            (&mut card.collection, &other.collection),
+            (&mut card.tier, &other.tier),
+            (&mut card.condition, &other.condition),
+        ] {
+            if mine.trim().is_empty() {
+                mine.clone_from(theirs);
+            }
+        }
+    })
+}
+
+/// The checklist entry each matched card counts towards, with its collection's name.
+fn matched_entries(report: &ChecklistReport) -> HashMap<String, (String, ChecklistEntry)> {
+    let mut matched = HashMap::new();
+    for collection in &report.collections {
+        for status in &collection.entries {
+            for id in &status.card_ids {
+                matched.insert(
+                    id.clone(),
+                    (collection.collection.clone(), status.entry.clone()),
+                );
+            }
+        }
+    }
+    matched
+}
+
+/// Indexes into `cards` of each set of copies, singletons included, in library order.
+fn group(
+    cards: &[CardMeta],
+    hashes: &HashMap<String, String>,
+    entries: &HashMap<String, (String, ChecklistEntry)>,
+) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..cards.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut first_with: HashMap<String, usize> = HashMap::new();
+    for (i, card) in cards.iter().enumerate() {
+        let collection = collection_key(&card.collection);
+        let same_card = match entries.get(&card.id) {
+            Some((_, entry)) => format!("entry\0{collection}\0{}", entry.key()),
+            None => format!("name\0{collection}\0{}", name_key(&card.name)),
+        };
+        let hash = hashes.get(&card.id).map(|hash| format!("file\0{hash}"));
+        for key in [Some(same_card), hash].into_iter().flatten() {
+            match first_with.get(&key) {
+                Some(&other) => {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, other));
+                    // The older card stays the root so groups list it first
+                    parent[a.max(b)] = a.min(b);
+                }
+                None => {
+                    first_with.insert(key, i);
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    for i in 0..cards.len() {
+        let r = root(&mut parent, i);
+        match group_of.get(&r) {
+            Some(&g) => groups[g].push(i),
+            None => {
+                group_of.insert(r, groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+    groups
+}
+
+/// A card name without the suffixes browsers and file managers add to the second
+/// download of a file: `Lady Ash (1)`, `Lady Ash - Copy`, `Lady Ash copy 2`.
+fn name_key(name: &str) -> String {
+    let mut name = name.trim();
+    let ends_with_copy = |name: &str| {
+        name.len() >= 4
+            && name.is_char_boundary(name.len() - 4)
+            && name[name.len() - 4..].eq_ignore_ascii_case("copy")
+    };
+    loop {
+        let stripped = if let Some(rest) = name.strip_suffix(')') {
+            rest.rsplit_once('(')
+                .filter(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                .map(|(before, _)| before)
+        } else if ends_with_copy(name) {
+            Some(&name[..name.len() - "copy".len()])
+        } else {
+            let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+            (trimmed.len() < name.len()
+                && trimmed.ends_with(' ')
+                && ends_with_copy(trimmed.trim_end()))
+            .then_some(trimmed)
+        };
+        match stripped {
+            Some(rest) => name = rest.trim_end_matches([' ', '-', '_']),
+            None => return words(name).join(" "),
+        }
+    }
+}
+
+impl TradeList {
+    pub fn render(&self, format: TradeFormat) -> String {
+        match format {
+            TradeFormat::Text => {
+                let mut text = String::new();
+                for (title, items) in [("Have", &self.have), ("Want", &self.want)] {
+                    if !text.is_empty() {
+                        text.push('\n');
+                    }
+                    text.push_str(title);
+                    text.push('\n');
+                    if items.is_empty() {
+                        text.push_str("- none\n");
+                    }
+                    for item in items {
+                        text.push_str(&format!("- {}\n", item.describe()));
+                    }
+                }
+                text
+            }
+            TradeFormat::Csv => {
+                let mut csv =
+                    String::from("list,collection,number,name,variant,tier,condition,count\n");
+                for (list, items) in [("have", &self.have), ("want", &self.want)] {
+                    for item in items {
+                        let count = item.count.to_string();
+                        let row = [
+                            list,
+                            &item.collection,
+                            &item.number,
+                            &item.name,
+                            &item.variant,
+                            &item.tier,
+                            &item.condition,
+                            &count,
+                        ];
+                        csv.push_str(&row.map(csv_field).join(","));
+                        csv.push('\n');
+                    }
+                }
+                csv
+            }
+            TradeFormat::Json => serde_json::to_string_pretty(self).expect("trade lists serialize"),
+        }
+    }
+}
+
+impl TradeItem {
+    /// One line for a chat message, e.g. `Lady Ash (Holo) · Quiet Court #002 · Jade ×2`.
+    fn describe(&self) -> String {
+        let mut name = self.name.clone();
+        if !self.variant.is_empty() {
+            name.push_str(&format!(" ({})", self.variant));
+        }
+        let mut place = self.collection.clone();
+        if !self.number.is_empty() {
+            place.push_str(&format!(" #{}", self.number));
+        }
+        let mut parts = vec![name];
+        parts.extend(
+            [place, self.tier.clone(), self.condition.clone()]
+                .into_iter()
+                .filter(|p| !p.is_empty()),
+        );
+        let mut line = parts.join(" · ");
+        if self.count > 1 {
+            line.push_str(&format!(" ×{}", self.count));
+        }
+        line
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_card;
+
+    fn card(id: &str, name: &str, quantity: u32) -> CardMeta {
+        let mut card = test_card(id);
+        card.name = name.into();
+        card.collection = "Hairpins".into();
+        card.quantity = quantity;
+        card
+    }
+
+    #[test]
+    fn groups_copies_by_file_entry_and_name() {
+        assert_eq!(name_key("Moon Hare (1)"), "moon hare");
+        assert_eq!(name_key("Moon_Hare - Copy"), "moon hare");
+        assert_eq!(name_key("moon hare copy 2"), "moon hare");
+        assert_eq!(name_key("Moon Hare 2"), "moon hare 2");
+
+        let cards = [
+            card("a", "Moon Hare", 1),
+            card("b", "Sun Crow", 2),
+            card("c", "Moon Hare (1)", 1),
+            card("d", "Renamed scan", 1),
+            card("e", "Star Fox", 1),
+        ];
+        let hashes = HashMap::from([
+            ("b".to_string(), "h1".to_string()),
+            ("d".to_string(), "h1".to_string()),
+        ]);
+        let entry = ChecklistEntry {
+            number: "7".into(),
+            name: "Star Fox".into(),
+            tier: String::new(),
+            variant: String::new(),
+        };
+        let entries = HashMap::from([("e".to_string(), ("Hairpins".to_string(), entry))]);
+        assert_eq!(
+            group(&cards, &hashes, &entries),
+            [vec![0, 2], vec![1, 3], vec![4]]
+        );
+    }
+
+    #[test]
+    fn renders_for_chat() {
+        let list = TradeList {
+            have: vec![TradeItem {
+                collection: "Quiet Court".into(),
+                number: "002".into(),
+                name: "Lady Ash".into(),
+                variant: "Holo".into(),
+                tier: "Jade".into(),
+                condition: "Mint, Played".into(),
+                count: 2,
+            }],
+            want: Vec::new(),
+        };
+        assert_eq!(
+            list.render(TradeFormat::Text),
+            "Have\n- Lady Ash (Holo) · Quiet Court #002 · Jade · Mint, Played ×2\n\nWant\n- none\n"
+        );
+        assert_eq!(
+            list.render(TradeFormat::Csv),
+            "list,collection,number,name,variant,tier,condition,count\n\
+             have,Quiet Court,002,Lady Ash,Holo,Jade,\"Mint, Played\",2\n"
+        );
+    }
+
+    #[test]
+    fn merging_adds_up_copies_without_overflowing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        let mut keep = card("a", "Kanzashi", u32::MAX - 1);
+        keep.tags = vec!["gold".into()];
+        library.add_card(keep, b"a").unwrap();
+        let mut other = card("b", "Kanzashi", 3);
+        other.tags = vec!["Gold".into(), "lacquer".into()];
+        library.add_card(other, b"b").unwrap();
+
+        let merged = merge(&library, "a", &["b".into()]).unwrap();
+        assert_eq!(merged.quantity, u32::MAX);
+        assert_eq!(merged.tags, ["gold", "lacquer"]);
+        assert_eq!(library.list_cards().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,236 @@
+//! Watch folders: PDFs and images dropped into a registered folder are imported
+//! automatically, with that folder's collection, tier and NSFW rules.
+//!
+//! Filesystem events are debounced so a file still being copied is only looked at
+//! once it settles. Every file imported (or found to be in the library already) is
+//! recorded in a ledger with its size, mtime and hash, so restarts, rescans and
+//! touch-only changes don't import it again, and deleting a watched card from the
+//! library doesn't bring it back. Only files directly inside a folder are watched.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, UNIX_EPOCH};
+
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+
+use crate::ingest;
+use crate::library::{
+    hash_bytes, AddOutcome, CardKind, CardMeta, Library, LibraryError, SeenFile, WatchFolder,
+};
+use crate::render::Renderer;
+
+/// How long a file has to stay quiet before it is imported.
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error("could not watch folder: {0}")]
+    Notify(#[from] notify_debouncer_mini::notify::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+}
+
+pub type Result<T> = std::result::Result<T, WatchError>;
+
+/// What happened to one watched file.
+#[derive(Debug)]
+pub struct WatchReport {
+    pub folder: String,
+    pub path: PathBuf,
+    pub result: Result<AddOutcome>,
+}
+
+pub struct WatchService {
+    debouncer: Mutex<Debouncer<RecommendedWatcher>>,
+    /// Held while importing, so the initial scan and live events never race on a file.
+    ingesting: Mutex<()>,
+}
+
+impl WatchService {
+    /// Starts the watcher thread. `on_change` gets the debounced paths that changed in
+    /// any watched folder, and is expected to pass them to [`ingest`](Self::ingest).
+    pub fn new(mut on_change: impl FnMut(Vec<PathBuf>) + Send + 'static) -> Result<Self> {
+        let debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+            // Errors here are transient (e.g. an overflowed event queue); the next
+            // rescan picks up anything missed.
+            if let Ok(events) = result {
+                on_change(events.into_iter().map(|event| event.path).collect());
+            }
+        })?;
+        Ok(Self {
+            debouncer: Mutex::new(debouncer),
+            ingesting: Mutex::new(()),
+        })
+    }
+
+    pub fn watch(&self, folder: &Path) -> Result<()> {
+        self.debouncer()
+            .watcher()
+            .watch(folder, RecursiveMode::NonRecursive)?;
+        Ok(())
+    }
+
+    pub fn unwatch(&self, folder: &Path) -> Result<()> {
+        self.debouncer().watcher().unwatch(folder)?;
+        Ok(())
+    }
+
+    /// Importable files currently in a folder, for the scan run when a folder is added
+    /// and at startup.
+    pub fn scan(folder: &Path) -> Result<Vec<PathBuf>> {
+        Ok(ingest::card_files(folder)?)
+    }
+
+    /// Imports a changed file if it is a card in a watched folder that the ledger
+    /// hasn't seen in this form. Returns `None` for anything skipped.
+    pub fn ingest(
+        &self,
+        library: &Library,
+        renderer: &Renderer,
+        path: &Path,
+    ) -> Option<WatchReport> {
+        let kind = ingest::card_kind(path)?;
+        // Folders are stored canonicalized and events may arrive under another
+        // spelling; key the ledger by the canonical one
+        let folder = fs::canonicalize(path.parent()?).ok()?;
+        let path = folder.join(path.file_name()?);
+        let folder = folder.display().to_string();
+        let _ingesting = self.ingesting.lock().unwrap_or_else(|e| e.into_inner());
+        let result = find_folder(library, &folder)
+            .and_then(|rules| match rules {
+                Some(rules) => import_file(library, renderer, &rules, &path, kind),
+                None => Ok(None),
+            })
+            .transpose()?;
+        Some(WatchReport {
+            folder,
+            path,
+            result,
+        })
+    }
+
+    fn debouncer(&self) -> MutexGuard<'_, Debouncer<RecommendedWatcher>> {
+        self.debouncer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn find_folder(library: &Library, path: &str) -> Result<Option<WatchFolder>> {
+    Ok(library
+        .watch_folders()?
+        .into_iter()
+        .find(|folder| folder.path == path))
+}
+
+/// `None` if the file is gone or unchanged since the ledger last saw it.
+fn import_file(
+    library: &Library,
+    renderer: &Renderer,
+    folder: &WatchFolder,
+    path: &Path,
+    kind: CardKind,
+) -> Result<Option<AddOutcome>> {
+    let key = path.display().to_string();
+    let metadata = match fs::metadata(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        other => other?,
+    };
+    let modified_at = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let previous = library.seen_file(&key)?;
+    if let Some(seen) = &previous {
+        if seen.size == metadata.len() && seen.modified_at == modified_at {
+            return Ok(None);
+        }
+    }
+
+    let bytes = fs::read(path)?;
+    let seen = SeenFile {
+        size: bytes.len() as u64,
+        modified_at,
+        hash: hash_bytes(&bytes),
+    };
+    // Touched or copied over with the same content
+    if previous.is_some_and(|previous| previous.hash == seen.hash) {
+        library.mark_seen(&folder.path, &key, &seen)?;
+        return Ok(None);
+    }
+
+    let meta = CardMeta {
+        collection: folder.collection.clone(),
+        tier: folder.tier.clone(),
+        nsfw: folder.nsfw,
+        ..ingest::card_meta(renderer, path, kind, &bytes)
+    };
+    let outcome = library.add_card(meta, &bytes)?;
+    library.mark_seen(&folder.path, &key, &seen)?;
+    Ok(Some(outcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ledger_skips_files_already_imported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let library = Library::open(tmp.path().join("library")).unwrap();
+        let renderer = Renderer::new(Vec::new());
+        let watch = WatchService::new(|_| {}).unwrap();
+        let dir = fs::canonicalize(tmp.path()).unwrap();
+        let rules = WatchFolder {
+            path: dir.display().to_string(),
+            collection: "Quiet Court".into(),
+            tier: "Lotus".into(),
+            nsfw: Some(true),
+        };
+        library.add_watch_folder(&rules).unwrap();
+
+        let file = dir.join("Moon Hare.png");
+        fs::write(&file, b"first").unwrap();
+        let report = watch.ingest(&library, &renderer, &file).unwrap();
+        let Ok(AddOutcome::Added { card }) = report.result else {
+            panic!("expected an import, got {:?}", report.result);
+        };
+        assert_eq!(
+            (
+                card.name.as_str(),
+                card.collection.as_str(),
+                card.tier.as_str()
+            ),
+            ("Moon Hare", "Quiet Court", "Lotus")
+        );
+        assert_eq!((card.kind, card.nsfw), (CardKind::Image, Some(true)));
+
+        // Unchanged, then rewritten with the same bytes: nothing to do
+        assert!(watch.ingest(&library, &renderer, &file).is_none());
+        fs::write(&file, b"first").unwrap();
+        assert!(watch.ingest(&library, &renderer, &file).is_none());
+
+        // Gone from the library, but the ledger still remembers it
+        library.delete_card(&card.id).unwrap();
+        assert!(watch.ingest(&library, &renderer, &file).is_none());
+
+        fs::write(&file, b"second version").unwrap();
+        let report = watch.ingest(&library, &renderer, &file).unwrap();
+        assert!(matches!(report.result, Ok(AddOutcome::Added { .. })));
+
+        // Not in a watched folder, or not a card
+        let other = tempfile::tempdir().unwrap();
+        fs::write(other.path().join("a.pdf"), b"%PDF").unwrap();
+        assert!(watch
+            .ingest(&library, &renderer, &other.path().join("a.pdf"))
+            .is_none());
+        fs::write(dir.join("notes.txt"), b"hi").unwrap();
+        assert!(watch
+            .ingest(&library, &renderer, &dir.join("notes.txt"))
+            .is_none());
+        assert_eq!(WatchService::scan(&dir).unwrap(), vec![file]);
+    }
+}
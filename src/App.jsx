@@ -2,7 +2,9 @@ import React, { useEffect, useMemo, useRef, useState } from "react";
 import localforage from "localforage";
 import { v4 as uuidv4 } from "uuid";
 import { GlobalWorkerOptions, getDocument } from "pdfjs-dist";
+import { convertFileSrc } from "@tauri-apps/api/core";
 import workerUrl from "pdfjs-dist/build/pdf.worker.min.mjs?url";
+import bundledCatalog from "../src-tauri/catalog.json";
 
 // Desktop & web-safe pdf.js worker init (module worker everywhere)
 try {
@@ -20,7 +22,15 @@ try {
 // ---- Simple persistent stores ----
 const metaStore = localforage.createInstance({ name: "pdf-card-binder-meta" });
 const fileStore = localforage.createInstance({ name: "pdf-card-binder-files" });
-const orderStore = localforage.createInstance({ name: "pdf-card-binder-order" });
+const legacyOrderStore = localforage.createInstance({ name: "pdf-card-binder-order" });
+// On desktop the order map lives in the Rust library (same getItem/setItem surface).
+// setItem is a step of the undo history there; restoreItem, for backups, isn't.
+const orderStore = isTauri() ? {
+  getItem: () => tauriInvoke('get_order_map'),
+  setItem: (_key, map) => tauriInvoke('set_order_map', { map: map || {} }),
+  restoreItem: (_key, map) => tauriInvoke('restore_order_map', { map: map || {} }),
+  clear: () => tauriInvoke('restore_order_map', { map: {} }),
+} : legacyOrderStore;
 
 // Idle helper (fallbacks for Safari/Firefox)
 const ric = window.requestIdleCallback || ((cb) => setTimeout(() => cb({ timeRemaining: () => 0 }), 0));
@@ -28,162 +38,67 @@ const cancelRic = window.cancelIdleCallback || clearTimeout;
 
 
 
-// ---- Tiers (hardcoded) ----
-const TIER_OPTIONS = [
-  "Dawn","Seal","Lotus","Scribe","Eclipse","Celestial",
-  "Phoenix","Transcendent","Sovereign","Ascendant","Empyreal",
-  "Jade","Immortal"
-];
-
-// Hardcoded default collections (edit this list whenever you like)
-const DEFAULT_COLLECTIONS = [
-  "Aurora Seal",
-  "Aurora Sunveil Pavilion",
-  "Celestial Oath",
-  "Everflame Mandate",
-  "Everglow Edict - S1",
-  "First Light - S1",
-  "Imperial Dayseal",
-  "Línglián Shì",
-  "Mooncrown Eclipse - S1",
-  "Pavilion Scrolls - S1",
-  "Quiet Court",
-  "Shadow Mandate",
-  "Starkeep Manuscript",
-  "Starseal Registry",
-  "Heaven's Seal - Tiān Xǐ",
-  "Sky Codex - Xiāo Diǎn",
-  "Two-Sun Covenant",
-  "Writ Archive",
-  "Zenith Gate",
-  "Firewing Aegis",
-  "Aether Gate",
-  "Dominion Scroll",
-  "Aerial Pavilion",
-  "Seraphic Testament",
-  "Jade-Antler Mooncrest",
-  "Jade Cat Private Garden",
-  "Immortal Cat Imperial Court",
-  "Immortal Fragments",
-  "Empress’ Favor Notes",
-  "Astral Testament",
-  "Hairpins",
-  "Cozy Court",
-  "Cozy Solstice Signs",
-  "Samharian Ladies",
-  "Celestial Foxfire",
-  "Spicy Cat Princess",
-  "Magazine",
-  "Court Tokens",
-  "Obsidian Veil",
-  "Moonspire Minutes",
-  "The Blooming Crown",
-  "Phoenix Oath",
-  "Black Aurora Mandate",
-];
-
 // Hoisted version — safe to use earlier in the file
 function keyForCollection(c) {
   return normalizeCollectionName(c || "(None)").toLowerCase();
 }
 
 
-const SORTED_COLLECTIONS = [...DEFAULT_COLLECTIONS].sort((a, b) =>
-  a.localeCompare(b, 'en', { sensitivity: 'base', numeric: true })
-);
-
-/** Map a collection name → its default Tier (case/whitespace-insensitive). */
-const COLLECTION_DEFAULT_TIER_RAW = {
-  "Aurora Seal" : "Immortal",
-  "Aurora Sunveil Pavilion" : "Dawn",
-  "Celestial Oath" : "Ascendant",
-  "Everflame Mandate" : "Phoenix",
-  "Everglow Edict - S1" : "Empyreal",
-  "First Light - S1" : "Dawn",
-  "Imperial Dayseal" : "Empyreal",
-  "Línglián Shì" : "Lotus",
-  "Mooncrown Eclipse - S1" : "Eclipse",
-  "Pavilion Scrolls - S1" : "Jade",
-  "Quiet Court" : "Lotus",
-  "Shadow Mandate" : "Eclipse",
-  "Starkeep Manuscript" : "Scribe",
-  "Starseal Registry" : "Scribe",
-  "Heaven's Seal - Tiān Xǐ" : "Celestial",
-  "Sky Codex - Xiāo Diǎn" : "Transcendent",
-  "Two-Sun Covenant" : "Jade",
-  "Writ Archive" : "Seal",
-  "Zenith Gate" : "Celestial",
-  "Firewing Aegis" : "Phoenix",
-  "Aether Gate" : "Transcendent",
-  "Dominion Scroll" : "Sovereign",
-  "Aerial Pavilion" : "Ascendant",
-  "Seraphic Testament" : "Empyreal",
-  "Jade-Antler Mooncrest" : "Immortal",
-  "Jade Cat Private Garden" : "Jade",
-  "Immortal Cat Imperial Court" : "Immortal",
-  "Astral Testament" : "Sovereign",
-  "Court Tokens" : "Jade",
-  "Obsidian Veil" : "Empyreal",
-  "Moonspire Minutes" : "Scribe",
-  "The Blooming Crown" : "Lotus",
-  "Phoenix Oath" : "Phoenix",
-  "Black Aurora Mandate" : "Eclipse",
-};
-
-/** Build a normalized lookup so "  Celestial  Oath " works the same. */
-const COLLECTION_DEFAULT_TIER = Object.fromEntries(
-  Object.entries(COLLECTION_DEFAULT_TIER_RAW).map(([k, v]) => [
-    keyForCollection(k), v
-  ])
-);
-
-/** Return a tier for a given collection name (or "" if none). */
-function defaultTierForCollection(name) {
-  const t = COLLECTION_DEFAULT_TIER[keyForCollection(name)];
-  return TIER_OPTIONS.includes(t) ? t : "";
-}
-
-/** Map a collection name → its default NSFW status (case/whitespace-insensitive). */
-const COLLECTION_DEFAULT_NSFW_RAW = {
-  // Add collection names here that should auto-mark as NSFW, e.g.:
-  // "Adult Collection": true,
-  // "Mature Artwork": true,
-  "Cozy Court": true,
-  "Cozy Solstice Signs": true,
-  "Samharian Ladies": true,
-  "Celestial Foxfire": true,
-  "Spicy Cat Princess": true,
-  "Obsidian Veil": true,
-};
-
-/** Build a normalized lookup so "  Adult  Collection " works the same. */
-const COLLECTION_DEFAULT_NSFW = Object.fromEntries(
-  Object.entries(COLLECTION_DEFAULT_NSFW_RAW).map(([k, v]) => [
-    keyForCollection(k), v
-  ])
-);
+// ---- Tiers and collections ----
+// The catalog (src-tauri/src/catalog.rs) lists the tiers in rank order, the collections
+// the app suggests, and the tier / NSFW flag a card gets when put in one of them. The
+// desktop app loads the user's copy from Rust; the web build uses the bundled one.
 
-/** Return NSFW status for a given collection name (or false if none). */
-function defaultNsfwForCollection(name) {
-  return COLLECTION_DEFAULT_NSFW[keyForCollection(name)] === true;
+/** Wraps a catalog document with the lookups the UI needs. */
+function indexCatalog(doc) {
+  const byKey = new Map(doc.collections.map((c) => [keyForCollection(c.name), c]));
+  const tierKeys = doc.tiers.map((t) => t.toLowerCase());
+  return {
+    doc,
+    tiers: doc.tiers,
+    collections: doc.collections
+      .map((c) => c.name)
+      .sort((a, b) => a.localeCompare(b, 'en', { sensitivity: 'base', numeric: true })),
+    /** A collection's default tier (case/whitespace-insensitive), or "". */
+    defaultTier(name) {
+      const t = byKey.get(keyForCollection(name))?.defaultTier;
+      return doc.tiers.includes(t) ? t : "";
+    },
+    /** Whether cards put in a collection are marked NSFW. */
+    defaultNsfw(name) {
+      return byKey.get(keyForCollection(name))?.nsfw === true;
+    },
+    /** Rank for tier sorting (case-insensitive; unknown/empty -> bottom). */
+    tierIndex(t) {
+      const i = tierKeys.indexOf(String(t || "").toLowerCase());
+      return i === -1 ? Number.POSITIVE_INFINITY : i;
+    },
+  };
 }
 
-
-
-// Rank helper for tier sorting (case-insensitive; unknown/empty -> bottom)
-const tierIndex = (t) => {
-  const i = TIER_OPTIONS.findIndex(x => x.toLowerCase() === String(t || "").toLowerCase());
-  return i === -1 ? Number.POSITIVE_INFINITY : i;
-};
+const BUNDLED_CATALOG = indexCatalog(bundledCatalog);
 
 
 // Persist only user-added collections separately
-const customCollectionStore = localforage.createInstance({
+const legacyCustomCollectionStore = localforage.createInstance({
   name: "pdf-card-binder-custom-collections"
 });
+const customCollectionStore = isTauri() ? {
+  getItem: () => tauriInvoke('get_custom_collections'),
+  setItem: (_key, names) => tauriInvoke('set_custom_collections', { names: names || [] }),
+  restoreItem: (_key, names) => tauriInvoke('restore_custom_collections', { names: names || [] }),
+  clear: () => tauriInvoke('restore_custom_collections', { names: [] }),
+} : legacyCustomCollectionStore;
+
+// Writes a restored backup's value: outside the undo history on desktop, a plain
+// setItem in the browser, which has no history
+function restoreItem(store, key, value) {
+  return store.restoreItem ? store.restoreItem(key, value) : store.setItem(key, value);
+}
 
 // Types
-/** @typedef {{ id: string; name: string; pages: number; tags: string[]; collection?: string; thumbnailDataUrl: string; createdAt: number; updatedAt: number; tier?: string; favorite?: boolean; kind?: "pdf" | "gif" | "image"; nsfw?: boolean; origExt?: string; mime?: string}} CardMeta */
+/** @typedef {{ id: string; name: string; pages: number; tags: string[]; collection?: string; thumbnailDataUrl: string; createdAt: number; updatedAt: number; tier?: string; favorite?: boolean; kind?: "pdf" | "gif" | "image"; nsfw?: boolean; origExt?: string; mime?: string; quantity?: number; condition?: string; faces?: CardFace[]}} CardMeta */
+/** @typedef {{ id: string; label: string; kind: "pdf" | "gif" | "image"; origExt: string; mime: string }} CardFace */
 
 const THEME_KEY = "pcb-theme"; // 'light' | 'dark'
 const AUTO_TIER_KEY = "pcb-auto-tier"; // '1' = on, '0' = off
@@ -192,7 +107,7 @@ const AUTO_NSFW_RELEASE_MIGRATION_KEY = "pcb-auto-nsfw-release-migration-v1"; //
 const DEBUG_DND = false;
 const d = (...args) => { if (DEBUG_DND) console.log("[DND]", ...args); };
 
-const BACKUP_SCHEMA_VERSION = 2;
+const BACKUP_SCHEMA_VERSION = 3; // 3: faceFiles
 
 // ---- Debug (no DevTools needed) ----
 const DEBUG_BUFFER_KEY = "pcb-debug-buffer";
@@ -246,17 +161,23 @@ const PdfCache = (() => {
 
     async preload(id) {
       // Only for PDFs in your library
-      const meta = await metaStore.getItem(id);
+      const meta = await cardStore.get(id);
         if (!meta || meta.kind === "gif" || meta.kind === "image") return null;
 
       const cached = this.get(id);
       if (cached) { touch(id); return cached; }
 
-      const raw = await fileStore.getItem(id);
-      const bytes = toUint8(raw);
-      if (!bytes?.length) return null;
+      // Desktop: pdf.js reads straight from disk via card://, in ranges as needed
+      let source;
+      if (isTauri()) {
+        source = { url: cardFileUrl(id) };
+      } else {
+        const bytes = await cardStore.readFile(id);
+        if (!bytes?.length) return null;
+        source = { data: bytes };
+      }
 
-      const task = getDocument({ data: bytes });
+      const task = getDocument(source);
       const pdf = await task.promise;       // throws if bad
       map.set(id, { pdf, ts: Date.now() });
       evictIfNeeded();
@@ -302,6 +223,239 @@ function isTauri() {
   );
 }
 
+// ---- Card store ----
+// On desktop, cards live in the Rust library (src-tauri/src/library) on disk; in the
+// browser they stay in the metaStore/fileStore localforage instances.
+async function tauriInvoke(cmd, args, options) {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke(cmd, args, options);
+}
+
+// Raw body keeps large PDFs out of JSON; the meta rides along in a header.
+function tauriUploadCard(cmd, meta, bytes) {
+  return tauriInvoke(cmd, toUint8(bytes), {
+    headers: { 'card-meta': encodeURIComponent(JSON.stringify(meta)) },
+  });
+}
+
+// `card://` URLs served by Rust straight from the library (see protocol.rs)
+function cardFileUrl(id) {
+  return convertFileSrc(`file/${id}`, 'card');
+}
+function cardFaceUrl(faceId) {
+  return convertFileSrc(`face/${faceId}`, 'card');
+}
+function cardThumbUrl(id, size = "grid") {
+  return `${convertFileSrc(`thumb/${id}`, 'card')}?size=${size}`;
+}
+
+// Rust commands fail with { kind, message, path?, id? }; turn that into something the
+// user can act on.
+function describeError(e, fallback = 'Something went wrong.') {
+  switch (e?.kind) {
+    case 'permissionDenied':
+      return `Permission denied${e.path ? ` for ${e.path}` : ''}. Pick a location you can write to.`;
+    case 'quotaExceeded':
+      return 'Not enough disk space. Free up some space and try again.';
+    case 'invalidArchive':
+      return `That file isn't a usable card archive (${e.message.replace(/^invalid archive: /, '')}).`;
+    case 'notFound':
+      return `${e.message.charAt(0).toUpperCase()}${e.message.slice(1)}.`;
+    case 'io':
+      return `Couldn't read or write ${e.path || 'a file'}. ${e.message}`;
+    case 'cancelled':
+      return 'Cancelled.';
+    default:
+      return e?.message ? `${fallback} ${e.message}` : fallback;
+  }
+}
+
+// Long-running Rust commands return a job id straight away and report through
+// `progress` / `job-finished` events; this resolves with the job's result.
+async function tauriJob(cmd, args, { onStart, onProgress } = {}) {
+  const { listen } = await import('@tauri-apps/api/event');
+  let jobId = null;
+  let finished = null; // a job-finished event that beat the invoke response
+  let settle;
+  const done = new Promise((resolve, reject) => { settle = { resolve, reject }; });
+  const onFinished = (evt) => {
+    if (evt.status === 'done') settle.resolve(evt.result);
+    else if (evt.status === 'cancelled') settle.reject({ kind: 'cancelled', message: 'cancelled' });
+    else settle.reject(evt.error);
+  };
+
+  const unlistenProgress = await listen('progress', ({ payload }) => {
+    if (payload.jobId === jobId) onProgress?.(payload);
+  });
+  const unlistenFinished = await listen('job-finished', ({ payload }) => {
+    if (jobId === null) finished = payload;
+    else if (payload.jobId === jobId) onFinished(payload);
+  });
+  try {
+    jobId = await tauriInvoke(cmd, args);
+    onStart?.(jobId);
+    if (finished?.jobId === jobId) onFinished(finished);
+    return await done;
+  } finally {
+    unlistenProgress();
+    unlistenFinished();
+  }
+}
+
+// Browser only: face files share fileStore with the cards' own files
+function faceFileKey(faceId) {
+  return `face:${faceId}`;
+}
+
+// Kind, extension and MIME type of a picked face file, the same way importFiles works them out
+function faceFromFile(file, label) {
+  const nameLower = (file.name || "").toLowerCase();
+  const typeLower = (file.type || "").toLowerCase();
+  const isPdf = nameLower.endsWith(".pdf") || typeLower.includes("pdf");
+  const isGif = nameLower.endsWith(".gif") || typeLower === "image/gif";
+  const extMatch = nameLower.match(/\.([a-z0-9]+)$/);
+  const origExt = extMatch ? extMatch[1] : (isPdf ? "pdf" : "png");
+  const mime = file.type || (origExt === "jpg" || origExt === "jpeg" ? "image/jpeg" : origExt === "gif" ? "image/gif" : origExt === "pdf" ? "application/pdf" : "image/png");
+  return { id: "", label, kind: isPdf ? "pdf" : isGif ? "gif" : "image", origExt, mime };
+}
+
+const cardStore = {
+  async list() {
+    if (isTauri()) return tauriInvoke('list_cards');
+    const keys = await metaStore.keys();
+    const all = await Promise.all(keys.map((k) => metaStore.getItem(k)));
+    return all.filter(Boolean);
+  },
+  async get(id) {
+    if (isTauri()) return tauriInvoke('get_card', { id });
+    return metaStore.getItem(id);
+  },
+  // Resolves to { status: "added", card } or, on desktop, { status: "alreadyInLibrary", existingId }
+  async add(meta, bytes) {
+    if (isTauri()) return tauriUploadCard('add_card', meta, bytes);
+    await fileStore.setItem(meta.id, bytes);
+    await metaStore.setItem(meta.id, meta);
+    return { status: "added", card: meta };
+  },
+  // Like add(), but keeps cards whose file is already in the library (backup restores)
+  async restore(meta, bytes) {
+    if (isTauri()) return tauriUploadCard('restore_card', meta, bytes);
+    await fileStore.setItem(meta.id, bytes);
+    await metaStore.setItem(meta.id, meta);
+    return meta;
+  },
+  // On desktop every edit is a step of the undo history (src-tauri/src/library/history.rs)
+  async update(id, patch) {
+    if (isTauri()) return tauriInvoke('edit_cards', { updates: [{ id, patch }] }).then(([card]) => card);
+    const existing = await metaStore.getItem(id);
+    const updated = { ...existing, ...patch, updatedAt: Date.now() };
+    await metaStore.setItem(id, updated);
+    return updated;
+  },
+  async remove(id) {
+    if (isTauri()) return tauriInvoke('delete_cards', { ids: [id] });
+    const existing = await metaStore.getItem(id);
+    await Promise.all((existing?.faces || []).map((f) => fileStore.removeItem(faceFileKey(f.id))));
+    await metaStore.removeItem(id);
+    await fileStore.removeItem(id);
+  },
+  // Like update() and remove() for several cards at once, as one step of the history.
+  async edit(updates) {
+    if (isTauri()) return tauriInvoke('edit_cards', { updates });
+    return Promise.all(updates.map(({ id, patch }) => cardStore.update(id, patch)));
+  },
+  async removeMany(ids) {
+    if (isTauri()) return tauriInvoke('delete_cards', { ids });
+    for (const id of ids) await cardStore.remove(id);
+  },
+  async readFile(id) {
+    if (isTauri()) return new Uint8Array(await tauriInvoke('read_card_file', { id }));
+    return toUint8(await fileStore.getItem(id));
+  },
+  // A card's other faces (back, alternate art) come after its own file, the front.
+  // Both resolve to the updated card.
+  async attachFace(id, face, bytes, position) {
+    if (isTauri()) {
+      return tauriInvoke('attach_face', toUint8(bytes), {
+        headers: { 'face-meta': encodeURIComponent(JSON.stringify({ ...face, cardId: id, position })) },
+      });
+    }
+    const existing = await metaStore.getItem(id);
+    const f = { ...face, id: face.id || uuidv4() };
+    const faces = [...(existing.faces || [])];
+    faces.splice(position ?? faces.length, 0, f);
+    await fileStore.setItem(faceFileKey(f.id), bytes);
+    const updated = { ...existing, faces, updatedAt: Date.now() };
+    await metaStore.setItem(id, updated);
+    return updated;
+  },
+  // Like attachFace(), but outside the undo history (backup restores)
+  async restoreFace(id, face, bytes) {
+    if (isTauri()) {
+      return tauriInvoke('restore_face', toUint8(bytes), {
+        headers: { 'face-meta': encodeURIComponent(JSON.stringify({ ...face, cardId: id })) },
+      });
+    }
+    return cardStore.attachFace(id, face, bytes);
+  },
+  async detachFace(id, faceId) {
+    if (isTauri()) return tauriInvoke('detach_face', { id, faceId });
+    const existing = await metaStore.getItem(id);
+    await fileStore.removeItem(faceFileKey(faceId));
+    const updated = { ...existing, faces: (existing.faces || []).filter((f) => f.id !== faceId), updatedAt: Date.now() };
+    await metaStore.setItem(id, updated);
+    return updated;
+  },
+  async readFaceFile(faceId) {
+    if (isTauri()) return new Uint8Array(await tauriInvoke('read_face_file', { faceId }));
+    return toUint8(await fileStore.getItem(faceFileKey(faceId)));
+  },
+  async clear() {
+    if (isTauri()) return tauriInvoke('clear_library');
+    await Promise.all([metaStore.clear(), fileStore.clear()]);
+  },
+};
+
+// One-time move of desktop cards, order and custom collections out of localforage into
+// the Rust library. Each item is dropped from localforage only once the library has it,
+// so an interrupted run just resumes on the next launch.
+async function migrateLegacyCardStores() {
+  if (!isTauri()) return;
+
+  const legacyOrder = await legacyOrderStore.getItem("map");
+  if (legacyOrder && typeof legacyOrder === "object") {
+    const current = (await orderStore.getItem("map")) || {};
+    await restoreItem(orderStore, "map", { ...legacyOrder, ...current });
+    await legacyOrderStore.removeItem("map");
+  }
+
+  const legacyCustom = await legacyCustomCollectionStore.getItem("list");
+  if (Array.isArray(legacyCustom)) {
+    const current = (await customCollectionStore.getItem("list")) || [];
+    const merged = [...current];
+    for (const c of legacyCustom) {
+      if (!merged.some((m) => m.toLowerCase() === String(c).toLowerCase())) merged.push(String(c));
+    }
+    merged.sort((a, b) => a.localeCompare(b));
+    await restoreItem(customCollectionStore, "list", merged);
+    await legacyCustomCollectionStore.removeItem("list");
+  }
+
+  const keys = await metaStore.keys();
+  for (const id of keys) {
+    try {
+      const meta = await metaStore.getItem(id);
+      if (meta && !(await cardStore.get(id))) {
+        await cardStore.restore(meta, toUint8(await fileStore.getItem(id)));
+      }
+      await fileStore.removeItem(id);
+      await metaStore.removeItem(id);
+    } catch (e) {
+      console.error("Failed to migrate card", id, e);
+    }
+  }
+}
+
 async function manualCheck() {
   const envInfo = { dev: !!import.meta?.env?.DEV, tauri: isTauri(), origin: location?.origin };
   dbg("updater", "manualCheck: start", envInfo);
@@ -524,6 +678,71 @@ async function renderPdfPageToDataUrl(arrayBuffer, pageNumber = 1, scale = 0.9)
   return { dataUrl: canvas.toDataURL("image/png"), numPages: pdf.numPages };
 }
 
+async function countPdfPages(arrayBuffer) {
+  const pdf = await getDocument({ data: arrayBuffer }).promise;
+  try { return pdf.numPages; } finally { pdf.destroy(); }
+}
+
+// Thumbnail baked into the meta as a data URL: the browser build's only option, and
+// the desktop fallback when the Rust renderer can't handle a file.
+async function renderThumbnailInJs(bytes, { kind, mime, name }) {
+  try {
+    if (kind === "pdf") return await renderPdfPageToDataUrl(bytes, 1, 0.9);
+
+    // Image (GIF, PNG, JPG): make a thumbnail from the first frame / image
+    const blob = new Blob([bytes], { type: mime || "image/png" });
+    const url = URL.createObjectURL(blob);
+    try {
+      const img = new Image();
+      await new Promise((res, rej) => {
+        img.onload = res; img.onerror = rej; img.src = url;
+      });
+      const W = 360, H = Math.max(240, Math.round((img.height / img.width) * 360) || 240);
+      const canvas = document.createElement("canvas");
+      canvas.width = W; canvas.height = H;
+      const ctx = canvas.getContext("2d");
+      ctx.fillStyle = "#fff"; ctx.fillRect(0,0,W,H);
+      // contain fit
+      const s = Math.min(W / img.width, H / img.height);
+      const w = Math.round(img.width * s);
+      const h = Math.round(img.height * s);
+      const x = Math.round((W - w) / 2);
+      const y = Math.round((H - h) / 2);
+      ctx.drawImage(img, x, y, w, h);
+      return { dataUrl: canvas.toDataURL("image/png"), numPages: 1 }; // single-page for images
+    } finally {
+      URL.revokeObjectURL(url);
+    }
+  } catch (renderErr) {
+    console.error("Thumbnail render failed, using placeholder", renderErr);
+    const canvas = document.createElement("canvas");
+    canvas.width = 360; canvas.height = 240;
+    const ctx = canvas.getContext("2d");
+    ctx.fillStyle = "#f1f5f9"; ctx.fillRect(0,0,canvas.width,canvas.height);
+    ctx.fillStyle = "#0f172a"; ctx.font = "bold 20px system-ui";
+    ctx.fillText(kind.toUpperCase(), 20, 40);
+    ctx.font = "14px system-ui";
+    ctx.fillText(name.slice(0, 40), 20, 70);
+    return { dataUrl: canvas.toDataURL("image/png"), numPages: 1 };
+  }
+}
+
+// On desktop, thumbnails come from the Rust cache via card://; the meta's data URL is
+// only used when Rust can't render the card.
+function CardThumbnail({ meta, size = "grid", ...imgProps }) {
+  const [failed, setFailed] = useState(false);
+  useEffect(() => setFailed(false), [meta.id]);
+  const src = isTauri() && !failed ? cardThumbUrl(meta.id, size) : meta.thumbnailDataUrl;
+  return (
+    <img
+      src={src || undefined}
+      alt={meta.name}
+      onError={() => { if (isTauri()) setFailed(true); }}
+      {...imgProps}
+    />
+  );
+}
+
 
 
 function useLocalMeta() {
@@ -532,17 +751,16 @@ function useLocalMeta() {
 
   useEffect(() => {
     (async () => {
-      const keys = await metaStore.keys();
-      const all = await Promise.all(keys.map((k) => metaStore.getItem(k)));
-      const list = /** @type {CardMeta[]} */ (all.filter(Boolean));
+      await migrateLegacyCardStores();
+      const list = /** @type {CardMeta[]} */ (await cardStore.list());
       list.sort((a, b) => a.createdAt - b.createdAt);
       setMetas(list);
       setLoading(false);
     })();
   }, []);
 
+  // In-memory only; callers persist through cardStore first.
   async function upsert(meta) {
-    await metaStore.setItem(meta.id, meta);
     setMetas((prev) => {
       const idx = prev.findIndex((m) => m.id === meta.id);
       if (idx === -1) return [...prev, meta];
@@ -553,33 +771,28 @@ function useLocalMeta() {
   }
 
   async function remove(id) {
-    await metaStore.removeItem(id);
-    await fileStore.removeItem(id);
-    setMetas((prev) => prev.filter((m) => m.id !== id));
+    await removeMany([id]);
   }
 
-  // Safe remove with timeout to avoid hangs in packaged apps
-  async function safeRemove(id, timeoutMs = 10000) {
-    const op = (async () => {
-      await metaStore.removeItem(id);
-      await fileStore.removeItem(id);
-      setMetas((prev) => prev.filter((m) => m.id !== id));
-    })();
-    // timeout helper
-    const timeout = new Promise((_, rej) => setTimeout(() => rej(new Error('remove timeout')), timeoutMs));
-    try {
-      await Promise.race([op, timeout]);
-    } catch (e) {
-      console.error(`safeRemove failed for ${id}:`, e);
-      // Try best-effort cleanup without blocking
-      try { await metaStore.removeItem(id); } catch (e2) { console.error('metaStore.removeItem retry failed', e2); }
-      try { await fileStore.removeItem(id); } catch (e3) { console.error('fileStore.removeItem retry failed', e3); }
-      // Still remove from in-memory list so UI doesn't hang
-      setMetas((prev) => prev.filter((m) => m.id !== id));
-    }
+  async function removeMany(ids) {
+    await cardStore.removeMany(ids);
+    forget(ids);
+  }
+
+  // After an undo or redo, which can touch any card
+  async function reload() {
+    const list = /** @type {CardMeta[]} */ (await cardStore.list());
+    list.sort((a, b) => a.createdAt - b.createdAt);
+    setMetas(list);
+  }
+
+  // In-memory only, for cards the library already deleted itself (e.g. merged away)
+  function forget(ids) {
+    const gone = new Set(ids);
+    setMetas((prev) => prev.filter((m) => !gone.has(m.id)));
   }
 
-  return { metas, loading, upsert, remove };
+  return { metas, loading, upsert, remove, removeMany, forget, reload };
 }
 
 function Tag({ label, onClick, active = false, theme }) {
@@ -763,7 +976,105 @@ function Lightbox({ open, onClose, fileBytes, name, theme }) {
 
 
 
-function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPrev, onNext, canPrev, canNext, isNsfw, nsfwMode }) {
+// ---- Card faces in the lightboxes ----
+// Side 0 is the card's own file (the front), then its `faces`. Flipping turns the view
+// edge-on, swaps what's shown, and turns it back.
+const FLIP_HALF_MS = 180;
+
+function useCardSide(open, cardId, faces) {
+  const [side, setSide] = React.useState(0);
+  const [turning, setTurning] = React.useState(false);
+  const count = (faces?.length || 0) + 1;
+
+  React.useEffect(() => { setSide(0); setTurning(false); }, [open, cardId]);
+
+  const flip = React.useCallback(() => {
+    if (count < 2 || turning) return;
+    setTurning(true);
+    window.setTimeout(() => { setSide((n) => (n + 1) % count); setTurning(false); }, FLIP_HALF_MS);
+  }, [count, turning]);
+
+  const face = side > 0 ? faces?.[side - 1] || null : null;
+  const style = {
+    transition: `transform ${FLIP_HALF_MS}ms ease-in-out`,
+    transform: turning ? "rotateY(90deg)" : "rotateY(0deg)",
+  };
+  return { side, count, face, flip, style };
+}
+
+function FlipButton({ count, face, onFlip }) {
+  if (count < 2) return null;
+  return (
+    <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={onFlip} title="Show the next face (F)">
+      ↻ {face ? face.label || "Back" : "Front"}
+    </button>
+  );
+}
+
+// One of a card's other faces, fitted to the window like the front
+function FaceView({ face, theme, blurred }) {
+  const [url, setUrl] = React.useState("");
+  const canvasRef = React.useRef(null);
+  const isDark = theme === "dark";
+
+  React.useEffect(() => {
+    let cancelled = false;
+    let objectUrl = "";
+    (async () => {
+      // Desktop streams it from card://; the browser has the bytes in fileStore
+      if (isTauri()) { setUrl(cardFaceUrl(face.id)); return; }
+      const bytes = await cardStore.readFaceFile(face.id);
+      if (cancelled) return;
+      objectUrl = URL.createObjectURL(new Blob([bytes], { type: face.mime || "image/png" }));
+      setUrl(objectUrl);
+    })();
+    return () => {
+      cancelled = true;
+      if (objectUrl) URL.revokeObjectURL(objectUrl);
+      setUrl("");
+    };
+  }, [face.id, face.mime]);
+
+  React.useEffect(() => {
+    if (face.kind !== "pdf" || !url) return;
+    let cancelled = false;
+    let doc = null;
+    (async () => {
+      doc = await getDocument({ url }).promise;
+      const page = await doc.getPage(1);
+      const canvas = canvasRef.current;
+      if (cancelled || !canvas) return;
+      const base = page.getViewport({ scale: 1 });
+      const s = Math.max(0.1, Math.min((window.innerWidth - 120) / base.width, (window.innerHeight - 160) / base.height, 6));
+      const dpr = Math.min(window.devicePixelRatio || 1, 2);
+      const viewport = page.getViewport({ scale: s * dpr });
+      canvas.width = Math.round(viewport.width);
+      canvas.height = Math.round(viewport.height);
+      canvas.style.width = `${Math.round(viewport.width / dpr)}px`;
+      canvas.style.height = `${Math.round(viewport.height / dpr)}px`;
+      await page.render({ canvasContext: canvas.getContext("2d", { alpha: false }), viewport }).promise;
+    })().catch((e) => console.error("Failed to open card face", e));
+    return () => { cancelled = true; doc?.destroy?.(); };
+  }, [face.kind, url]);
+
+  const className = `block shadow-2xl rounded ${isDark ? "bg-slate-900" : "bg-white"} ${blurred ? "blur-xl" : ""}`;
+  if (face.kind === "pdf") {
+    return <canvas ref={canvasRef} className={className} onClick={(e) => e.stopPropagation()} />;
+  }
+  if (!url) return null;
+  return (
+    <img
+      src={url}
+      alt={face.label || "Back"}
+      className={`${className} max-w-[calc(100vw-120px)] max-h-[calc(100vh-160px)]`}
+      draggable={false}
+      onClick={(e) => e.stopPropagation()}
+    />
+  );
+}
+
+
+function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPrev, onNext, canPrev, canNext, isNsfw, nsfwMode, cardId, faces }) {
   const [pdf, setPdf] = React.useState(null);
   const [numPages, setNumPages] = React.useState(1);
   const [scale, setScale] = React.useState("fit");
@@ -771,16 +1082,18 @@ function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPr
   const containerRef = React.useRef(null);
   const headerRef = React.useRef(null);
   const isDark = theme === "dark";
+  const { side, count, face, flip, style: flipStyle } = useCardSide(open, cardId, faces);
 
   React.useEffect(() => {
     if (!open) return;
     const onKey = (e) => {
       if (e.key === "ArrowRight") { e.preventDefault(); onNext?.(); }
       if (e.key === "ArrowLeft")  { e.preventDefault(); onPrev?.(); }
+      if (e.key === "f" || e.key === "F") { e.preventDefault(); flip(); }
     };
     window.addEventListener("keydown", onKey);
     return () => window.removeEventListener("keydown", onKey);
-  }, [open, onNext, onPrev]);
+  }, [open, onNext, onPrev, flip]);
 
 
   // make default view slightly larger
@@ -850,7 +1163,8 @@ function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPr
 
 
   React.useEffect(() => {
-    if (!pdf || !open) return;
+    // The page canvases are only mounted while the front is showing
+    if (!pdf || !open || side !== 0) return;
     let cancelled = false;
     const idleHandles = [];
 
@@ -867,7 +1181,7 @@ function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPr
       cancelled = true;
       idleHandles.forEach(cancelRic);
     };
-  }, [pdf, numPages, open, scale, renderOne]);
+  }, [pdf, numPages, open, scale, renderOne, side]);
 
 
   React.useEffect(() => {
@@ -925,6 +1239,7 @@ function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPr
         <div className="font-semibold truncate">{name}</div>
         <div className="text-sm opacity-80">{numPages} page{numPages > 1 ? "s" : ""}</div>
         <div className="ml-auto flex items-center gap-2">
+          <FlipButton count={count} face={face} onFlip={flip} />
           <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={zoomOut}>–</button>
           <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={zoomIn}>+</button>
           <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={fit}>Fit</button>
@@ -936,9 +1251,12 @@ function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPr
       <div
         ref={containerRef}
         className="flex-1 overflow-auto px-6 pb-6"
+        style={{ perspective: "1600px" }}
       >
-        <div className="mx-auto w-max space-y-6">
-          {Array.from({ length: numPages }).map((_, idx) => (
+        <div className="mx-auto w-max space-y-6" style={flipStyle}>
+          {face ? (
+            <FaceView face={face} theme={theme} blurred={isNsfw && !nsfwMode} />
+          ) : Array.from({ length: numPages }).map((_, idx) => (
             <canvas
               key={idx}
               ref={(el) => { canvasesRef.current[idx + 1] = el; }}
@@ -953,7 +1271,7 @@ function MultiPageLightbox({ open, onClose, fileBytes, pdfDoc, name, theme, onPr
 }
 
 
-function GifLightbox({ open, onClose, fileBytes, name, theme, onPrev, onNext, canPrev, canNext, fileMime, isNsfw, nsfwMode }) {
+function GifLightbox({ open, onClose, fileBytes, fileUrl, name, theme, onPrev, onNext, canPrev, canNext, fileMime, isNsfw, nsfwMode, cardId, faces }) {
   const [url, setUrl] = React.useState("");
   const [scale, setScale] = React.useState("fit"); // "fit" or number
   const [fitTick, setFitTick] = React.useState(0); // bump to recompute fit after layout/load
@@ -961,6 +1279,7 @@ function GifLightbox({ open, onClose, fileBytes, name, theme, onPrev, onNext, ca
   const headerRef = React.useRef(null);
   const imgRef = React.useRef(null);
   const isDark = theme === "dark";
+  const { count, face, flip, style: flipStyle } = useCardSide(open, cardId, faces);
 
   // Match MultiPageLightbox padding (so it shows smaller and avoids scrolling)
   const FIT_PAD_X = 30;
@@ -971,26 +1290,26 @@ function GifLightbox({ open, onClose, fileBytes, name, theme, onPrev, onNext, ca
     const onKey = (e) => {
       if (e.key === "ArrowRight") { e.preventDefault(); onNext?.(); }
       if (e.key === "ArrowLeft")  { e.preventDefault(); onPrev?.(); }
+      if (e.key === "f" || e.key === "F") { e.preventDefault(); flip(); }
     };
     window.addEventListener("keydown", onKey);
     return () => window.removeEventListener("keydown", onKey);
-  }, [open, onNext, onPrev]);
+  }, [open, onNext, onPrev, flip]);
 
 
   React.useEffect(() => {
-    if (!open || !fileBytes) return;
-    const blob = new Blob([fileBytes], { type: fileMime || "image/gif" });
-    const u = URL.createObjectURL(blob);
+    if (!open || (!fileBytes && !fileUrl)) return;
+    const u = fileUrl || URL.createObjectURL(new Blob([fileBytes], { type: fileMime || "image/gif" }));
     setUrl(u);
     setScale("fit");
     // nudge a refit on next frame, after DOM paints
     requestAnimationFrame(() => setFitTick((t) => t + 1));
 
     return () => {
-      URL.revokeObjectURL(u);
+      if (!fileUrl) URL.revokeObjectURL(u);
       setUrl("");
     };
-  }, [open, fileBytes]);
+  }, [open, fileBytes, fileUrl]);
 
   // Refit on window resize
   React.useEffect(() => {
@@ -1108,6 +1427,7 @@ function GifLightbox({ open, onClose, fileBytes, name, theme, onPrev, onNext, ca
       >
         <div className="font-semibold truncate">{name}</div>
         <div className="ml-auto flex items-center gap-2">
+          <FlipButton count={count} face={face} onFlip={flip} />
           <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={zoomOut}>–</button>
           <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={zoomIn}>+</button>
           <button className="px-2 py-1 rounded bg-white/10 hover:bg-white/20" onClick={fit}>Fit</button>
@@ -1119,21 +1439,28 @@ function GifLightbox({ open, onClose, fileBytes, name, theme, onPrev, onNext, ca
       <div
         ref={containerRef}
         className="flex-1 flex items-center justify-center px-6 pb-6"
+        style={{ perspective: "1600px" }}
       >
-        <div className={`shadow-2xl rounded ${isDark ? "bg-slate-900" : "bg-white"} overflow-hidden`}>
-          {url && (
-            <img
-              ref={imgRef}
-              src={url}
-              alt={name}
-              onLoad={handleImgLoad}
-              style={imgStyle}
-              className={`${isDark ? "bg-slate-900" : "bg-white"} block ${isNsfw && !nsfwMode ? "blur-xl" : ""}`}
-              draggable={false}
-              onClick={(e) => e.stopPropagation()}
-            />
-          )}
-        </div>
+        {face ? (
+          <div style={flipStyle}>
+            <FaceView face={face} theme={theme} blurred={isNsfw && !nsfwMode} />
+          </div>
+        ) : (
+          <div className={`shadow-2xl rounded ${isDark ? "bg-slate-900" : "bg-white"} overflow-hidden`} style={flipStyle}>
+            {url && (
+              <img
+                ref={imgRef}
+                src={url}
+                alt={name}
+                onLoad={handleImgLoad}
+                style={imgStyle}
+                className={`${isDark ? "bg-slate-900" : "bg-white"} block ${isNsfw && !nsfwMode ? "blur-xl" : ""}`}
+                draggable={false}
+                onClick={(e) => e.stopPropagation()}
+              />
+            )}
+          </div>
+        )}
       </div>
     </div>
   );
@@ -1156,6 +1483,14 @@ function normalizeCollectionName(s) {
 
 
 
+// Suggested grades for a card's condition; any text is accepted
+const CARD_CONDITIONS = ["Mint", "Near Mint", "Excellent", "Good", "Played", "Poor"];
+
+// Smart collections (src-tauri/src/smart.rs) appear in the grouped view as groups named
+// `smart:<id>`, so their collapse state and card order live alongside regular ones.
+const SMART_PREFIX = "smart:";
+const smartGroupId = (name) => (name.startsWith(SMART_PREFIX) ? name.slice(SMART_PREFIX.length) : null);
+
 /** Returns items in a group in persisted order, with any new items appended by createdAt. */
 function orderItemsInGroup(orderMap, groupName, items) {
   const key = keyForCollection(groupName);
@@ -1322,8 +1657,9 @@ async function confirmDialog(message, title = "Confirm") {
 
 
 export default function App() {
-  const { metas, loading, upsert, remove } = useLocalMeta();
+  const { metas, loading, upsert, remove, removeMany, forget, reload } = useLocalMeta();
   const [updateProgress, setUpdateProgress] = useState(null);
+  const [jobProgress, setJobProgress] = useState(null);
 
   useTauriAutoUpdate({ promptUser: true, skipInDev: true });
 
@@ -1374,6 +1710,10 @@ export default function App() {
   }, []);
 
   const [query, setQuery] = useState("");
+  // Full-text matches for `query` (id -> highlighted snippet), desktop only
+  const [searchHits, setSearchHits] = useState(null);
+  // Ids matching the filter query bar (null = no filter), desktop only
+  const [queryIds, setQueryIds] = useState(null);
   const [activeTag, setActiveTag] = useState("");
   const [activeCollection, setActiveCollection] = useState("");
   const [lightbox, setLightbox] = useState({ open: false, id: "" });
@@ -1383,6 +1723,16 @@ export default function App() {
   const [lastError, setLastError] = useState("");
   const [customCollections, setCustomCollections] = useState([]);
   const [collectionsOpen, setCollectionsOpen] = useState(false);
+  const [watchFoldersOpen, setWatchFoldersOpen] = useState(false);
+  const [smartOpen, setSmartOpen] = useState(false);
+  const [catalogOpen, setCatalogOpen] = useState(false);
+  const [completionOpen, setCompletionOpen] = useState(false);
+  const [tradeOpen, setTradeOpen] = useState(false);
+  const [printOpen, setPrintOpen] = useState(false);
+  const [sheetOpen, setSheetOpen] = useState(false);
+  const [catalog, setCatalog] = useState(BUNDLED_CATALOG);
+  // [{ id, name, filter, cardIds }], evaluated in Rust; desktop only
+  const [smartGroups, setSmartGroups] = useState([]);
   const [activeTier, setActiveTier] = useState("");
   const [favoritesOnly, setFavoritesOnly] = useState(false);
   const [editMode, setEditMode] = useState(false);
@@ -1423,7 +1773,7 @@ export default function App() {
   // collapsed map: { [groupKey]: true|false }
   const [collapsed, setCollapsed] = useState({});
 
-  const [toast, setToast] = useState({ open: false, message: "", kind: "info" });
+  const [toast, setToast] = useState({ open: false, message: "", kind: "info", action: null });
 
   // in App()
   const [debugOpen, setDebugOpen] = useState(false);
@@ -1453,15 +1803,13 @@ export default function App() {
 
       (async () => {
         try {
-          // iterate all metas and set nsfw=false for each existing card
-          for (const m of metas) {
-            const meta = { ...m };
-            // Only write if needed to avoid unnecessary writes
-            if (meta.nsfw !== false) {
-              meta.nsfw = false;
-              meta.updatedAt = Date.now();
-              await metaStore.setItem(meta.id, meta);
-              try { upsert(meta); } catch {}
+          // set nsfw=false on every existing card, as one write (one undo step on desktop)
+          const updates = metas
+            .filter((m) => m.nsfw !== false)
+            .map((m) => ({ id: m.id, patch: { nsfw: false } }));
+          if (updates.length) {
+            for (const updated of await cardStore.edit(updates)) {
+              try { upsert(updated); } catch {}
             }
           }
           // mark migration complete
@@ -1509,25 +1857,34 @@ export default function App() {
         names.map(n => normalizeCollectionName(n).toLowerCase())
       );
 
-      // 1) Reassign any cards using a soon-to-be-deleted custom collection
-      for (const m of metas) {
-        const col = normalizeCollectionName(m.collection || "");
-        if (delSet.has(col.toLowerCase())) {
-          await updateMeta(m.id, { collection: "" }); // (None)
-        }
-      }
-
-      // 2) Clear custom collections list
-      setCustomCollections([]);
-      await customCollectionStore.setItem("list", []);
-
-      // 3) Clean persisted order map entries for those groups
       const nextOrder = { ...(orderMap || {}) };
       for (const n of names) {
         const k = keyForCollection(n);
         delete nextOrder[k];
       }
-      persistOrder(nextOrder);
+
+      if (isTauri()) {
+        // 1-3) One undoable step in Rust
+        const changed = await tauriInvoke('delete_custom_collections', { names });
+        for (const card of changed) await upsert(card);
+        setCustomCollections([]);
+        setOrderMap(nextOrder);
+      } else {
+        // 1) Reassign any cards using a soon-to-be-deleted custom collection
+        for (const m of metas) {
+          const col = normalizeCollectionName(m.collection || "");
+          if (delSet.has(col.toLowerCase())) {
+            await updateMeta(m.id, { collection: "" }); // (None)
+          }
+        }
+
+        // 2) Clear custom collections list
+        setCustomCollections([]);
+        await customCollectionStore.setItem("list", []);
+
+        // 3) Clean persisted order map entries for those groups
+        persistOrder(nextOrder);
+      }
 
       // 4) Clean collapsed state for those groups
       setCollapsed(prev => {
@@ -1603,20 +1960,18 @@ export default function App() {
       setLightboxBytes(null);
 
       const ids = Array.from(selectedIds);
-      for (const id of ids) {
-        // use safeRemove to avoid indefinite hangs in packaged apps
-        await safeRemove(id);
-      }
+      await removeMany(ids);
 
       setSelectedIds(new Set());
       setBulkCollection("");
       setBulkTier("");
       setBulkFavorite("");
 
-      showToast(`Deleted ${ids.length} card${ids.length > 1 ? "s" : ""}.`, "success");
+      showToast(`Deleted ${ids.length} card${ids.length > 1 ? "s" : ""}.`, "success", 8000,
+        isTauri() ? { label: "Undo", run: () => stepHistory('undo') } : null);
     } catch (e) {
       console.error(e);
-      showToast("Delete failed. See console for details.", "error");
+      showToast(describeError(e, "Delete failed."), "error");
     } finally {
       setBulkApplying(false);
     }
@@ -1624,12 +1979,70 @@ export default function App() {
 
 
 
-  function showToast(message, kind = "info", ms = 2400) {
-    setToast({ open: true, message, kind });
+  // `action` is an optional { label, run } button, e.g. Undo
+  function showToast(message, kind = "info", ms = 2400, action = null) {
+    setToast({ open: true, message, kind, action });
     window.clearTimeout(showToast._t);
     showToast._t = window.setTimeout(() => setToast(t => ({ ...t, open: false })), ms);
   }
 
+  // Cards imported by watch folders (see src-tauri/src/watch.rs) arrive as events
+  useEffect(() => {
+    if (!isTauri()) return;
+    let unlisten = null;
+    let disposed = false;
+    (async () => {
+      const { listen } = await import('@tauri-apps/api/event');
+      const off = await listen('watch-imported', ({ payload }) => {
+        const fileName = payload.path.split(/[\\/]/).pop();
+        if (payload.status === "added") {
+          upsert(payload.card);
+          showToast(`Imported “${payload.card.name || fileName}” from a watch folder.`, "success", 3000);
+        } else if (payload.status === "alreadyInLibrary") {
+          showToast(`“${fileName}” is already in your library.`, "info", 3000);
+        } else {
+          console.error("Watch folder import failed", payload);
+          setLastError(describeError(payload.error, `Failed to import ${fileName}.`));
+        }
+      });
+      if (disposed) off(); else unlisten = off;
+    })();
+    return () => { disposed = true; unlisten?.(); };
+  }, []);
+
+  // Tiers and collections come from the user's catalog on desktop
+  useEffect(() => {
+    if (!isTauri()) return;
+    let unlisten = null;
+    let disposed = false;
+    tauriInvoke('get_catalog').then((doc) => setCatalog(indexCatalog(doc))).catch((e) => console.error("get_catalog failed", e));
+    (async () => {
+      const { listen } = await import('@tauri-apps/api/event');
+      const off = await listen('catalog-changed', ({ payload }) => setCatalog(indexCatalog(payload)));
+      if (disposed) off(); else unlisten = off;
+    })();
+    return () => { disposed = true; unlisten?.(); };
+  }, []);
+
+  // Smart collection membership is re-evaluated in Rust whenever the library changes
+  useEffect(() => {
+    if (!isTauri()) return;
+    let unlisten = null;
+    let disposed = false;
+    tauriInvoke('list_smart_collections').then(setSmartGroups).catch((e) => console.error("list_smart_collections failed", e));
+    (async () => {
+      const { listen } = await import('@tauri-apps/api/event');
+      const off = await listen('smart-collections-changed', ({ payload }) => setSmartGroups(payload));
+      if (disposed) off(); else unlisten = off;
+    })();
+    return () => { disposed = true; unlisten?.(); };
+  }, []);
+
+  const smartMembers = useMemo(
+    () => new Map(smartGroups.map((g) => [g.id, new Set(g.cardIds)])),
+    [smartGroups]
+  );
+
   useEffect(() => {
     try {
       const raw = localStorage.getItem(COLLAPSE_KEY);
@@ -1706,19 +2119,21 @@ export default function App() {
             .map(m => m.collection)
             .filter(c =>
               c &&
-              !SORTED_COLLECTIONS.some(d => d.toLowerCase() === c.toLowerCase()) &&
+              !catalog.collections.some(d => d.toLowerCase() === c.toLowerCase()) &&
               !stored.some(s => s.toLowerCase() === c.toLowerCase())
             )
         ));
         if (extras.length) stored = stored.concat(extras);
         stored.sort((a, b) => a.localeCompare(b));
         setCustomCollections(stored);
-        await customCollectionStore.setItem("list", stored);
+        // On desktop writing them back would be an undo step of its own, so they're only
+        // saved along with the next real change to the list
+        if (!isTauri()) await customCollectionStore.setItem("list", stored);
       } catch (e) {
         console.error("Failed to load custom collections", e);
       }
     })();
-  }, [metas]);
+  }, [metas, catalog]);
 
   useEffect(() => {
     if (!editMode) return;
@@ -1730,7 +2145,7 @@ export default function App() {
   const allCollections = useMemo(() => {
     const seen = new Set();
     const out = [];
-    for (const c of SORTED_COLLECTIONS) {
+    for (const c of catalog.collections) {
       const k = c.toLowerCase();
       if (!seen.has(k)) { seen.add(k); out.push(c); }
     }
@@ -1739,15 +2154,33 @@ export default function App() {
       if (!seen.has(k)) { seen.add(k); out.push(c); }
     }
     return out;
-  }, [customCollections]);
+  }, [customCollections, catalog]);
 
-  // Load order map
+  // Load order map (after useLocalMeta has migrated legacy stores on desktop)
   useEffect(() => {
+    if (loading) return;
     (async () => {
       const map = (await orderStore.getItem("map")) || {};
       setOrderMap(map);
     })();
-  }, []);
+  }, [loading]);
+
+  // Full-text search over PDF text, with phrase/prefix/field syntax (see src-tauri/src/search.rs)
+  useEffect(() => {
+    const q = query.trim();
+    if (!isTauri() || !q) { setSearchHits(null); return; }
+    let cancelled = false;
+    const t = window.setTimeout(async () => {
+      try {
+        const hits = await tauriInvoke('search_cards', { query: q });
+        if (!cancelled) setSearchHits(new Map(hits.map((h) => [h.id, h.snippet])));
+      } catch (e) {
+        console.error("search_cards failed", e);
+        if (!cancelled) setSearchHits(null);
+      }
+    }, 200);
+    return () => { cancelled = true; window.clearTimeout(t); };
+  }, [query]);
 
   // Filters
   const filtered = useMemo(() => {
@@ -1759,15 +2192,18 @@ export default function App() {
       const tier = m?.tier || "";
       const fav  = isFav(m?.favorite);
 
-      const okQ    = !q || m?.name?.toLowerCase().includes(q) || tags.some((t) => t.toLowerCase().includes(q));
+      const okQ    = !q || m?.name?.toLowerCase().includes(q) || tags.some((t) => t.toLowerCase().includes(q))
+                            || !!searchHits?.has(m.id);
       const okTag  = !activeTag || tags.includes(activeTag);
-      const okCol  = !activeCollection || col === activeCollection;
+      const smartId = smartGroupId(activeCollection);
+      const okCol  = !activeCollection || (smartId ? !!smartMembers.get(smartId)?.has(m.id) : col === activeCollection);
       const okTier = !activeTier || tier === activeTier;
       const okFav  = !favoritesOnly || fav;
+      const okQuery = !queryIds || queryIds.has(m.id);
 
-      return okQ && okTag && okCol && okTier && okFav;
+      return okQ && okTag && okCol && okTier && okFav && okQuery;
     });
-  }, [metas, query, activeTag, activeCollection, activeTier, favoritesOnly]);
+  }, [metas, query, searchHits, queryIds, smartMembers, activeTag, activeCollection, activeTier, favoritesOnly]);
 
   // Sorting
   const sorters = {
@@ -1782,7 +2218,7 @@ export default function App() {
 
     // NEW:
     tier_asc: (a, b) => {
-      const ra = tierIndex(a.tier), rb = tierIndex(b.tier);
+      const ra = catalog.tierIndex(a.tier), rb = catalog.tierIndex(b.tier);
 
       // keep "no tier" at the end
       const aNo = !Number.isFinite(ra), bNo = !Number.isFinite(rb);
@@ -1795,7 +2231,7 @@ export default function App() {
     },
 
     tier_desc: (a, b) => {
-      const ra = tierIndex(a.tier), rb = tierIndex(b.tier);
+      const ra = catalog.tierIndex(a.tier), rb = catalog.tierIndex(b.tier);
 
       // keep "no tier" at the end
       const aNo = !Number.isFinite(ra), bNo = !Number.isFinite(rb);
@@ -1832,7 +2268,7 @@ export default function App() {
     const cmp = sorters[sortMode] || sorters.none;
     if (isCardSort(sortMode) && sortMode !== "none") arr.sort(cmp);
     return arr;
-  }, [filtered, sortMode]);
+  }, [filtered, sortMode, catalog]);
 
 
   const groupedByCollection = useMemo(() => {
@@ -1846,11 +2282,11 @@ export default function App() {
 
     // Default section order: built-ins → customs (A→Z) → (None)
     const customs = [...map.keys()]
-      .filter(k => k !== "(None)" && !SORTED_COLLECTIONS.some(d => d.toLowerCase() === k.toLowerCase()))
+      .filter(k => k !== "(None)" && !catalog.collections.some(d => d.toLowerCase() === k.toLowerCase()))
       .sort((a,b) => a.localeCompare(b, 'en', { sensitivity: 'base', numeric: true }));
 
     const keys = [];
-    for (const c of SORTED_COLLECTIONS) if (map.has(c)) keys.push(c);
+    for (const c of catalog.collections) if (map.has(c)) keys.push(c);
     for (const c of customs) keys.push(c);
     if (map.has("(None)")) keys.push("(None)");
 
@@ -1858,7 +2294,7 @@ export default function App() {
     const collectionTierRank = (items) => {
       let best = Number.POSITIVE_INFINITY;
       for (const m of items) {
-        const r = tierIndex(m.tier);
+        const r = catalog.tierIndex(m.tier);
         if (Number.isFinite(r) && r < best) best = r;
       }
       return best; // Infinity if no tiered cards
@@ -1879,7 +2315,7 @@ export default function App() {
         let min = Number.POSITIVE_INFINITY;
         let max = Number.NEGATIVE_INFINITY;
         for (const m of items) {
-          const r = tierIndex(m.tier);
+          const r = catalog.tierIndex(m.tier);
           if (Number.isFinite(r)) {
             if (r < min) min = r;
             if (r > max) max = r;
@@ -1917,18 +2353,26 @@ export default function App() {
     }
 
 
-    return entries; // [ [collectionName, items[]], ... ]
-  }, [filtered, sortMode]);
+    // Smart collections first, in their own order; they hold copies of cards shown below
+    const smartEntries = smartGroups
+      .map((g) => [SMART_PREFIX + g.id, filtered.filter((m) => smartMembers.get(g.id)?.has(m.id))])
+      .filter(([, items]) => items.length);
+
+    return [...smartEntries, ...entries]; // [ [collectionName, items[]], ... ]
+  }, [filtered, sortMode, smartGroups, smartMembers, catalog]);
 
   const visibleList = useMemo(() => {
     // Flat view when card sorting is active
     if (isCardSort(sortMode) && sortMode !== "none") return sortedFlat;
 
-    // Grouped view: flatten groups in persisted order
+    // Grouped view: flatten groups in persisted order, each card once
     const out = [];
+    const seen = new Set();
     for (const [name, items] of groupedByCollection) {
       const ordered = orderItemsInGroup(orderMap, name, items);
-      out.push(...ordered);
+      for (const m of ordered) {
+        if (!seen.has(m.id)) { seen.add(m.id); out.push(m); }
+      }
     }
     return out;
   }, [sortedFlat, groupedByCollection, orderMap, sortMode]);
@@ -1996,9 +2440,9 @@ export default function App() {
 
     const ids = Array.from(selectedIds);
 
-    // Apply per-card, so we can compute tier suggestions only for untiered cards
-    await Promise.all(ids.map(async (id) => {
-      const existing = /** @type {CardMeta} */ (await metaStore.getItem(id));
+    // Patch per card, so we can compute tier suggestions only for untiered cards
+    const updates = await Promise.all(ids.map(async (id) => {
+      const existing = /** @type {CardMeta} */ (await cardStore.get(id));
 
       // Start with a fresh patch each time
       const patch = { ...basePatch };
@@ -2018,7 +2462,7 @@ export default function App() {
             typeof basePatch.collection === "string" &&
             basePatch.collection.trim() !== "" &&
             !(existing?.tier)) {
-          const suggested = defaultTierForCollection(basePatch.collection);
+          const suggested = catalog.defaultTier(basePatch.collection);
           if (suggested) {
             patch.tier = suggested;
           }
@@ -2040,18 +2484,22 @@ export default function App() {
             typeof basePatch.collection === "string" &&
             basePatch.collection.trim() !== "" &&
             !("nsfw" in existing)) {
-          const suggested = defaultNsfwForCollection(basePatch.collection);
+          const suggested = catalog.defaultNsfw(basePatch.collection);
           if (suggested) {
             patch.nsfw = suggested;
           }
         }
       }
 
-      // Now use your existing updater (it still normalizes, clamps, etc.)
-      await updateMeta(id, patch);
+      // Normalized and clamped the same as updateMeta
+      return { id, patch: normalizePatch(patch) };
     }));
 
-    showToast(`Updated ${ids.length} card${ids.length > 1 ? "s" : ""}.`, "success");
+    // One step of the undo history for the lot
+    for (const card of await cardStore.edit(updates)) await upsert(card);
+
+    showToast(`Updated ${ids.length} card${ids.length > 1 ? "s" : ""}.`, "success", 8000,
+      isTauri() ? { label: "Undo", run: () => stepHistory('undo') } : null);
 
     // Clear selection + reset bulk inputs for next run
     clearSelection();
@@ -2067,7 +2515,7 @@ export default function App() {
   async function addCustomCollection(name) {
     const n = (name || "").trim();
     if (!n) return;
-    const exists = SORTED_COLLECTIONS.concat(customCollections)
+    const exists = catalog.collections.concat(customCollections)
       .some(c => c.toLowerCase() === n.toLowerCase());
     if (exists) return;
     const next = [...customCollections, n].sort((a,b) => a.localeCompare(b));
@@ -2081,7 +2529,7 @@ export default function App() {
     if (!n) return;
 
     // Block deletion of defaults
-    const isDefault = SORTED_COLLECTIONS.some(
+    const isDefault = catalog.collections.some(
       c => normalizeCollectionName(c).toLowerCase() === n.toLowerCase()
     );
     if (isDefault) {
@@ -2100,15 +2548,29 @@ export default function App() {
     const next = customCollections.filter(
       c => normalizeCollectionName(c).toLowerCase() !== n.toLowerCase()
     );
-    setCustomCollections(next);
-    await customCollectionStore.setItem("list", next);
+    const key = keyForCollection(n);
+    const nextOrderMap = { ...orderMap };
+    delete nextOrderMap[key];
 
-    // Reassign any cards using *any* variant of this name
-    for (const m of metas) {
-      const mc = normalizeCollectionName(m.collection || "");
-      if (mc.toLowerCase() === n.toLowerCase()) {
-        await updateMeta(m.id, { collection: "" }); // normalized in updateMeta
+    if (isTauri()) {
+      // One undoable step in Rust: the list, the cards using it and its persisted order
+      const changed = await tauriInvoke('delete_custom_collections', { names: [n] });
+      for (const card of changed) await upsert(card);
+      setCustomCollections(next);
+      setOrderMap(nextOrderMap);
+    } else {
+      setCustomCollections(next);
+      await customCollectionStore.setItem("list", next);
+
+      // Reassign any cards using *any* variant of this name
+      for (const m of metas) {
+        const mc = normalizeCollectionName(m.collection || "");
+        if (mc.toLowerCase() === n.toLowerCase()) {
+          await updateMeta(m.id, { collection: "" }); // normalized in updateMeta
+        }
       }
+
+      persistOrder(nextOrderMap);
     }
 
     // If currently filtered to that collection, clear the filter
@@ -2116,11 +2578,7 @@ export default function App() {
       setActiveCollection("");
     }
 
-    // Clean persisted order + collapsed state for this collection group
-    const key = keyForCollection(n);
-    const nextOrderMap = { ...orderMap };
-    delete nextOrderMap[key];
-    persistOrder(nextOrderMap);
+    // Clean collapsed state for this collection group
 
     setCollapsed(prev => {
       const copy = { ...prev };
@@ -2150,57 +2608,21 @@ export default function App() {
 
         let kind = isPdf ? "pdf" : isImg ? "image" : "pdf"; // prefer image for images (gif included)
 
-        let dataUrl = "";
-        let numPages = 1;
-        try {
-          if (kind === "pdf") {
-            const r = await renderPdfPageToDataUrl(renderBytes, 1, 0.9);
-            dataUrl = r.dataUrl;
-            numPages = r.numPages;
-          } else {
-            // Image (GIF, PNG, JPG): make a thumbnail from the first frame / image
-            const mime = file.type || (isJpg ? "image/jpeg" : isPng ? "image/png" : isGif ? "image/gif" : "image/png");
-            const blob = new Blob([renderBytes], { type: mime });
-            const url = URL.createObjectURL(blob);
-            const img = new Image();
-            await new Promise((res, rej) => {
-              img.onload = res; img.onerror = rej; img.src = url;
-            });
-            const W = 360, H = Math.max(240, Math.round((img.height / img.width) * 360) || 240);
-            const canvas = document.createElement("canvas");
-            canvas.width = W; canvas.height = H;
-            const ctx = canvas.getContext("2d");
-            ctx.fillStyle = "#fff"; ctx.fillRect(0,0,W,H);
-            // contain fit
-            const s = Math.min(W / img.width, H / img.height);
-            const w = Math.round(img.width * s);
-            const h = Math.round(img.height * s);
-            const x = Math.round((W - w) / 2);
-            const y = Math.round((H - h) / 2);
-            ctx.drawImage(img, x, y, w, h);
-            dataUrl = canvas.toDataURL("image/png");
-            URL.revokeObjectURL(url);
-            numPages = 1; // single-page for images
-          }
-        } catch (renderErr) {
-          console.error("Thumbnail render failed, using placeholder", renderErr);
-          const canvas = document.createElement("canvas");
-          canvas.width = 360; canvas.height = 240;
-          const ctx = canvas.getContext("2d");
-          ctx.fillStyle = "#f1f5f9"; ctx.fillRect(0,0,canvas.width,canvas.height);
-          ctx.fillStyle = "#0f172a"; ctx.font = "bold 20px system-ui";
-          ctx.fillText(kind.toUpperCase(), 20, 40);
-          ctx.font = "14px system-ui";
-          ctx.fillText(file.name.slice(0, 40), 20, 70);
-          dataUrl = canvas.toDataURL("image/png");
-        }
-
         const id = uuidv4();
         // original extension (for correct download filename) and mime
         const extMatch = (file.name || "").match(/\.([a-z0-9]+)$/i);
         const origExt = extMatch ? (extMatch[1] || "").toLowerCase() : (kind === "pdf" ? "pdf" : "png");
         const mime = file.type || (origExt === "jpg" || origExt === "jpeg" ? "image/jpeg" : origExt === "png" ? "image/png" : origExt === "gif" ? "image/gif" : origExt === "pdf" ? "application/pdf" : "");
 
+        // Desktop renders thumbnails in Rust after the card is stored; only count pages here
+        let dataUrl = "";
+        let numPages = 1;
+        if (isTauri()) {
+          if (kind === "pdf") numPages = await countPdfPages(renderBytes.slice()).catch(() => 1);
+        } else {
+          ({ dataUrl, numPages } = await renderThumbnailInJs(renderBytes, { kind, mime, name: file.name }));
+        }
+
         const meta = {
           id,
           name: file.name.replace(/\.(pdf|gif|png|jpe?g)$/i, ""),
@@ -2217,9 +2639,23 @@ export default function App() {
           mime,
         };
 
-        await fileStore.setItem(id, bytes);
-        await metaStore.setItem(id, meta);
-        await upsert(meta);
+        const res = await cardStore.add(meta, bytes);
+        if (res.status === "alreadyInLibrary") {
+          const existing = metas.find((m) => m.id === res.existingId);
+          showToast(`“${file.name}” is already in your library${existing ? ` as “${existing.name}”` : ""}.`, "info", 4000);
+          continue;
+        }
+        let card = res.card;
+        if (isTauri()) {
+          try {
+            await tauriInvoke('card_thumbnail', { id: card.id, size: "grid" });
+          } catch {
+            // Rust couldn't render it (e.g. no pdfium); bake a JS thumbnail like the browser build
+            const r = await renderThumbnailInJs(renderBytes, { kind, mime, name: file.name });
+            card = await cardStore.update(card.id, { thumbnailDataUrl: r.dataUrl });
+          }
+        }
+        await upsert(card);
       } catch (e) {
         console.error("Failed to import", file?.name, e);
         setLastError(`Failed to import ${file?.name || ""}: ${e?.message || e}`);
@@ -2243,16 +2679,15 @@ export default function App() {
 
 
   async function openLightbox(id) {
-    const meta = /** @type {CardMeta} */ (await metaStore.getItem(id));
+    const meta = /** @type {CardMeta} */ (await cardStore.get(id));
 
     if (meta?.kind === "gif" || meta?.kind === "image") {
       // ensure only one viewer is open
       setLightbox({ open: false, id: "" });
       setLightboxBytes(null);
 
-      // fetch GIF bytes, then show
-      const gifBytes = await fileStore.getItem(id);
-      setGifBytes(gifBytes);
+      // fetch GIF bytes, then show (desktop streams it from card:// instead)
+      if (!isTauri()) setGifBytes(await cardStore.readFile(id));
       setGifState({ open: true, id });
       return;
     }
@@ -2269,8 +2704,8 @@ export default function App() {
     const doc = cached || await PdfCache.preload(id);
     setLightboxPdf(doc);
 
-    // 3) fetch bytes in the background for download/export use
-    fileStore.getItem(id).then((b) => setLightboxBytes(b));
+    // 3) browser only: keep the bytes around in case the doc couldn't be warmed
+    if (!isTauri()) cardStore.readFile(id).then((b) => setLightboxBytes(b));
 
     // 4) warm neighbors
     preloadNeighbors(id);
@@ -2278,44 +2713,160 @@ export default function App() {
 
 
 
-  async function updateMeta(id, patch) {
-    const existing = /** @type {CardMeta} */ (await metaStore.getItem(id));
-    let p = { ...patch };
-
-    // If collection is being changed, normalize it and maybe auto-tier/auto-nsfw
-    if (Object.prototype.hasOwnProperty.call(p, "collection")) {
-      p.collection = normalizeCollectionName(p.collection);
+  // Desktop only: folds copies of a card into `keep` (quantity, tags, ...) and deletes them
+  async function mergeCards(keep, others) {
+    const card = await tauriInvoke('merge_cards', { keep, others });
+    await upsert(card);
+    forget(others);
+    return card;
+  }
 
-      // auto-tier on collection change
-      // unless the caller explicitly provided a tier in the patch
-      if (autoTierFromCollection) {
-        const suggested = defaultTierForCollection(p.collection);
-        if (suggested && !("tier" in p)) {
-          p.tier = suggested;
-        }
-      }
+  // Desktop only: the undo history of every change to cards, their faces, collections and
+  // order, kept by Rust across restarts (src-tauri/src/library/history.rs). Newest first; the
+  // undone steps, which Redo walks back through, come before the rest.
+  const [history, setHistory] = useState([]);
+  const [historyOpen, setHistoryOpen] = useState(false);
+  const nextUndo = history.find((s) => !s.undone);
+  const nextRedo = history.filter((s) => s.undone).pop();
 
-      // auto-NSFW on collection change
-      // unless the caller explicitly provided nsfw in the patch
-      if (autoNsfwFromCollection) {
-        const suggested = defaultNsfwForCollection(p.collection);
-        if (suggested && !("nsfw" in p)) {
-          p.nsfw = suggested;
-        }
-      }
+  useEffect(() => {
+    if (!isTauri() || loading) return;
+    tauriInvoke('history').then(setHistory).catch((e) => console.error("Failed to load history", e));
+  }, [loading, metas, customCollections, orderMap]);
 
+  // `command` is 'undo' or 'redo'. Either can touch any card, collection or order.
+  async function stepHistory(command) {
+    try {
+      const step = await tauriInvoke(command);
+      if (!step) return;
+      await reload();
+      setCustomCollections((await customCollectionStore.getItem("list")) || []);
+      setOrderMap((await orderStore.getItem("map")) || {});
+      showToast(`${command === 'undo' ? "Undid" : "Redid"}: ${step.description}`, 'success', 4000);
+    } catch (e) {
+      showToast(describeError(e, `Couldn't ${command} that.`), 'error', 6000);
     }
+  }
 
-    // Safety: clamp unknown tiers to "" (protects against typos)
-    if (Object.prototype.hasOwnProperty.call(p, "tier")) {
-      if (p.tier && !TIER_OPTIONS.includes(p.tier)) p.tier = "";
+  // Ctrl/Cmd+Z, and Ctrl/Cmd+Shift+Z or Ctrl+Y to redo. Text fields keep their own undo.
+  useEffect(() => {
+    if (!isTauri()) return;
+    const onKeyDown = (e) => {
+      if (!(e.ctrlKey || e.metaKey) || e.altKey) return;
+      if (e.target instanceof Element && e.target.closest("input, textarea, select, [contenteditable='true']")) return;
+      const key = e.key.toLowerCase();
+      if (key === "z") {
+        e.preventDefault();
+        stepHistory(e.shiftKey ? 'redo' : 'undo');
+      } else if (key === "y") {
+        e.preventDefault();
+        stepHistory('redo');
+      }
+    };
+    window.addEventListener("keydown", onKeyDown);
+    return () => window.removeEventListener("keydown", onKeyDown);
+  }, []);
+
+  // Desktop only (src-tauri/src/pdf_edit.rs): a card per page, or several cards as one PDF.
+  // Each is a step of the history, so the toast's Undo is the same as Ctrl+Z.
+  async function applyCardEdit(outcome, message) {
+    for (const card of outcome.cards) await upsert(card);
+    forget(outcome.replaced);
+    showToast(message, 'success', 8000, { label: 'Undo', run: () => stepHistory('undo') });
+  }
+
+  async function splitCard(m) {
+    if (!window.confirm(`Split “${m.name}” into ${m.pages} cards, one per page?`)) return;
+    try {
+      const outcome = await tauriInvoke('split_card', { id: m.id });
+      await applyCardEdit(outcome, `Split “${m.name}” into ${outcome.cards.length} cards.`);
+    } catch (e) {
+      showToast(describeError(e, "Couldn't split that card."), 'error', 6000);
+    }
+  }
+
+  async function mergeSelectedIntoPdf() {
+    const ids = visibleList.filter((m) => selectedIds.has(m.id)).map((m) => m.id);
+    if (ids.length < 2) {
+      showToast("Select at least two cards to merge.", "info");
+      return;
+    }
+    if (!window.confirm(`Merge ${ids.length} cards into one PDF, in the order shown?`)) return;
+    setBulkApplying(true);
+    try {
+      const outcome = await tauriInvoke('merge_cards_into_pdf', { ids });
+      clearSelection();
+      const [card] = outcome.cards;
+      await applyCardEdit(outcome, `Merged ${ids.length} cards into “${card.name}” (${card.pages} pages).`);
+    } catch (e) {
+      showToast(describeError(e, "Couldn't merge those cards."), 'error', 6000);
+    } finally {
+      setBulkApplying(false);
     }
+  }
 
-    const updated = { ...existing, ...p, updatedAt: Date.now() };
-    await metaStore.setItem(id, updated);
+  // Backs and alternate art, shown with the lightbox's flip
+  async function attachFace(m, file) {
+    const faces = m.faces || [];
+    const label = window.prompt("What does this face show?", faces.length ? "Alternate art" : "Back");
+    if (label === null) return;
+    try {
+      const bytes = new Uint8Array(await file.arrayBuffer());
+      await upsert(await cardStore.attachFace(m.id, faceFromFile(file, label.trim()), bytes));
+    } catch (e) {
+      showToast(describeError(e, "Couldn't add that face."), "error", 5000);
+    }
+  }
+
+  async function detachFace(m, face) {
+    if (!window.confirm(`Remove “${face.label || "Back"}” from “${m.name}”?`)) return;
+    try {
+      await upsert(await cardStore.detachFace(m.id, face.id));
+    } catch (e) {
+      showToast(describeError(e, "Couldn't remove that face."), "error", 5000);
+    }
+  }
+
+  async function updateMeta(id, patch) {
+    const [updated] = await cardStore.edit([{ id, patch: normalizePatch(patch) }]);
     await upsert(updated);
   }
 
+  function normalizePatch(patch) {
+    let p = { ...patch };
+
+    // If collection is being changed, normalize it and maybe auto-tier/auto-nsfw
+    if (Object.prototype.hasOwnProperty.call(p, "collection")) {
+      p.collection = normalizeCollectionName(p.collection);
+
+      // auto-tier on collection change
+      // unless the caller explicitly provided a tier in the patch
+      if (autoTierFromCollection) {
+        const suggested = catalog.defaultTier(p.collection);
+        if (suggested && !("tier" in p)) {
+          p.tier = suggested;
+        }
+      }
+
+      // auto-NSFW on collection change
+      // unless the caller explicitly provided nsfw in the patch
+      if (autoNsfwFromCollection) {
+        const suggested = catalog.defaultNsfw(p.collection);
+        if (suggested && !("nsfw" in p)) {
+          p.nsfw = suggested;
+        }
+      }
+
+    }
+
+    // Safety: clamp unknown tiers to "" (protects against typos)
+    if (Object.prototype.hasOwnProperty.call(p, "tier")) {
+      if (p.tier && !catalog.tiers.includes(p.tier)) p.tier = "";
+    }
+
+    return p;
+  }
+
 
   async function exportJson() {
     // Gather auxiliary stores
@@ -2327,10 +2878,13 @@ export default function App() {
 
       // Collect files keyed by id
       const files = {};
+      const faceFiles = {};
       for (const m of metas) {
-        const raw = await fileStore.getItem(m.id);
-        const bytes = toUint8(raw);
+        const bytes = await cardStore.readFile(m.id);
         files[m.id] = Array.from(bytes); // store as number array for portability
+        for (const f of m.faces || []) {
+          faceFiles[f.id] = Array.from(await cardStore.readFaceFile(f.id));
+        }
       }
 
       const backup = {
@@ -2340,6 +2894,7 @@ export default function App() {
         // core library
         metas,
         files,
+        faceFiles, // by face id, for each card's `faces`
         // organization
         orderMap: orderMapOnDisk,
         customCollections: customCollectionsOnDisk,
@@ -2359,69 +2914,138 @@ export default function App() {
       showToast("Backup exported and download started.", "success");
     } catch (err) {
       console.error("Export failed", err);
-      showToast("Export failed. See console for details.", "error");
+      showToast(describeError(err, "Export failed."), "error");
     }
   }
 
   // Export archive using Tauri-native zip (writes files to temp then asks Rust to zip)
+  // Runs a Rust job, mirroring its progress into the banner under the header
+  function runJob(label, cmd, args) {
+    setJobProgress({ label, pct: 0 });
+    return tauriJob(cmd, args, {
+      onStart: (jobId) => setJobProgress((p) => p && { ...p, jobId }),
+      onProgress: (evt) => {
+        const pct = evt.bytesTotal
+          ? Math.round((evt.bytesDone / evt.bytesTotal) * 100)
+          : evt.filesTotal ? Math.round((evt.filesDone / evt.filesTotal) * 100) : 0;
+        setJobProgress((p) => p && { ...p, ...evt, pct });
+      },
+    });
+  }
+
+  // Binder pages (src-tauri/src/binder.rs): the selected cards as shown, or a whole collection
+  async function printBinderPages(options, collection) {
+    try {
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const dest = await save({
+        title: 'Save binder pages',
+        defaultPath: `${collection || 'cards'}-binder.pdf`,
+        filters: [{ name: 'PDF', extensions: ['pdf'] }],
+      });
+      if (!dest) return;
+      const args = collection
+        ? { collection, options, dest }
+        : { cardIds: visibleList.filter((m) => selectedIds.has(m.id)).map((m) => m.id), options, dest };
+      try {
+        const summary = await runJob('Laying out binder pages', 'export_binder_pages', args);
+        showToast(`Saved ${summary.cards} cards on ${summary.pages} page${summary.pages === 1 ? "" : "s"} to ${dest}`, 'success', 6000);
+        setPrintOpen(false);
+      } finally {
+        setJobProgress(null);
+      }
+    } catch (e) {
+      if (e?.kind === 'cancelled') { showToast('Printing cancelled.', 'info'); return; }
+      showToast(describeError(e, "Couldn't lay out the binder pages."), 'error', 6000);
+    }
+  }
+
+  // Contact sheet (src-tauri/src/contact_sheet.rs): the cards on screen as shown, or a whole collection
+  async function exportContactSheet(options, collection) {
+    try {
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const dest = await save({
+        title: 'Save contact sheet',
+        defaultPath: `${collection || 'cards'}.${options.format}`,
+        filters: [{ name: options.format.toUpperCase(), extensions: [options.format] }],
+      });
+      if (!dest) return;
+      const args = collection
+        ? { collection, options, dest }
+        : { cardIds: visibleList.map((m) => m.id), title: 'My cards', options, dest };
+      try {
+        const summary = await runJob('Drawing contact sheet', 'export_contact_sheet', args);
+        showToast(`Saved ${summary.cards} cards (${summary.width}×${summary.height}) to ${dest}`, 'success', 6000);
+        setSheetOpen(false);
+      } finally {
+        setJobProgress(null);
+      }
+    } catch (e) {
+      if (e?.kind === 'cancelled') { showToast('Contact sheet cancelled.', 'info'); return; }
+      showToast(describeError(e, "Couldn't draw the contact sheet."), 'error', 6000);
+    }
+  }
+
+  async function cancelJob(jobId) {
+    if (jobId == null) return;
+    try { await tauriInvoke('cancel_job', { jobId }); } catch (e) { console.warn('cancel_job failed', e); }
+  }
+
   async function exportArchiveAll() {
     if (!isTauri()) {
       showToast("Export archive is only available in the desktop app.", "info");
       return;
     }
     try {
-      const { mkdir, writeFile, BaseDirectory } = await import('@tauri-apps/plugin-fs');
-      const { tempDir } = await import('@tauri-apps/api/path');
-      const { invoke } = await import('@tauri-apps/api/core');
-
-      const tmpBase = await tempDir();
-      const relDir = `empire-export-${Date.now()}`;
-      const relPath = `empire_exports/${relDir}`;
-
-      // Ensure temp subdirs via fs API (relative to BaseDirectory.Temp)
-      await mkdir(relPath, { dir: BaseDirectory.Temp, recursive: true });
-      await mkdir(`${relPath}/files`, { dir: BaseDirectory.Temp, recursive: true });
-
-      // Write metadata.json
-      const metasToWrite = metas.map(m => ({ ...m }));
-      await writeFile({ path: `${relPath}/metadata.json`, contents: new TextEncoder().encode(JSON.stringify(metasToWrite)), dir: BaseDirectory.Temp });
-
-      // Write files one-by-one
-      for (const m of metasToWrite) {
-        const raw = await fileStore.getItem(m.id);
-        const bytes = toUint8(raw);
-        await writeFile({ path: `${relPath}/files/${m.id}.bin`, contents: bytes, dir: BaseDirectory.Temp });
-      }
-
-      const absSrc = `${tmpBase.replace(/\\\\/g,'/')}/${relPath}`; // tempDir + relative
-      const absDest = `${tmpBase.replace(/\\\\/g,'/')}/${relPath}.zip`;
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const dest = await save({
+        title: 'Export archive',
+        defaultPath: `empire-cards-${new Date().toISOString().slice(0, 10)}.zip`,
+        filters: [{ name: 'Card archive', extensions: ['zip'] }],
+      });
+      if (!dest) return; // dialog cancelled
 
-      // Call Rust to zip the directory (streams to disk)
-      const out = await invoke('zip_dir', { src: absSrc, dest: absDest });
-      showToast(`Exported archive: ${out}`, 'success', 6000);
+      // Rust streams cards straight from the library into the zip
+      try {
+        const summary = await runJob('Exporting archive', 'export_archive', { dest });
+        showToast(`Exported ${summary.cards} cards to ${summary.path}`, 'success', 6000);
+      } finally {
+        setJobProgress(null);
+      }
     } catch (e) {
+      if (e?.kind === 'cancelled') { showToast('Export cancelled.', 'info'); return; }
       console.error('Export archive failed', e);
-      showToast('Export failed. See console for details.', 'error');
+      showToast(describeError(e, 'Export failed.'), 'error', 6000);
     }
   }
 
-  // Import archive using Tauri-native unzip
-  async function importArchiveFromPath(zipPath) {
+  // Restore an archive (from exportArchiveAll) into the library, natively in Rust
+  async function importArchiveFromPath(zipPath, { mode = "merge" } = {}) {
     if (!isTauri()) {
       showToast("Import archive is only available in the desktop app.", "info");
       return;
     }
     try {
-      const { tempDir } = await import('@tauri-apps/api/path');
-      const { invoke } = await import('@tauri-apps/api/core');
-      const tmpBase = await tempDir();
-      const outDir = `${tmpBase}/empire-import-${Date.now()}`;
-      const res = await invoke('unzip_to_dir', { zip_path: zipPath, dest: outDir });
-      showToast(`Archive extracted to ${res}`, 'success');
-      // You can then read metadata.json and files from outDir using fs API or implement a follow-up importer.
+      let report;
+      try {
+        report = await runJob('Importing archive', 'import_archive', { path: zipPath, mode });
+      } finally {
+        setJobProgress(null);
+      }
+      const parts = [`${report.added.length} added`];
+      if (report.skipped.length) parts.push(`${report.skipped.length} already in library`);
+      if (report.conflicts.length) parts.push(`${report.conflicts.length} kept as-is (id conflict)`);
+      if (report.conflicts.length) console.warn('Archive import conflicts', report.conflicts);
+      if (report.savedQueriesAdded?.length) parts.push(`${report.savedQueriesAdded.length} saved filters`);
+      if (report.smartCollectionsAdded?.length) parts.push(`${report.smartCollectionsAdded.length} smart collections`);
+      if (report.checklistMatchesAdded) parts.push(`${report.checklistMatchesAdded} checklist matches`);
+      // alert() rather than a toast: the reload below would swallow it
+      alert(`Archive restored: ${parts.join(", ")}.`);
+      // Rehydrate in-memory state from the library, same as importJson
+      window.location.reload();
     } catch (e) {
+      if (e?.kind === 'cancelled') { showToast('Import cancelled.', 'info'); return; }
       console.error('Import archive failed', e);
-      showToast('Import failed. See console for details.', 'error');
+      showToast(describeError(e, 'Import failed.'), 'error', 6000);
     }
   }
 
@@ -2433,25 +3057,31 @@ export default function App() {
     if (mode === "replace") {
       // Full reset before restore
       await Promise.all([
-        metaStore.clear(),
-        fileStore.clear(),
+        cardStore.clear(),
         orderStore.clear(),
         customCollectionStore.clear(),
       ]);
     }
 
     const importedMetas = Array.isArray(data.metas) ? data.metas : [];
+    const importedFiles = data.files || {};
+    const importedFaceFiles = data.faceFiles || {};
+    // Backup wins over what's already here, same as the old setItem overwrite
+    const existing = [];
     for (const m of importedMetas) {
-      await metaStore.setItem(m.id, m);
+      if (await cardStore.get(m.id)) existing.push(m.id);
     }
-
-    const importedFiles = data.files || {};
-    for (const [id, entry] of Object.entries(importedFiles)) {
-      await fileStore.setItem(id, decodeFileEntry(entry));
+    if (existing.length) await cardStore.removeMany(existing);
+    for (const m of importedMetas) {
+      const { faces = [], ...meta } = m;
+      await cardStore.restore(meta, decodeFileEntry(importedFiles[m.id]));
+      for (const f of faces) {
+        if (importedFaceFiles[f.id]) await cardStore.restoreFace(m.id, f, decodeFileEntry(importedFaceFiles[f.id]));
+      }
     }
 
     if (data.orderMap && typeof data.orderMap === "object") {
-      await orderStore.setItem("map", data.orderMap);
+      await restoreItem(orderStore, "map", data.orderMap);
     }
 
     if (Array.isArray(data.customCollections)) {
@@ -2459,7 +3089,7 @@ export default function App() {
       const dedup = Array.from(
         new Set(data.customCollections.map((c) => String(c)))
       ).sort((a, b) => a.localeCompare(b));
-      await customCollectionStore.setItem("list", dedup);
+      await restoreItem(customCollectionStore, "list", dedup);
     }
 
     if (data.theme === "dark" || data.theme === "light") {
@@ -2493,9 +3123,12 @@ export default function App() {
     const key = keyForCollection(groupName);
     const currentIds = orderMap[key] || [];
 
-    const groupIds = metas
-      .filter(m => (m.collection || "(None)") === groupName)
-      .map(m => m.id);
+    const smartId = smartGroupId(groupName);
+    const groupIds = smartId
+      ? metas.filter(m => smartMembers.get(smartId)?.has(m.id)).map(m => m.id)
+      : metas
+        .filter(m => (m.collection || "(None)") === groupName)
+        .map(m => m.id);
 
     let ids = currentIds.filter(id => groupIds.includes(id));
     if (!ids.length) ids = groupIds.slice();
@@ -2579,10 +3212,10 @@ export default function App() {
       window.removeEventListener("pointerup", handlePointerUp);
       window.removeEventListener("pointercancel", handlePointerUp);
     };
-  }, [dragging, dropTarget, orderMap, metas]);
+  }, [dragging, dropTarget, orderMap, metas, smartMembers]);
 
   // ===== Keyboard reorder (Ctrl/Cmd + ↑/↓) =====
-  function handleCardKeyDown(e, m, groupItemsOrdered) {
+  function handleCardKeyDown(e, m, groupItemsOrdered, group) {
     if (!(reorderMode && sortMode === "none")) return;
     const isCtrl = e.ctrlKey || e.metaKey;
     if (!isCtrl) return;
@@ -2594,21 +3227,22 @@ export default function App() {
       e.preventDefault();
       const neighbor = groupItemsOrdered[Math.max(0, idx - 1)];
       if (neighbor && neighbor.id !== m.id) {
-        moveWithinGroup(m.collection || "(None)", m.id, neighbor.id, true);
+        moveWithinGroup(group, m.id, neighbor.id, true);
       }
     } else if (e.key === "ArrowDown") {
       e.preventDefault();
       const neighbor = groupItemsOrdered[Math.min(groupItemsOrdered.length - 1, idx + 1)];
       if (neighbor && neighbor.id !== m.id) {
-        moveWithinGroup(m.collection || "(None)", m.id, neighbor.id, false);
+        moveWithinGroup(group, m.id, neighbor.id, false);
       }
     }
   }
 
   // ===== Card renderer (pointer-based drag) =====
-  function renderCardFactory(groupOrderedItems) {
+  function renderCardFactory(groupOrderedItems, groupName = null) {
     const isDark = theme === "dark";
     return function renderCard(m) {
+      const group = groupName ?? (m.collection || "(None)");
       const isDragSource = dragging.active && dragging.id === m.id;
       const isDropTarget = dropTarget.id === m.id;
 
@@ -2617,7 +3251,7 @@ export default function App() {
         if (e.button !== 0) return;
         e.preventDefault();
         pointerIdRef.current = e.pointerId ?? null;
-        setDragging({ active:true, id:m.id, group: m.collection || "(None)" });
+        setDragging({ active:true, id:m.id, group });
         setDropTarget({ id:m.id, pos:"before" });
       };
 
@@ -2625,12 +3259,12 @@ export default function App() {
         <article
           key={m.id}
           data-card-id={m.id}
-          data-card-group={m.collection || "(None)"}
+          data-card-group={group}
           className={`relative border rounded-2xl shadow-sm hover:shadow-md transition
             ${reorderMode ? "cursor-default" : ""} ${isDragSource ? "opacity-80" : ""}
             ${isDark ? "border-slate-700 bg-slate-900" : "border-slate-200 bg-white"}`}
           tabIndex={0}
-          onKeyDown={(e) => handleCardKeyDown(e, m, groupOrderedItems)}
+          onKeyDown={(e) => handleCardKeyDown(e, m, groupOrderedItems, group)}
         >
           {/* Drag handle */}
           {reorderMode && sortMode === "none" && (
@@ -2666,9 +3300,8 @@ export default function App() {
             style={reorderMode ? { pointerEvents: "none" } : undefined}
             className={`${isDark ? "bg-slate-800" : "bg-gray-50"}`}
           >
-            <img
-              src={m.thumbnailDataUrl}
-              alt={m.name}
+            <CardThumbnail
+              meta={m}
               className={`w-full h-64 object-contain cursor-pointer ${isDark ? "bg-slate-900" : "bg-white"} ${m.nsfw && !nsfwMode ? "blur-xl" : ""}`}
               draggable={false}
               onMouseEnter={() => {
@@ -2721,8 +3354,25 @@ export default function App() {
 
             <div className={`text-xs mb-2 ${isDark ? "text-gray-400" : "text-gray-500"}`}>
               {m.pages} page{m.pages > 1 ? "s" : ""}
+              {isTauri() && m.kind === "pdf" && m.pages > 1 && (
+                <button
+                  className={`ml-2 underline cursor-pointer ${isDark ? "hover:text-gray-200" : "hover:text-gray-800"}`}
+                  onClick={() => splitCard(m)}
+                  title="Make a card of each page"
+                >
+                  Split pages
+                </button>
+              )}
             </div>
 
+            {searchHits?.get(m.id) && (
+              // Escaped by the index; only the <b> highlights are markup
+              <div
+                className={`text-xs mb-2 line-clamp-3 [&_b]:font-semibold ${isDark ? "text-gray-300 [&_b]:text-amber-300" : "text-gray-600 [&_b]:text-amber-700"}`}
+                dangerouslySetInnerHTML={{ __html: searchHits.get(m.id) }}
+              />
+            )}
+
             {editMode ? (
               <>
                 <div className="flex items-center gap-2 mb-2">
@@ -2741,7 +3391,7 @@ export default function App() {
 
                         // Prefill tier (only when toggle is on AND this card has no tier yet)
                         if (autoTierFromCollection && !m.tier) {
-                          const suggested = defaultTierForCollection(n);
+                          const suggested = catalog.defaultTier(n);
                           if (suggested) {
                             await updateMeta(m.id, { collection: n, tier: suggested });
                             return;
@@ -2757,7 +3407,7 @@ export default function App() {
 
                     // Normal path
                     if (autoTierFromCollection && !m.tier) {
-                      const suggested = defaultTierForCollection(val);
+                      const suggested = catalog.defaultTier(val);
                       if (suggested) {
                         await updateMeta(m.id, { collection: val, tier: suggested });
                         return;
@@ -2770,7 +3420,7 @@ export default function App() {
                   >
                     <option value="">(None)</option>
                     <optgroup label="Default collections">
-                      {SORTED_COLLECTIONS.map((c) => (
+                      {catalog.collections.map((c) => (
                         <option key={"def-" + c} value={c}>{c}</option>
                       ))}
                     </optgroup>
@@ -2797,12 +3447,37 @@ export default function App() {
                     title={autoTierFromCollection ? "Tier is set automatically from the collection" : "Set tier"}
                   >
                     <option value="">(No tier)</option>
-                    {TIER_OPTIONS.map((t) => (
+                    {catalog.tiers.map((t) => (
                       <option key={t} value={t}>{t}</option>
                     ))}
                   </select>
                 </div>
 
+                <div className="flex items-center gap-2 mb-2">
+                  <input
+                    type="number"
+                    min={1}
+                    className={`border rounded-md px-2 py-1 text-sm w-20 ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`}
+                    value={m.quantity ?? 1}
+                    onChange={(e) => {
+                      const quantity = Math.max(1, Math.floor(Number(e.target.value) || 1));
+                      if (quantity !== (m.quantity ?? 1)) updateMeta(m.id, { quantity });
+                    }}
+                    title="Copies owned"
+                    aria-label="Copies owned"
+                  />
+                  <select
+                    className={`border rounded-md px-2 py-1 text-sm w-full cursor-pointer
+                      ${isDark ? "bg-slate-800 border-slate-700 hover:bg-slate-700" : "bg-white border-slate-300 hover:bg-slate-50"}`}
+                    value={m.condition || ""}
+                    onChange={(e) => updateMeta(m.id, { condition: e.target.value })}
+                    title="Condition"
+                  >
+                    <option value="">(No condition)</option>
+                    {m.condition && !CARD_CONDITIONS.includes(m.condition) && <option value={m.condition}>{m.condition}</option>}
+                    {CARD_CONDITIONS.map((c) => <option key={c} value={c}>{c}</option>)}
+                  </select>
+                </div>
 
                 <TagEditor value={m.tags} onChange={(tags) => updateMeta(m.id, { tags })} theme={theme} />
 
@@ -2816,6 +3491,13 @@ export default function App() {
                   <span className="text-sm">Mark as NSFW</span>
                 </label>
 
+                <FaceEditor
+                  faces={m.faces || []}
+                  onAttach={(file) => attachFace(m, file)}
+                  onDetach={(face) => detachFace(m, face)}
+                  theme={theme}
+                />
+
                 <div className="flex items-center justify-between mt-3">
                   <div className="flex gap-2">
                     <button className={`px-3 py-1 rounded-md border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`} onClick={() => openLightbox(m.id)}>
@@ -2852,7 +3534,18 @@ export default function App() {
                     ${isDark ? "bg-slate-800 border-slate-700" : "bg-gray-50 border-slate-300"}`}>
                     {m.tier || "(No tier)"}
                   </span>
-                  
+                  {(m.quantity ?? 1) > 1 && (
+                    <span className={`inline-flex items-center rounded-full border px-2 py-1 text-xs
+                      ${isDark ? "bg-slate-800 border-slate-700" : "bg-gray-50 border-slate-300"}`} title="Copies owned">
+                      ×{m.quantity}
+                    </span>
+                  )}
+                  {m.condition && (
+                    <span className={`inline-flex items-center rounded-full border px-2 py-1 text-xs
+                      ${isDark ? "bg-slate-800 border-slate-700" : "bg-gray-50 border-slate-300"}`}>
+                      {m.condition}
+                    </span>
+                  )}
                 </div>
 
                 {Array.isArray(m.tags) && m.tags.length > 0 && (
@@ -2928,9 +3621,8 @@ export default function App() {
   // inside App(), add:
   async function downloadCard(id) {
     try {
-      const meta = /** @type {CardMeta} */ (await metaStore.getItem(id));
-      const raw  = await fileStore.getItem(id);
-      const bytes = toUint8(raw);
+      const meta = /** @type {CardMeta} */ (await cardStore.get(id));
+      const bytes = await cardStore.readFile(id);
 
       if (!meta || !bytes.length) {
         showToast("Sorry, that file isn’t available.", "error");
@@ -2979,7 +3671,7 @@ export default function App() {
           <input
             value={query}
             onChange={(e) => setQuery(e.target.value)}
-            placeholder="Search name or tag…"
+            placeholder={isTauri() ? "Search cards… (\"phrase\", pre*, tag:, tier:, collection:)" : "Search name or tag…"}
             className={`flex-1 min-w-[200px] border rounded-xl px-3 py-2 placeholder:text-gray-400 
               ${isDark ? "bg-slate-800 border-slate-700 text-slate-100 hover:bg-slate-700" : "bg-white border-slate-300 text-slate-900 hover:bg-slate-50"}`}
           />
@@ -2990,6 +3682,13 @@ export default function App() {
             onChange={(e) => setActiveCollection(e.target.value)}
           >
             <option value="">All collections</option>
+            {smartGroups.length > 0 && (
+              <optgroup label="Smart collections">
+                {smartGroups.map((g) => (
+                  <option key={g.id} value={SMART_PREFIX + g.id}>⚡ {g.name} ({g.cardIds.length})</option>
+                ))}
+              </optgroup>
+            )}
             {allCollections.map((c) => (
               <option key={c} value={c}>{c}</option>
             ))}
@@ -3002,7 +3701,7 @@ export default function App() {
             onChange={(e) => setActiveTier(e.target.value)}
           >
             <option value="">All tiers</option>
-            {TIER_OPTIONS.map((t) => (
+            {catalog.tiers.map((t) => (
               <option key={t} value={t}>{t}</option>
             ))}
           </select>
@@ -3196,8 +3895,104 @@ export default function App() {
               Manage
             </button>
 
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setWatchFoldersOpen(true)}
+              >
+                Watch folders
+              </button>
+            )}
+
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setSmartOpen(true)}
+              >
+                Smart collections
+              </button>
+            )}
+
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setCatalogOpen(true)}
+              >
+                Catalog
+              </button>
+            )}
+
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setCompletionOpen(true)}
+              >
+                Completion
+              </button>
+            )}
+
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setTradeOpen(true)}
+              >
+                Duplicates &amp; trades
+              </button>
+            )}
+
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setPrintOpen(true)}
+                disabled={!!jobProgress}
+              >
+                Print binder pages
+              </button>
+            )}
+
+            {isTauri() && (
+              <button
+                className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                onClick={() => setSheetOpen(true)}
+                disabled={!!jobProgress}
+              >
+                Contact sheet
+              </button>
+            )}
+
+            {isTauri() && (
+              <>
+                <button
+                  className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                  onClick={() => stepHistory('undo')}
+                  disabled={!nextUndo}
+                  title={nextUndo ? `Undo: ${nextUndo.description} (Ctrl+Z)` : "Nothing to undo"}
+                >
+                  Undo
+                </button>
+                <button
+                  className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                  onClick={() => stepHistory('redo')}
+                  disabled={!nextRedo}
+                  title={nextRedo ? `Redo: ${nextRedo.description} (Ctrl+Shift+Z)` : "Nothing to redo"}
+                >
+                  Redo
+                </button>
+                <button
+                  className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                  onClick={() => setHistoryOpen(true)}
+                >
+                  History
+                </button>
+              </>
+            )}
+
             <button className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`} onClick={exportJson}>Export</button>
 
+            {isTauri() && (
+              <button className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`} onClick={exportArchiveAll} disabled={!!jobProgress}>Export archive</button>
+            )}
+
             <label className={`px-3 py-2 rounded-xl border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}>
               Restore
               <input
@@ -3229,6 +4024,16 @@ export default function App() {
           
           
 
+          {isTauri() && (
+            <FilterQueryBar
+              metas={metas}
+              onResult={setQueryIds}
+              onError={(e) => showToast(describeError(e, "Filter failed."), "error", 5000)}
+              onNotice={(message) => showToast(message, "success", 2500)}
+              theme={theme}
+            />
+          )}
+
           {bulkMode && (
             <div className="w-full mt-2 flex flex-wrap items-center gap-2">
               <span className="text-sm">
@@ -3255,7 +4060,7 @@ export default function App() {
                       await addCustomCollection(n);
                       setBulkCollection(n);
                       if (autoTierFromCollection) {
-                        const suggested = defaultTierForCollection(n);
+                        const suggested = catalog.defaultTier(n);
                         setBulkTier(suggested || "");
                       }
                     } else {
@@ -3271,7 +4076,7 @@ export default function App() {
                     if (!v || v === "__clear__" || v === "__keep__") {
                       // don't touch the user's current tier choice when clearing/keeping
                     } else {
-                      const suggested = defaultTierForCollection(v);
+                      const suggested = catalog.defaultTier(v);
                       setBulkTier(suggested || ""); // empty if no mapping
                     }
                   }
@@ -3281,7 +4086,7 @@ export default function App() {
                     if (!v || v === "__clear__" || v === "__keep__") {
                       // don't touch the user's current NSFW choice when clearing/keeping
                     } else {
-                      const suggested = defaultNsfwForCollection(v);
+                      const suggested = catalog.defaultNsfw(v);
                       setBulkNsfw(suggested ? "true" : ""); // empty if no mapping
                     }
                   }
@@ -3292,7 +4097,7 @@ export default function App() {
                 <option value="">Collection (no change)</option>
                 <option value="__clear__">— Clear collection —</option>
                 <optgroup label="Default">
-                  {SORTED_COLLECTIONS.map((c) => (
+                  {catalog.collections.map((c) => (
                     <option key={"bdef-" + c} value={c}>{c}</option>
                   ))}
                 </optgroup>
@@ -3319,7 +4124,7 @@ export default function App() {
               >
                 <option value="">Tier (no change)</option>
                 <option value="__clear__">— Clear tier —</option>
-                {TIER_OPTIONS.map((t) => (
+                {catalog.tiers.map((t) => (
                   <option key={"bt-" + t} value={t}>{t}</option>
                 ))}
               </select>
@@ -3356,6 +4161,17 @@ export default function App() {
                 Delete
               </button>
 
+              {isTauri() && (
+                <button
+                  className={`border rounded-xl px-3 py-2 cursor-pointer ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                  onClick={mergeSelectedIntoPdf}
+                  disabled={selectedIds.size < 2 || bulkApplying}
+                  title="Merge the selected cards into one multi-page PDF card"
+                >
+                  Merge into one PDF
+                </button>
+              )}
+
 
               <button
                 className="px-3 py-2 rounded-xl border bg-blue-600 text-white cursor-pointer hover:bg-slate-700"
@@ -3429,7 +4245,13 @@ export default function App() {
                         >
                           {isGroupCollapsed(name) ? "▸" : "▾"}
                         </button>
-                        <h2 className="font-semibold">{name}</h2>
+                        {smartGroupId(name) ? (
+                          <h2 className="font-semibold" title="Smart collection: cards are picked by its filter">
+                            ⚡ {smartGroups.find((g) => g.id === smartGroupId(name))?.name}
+                          </h2>
+                        ) : (
+                          <h2 className="font-semibold">{name}</h2>
+                        )}
                       </div>
 
                       <div className="flex items-center gap-2">
@@ -3482,7 +4304,7 @@ export default function App() {
                             className="relative p-4 grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 gap-4"
                             data-grid-key={gridKey}
                           >
-                            {ordered.map(renderCardFactory(ordered))}
+                            {ordered.map(renderCardFactory(ordered, name))}
 
                             {reorderMode && dropLine.visible && dropLine.groupKey === gridKey && (
                               <div
@@ -3565,6 +4387,24 @@ export default function App() {
             </div>
           )}
 
+          {jobProgress && (
+            <div className="max-w-6xl mx-auto mt-3 px-4">
+              <div className={`rounded-xl border px-4 py-2 text-sm flex items-center gap-3 ${isDark ? "bg-slate-900 border-slate-700" : "bg-white border-slate-300"}`}>
+                <span className="flex-1 truncate">
+                  {jobProgress.label}… {jobProgress.filesTotal ? `${jobProgress.filesDone}/${jobProgress.filesTotal} files, ` : ""}{jobProgress.pct}%
+                  {jobProgress.currentEntry ? ` — ${jobProgress.currentEntry}` : ""}
+                </span>
+                <button
+                  className={`px-2 py-1 rounded-lg border cursor-pointer ${isDark ? "border-slate-700 hover:bg-slate-800" : "border-slate-300 hover:bg-slate-50"}`}
+                  onClick={() => cancelJob(jobProgress.jobId)}
+                  disabled={jobProgress.jobId == null}
+                >
+                  Cancel
+                </button>
+              </div>
+            </div>
+          )}
+
 
         </div>
       </main>
@@ -3582,12 +4422,15 @@ export default function App() {
         canNext={canNextId(lightbox.id)}
         isNsfw={metas.find((m) => m.id === lightbox.id)?.nsfw || false}
         nsfwMode={nsfwMode}
+        cardId={lightbox.id}
+        faces={metas.find((m) => m.id === lightbox.id)?.faces}
       />
 
       <GifLightbox
         open={gifState.open}
         onClose={() => { setGifState({ open: false, id: "" }); setGifBytes(null); }}
         fileBytes={gifBytes}
+        fileUrl={isTauri() && gifState.id ? cardFileUrl(gifState.id) : null}
         name={metas.find((m) => m.id === gifState.id)?.name || ""}
         theme={theme}
         onPrev={() => goSibling(gifState.id, -1)}
@@ -3597,18 +4440,103 @@ export default function App() {
         fileMime={metas.find((m) => m.id === gifState.id)?.mime || "image/gif"}
         isNsfw={metas.find((m) => m.id === gifState.id)?.nsfw || false}
         nsfwMode={nsfwMode}
+        cardId={gifState.id}
+        faces={metas.find((m) => m.id === gifState.id)?.faces}
       />
 
 
       <CollectionsManager
         open={collectionsOpen}
         onClose={() => setCollectionsOpen(false)}
-        defaults={SORTED_COLLECTIONS}
+        defaults={catalog.collections}
         custom={customCollections}
         onDelete={deleteCustomCollection}
         theme={theme}
       />
 
+      <SmartCollectionsManager
+        open={smartOpen}
+        onClose={() => setSmartOpen(false)}
+        groups={smartGroups}
+        onChange={(groups) => {
+          setSmartGroups(groups);
+          // A deleted smart collection can't stay the active filter
+          const active = smartGroupId(activeCollection);
+          if (active && !groups.some((g) => g.id === active)) setActiveCollection("");
+        }}
+        tags={allTags}
+        catalog={catalog}
+        onError={(e) => showToast(describeError(e, "Couldn't update smart collections."), "error", 5000)}
+        theme={theme}
+      />
+
+      <CatalogManager
+        open={catalogOpen}
+        onClose={() => setCatalogOpen(false)}
+        catalog={catalog}
+        onSaved={(doc) => setCatalog(indexCatalog(doc))}
+        onError={(e) => showToast(describeError(e, "Couldn't update the catalog."), "error", 5000)}
+        onNotice={(message) => showToast(message, "success")}
+        theme={theme}
+      />
+
+      <CompletionView
+        open={completionOpen}
+        onClose={() => setCompletionOpen(false)}
+        metas={metas}
+        catalog={catalog}
+        onError={(e) => showToast(describeError(e, "Couldn't update the checklist."), "error", 5000)}
+        theme={theme}
+      />
+
+      <TradeView
+        open={tradeOpen}
+        onClose={() => setTradeOpen(false)}
+        metas={metas}
+        catalog={catalog}
+        onMerge={mergeCards}
+        onError={(e) => showToast(describeError(e, "Couldn't update duplicates."), "error", 5000)}
+        onNotice={(message) => showToast(message, "success")}
+        theme={theme}
+      />
+
+      <PrintBinderDialog
+        open={printOpen}
+        onClose={() => setPrintOpen(false)}
+        selectedCount={selectedIds.size}
+        collections={allCollections}
+        busy={!!jobProgress}
+        onPrint={printBinderPages}
+        theme={theme}
+      />
+
+      <HistoryDialog
+        open={historyOpen}
+        onClose={() => setHistoryOpen(false)}
+        steps={history}
+        onStep={stepHistory}
+        theme={theme}
+      />
+
+      <ContactSheetDialog
+        open={sheetOpen}
+        onClose={() => setSheetOpen(false)}
+        visibleCount={visibleList.length}
+        collections={allCollections}
+        busy={!!jobProgress}
+        onExport={exportContactSheet}
+        theme={theme}
+      />
+
+      <WatchFoldersManager
+        open={watchFoldersOpen}
+        onClose={() => setWatchFoldersOpen(false)}
+        collections={allCollections}
+        catalog={catalog}
+        onError={(e) => showToast(describeError(e, "Couldn't update watch folders."), "error", 5000)}
+        theme={theme}
+      />
+
       {/* Toast */}
       <div
         className={`
@@ -3632,6 +4560,14 @@ export default function App() {
             {toast.kind === "success" ? "✅" : toast.kind === "error" ? "⚠️" : "ℹ️"}
           </span>
           <span className="align-middle">{toast.message}</span>
+          {toast.action && (
+            <button
+              className={`ml-3 px-2 py-0.5 rounded border text-sm cursor-pointer ${isDark ? "border-slate-600 hover:bg-slate-800" : "border-slate-300 hover:bg-slate-50"}`}
+              onClick={() => { const { run } = toast.action; setToast(t => ({ ...t, open: false, action: null })); run(); }}
+            >
+              {toast.action.label}
+            </button>
+          )}
         </div>
       </div>
 
@@ -3672,9 +4608,9 @@ function CollectionsManager({ open, onClose, defaults, custom, onDelete, theme }
         </div>
 
         <div className="mb-4">
-          <div className={`text-xs font-semibold mb-1 ${isDark ? "text-gray-300" : "text-gray-600"}`}>Default (built-in)</div>
+          <div className={`text-xs font-semibold mb-1 ${isDark ? "text-gray-300" : "text-gray-600"}`}>From the catalog</div>
           <div className="space-y-1">
-            {SORTED_COLLECTIONS.map(c => (
+            {defaults.map(c => (
               <div key={c} className="flex items-center justify-between text-sm">
                 <span>{c}</span>
                 <span className={isDark ? "text-gray-500" : "text-gray-400"}>(locked)</span>
@@ -3708,30 +4644,1155 @@ function CollectionsManager({ open, onClose, defaults, custom, onDelete, theme }
   );
 }
 
-function EditableName({ value, onSave }) {
-  const [text, setText] = React.useState(value);
-  React.useEffect(() => setText(value), [value]);
+// Filter query bar: the query language in src-tauri/src/query.rs, plus saved queries.
+// Reports matching ids through onResult (null when empty); a query that doesn't parse
+// keeps the last result and underlines the offending text.
+function FilterQueryBar({ metas, onResult, onError, onNotice, theme }) {
+  const [text, setText] = useState("");
+  const [error, setError] = useState(null);
+  const [saved, setSaved] = useState([]);
+  const isDark = theme === "dark";
+  const button = `px-3 py-2 rounded-xl border cursor-pointer disabled:opacity-50 disabled:cursor-not-allowed
+    ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`;
+  const current = saved.find((q) => q.query === text.trim());
 
-  const commit = React.useCallback(() => {
-    const next = text.trim();
-    if (!next || next === value) return;
-    onSave(next);
-  }, [text, value, onSave]);
+  useEffect(() => {
+    tauriInvoke('list_saved_queries').then(setSaved).catch(onError);
+  }, []);
 
-  return (
-    <input
-      value={text}
-      onChange={(e) => setText(e.target.value)}
-      onBlur={commit}
-      onKeyDown={(e) => {
-        if (e.key === "Enter") { e.preventDefault(); commit(); e.currentTarget.blur(); }
-        if (e.key === "Escape") { setText(value); e.currentTarget.blur(); }
-      }}
-      placeholder="Untitled card"
-      spellCheck={false}
-      className="w-full min-w-0 bg-transparent border border-transparent focus:border-indigo-400 focus:ring-0 rounded-md px-1 h-7 text-sm font-medium"
-      aria-label="Card name"
-    />
+  // Re-run when the library changes too, since matches depend on card metadata
+  useEffect(() => {
+    const q = text.trim();
+    if (!q) { setError(null); onResult(null); return; }
+    let cancelled = false;
+    const t = window.setTimeout(async () => {
+      try {
+        const ids = await tauriInvoke('filter_cards', { query: q });
+        if (cancelled) return;
+        setError(null);
+        onResult(new Set(ids));
+      } catch (e) {
+        if (cancelled) return;
+        if (e?.kind === 'invalidQuery') setError(e); else onError(e);
+      }
+    }, 250);
+    return () => { cancelled = true; window.clearTimeout(t); };
+  }, [text, metas]);
+
+  async function saveCurrent() {
+    const name = window.prompt("Save this filter as", current?.name || "");
+    if (!name?.trim()) return;
+    try {
+      await tauriInvoke('save_query', { query: { name: name.trim(), query: text.trim() } });
+      setSaved(await tauriInvoke('list_saved_queries'));
+    } catch (e) {
+      if (e?.kind === 'invalidQuery') setError(e); else onError(e);
+    }
+  }
+
+  async function deleteCurrent() {
+    if (!current || !window.confirm(`Delete the saved filter "${current.name}"?`)) return;
+    try {
+      await tauriInvoke('delete_saved_query', { name: current.name });
+      setSaved((prev) => prev.filter((q) => q.name !== current.name));
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function copyCurrent() {
+    try {
+      await navigator.clipboard.writeText(text.trim());
+      onNotice("Filter copied. Paste it into the filter box to share it.");
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  // Offsets from Rust count characters, not UTF-16 units
+  const chars = Array.from(text);
+  return (
+    <div className="w-full flex flex-col gap-1">
+      <div className="flex flex-wrap items-center gap-2">
+        <input
+          value={text}
+          onChange={(e) => setText(e.target.value)}
+          placeholder='Filter… e.g. tier>=Phoenix collection:"Quiet Court" -tag:dupe fav:yes added:<2026-01-01'
+          spellCheck={false}
+          className={`flex-1 min-w-[240px] border rounded-xl px-3 py-2 font-mono text-sm placeholder:text-gray-400
+            ${error ? "border-red-500" : isDark ? "border-slate-700" : "border-slate-300"}
+            ${isDark ? "bg-slate-800 text-slate-100 hover:bg-slate-700" : "bg-white text-slate-900 hover:bg-slate-50"}`}
+        />
+        <select
+          className={`border rounded-xl px-3 py-2 cursor-pointer ${isDark ? "bg-slate-800 border-slate-700 hover:bg-slate-700" : "bg-white border-slate-300 hover:bg-slate-50"}`}
+          value={current?.name || ""}
+          onChange={(e) => {
+            const picked = saved.find((q) => q.name === e.target.value);
+            setText(picked ? picked.query : "");
+          }}
+        >
+          <option value="">Saved filters</option>
+          {saved.map((q) => <option key={q.name} value={q.name}>{q.name}</option>)}
+        </select>
+        <button className={button} onClick={saveCurrent} disabled={!text.trim() || !!error}>Save</button>
+        <button className={button} onClick={copyCurrent} disabled={!text.trim()}>Copy</button>
+        {current && <button className={button} onClick={deleteCurrent}>Delete</button>}
+      </div>
+      {error && (
+        <div className="text-sm">
+          <div className={`font-mono whitespace-pre-wrap ${isDark ? "text-slate-300" : "text-slate-700"}`}>
+            {chars.slice(0, error.start).join("")}
+            <span className="underline decoration-wavy decoration-red-500 bg-red-500/20">
+              {chars.slice(error.start, error.end).join("") || " "}
+            </span>
+            {chars.slice(error.end).join("")}
+          </div>
+          <div className="text-red-500">{error.message.replace(/^invalid query: /, "")}</div>
+        </div>
+      )}
+    </div>
+  );
+}
+
+const EMPTY_SMART_FILTER = {
+  tierMin: "", tierMax: "", tags: [], excludeTags: [], nsfw: null, favorite: null,
+  addedFrom: null, addedUntil: null, kinds: [], query: "",
+};
+
+// Dates are whole UTC days; `addedUntil` is exclusive, so the form shows the day before it
+const DAY_MS = 24 * 60 * 60 * 1000;
+const dayInput = (ms, offset = 0) => (ms == null ? "" : new Date(ms + offset).toISOString().slice(0, 10));
+const dayValue = (text, offset = 0) => (text ? Date.parse(text) + offset : null);
+const splitTags = (text) => text.split(",").map((t) => t.trim()).filter(Boolean);
+
+function SmartCollectionsManager({ open, onClose, groups, onChange, tags, catalog, onError, theme }) {
+  // { id?, name, filter, tagsText, excludeText } while the form is open
+  const [draft, setDraft] = useState(null);
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+  const button = `px-2 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`;
+
+  useEffect(() => { if (!open) setDraft(null); }, [open]);
+
+  function edit(group) {
+    const filter = { ...EMPTY_SMART_FILTER, ...(group?.filter || {}) };
+    setDraft({
+      id: group?.id,
+      name: group?.name || "",
+      filter,
+      tagsText: filter.tags.join(", "),
+      excludeText: filter.excludeTags.join(", "),
+    });
+  }
+
+  const setFilter = (patch) => setDraft((d) => ({ ...d, filter: { ...d.filter, ...patch } }));
+
+  async function save() {
+    const collection = {
+      id: draft.id || "",
+      name: draft.name,
+      filter: { ...draft.filter, tags: splitTags(draft.tagsText), excludeTags: splitTags(draft.excludeText) },
+    };
+    try {
+      const next = draft.id
+        ? await tauriInvoke('update_smart_collection', { collection })
+        : await tauriInvoke('add_smart_collection', { collection });
+      onChange(next);
+      setDraft(null);
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function remove(id) {
+    try {
+      onChange(await tauriInvoke('delete_smart_collection', { id }));
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function move(index, delta) {
+    const ids = groups.map((g) => g.id);
+    const target = index + delta;
+    if (target < 0 || target >= ids.length) return;
+    [ids[index], ids[target]] = [ids[target], ids[index]];
+    try {
+      onChange(await tauriInvoke('reorder_smart_collections', { ids }));
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  const triState = (value) => (value == null ? "" : String(value));
+  const fromTriState = (text) => (text === "" ? null : text === "true");
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-2xl max-h-[90vh] overflow-y-auto ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Smart Collections</div>
+          <div className="flex gap-2">
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={() => edit(null)}>New…</button>
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+          </div>
+        </div>
+
+        <div className={`text-xs mb-3 ${isDark ? "text-gray-400" : "text-gray-500"}`}>
+          Smart collections pick their cards by filter and update as cards change. They show at the top of the grouped view; a card can be in any number of them.
+        </div>
+
+        {draft && (
+          <div className={`rounded-xl border p-3 mb-3 space-y-2 ${isDark ? "border-slate-700" : "border-slate-200"}`}>
+            <input className={`${field} w-full`} placeholder="Name" value={draft.name} onChange={(e) => setDraft({ ...draft, name: e.target.value })} />
+            <div className="flex flex-wrap gap-2">
+              <select className={field} value={draft.filter.tierMin} onChange={(e) => setFilter({ tierMin: e.target.value })}>
+                <option value="">Lowest tier: any</option>
+                {catalog.tiers.map((t) => <option key={t} value={t}>From {t}</option>)}
+              </select>
+              <select className={field} value={draft.filter.tierMax} onChange={(e) => setFilter({ tierMax: e.target.value })}>
+                <option value="">Highest tier: any</option>
+                {catalog.tiers.map((t) => <option key={t} value={t}>Up to {t}</option>)}
+              </select>
+              <select className={field} value={triState(draft.filter.nsfw)} onChange={(e) => setFilter({ nsfw: fromTriState(e.target.value) })}>
+                <option value="">NSFW: any</option>
+                <option value="true">NSFW only</option>
+                <option value="false">No NSFW</option>
+              </select>
+              <select className={field} value={triState(draft.filter.favorite)} onChange={(e) => setFilter({ favorite: fromTriState(e.target.value) })}>
+                <option value="">Favorites: any</option>
+                <option value="true">Favorites only</option>
+                <option value="false">No favorites</option>
+              </select>
+            </div>
+            <div className="flex flex-wrap gap-2">
+              <input className={`${field} flex-1 min-w-[12rem]`} list="smart-tag-options" placeholder="All of these tags (comma separated)" value={draft.tagsText} onChange={(e) => setDraft({ ...draft, tagsText: e.target.value })} />
+              <input className={`${field} flex-1 min-w-[12rem]`} list="smart-tag-options" placeholder="None of these tags" value={draft.excludeText} onChange={(e) => setDraft({ ...draft, excludeText: e.target.value })} />
+              <datalist id="smart-tag-options">
+                {tags.map((t) => <option key={t} value={t} />)}
+              </datalist>
+            </div>
+            <div className="flex flex-wrap items-center gap-2 text-sm">
+              <label className="flex items-center gap-1">
+                Added from
+                <input type="date" className={field} value={dayInput(draft.filter.addedFrom)} onChange={(e) => setFilter({ addedFrom: dayValue(e.target.value) })} />
+              </label>
+              <label className="flex items-center gap-1">
+                to
+                <input type="date" className={field} value={dayInput(draft.filter.addedUntil, -DAY_MS)} onChange={(e) => setFilter({ addedUntil: dayValue(e.target.value, DAY_MS) })} />
+              </label>
+              {["pdf", "image", "gif"].map((kind) => (
+                <label key={kind} className="flex items-center gap-1">
+                  <input
+                    type="checkbox"
+                    checked={draft.filter.kinds.includes(kind)}
+                    onChange={(e) => setFilter({
+                      kinds: e.target.checked ? [...draft.filter.kinds, kind] : draft.filter.kinds.filter((k) => k !== kind),
+                    })}
+                  />
+                  {kind.toUpperCase()}
+                </label>
+              ))}
+            </div>
+            <input
+              className={`${field} w-full font-mono`}
+              placeholder='Filter query, e.g. tag:holo -collection:"Promo"'
+              spellCheck={false}
+              value={draft.filter.query}
+              onChange={(e) => setFilter({ query: e.target.value })}
+            />
+            <div className="flex justify-end gap-2">
+              <button className={button} onClick={() => setDraft(null)}>Cancel</button>
+              <button className={button} onClick={save} disabled={!draft.name.trim()}>{draft.id ? "Save" : "Create"}</button>
+            </div>
+          </div>
+        )}
+
+        {groups.length === 0 ? (
+          <div className={isDark ? "text-gray-400" : "text-gray-500"}>No smart collections yet.</div>
+        ) : (
+          <div className="space-y-2">
+            {groups.map((g, i) => (
+              <div key={g.id} className={`flex items-center gap-2 rounded-xl border p-2 ${isDark ? "border-slate-700" : "border-slate-200"}`}>
+                <span className="flex-1 text-sm font-medium truncate">⚡ {g.name}</span>
+                <span className={`text-xs ${isDark ? "text-gray-400" : "text-gray-500"}`}>{g.cardIds.length} cards</span>
+                <button className={button} onClick={() => move(i, -1)} disabled={i === 0} aria-label={`Move ${g.name} up`}>↑</button>
+                <button className={button} onClick={() => move(i, 1)} disabled={i === groups.length - 1} aria-label={`Move ${g.name} down`}>↓</button>
+                <button className={button} onClick={() => edit(g)}>Edit</button>
+                <button className={`${button} text-red-600`} onClick={() => remove(g.id)}>Delete</button>
+              </div>
+            ))}
+          </div>
+        )}
+      </div>
+    </div>
+  );
+}
+
+/** Desktop only: edit, import and export the tier and collection catalog. */
+function CatalogManager({ open, onClose, catalog, onSaved, onError, onNotice, theme }) {
+  // Working copy of the catalog document; saved as a whole
+  const [draft, setDraft] = useState(null);
+  const [newTier, setNewTier] = useState("");
+  // What a downloaded update would change, while it waits for approval
+  const [update, setUpdate] = useState(null);
+  const [checking, setChecking] = useState(false);
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+  const button = `px-2 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`;
+
+  useEffect(() => {
+    if (open) setDraft(structuredClone(catalog.doc));
+  }, [open, catalog]);
+
+  useEffect(() => { if (!open) setUpdate(null); }, [open]);
+
+  const setTiers = (tiers) => setDraft((d) => ({ ...d, tiers }));
+  const setCollection = (index, patch) => setDraft((d) => ({
+    ...d,
+    collections: d.collections.map((c, i) => (i === index ? { ...c, ...patch } : c)),
+  }));
+
+  function renameTier(index, name) {
+    const old = draft.tiers[index];
+    setDraft((d) => ({
+      ...d,
+      tiers: d.tiers.map((t, i) => (i === index ? name : t)),
+      // Collections defaulting to the tier follow the rename
+      collections: d.collections.map((c) => (c.defaultTier === old ? { ...c, defaultTier: name } : c)),
+    }));
+  }
+
+  function moveTier(index, delta) {
+    const tiers = draft.tiers.slice();
+    const target = index + delta;
+    if (target < 0 || target >= tiers.length) return;
+    [tiers[index], tiers[target]] = [tiers[target], tiers[index]];
+    setTiers(tiers);
+  }
+
+  function addTier() {
+    const name = newTier.trim();
+    if (!name) return;
+    setTiers([...draft.tiers, name]);
+    setNewTier("");
+  }
+
+  async function save() {
+    try {
+      onSaved(await tauriInvoke('set_catalog', { catalog: draft }));
+      onNotice("Catalog saved.");
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function checkForUpdate() {
+    setChecking(true);
+    try {
+      const diff = await tauriInvoke('check_catalog_update');
+      if (diff) setUpdate(diff);
+      else onNotice("The catalog is up to date.");
+    } catch (e) {
+      onError(e);
+    } finally {
+      setChecking(false);
+    }
+  }
+
+  async function applyUpdate() {
+    try {
+      onSaved(await tauriInvoke('apply_catalog_update'));
+      setUpdate(null);
+      onNotice("Catalog updated.");
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function importFile() {
+    try {
+      const { open: openDialog } = await import('@tauri-apps/plugin-dialog');
+      const path = await openDialog({ title: 'Import catalog', filters: [{ name: 'Catalog', extensions: ['json'] }] });
+      if (!path) return;
+      if (!(await confirmDialog("Replace your tiers and collections with the ones in this file?", "Import catalog"))) return;
+      onSaved(await tauriInvoke('import_catalog', { path }));
+      onNotice("Catalog imported.");
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function exportFile() {
+    try {
+      const { save: saveDialog } = await import('@tauri-apps/plugin-dialog');
+      const dest = await saveDialog({ title: 'Export catalog', defaultPath: 'catalog.json', filters: [{ name: 'Catalog', extensions: ['json'] }] });
+      if (!dest) return;
+      await tauriInvoke('export_catalog', { dest });
+      onNotice(`Exported the catalog to ${dest}`);
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  if (!open || !draft) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-3xl max-h-[90vh] overflow-y-auto ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Catalog</div>
+          <div className="flex gap-2">
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={checkForUpdate} disabled={checking}>{checking ? "Checking…" : "Check for updates"}</button>
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={importFile}>Import…</button>
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={exportFile}>Export…</button>
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+          </div>
+        </div>
+
+        <div className={`text-xs mb-3 ${isDark ? "text-gray-400" : "text-gray-500"}`}>
+          Tiers are listed lowest first. Picking a collection for a card fills in its default tier and NSFW flag. Renaming or removing a tier doesn't change cards that already have it. Checklists for completion tracking are edited in the catalog file (Export…, then Import…).
+        </div>
+
+        {update && (
+          <div className={`rounded-xl border p-3 mb-4 text-sm ${isDark ? "border-indigo-700 bg-indigo-950/40" : "border-indigo-300 bg-indigo-50"}`}>
+            <div className="font-semibold mb-1">A catalog update is available</div>
+            <ul className="list-disc pl-5 space-y-0.5">
+              {update.addedTiers.length > 0 && <li>New tiers: {update.addedTiers.join(", ")}</li>}
+              {update.reorderedTiers && <li>Existing tiers are ranked differently</li>}
+              {update.addedCollections.map((c) => (
+                <li key={`add-${c.name}`}>
+                  New collection {c.name}
+                  {c.defaultTier ? ` (${c.defaultTier}${c.nsfw ? ", NSFW" : ""})` : c.nsfw ? " (NSFW)" : ""}
+                  {c.checklist?.length ? `, ${c.checklist.length} cards in its checklist` : ""}
+                </li>
+              ))}
+              {update.changedCollections.map(({ before, after }) => (
+                <li key={`change-${before.name}`}>
+                  {before.name}: {before.defaultTier || "no tier"}{before.nsfw ? ", NSFW" : ""} → {after.name !== before.name ? `${after.name}, ` : ""}{after.defaultTier || "no tier"}{after.nsfw ? ", NSFW" : ""}
+                  {JSON.stringify(before.checklist || []) !== JSON.stringify(after.checklist || []) && ` (checklist: ${before.checklist?.length || 0} → ${after.checklist?.length || 0} cards)`}
+                </li>
+              ))}
+            </ul>
+            <div className={`text-xs mt-2 ${isDark ? "text-gray-400" : "text-gray-500"}`}>
+              Nothing you added is removed. Unsaved edits below are replaced.
+            </div>
+            <div className="flex justify-end gap-2 mt-2">
+              <button className={button} onClick={() => setUpdate(null)}>Not now</button>
+              <button className={button} onClick={applyUpdate}>Apply update</button>
+            </div>
+          </div>
+        )}
+
+        <div className="mb-4">
+          <div className={`text-xs font-semibold mb-1 ${isDark ? "text-gray-300" : "text-gray-600"}`}>Tiers</div>
+          <div className="space-y-1">
+            {draft.tiers.map((t, i) => (
+              <div key={i} className="flex items-center gap-2">
+                <input className={`${field} flex-1`} value={t} onChange={(e) => renameTier(i, e.target.value)} aria-label={`Tier ${i + 1}`} />
+                <button className={button} onClick={() => moveTier(i, -1)} disabled={i === 0} aria-label={`Move ${t} up`}>↑</button>
+                <button className={button} onClick={() => moveTier(i, 1)} disabled={i === draft.tiers.length - 1} aria-label={`Move ${t} down`}>↓</button>
+                <button className={`${button} text-red-600`} onClick={() => setTiers(draft.tiers.filter((_, j) => j !== i))}>Remove</button>
+              </div>
+            ))}
+            <div className="flex items-center gap-2">
+              <input
+                className={`${field} flex-1`}
+                placeholder="New tier (ranks highest)"
+                value={newTier}
+                onChange={(e) => setNewTier(e.target.value)}
+                onKeyDown={(e) => { if (e.key === "Enter") addTier(); }}
+              />
+              <button className={button} onClick={addTier}>Add</button>
+            </div>
+          </div>
+        </div>
+
+        <div className="mb-4">
+          <div className="flex items-center justify-between mb-1">
+            <div className={`text-xs font-semibold ${isDark ? "text-gray-300" : "text-gray-600"}`}>Collections</div>
+            <button className={button} onClick={() => setDraft((d) => ({ ...d, collections: [{ name: "" }, ...d.collections] }))}>Add collection</button>
+          </div>
+          <div className="space-y-1">
+            {draft.collections.map((c, i) => (
+              <div key={i} className="flex items-center gap-2">
+                <input className={`${field} flex-1`} placeholder="Collection name" value={c.name} onChange={(e) => setCollection(i, { name: e.target.value })} />
+                <select className={field} value={c.defaultTier || ""} onChange={(e) => setCollection(i, { defaultTier: e.target.value })}>
+                  <option value="">No default tier</option>
+                  {draft.tiers.map((t, j) => <option key={j} value={t}>{t}</option>)}
+                </select>
+                <label className="flex items-center gap-1 text-sm">
+                  <input type="checkbox" checked={!!c.nsfw} onChange={(e) => setCollection(i, { nsfw: e.target.checked })} />
+                  NSFW
+                </label>
+                {c.checklist?.length > 0 && (
+                  <span className={`text-xs ${isDark ? "text-gray-400" : "text-gray-500"}`}>{c.checklist.length} in checklist</span>
+                )}
+                <button className={`${button} text-red-600`} onClick={() => setDraft((d) => ({ ...d, collections: d.collections.filter((_, j) => j !== i) }))}>Remove</button>
+              </div>
+            ))}
+          </div>
+        </div>
+
+        <label className="block mb-4 text-sm">
+          <span className={`text-xs font-semibold ${isDark ? "text-gray-300" : "text-gray-600"}`}>Update URL</span>
+          <input
+            className={`${field} w-full mt-1`}
+            placeholder="Default: the catalog published with app releases"
+            value={draft.updateUrl || ""}
+            onChange={(e) => setDraft((d) => ({ ...d, updateUrl: e.target.value.trim() || undefined }))}
+          />
+        </label>
+
+        <div className="flex justify-between gap-2">
+          <button className={button} onClick={() => setDraft({ ...structuredClone(bundledCatalog), updateUrl: draft.updateUrl })}>Start from the bundled catalog</button>
+          <div className="flex gap-2">
+            <button className={button} onClick={() => setDraft(structuredClone(catalog.doc))}>Discard changes</button>
+            <button className={button} onClick={save}>Save</button>
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+/** Desktop only: how much of each catalog checklist the library holds, with missing cards and duplicates. */
+function CompletionView({ open, onClose, metas, catalog, onError, theme }) {
+  const [report, setReport] = useState(null);
+  // Collection whose entries are listed
+  const [expanded, setExpanded] = useState("");
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+  const muted = isDark ? "text-gray-400" : "text-gray-500";
+
+  const names = useMemo(() => new Map(metas.map((m) => [m.id, m.name || "(untitled)"])), [metas]);
+
+  // Cards and the catalog both change what matches, so reload with either
+  useEffect(() => {
+    if (!open) return;
+    tauriInvoke('checklist_report').then(setReport).catch(onError);
+  }, [open, metas, catalog]);
+
+  async function match(cardId, entry) {
+    try {
+      await tauriInvoke('match_checklist_entry', { cardId, entry });
+      setReport(await tauriInvoke('checklist_report'));
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  const bar = (percent) => (
+    <div className={`h-2 rounded-full overflow-hidden ${isDark ? "bg-slate-800" : "bg-slate-200"}`}>
+      <div className="h-full bg-emerald-500" style={{ width: `${percent}%` }} />
+    </div>
+  );
+  const entryLabel = (e) => `${e.number ? `#${e.number} ` : ""}${e.name}${e.variant ? ` (${e.variant})` : ""}`;
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-3xl max-h-[90vh] overflow-y-auto ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Completion</div>
+          <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+        </div>
+
+        {report && report.collections.length === 0 && (
+          <div className={`text-sm ${muted}`}>No collection in the catalog has a checklist yet.</div>
+        )}
+
+        {report && report.collections.length > 0 && (
+          <>
+            <div className="mb-4">
+              <div className="flex justify-between text-sm mb-1">
+                <span className="font-semibold">Whole binder</span>
+                <span>{report.owned} / {report.expected} ({Math.floor(report.percent)}%)</span>
+              </div>
+              {bar(report.percent)}
+              <div className={`text-xs mt-1 ${muted}`}>{report.missing} missing · {report.duplicates} duplicates</div>
+            </div>
+
+            <div className="space-y-3">
+              {report.collections.map((c) => (
+                <div key={c.collection} className={`rounded-xl border p-2 ${isDark ? "border-slate-700" : "border-slate-200"}`}>
+                  <button className="w-full text-left" onClick={() => setExpanded((x) => (x === c.collection ? "" : c.collection))}>
+                    <div className="flex justify-between text-sm mb-1">
+                      <span>{expanded === c.collection ? "▾" : "▸"} {c.collection}</span>
+                      <span>{c.owned} / {c.expected} ({Math.floor(c.percent)}%)</span>
+                    </div>
+                    {bar(c.percent)}
+                    <div className={`text-xs mt-1 ${muted}`}>
+                      {c.missing} missing · {c.duplicates} duplicates{c.unmatched.length ? ` · ${c.unmatched.length} unmatched` : ""}
+                    </div>
+                  </button>
+
+                  {expanded === c.collection && (
+                    <div className="mt-2 space-y-1 text-sm">
+                      {c.entries.map((e) => (
+                        <div key={e.key} className="flex items-start gap-2">
+                          <span className={`w-5 ${e.cardIds.length ? "text-emerald-500" : muted}`}>{e.cardIds.length ? "✓" : "·"}</span>
+                          <div className="flex-1">
+                            <div className={e.cardIds.length ? "" : muted}>
+                              {entryLabel(e)}
+                              {e.tier && <span className={`ml-1 text-xs ${muted}`}>{e.tier}</span>}
+                              {e.cardIds.length > 1 && <span className="ml-1 text-xs text-amber-500">×{e.cardIds.length}</span>}
+                            </div>
+                            {e.manual.map((id) => (
+                              <div key={id} className={`text-xs ${muted}`}>
+                                {names.get(id) || id} (matched by hand)
+                                <button className="ml-2 underline" onClick={() => match(id, null)}>Unlink</button>
+                              </div>
+                            ))}
+                          </div>
+                        </div>
+                      ))}
+
+                      {c.unmatched.length > 0 && (
+                        <div className="pt-2">
+                          <div className={`text-xs font-semibold mb-1 ${isDark ? "text-gray-300" : "text-gray-600"}`}>Cards that match no entry</div>
+                          {c.unmatched.map((id) => (
+                            <div key={id} className="flex items-center gap-2">
+                              <span className="flex-1 truncate">{names.get(id) || id}</span>
+                              <select className={field} value="" onChange={(ev) => ev.target.value && match(id, ev.target.value)}>
+                                <option value="">Match to…</option>
+                                {c.entries.map((e) => <option key={e.key} value={e.key}>{entryLabel(e)}</option>)}
+                              </select>
+                            </div>
+                          ))}
+                        </div>
+                      )}
+                    </div>
+                  )}
+                </div>
+              ))}
+            </div>
+          </>
+        )}
+      </div>
+    </div>
+  );
+}
+
+/** Desktop only: cards that are copies of each other, and the have/want list for trading them. */
+function TradeView({ open, onClose, metas, catalog, onMerge, onError, onNotice, theme }) {
+  const [groups, setGroups] = useState([]);
+  // Cards that look alike (perceptual hashes), and which card of each cluster to keep
+  const [threshold, setThreshold] = useState(0.9);
+  const [clusters, setClusters] = useState([]);
+  const [keepers, setKeepers] = useState({});
+  const [format, setFormat] = useState("text");
+  const [list, setList] = useState("");
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+  const button = `px-2 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`;
+  const muted = isDark ? "text-gray-400" : "text-gray-500";
+
+  const byId = useMemo(() => new Map(metas.map((m) => [m.id, m])), [metas]);
+
+  useEffect(() => {
+    if (!open) return;
+    tauriInvoke('duplicate_groups').then(setGroups).catch(onError);
+  }, [open, metas, catalog]);
+
+  useEffect(() => {
+    if (!open) return;
+    tauriInvoke('trade_list', { format }).then(setList).catch(onError);
+  }, [open, format, metas, catalog]);
+
+  useEffect(() => {
+    if (!open) return;
+    tauriInvoke('similar_cards', { threshold }).then(setClusters).catch(onError);
+  }, [open, threshold, metas]);
+
+  // The other cards' copies, tags and so on are folded into `keep`
+  async function merge(keep, cardIds, title) {
+    const rest = cardIds.filter((id) => id !== keep);
+    const names = rest.map((id) => byId.get(id)?.name || id).join(", ");
+    const message = `Delete ${rest.length} ${rest.length === 1 ? "copy" : "copies"} (${names}) and count ${rest.length === 1 ? "it" : "them"} on "${byId.get(keep)?.name || keep}" instead?`;
+    if (!(await confirmDialog(message, title))) return;
+    try {
+      await onMerge(keep, rest);
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function copy() {
+    try {
+      await navigator.clipboard.writeText(list);
+      onNotice("Copied the trade list.");
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function saveFile() {
+    const extension = format === "text" ? "txt" : format;
+    try {
+      const { save: saveDialog } = await import('@tauri-apps/plugin-dialog');
+      const dest = await saveDialog({ title: 'Save trade list', defaultPath: `trade-list.${extension}`, filters: [{ name: format.toUpperCase(), extensions: [extension] }] });
+      if (!dest) return;
+      await tauriInvoke('trade_list', { format, dest });
+      onNotice(`Saved the trade list to ${dest}`);
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-3xl max-h-[90vh] overflow-y-auto ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Duplicates &amp; trades</div>
+          <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+        </div>
+
+        <div className="mb-4">
+          <div className={`text-xs font-semibold mb-1 ${isDark ? "text-gray-300" : "text-gray-600"}`}>Copies of the same card</div>
+          {groups.length === 0 && <div className={`text-sm ${muted}`}>No duplicates found.</div>}
+          <div className="space-y-2">
+            {groups.map((g) => (
+              <div key={g.cardIds[0]} className={`rounded-xl border p-2 text-sm ${isDark ? "border-slate-700" : "border-slate-200"}`}>
+                <div className="flex items-center justify-between">
+                  <span>
+                    {byId.get(g.cardIds[0])?.name || g.cardIds[0]}
+                    <span className={`ml-2 text-xs ${muted}`}>{g.identical ? "identical files" : "same card"} · {g.copies} copies</span>
+                  </span>
+                  {g.identical && <button className={button} onClick={() => merge(g.cardIds[0], g.cardIds, "Keep one card")}>Keep one</button>}
+                </div>
+                <ul className={`text-xs mt-1 ${muted}`}>
+                  {g.cardIds.map((id) => {
+                    const m = byId.get(id);
+                    return (
+                      <li key={id}>
+                        {m?.name || id}{m?.collection ? ` · ${m.collection}` : ""}{(m?.quantity ?? 1) > 1 ? ` · ×${m.quantity}` : ""}{m?.condition ? ` · ${m.condition}` : ""}
+                      </li>
+                    );
+                  })}
+                </ul>
+              </div>
+            ))}
+          </div>
+        </div>
+
+        <div className="mb-4">
+          <div className="flex items-center justify-between mb-1">
+            <div className={`text-xs font-semibold ${isDark ? "text-gray-300" : "text-gray-600"}`}>Cards that look alike</div>
+            <label className={`flex items-center gap-2 text-xs ${muted}`}>
+              Similarity {Math.round(threshold * 100)}%
+              <input type="range" min={0.75} max={1} step={0.01} value={threshold} onChange={(e) => setThreshold(Number(e.target.value))} />
+            </label>
+          </div>
+          <div className={`text-xs mb-1 ${muted}`}>
+            Compares what cards look like, so a screenshot and a PDF of the same card are found too. New cards are checked shortly after import.
+          </div>
+          {clusters.length === 0 && <div className={`text-sm ${muted}`}>No look-alikes at this similarity.</div>}
+          <div className="space-y-2">
+            {clusters.map((c) => {
+              const keep = c.cardIds.includes(keepers[c.cardIds[0]]) ? keepers[c.cardIds[0]] : c.cardIds[0];
+              return (
+                <div key={c.cardIds[0]} className={`rounded-xl border p-2 text-sm ${isDark ? "border-slate-700" : "border-slate-200"}`}>
+                  <div className="flex items-center justify-between mb-2">
+                    <span className={`text-xs ${muted}`}>{Math.round(c.similarity * 100)}% alike · pick the card to keep</span>
+                    <button className={button} onClick={() => merge(keep, c.cardIds, "Merge look-alikes")}>Merge</button>
+                  </div>
+                  <div className="flex flex-wrap gap-2">
+                    {c.cardIds.map((id) => {
+                      const m = byId.get(id);
+                      if (!m) return null;
+                      return (
+                        <label key={id} className={`w-28 rounded-lg border p-1 cursor-pointer ${id === keep ? "border-emerald-500" : isDark ? "border-slate-700" : "border-slate-200"}`}>
+                          <CardThumbnail meta={m} className="w-full h-32 object-contain" />
+                          <div className="flex items-center gap-1 text-xs mt-1">
+                            <input type="radio" name={`keep-${c.cardIds[0]}`} checked={id === keep} onChange={() => setKeepers((k) => ({ ...k, [c.cardIds[0]]: id }))} />
+                            <span className="truncate" title={m.name}>{m.name}</span>
+                          </div>
+                        </label>
+                      );
+                    })}
+                  </div>
+                </div>
+              );
+            })}
+          </div>
+        </div>
+
+        <div>
+          <div className="flex items-center justify-between mb-1">
+            <div className={`text-xs font-semibold ${isDark ? "text-gray-300" : "text-gray-600"}`}>Have / want list</div>
+            <div className="flex gap-2">
+              <select className={field} value={format} onChange={(e) => setFormat(e.target.value)} aria-label="Format">
+                <option value="text">Text</option>
+                <option value="csv">CSV</option>
+                <option value="json">JSON</option>
+              </select>
+              <button className={button} onClick={copy}>Copy</button>
+              <button className={button} onClick={saveFile}>Save…</button>
+            </div>
+          </div>
+          <div className={`text-xs mb-1 ${muted}`}>
+            Have: every card with more than one copy, keeping one back. Want: checklist entries you have no copy of.
+          </div>
+          <textarea className={`${field} w-full h-64 font-mono text-xs`} readOnly value={list} />
+        </div>
+      </div>
+    </div>
+  );
+}
+
+// Options for export_binder_pages; the defaults match BinderOptions in src-tauri/src/binder.rs
+const BINDER_DEFAULTS = { paper: "a4", bleedMm: 0, marginMm: 5, cropMarks: true, captions: false };
+
+function PrintBinderDialog({ open, onClose, selectedCount, collections, busy, onPrint, theme }) {
+  const [options, setOptions] = useState(BINDER_DEFAULTS);
+  // "" prints the selected cards
+  const [collection, setCollection] = useState("");
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+  const muted = isDark ? "text-gray-400" : "text-gray-500";
+
+  useEffect(() => {
+    if (open && selectedCount === 0 && !collection && collections.length) setCollection(collections[0]);
+  }, [open]);
+
+  const set = (patch) => setOptions((o) => ({ ...o, ...patch }));
+  const number = (value) => Math.max(0, Number(value) || 0);
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-md ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Print binder pages</div>
+          <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+        </div>
+        <div className={`text-xs mb-3 ${muted}`}>
+          Nine cards to a page in 63×88 mm pockets. PDF cards stay sharp at any printer resolution.
+        </div>
+
+        <div className="space-y-2 text-sm">
+          <label className="flex items-center justify-between gap-2">
+            Cards
+            <select className={field} value={collection} onChange={(e) => setCollection(e.target.value)}>
+              <option value="" disabled={selectedCount === 0}>Selected cards ({selectedCount})</option>
+              {collections.map((c) => <option key={c} value={c}>{c}</option>)}
+            </select>
+          </label>
+          <label className="flex items-center justify-between gap-2">
+            Paper
+            <select className={field} value={options.paper} onChange={(e) => set({ paper: e.target.value })}>
+              <option value="a4">A4</option>
+              <option value="letter">Letter</option>
+            </select>
+          </label>
+          <label className="flex items-center justify-between gap-2">
+            Margins (mm)
+            <input type="number" min={0} max={50} step={0.5} className={`${field} w-20`} value={options.marginMm} onChange={(e) => set({ marginMm: number(e.target.value) })} />
+          </label>
+          <label className="flex items-center justify-between gap-2">
+            Bleed (mm)
+            <input type="number" min={0} max={10} step={0.5} className={`${field} w-20`} value={options.bleedMm} onChange={(e) => set({ bleedMm: number(e.target.value) })} />
+          </label>
+          <label className="flex items-center gap-2">
+            <input type="checkbox" checked={options.cropMarks} onChange={(e) => set({ cropMarks: e.target.checked })} />
+            Crop marks
+          </label>
+          <label className="flex items-center gap-2">
+            <input type="checkbox" checked={options.captions} onChange={(e) => set({ captions: e.target.checked })} />
+            Name and tier under each card
+          </label>
+        </div>
+
+        <div className="flex justify-end mt-4">
+          <button
+            className="px-3 py-2 rounded-xl border bg-blue-600 text-white cursor-pointer hover:bg-slate-700"
+            disabled={busy || (!collection && selectedCount === 0)}
+            onClick={() => onPrint(options, collection || null)}
+          >
+            Save PDF…
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+function ContactSheetDialog({ open, onClose, visibleCount, collections, busy, onExport, theme }) {
+  const [options, setOptions] = useState({ format: "png", arrange: "custom", columns: 6, blurNsfw: true });
+  // "" draws the cards currently shown
+  const [collection, setCollection] = useState("");
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+  const muted = isDark ? "text-gray-400" : "text-gray-500";
+
+  const set = (patch) => setOptions((o) => ({ ...o, ...patch }));
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-md ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Contact sheet</div>
+          <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+        </div>
+        <div className={`text-xs mb-3 ${muted}`}>
+          One image of your cards to share, in the current {isDark ? "dark" : "light"} theme, with how much of the collection you have.
+        </div>
+
+        <div className="space-y-2 text-sm">
+          <label className="flex items-center justify-between gap-2">
+            Cards
+            <select className={field} value={collection} onChange={(e) => setCollection(e.target.value)}>
+              <option value="">Cards shown now ({visibleCount})</option>
+              {collections.map((c) => <option key={c} value={c}>{c}</option>)}
+            </select>
+          </label>
+          <label className="flex items-center justify-between gap-2">
+            Arrange
+            <select className={field} value={options.arrange} onChange={(e) => set({ arrange: e.target.value })}>
+              <option value="custom">As ordered</option>
+              <option value="tier">By tier</option>
+            </select>
+          </label>
+          <label className="flex items-center justify-between gap-2">
+            Cards per row
+            <input type="number" min={1} max={12} className={`${field} w-20`} value={options.columns} onChange={(e) => set({ columns: Math.min(12, Math.max(1, Number(e.target.value) || 1)) })} />
+          </label>
+          <label className="flex items-center justify-between gap-2">
+            Format
+            <select className={field} value={options.format} onChange={(e) => set({ format: e.target.value })}>
+              <option value="png">PNG</option>
+              <option value="webp">WebP</option>
+            </select>
+          </label>
+          <label className="flex items-center gap-2">
+            <input type="checkbox" checked={options.blurNsfw} onChange={(e) => set({ blurNsfw: e.target.checked })} />
+            Blur NSFW cards
+          </label>
+        </div>
+
+        <div className="flex justify-end mt-4">
+          <button
+            className="px-3 py-2 rounded-xl border bg-blue-600 text-white cursor-pointer hover:bg-slate-700"
+            disabled={busy || (!collection && visibleCount === 0)}
+            onClick={() => onExport({ ...options, theme: isDark ? "dark" : "light" }, collection || null)}
+          >
+            Save image…
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+/** Desktop only: the steps Undo and Redo walk through, newest first. */
+function HistoryDialog({ open, onClose, steps, onStep, theme }) {
+  const isDark = theme === "dark";
+  const muted = isDark ? "text-gray-400" : "text-gray-500";
+  const button = `px-3 py-1 rounded-md border cursor-pointer ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`;
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-md ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">History</div>
+          <button className={button} onClick={onClose}>Close</button>
+        </div>
+        <div className={`text-xs mb-3 ${muted}`}>
+          Card edits and deletes and deleted collections, kept across restarts. Undone steps can be redone until your next change.
+        </div>
+
+        {steps.length === 0 ? (
+          <div className={`text-sm ${muted}`}>Nothing to undo yet.</div>
+        ) : (
+          <ul className="space-y-1 text-sm max-h-80 overflow-auto">
+            {steps.map((s) => (
+              <li key={s.id} className={`flex items-baseline justify-between gap-3 ${s.undone ? `${muted} line-through` : ""}`}>
+                <span>{s.description}</span>
+                <span className={`text-xs shrink-0 ${muted}`}>{new Date(s.createdAt).toLocaleString()}</span>
+              </li>
+            ))}
+          </ul>
+        )}
+
+        <div className="flex justify-end gap-2 mt-4">
+          <button className={button} disabled={!steps.some((s) => s.undone)} onClick={() => onStep('redo')}>Redo</button>
+          <button className={button} disabled={!steps.some((s) => !s.undone)} onClick={() => onStep('undo')}>Undo</button>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+/** Desktop only: folders whose new files are imported automatically, with their rules. */
+function WatchFoldersManager({ open, onClose, collections, catalog, onError, theme }) {
+  const [folders, setFolders] = useState([]);
+  const isDark = theme === "dark";
+  const field = `border rounded-md px-2 py-1 text-sm ${isDark ? "bg-slate-800 border-slate-700" : "bg-white border-slate-300"}`;
+
+  useEffect(() => {
+    if (!open) return;
+    tauriInvoke('list_watch_folders').then(setFolders).catch(onError);
+  }, [open]);
+
+  async function addFolder() {
+    try {
+      const { open: openDialog } = await import('@tauri-apps/plugin-dialog');
+      const path = await openDialog({ directory: true, title: 'Watch a folder' });
+      if (!path) return;
+      const folder = await tauriInvoke('add_watch_folder', {
+        folder: { path, collection: "", tier: "", nsfw: null },
+      });
+      setFolders((prev) => [...prev, folder].sort((a, b) => a.path.localeCompare(b.path)));
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  async function updateFolder(path, patch) {
+    const current = folders.find((f) => f.path === path);
+    if (!current) return;
+    const next = { ...current, ...patch };
+    // Picking a collection suggests its tier/NSFW defaults, like editing a card does
+    if (patch.collection !== undefined && !current.tier) next.tier = catalog.defaultTier(patch.collection);
+    if (patch.collection !== undefined && current.nsfw == null && catalog.defaultNsfw(patch.collection)) next.nsfw = true;
+    setFolders((prev) => prev.map((f) => (f.path === path ? next : f)));
+    try {
+      await tauriInvoke('update_watch_folder', { folder: next });
+    } catch (e) {
+      setFolders((prev) => prev.map((f) => (f.path === path ? current : f)));
+      onError(e);
+    }
+  }
+
+  async function removeFolder(path) {
+    try {
+      await tauriInvoke('remove_watch_folder', { path });
+      setFolders((prev) => prev.filter((f) => f.path !== path));
+    } catch (e) {
+      onError(e);
+    }
+  }
+
+  if (!open) return null;
+  return (
+    <div className="fixed inset-0 bg-black/70 z-50 flex items-center justify-center p-4" onClick={onClose}>
+      <div className={`rounded-2xl p-4 w-full max-w-2xl ${isDark ? "bg-slate-900" : "bg-white"}`} onClick={(e) => e.stopPropagation()}>
+        <div className="flex items-center justify-between mb-3">
+          <div className="font-semibold">Watch Folders</div>
+          <div className="flex gap-2">
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={addFolder}>Add folder…</button>
+            <button className={`px-3 py-1 rounded-md border ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`} onClick={onClose}>Close</button>
+          </div>
+        </div>
+
+        <div className={`text-xs mb-3 ${isDark ? "text-gray-400" : "text-gray-500"}`}>
+          PDFs and images saved into these folders are imported automatically. Files already imported once are skipped, even if you delete the card.
+        </div>
+
+        {folders.length === 0 ? (
+          <div className={isDark ? "text-gray-400" : "text-gray-500"}>No watch folders yet.</div>
+        ) : (
+          <div className="space-y-3">
+            {folders.map((f) => (
+              <div key={f.path} className={`rounded-xl border p-2 ${isDark ? "border-slate-700" : "border-slate-200"}`}>
+                <div className="flex items-center justify-between gap-2 mb-2">
+                  <span className="text-sm font-medium truncate" title={f.path}>{f.path}</span>
+                  <button
+                    className={`px-2 py-1 rounded-md border text-red-600 ${isDark ? "border-slate-700 bg-slate-800 hover:bg-slate-700" : "border-slate-300 bg-white hover:bg-slate-50"}`}
+                    onClick={() => removeFolder(f.path)}
+                  >
+                    Remove
+                  </button>
+                </div>
+                <div className="flex flex-wrap gap-2">
+                  <select className={field} value={f.collection} onChange={(e) => updateFolder(f.path, { collection: e.target.value })}>
+                    <option value="">No collection</option>
+                    {collections.map((c) => <option key={c} value={c}>{c}</option>)}
+                  </select>
+                  <select className={field} value={f.tier} onChange={(e) => updateFolder(f.path, { tier: e.target.value })}>
+                    <option value="">No tier</option>
+                    {catalog.tiers.map((t) => <option key={t} value={t}>{t}</option>)}
+                  </select>
+                  <select
+                    className={field}
+                    value={f.nsfw == null ? "" : String(f.nsfw)}
+                    onChange={(e) => updateFolder(f.path, { nsfw: e.target.value === "" ? null : e.target.value === "true" })}
+                  >
+                    <option value="">NSFW: collection default</option>
+                    <option value="true">NSFW: yes</option>
+                    <option value="false">NSFW: no</option>
+                  </select>
+                </div>
+              </div>
+            ))}
+          </div>
+        )}
+      </div>
+    </div>
+  );
+}
+
+function EditableName({ value, onSave }) {
+  const [text, setText] = React.useState(value);
+  React.useEffect(() => setText(value), [value]);
+
+  const commit = React.useCallback(() => {
+    const next = text.trim();
+    if (!next || next === value) return;
+    onSave(next);
+  }, [text, value, onSave]);
+
+  return (
+    <input
+      value={text}
+      onChange={(e) => setText(e.target.value)}
+      onBlur={commit}
+      onKeyDown={(e) => {
+        if (e.key === "Enter") { e.preventDefault(); commit(); e.currentTarget.blur(); }
+        if (e.key === "Escape") { setText(value); e.currentTarget.blur(); }
+      }}
+      placeholder="Untitled card"
+      spellCheck={false}
+      className="w-full min-w-0 bg-transparent border border-transparent focus:border-indigo-400 focus:ring-0 rounded-md px-1 h-7 text-sm font-medium"
+      aria-label="Card name"
+    />
+  );
+}
+
+function FaceEditor({ faces, onAttach, onDetach, theme }) {
+  const inputRef = useRef(null);
+  const isDark = theme === "dark";
+  const chip = `inline-flex items-center gap-1 rounded-full border px-2 py-0.5 text-xs
+    ${isDark ? "border-slate-700 bg-slate-800" : "border-slate-300 bg-white"}`;
+  return (
+    <div className="mt-2 flex flex-wrap items-center gap-1">
+      <span className={`text-xs mr-1 ${isDark ? "text-gray-400" : "text-gray-500"}`}>Faces:</span>
+      <span className={chip}>Front</span>
+      {faces.map((f) => (
+        <span key={f.id} className={chip}>
+          {f.label || "Back"}
+          <button className="cursor-pointer opacity-60 hover:opacity-100" onClick={() => onDetach(f)} aria-label={`Remove ${f.label || "Back"}`}>×</button>
+        </span>
+      ))}
+      <button className={`${chip} cursor-pointer ${isDark ? "hover:bg-slate-700" : "hover:bg-slate-50"}`} onClick={() => inputRef.current?.click()}>
+        + Add face
+      </button>
+      <input
+        ref={inputRef}
+        type="file"
+        accept="application/pdf,image/png,image/jpeg,image/gif"
+        className="hidden"
+        onChange={(e) => {
+          const file = e.target.files?.[0];
+          if (file) onAttach(file);
+          e.target.value = "";
+        }}
+      />
+    </div>
   );
 }
 